@@ -0,0 +1,59 @@
+//! Runs the engine with a custom layer through the public API.
+//!
+//! cargo run --example custom_layer
+use legion::systems::{CommandBuffer, Step};
+use legion::{system, Resources, Schedule};
+use penguin_engine::layer::Layer;
+use penguin_engine::App;
+
+/// Number of frames rendered since startup.
+#[derive(Default)]
+struct FrameCount(u64);
+
+/// Counts the rendered frames and logs the count every 100 frames.
+struct FrameCounterLayer;
+
+impl Layer for FrameCounterLayer {
+    fn init(self, _cmd: &mut CommandBuffer, r: &mut Resources) {
+        r.insert(FrameCount::default());
+    }
+
+    fn startup_steps() -> Option<Vec<Step>> {
+        None
+    }
+
+    fn run_steps() -> Option<Vec<Step>> {
+        Some(
+            Schedule::builder()
+                .add_system(count_frames_system())
+                .build()
+                .into_vec(),
+        )
+    }
+
+    fn order() -> i32 {
+        500
+    }
+
+    fn shutdown(r: &mut Resources) {
+        if let Some(frame_count) = r.get::<FrameCount>() {
+            log::info!("rendered {} frames", frame_count.0);
+        }
+    }
+}
+
+#[system]
+fn count_frames(#[resource] frame_count: &mut FrameCount) {
+    frame_count.0 += 1;
+
+    if frame_count.0 % 100 == 0 {
+        log::info!("frame {}", frame_count.0);
+    }
+}
+
+fn main() {
+    App::new()
+        .with_title("Penguin engine - custom layer")
+        .with_layer(FrameCounterLayer)
+        .run();
+}
@@ -1,16 +1,27 @@
-use wgpu::BufferDescriptor;
+use std::mem;
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::BufferDescriptor;
 
 /// Typed wgpu::Buffer for more readable code.
 pub struct GpuBuffer<T> {
     pub inner: wgpu::Buffer,
-    _marker: std::marker::PhantomData<T>
+    /// Usage and length of the buffer. None if the buffer wasn't created through GpuBufferDeviceExt.
+    info: Option<BufferInfo>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct BufferInfo {
+    usage: wgpu::BufferUsages,
+    /// Number of elements in the buffer.
+    len: usize,
 }
 
 impl<T> From<wgpu::Buffer> for GpuBuffer<T> {
     fn from(buffer: wgpu::Buffer) -> Self {
         Self {
             inner: buffer,
+            info: None,
             _marker: std::marker::PhantomData,
         }
     }
@@ -22,6 +33,153 @@ impl<T> std::ops::Deref for GpuBuffer<T> {
     }
 }
 
+impl<T> GpuBuffer<T> {
+    fn with_info(
+        buffer: wgpu::Buffer,
+        usage: wgpu::BufferUsages,
+        size: wgpu::BufferAddress,
+    ) -> Self {
+        let len = size as usize / Self::element_size();
+
+        Self {
+            inner: buffer,
+            info: Some(BufferInfo { usage, len }),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn info(&self) -> BufferInfo {
+        self.info
+            .expect("GpuBuffer: unknown buffer length (not created through GpuBufferDeviceExt)")
+    }
+
+    /// Size of an element in bytes.
+    pub fn element_size() -> usize {
+        mem::size_of::<T>()
+    }
+
+    /// Number of elements in the buffer. Panics if the buffer wasn't created through
+    /// GpuBufferDeviceExt.
+    pub fn len(&self) -> usize {
+        self.info().len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Size of the elements in the buffer in bytes, for buffer copies. Panics if the buffer wasn't
+    /// created through GpuBufferDeviceExt.
+    pub fn byte_len(&self) -> wgpu::BufferAddress {
+        (self.len() * Self::element_size()) as _
+    }
+}
+
+// write and readback
+impl<T: bytemuck::Pod> GpuBuffer<T> {
+    /// Schedules writing values to the buffer, starting at the element at index. The buffer needs
+    /// COPY_DST usage.
+    pub fn write(&self, queue: &wgpu::Queue, index: usize, values: &[T]) {
+        if let Some(info) = self.info {
+            debug_assert!(
+                index + values.len() <= info.len,
+                "GpuBuffer::write: writing elements {}..{} out of bounds of buffer with length {}",
+                index,
+                index + values.len(),
+                info.len
+            );
+        }
+
+        let byte_offset = (index * Self::element_size()) as wgpu::BufferAddress;
+        queue.write_buffer(&self.inner, byte_offset, bytemuck::cast_slice(values));
+    }
+
+    /// Reads the whole buffer back to the CPU, blocking until the GPU is done with it.
+    /// The buffer needs COPY_SRC usage.
+    pub fn read_all(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<T> {
+        let len = self
+            .info
+            .expect("GpuBuffer::read_all: unknown buffer length (not created through GpuBufferDeviceExt), use read_range")
+            .len;
+
+        self.read_range(device, queue, 0, len)
+    }
+
+    /// Reads count elements starting at the element at offset back to the CPU, blocking until the
+    /// GPU is done with the buffer. The buffer needs COPY_SRC usage.
+    pub fn read_range(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        offset: usize,
+        count: usize,
+    ) -> Vec<T> {
+        if let Some(info) = self.info {
+            assert!(
+                info.usage.contains(wgpu::BufferUsages::COPY_SRC),
+                "GpuBuffer readback requires the buffer to be created with BufferUsages::COPY_SRC, buffer usage: {:?}",
+                info.usage
+            );
+            assert!(
+                offset + count <= info.len,
+                "GpuBuffer::read_range: reading elements {}..{} out of bounds of buffer with length {}",
+                offset,
+                offset + count,
+                info.len
+            );
+        }
+
+        if count == 0 {
+            return vec![];
+        }
+
+        let element_size = Self::element_size();
+        let byte_offset = (offset * element_size) as wgpu::BufferAddress;
+        let byte_size = (count * element_size) as wgpu::BufferAddress;
+
+        // buffer copies has to be aligned to 4 bytes
+        assert_eq!(
+            byte_offset % wgpu::COPY_BUFFER_ALIGNMENT,
+            0,
+            "GpuBuffer::read_range: offset not aligned to 4 bytes"
+        );
+        assert_eq!(
+            byte_size % wgpu::COPY_BUFFER_ALIGNMENT,
+            0,
+            "GpuBuffer::read_range: size not aligned to 4 bytes"
+        );
+
+        let staging_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("readback staging buffer"),
+            size: byte_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut cmd = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("readback encoder"),
+        });
+        cmd.copy_buffer_to_buffer(&self.inner, byte_offset, &staging_buffer, 0, byte_size);
+        queue.submit(std::iter::once(cmd.finish()));
+
+        let buffer_slice = staging_buffer.slice(..);
+        let map_future = buffer_slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(map_future).expect("GpuBuffer readback: failed to map staging buffer");
+
+        let data = {
+            let mapped = buffer_slice.get_mapped_range();
+            mapped
+                .chunks_exact(element_size)
+                .map(bytemuck::pod_read_unaligned)
+                .collect::<Vec<T>>()
+        };
+        staging_buffer.unmap();
+
+        data
+    }
+}
+
 /// Extention methods for wgpu::Device.
 pub trait GpuBufferDeviceExt {
     fn create_buffer_t<T>(&self, desc: &wgpu::BufferDescriptor<'_>) -> GpuBuffer<T>;
@@ -31,11 +189,15 @@ pub trait GpuBufferDeviceExt {
 impl GpuBufferDeviceExt for wgpu::Device {
     /// Creates a typed wgpu::Buffer.
     fn create_buffer_t<T>(&self, desc: &BufferDescriptor<'_>) -> GpuBuffer<T> {
-        GpuBuffer::<T>::from(self.create_buffer(desc))
+        GpuBuffer::<T>::with_info(self.create_buffer(desc), desc.usage, desc.size)
     }
 
     /// Creates and initializes a typed wgpu::Buffer.
     fn create_buffer_init_t<T>(&self, desc: &BufferInitDescriptor<'_>) -> GpuBuffer<T> {
-        GpuBuffer::<T>::from(self.create_buffer_init(desc))
+        GpuBuffer::<T>::with_info(
+            self.create_buffer_init(desc),
+            desc.usage,
+            desc.contents.len() as _,
+        )
     }
 }
@@ -5,11 +5,10 @@ use std::ops::{Deref, DerefMut, Index, IndexMut};
 #[derive(Debug, Copy, Clone, Default, PartialOrd, PartialEq, Eq, Ord)]
 pub struct Handle<T> {
     pub id: u32,
-    _marker: std::marker::PhantomData<T>
+    _marker: std::marker::PhantomData<T>,
 }
 
-impl<T> From<usize> for Handle<T>
-{
+impl<T> From<usize> for Handle<T> {
     fn from(handle: usize) -> Self {
         Self {
             id: handle as _,
@@ -17,8 +16,7 @@ impl<T> From<usize> for Handle<T>
         }
     }
 }
-impl<T> From<Handle<T>> for usize
-{
+impl<T> From<Handle<T>> for usize {
     fn from(handle: Handle<T>) -> Self {
         handle.id as _
     }
@@ -37,15 +35,42 @@ pub struct HandleMap<T> {
 }
 impl<T> HandleMap<T> {
     pub fn new() -> Self {
-        Self {
-            inner: Vec::new()
-        }
+        Self { inner: Vec::new() }
     }
 
     pub fn push(&mut self, value: T) -> Handle<T> {
         self.inner.push(value);
         Handle::from(self.inner.len() - 1)
     }
+
+    /// The value of the handle, or None if it's out of bounds.
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.inner.get(handle.id as usize)
+    }
+
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.inner.get_mut(handle.id as usize)
+    }
+
+    fn out_of_bounds(&self, id: u32) -> ! {
+        panic!("Handle({}) out of bounds (len {})", id, self.inner.len())
+    }
+
+    /// Iterates over the values along with their handles.
+    pub fn iter_handles(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        self.inner
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (Handle::from(index), value))
+    }
+
+    /// Iterates mutably over the values along with their handles.
+    pub fn iter_handles_mut(&mut self) -> impl Iterator<Item = (Handle<T>, &mut T)> {
+        self.inner
+            .iter_mut()
+            .enumerate()
+            .map(|(index, value)| (Handle::from(index), value))
+    }
 }
 
 impl<T> Deref for HandleMap<T> {
@@ -55,23 +80,68 @@ impl<T> Deref for HandleMap<T> {
         &self.inner
     }
 }
-impl<T> DerefMut for HandleMap<T>
-{
+impl<T> DerefMut for HandleMap<T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.inner
     }
 }
 
-impl<T> Index<Handle<T>> for HandleMap<T>
-{
+impl<T> Index<Handle<T>> for HandleMap<T> {
     type Output = T;
 
     fn index(&self, handle: Handle<T>) -> &Self::Output {
-        &self.inner[handle.id as usize]
+        let id = handle.id;
+        self.get(handle).unwrap_or_else(|| self.out_of_bounds(id))
     }
 }
 impl<T> IndexMut<Handle<T>> for HandleMap<T> {
     fn index_mut(&mut self, handle: Handle<T>) -> &mut Self::Output {
+        if handle.id as usize >= self.inner.len() {
+            self.out_of_bounds(handle.id);
+        }
         &mut self.inner[handle.id as usize]
     }
 }
+
+#[test]
+fn test_iter_handles() {
+    let mut map = HandleMap::new();
+    map.push(10);
+    map.push(20);
+    map.push(30);
+
+    for (handle, value) in map.iter_handles_mut() {
+        *value += handle.id as i32;
+    }
+
+    let pairs = map.iter_handles().collect::<Vec<_>>();
+    assert_eq!(pairs.len(), 3);
+    for (handle, value) in pairs {
+        assert_eq!(&map[handle], value);
+    }
+    assert_eq!(map.inner, vec![10, 21, 32]);
+}
+
+#[test]
+fn test_get() {
+    let mut map = HandleMap::new();
+    let handle = map.push(10);
+
+    assert_eq!(map.get(handle), Some(&10));
+    *map.get_mut(handle).unwrap() += 1;
+    assert_eq!(map[handle], 11);
+
+    assert_eq!(map.get(Handle::from(1)), None);
+    assert_eq!(map.get_mut(Handle::from(1)), None);
+}
+
+#[test]
+#[should_panic(expected = "Handle(42) out of bounds (len 5)")]
+fn test_index_out_of_bounds() {
+    let mut map = HandleMap::new();
+    for value in 0..5 {
+        map.push(value);
+    }
+
+    let _ = map[Handle::from(42)];
+}
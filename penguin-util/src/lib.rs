@@ -1,6 +1,6 @@
 pub mod handle;
-pub mod raw_gpu_types;
 pub mod macros;
+pub mod raw_gpu_types;
 
 mod buffer;
 pub use buffer::*;
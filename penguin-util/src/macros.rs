@@ -98,12 +98,12 @@ macro_rules! impl_deref {
 #[macro_export]
 macro_rules! impl_default {
     ($type_name:ty, $expression:expr) => {
-       impl std::default::Default for $type_name {
-        fn default() -> Self {
-            $expression
+        impl std::default::Default for $type_name {
+            fn default() -> Self {
+                $expression
+            }
         }
-       }
-    }
+    };
 }
 
 /// Shorthand for giving std::fmt::Display the same output as std::fmt::Debug.
@@ -38,3 +38,15 @@ pub struct DrawIndirect {
     /// The instance id of the first instance to be drawn.
     pub first_instance: u32,
 }
+
+/// Struct to be submitted to wgpu to execute indirect compute dispatches.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DispatchIndirect {
+    /// Number of workgroups in the x dimension.
+    pub x: u32,
+    /// Number of workgroups in the y dimension.
+    pub y: u32,
+    /// Number of workgroups in the z dimension.
+    pub z: u32,
+}
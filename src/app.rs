@@ -0,0 +1,329 @@
+//! Public entry point of the engine.
+use crate::depth_prepass::DepthPrepassSettings;
+use crate::graphics_context::{DepthMode, GraphicsContext, GraphicsContextDescriptor};
+use crate::layer::{self, Layer, LayerStack};
+use crate::{events, input, render_scene};
+use legion::systems::CommandBuffer;
+use legion::Resources;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::WindowBuilder,
+};
+
+/// Pushes a layer added with App::with_layer to the layer stack.
+type PushLayer = Box<dyn FnOnce(&mut LayerStack, &mut CommandBuffer, &mut Resources)>;
+
+/// Builds and runs the engine. Creates the window, pushes the engine's layers followed by the
+/// layers added with with_layer, and runs the event loop. See examples/custom_layer.rs.
+pub struct App {
+    title: String,
+    /// Mesh assets loaded in addition to the startup scene's.
+    mesh_assets: Vec<String>,
+    render_limits: render_scene::RenderLimits,
+    context_descriptor: GraphicsContextDescriptor,
+    redraw_mode: layer::RedrawMode,
+    layers: Vec<PushLayer>,
+}
+
+penguin_util::impl_default!(App, App::new());
+
+impl App {
+    pub fn new() -> Self {
+        Self {
+            title: "Penguin engine".to_owned(),
+            mesh_assets: Vec::new(),
+            render_limits: render_scene::RenderLimits::default(),
+            context_descriptor: GraphicsContextDescriptor::default(),
+            redraw_mode: layer::RedrawMode::Continuous,
+            layers: Vec::new(),
+        }
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_owned();
+        self
+    }
+
+    /// Adds a layer, pushed after the engine's layers. Its Layer::order decides where its steps
+    /// run relative to the engine's layers.
+    pub fn with_layer<L: Layer + 'static>(mut self, layer: L) -> Self {
+        self.layers
+            .push(Box::new(move |layer_stack, cmd, resources| {
+                layer_stack.push(layer, cmd, resources)
+            }));
+        self
+    }
+
+    /// Loads the mesh assets in addition to the startup scene's. They're placed after the scene's
+    /// mesh assets, so their MeshComponent index is offset by the scene's mesh asset count.
+    pub fn with_mesh_assets(mut self, mesh_assets: &[&str]) -> Self {
+        self.mesh_assets
+            .extend(mesh_assets.iter().map(|&mesh_asset| mesh_asset.to_owned()));
+        self
+    }
+
+    /// Sizes of the render buffers, read by BaseRenderSceneLayer.
+    pub fn with_render_limits(mut self, render_limits: render_scene::RenderLimits) -> Self {
+        self.render_limits = render_limits;
+        self
+    }
+
+    /// How the main pass maps depth, Standard by default. ReverseZ reduces z-fighting far from the
+    /// camera.
+    pub fn with_depth_mode(mut self, depth_mode: DepthMode) -> Self {
+        self.context_descriptor.depth_mode = depth_mode;
+        self
+    }
+
+    /// Whether frames are drawn continuously (the default) or only when something changes. Can be
+    /// changed at runtime through the layer::Redraw resource.
+    pub fn with_redraw_mode(mut self, redraw_mode: layer::RedrawMode) -> Self {
+        self.redraw_mode = redraw_mode;
+        self
+    }
+
+    /// Runs the engine until the window is closed.
+    pub fn run(self) -> ! {
+        env_logger::init();
+        let event_loop = EventLoop::with_user_event();
+        let window = WindowBuilder::new()
+            .with_title(&self.title)
+            .build(&event_loop)
+            .unwrap();
+
+        let _event_sender = events::PenguinEventSender::init(event_loop.create_proxy());
+
+        let mut world = legion::World::default();
+        let mut resources = legion::Resources::default();
+        resources.insert(self.render_limits);
+
+        let mut cmd = legion::systems::CommandBuffer::new(&world);
+
+        // layers -------
+        let mut layer_stack = layer::LayerStack::default();
+
+        layer_stack.push(layer::ApplicationLayer, &mut cmd, &mut resources);
+        resources.get_mut::<layer::Redraw>().unwrap().mode = self.redraw_mode;
+        layer_stack.push(layer::SceneLayer, &mut cmd, &mut resources);
+        resources
+            .get_mut::<layer::MeshAssets>()
+            .unwrap()
+            .extend(self.mesh_assets);
+        cmd.flush(&mut world, &mut resources);
+
+        layer_stack.push(
+            layer::GraphicsContextLayer {
+                window: &window,
+                context_descriptor: self.context_descriptor,
+            },
+            &mut cmd,
+            &mut resources,
+        );
+        layer_stack.push(layer::BaseRenderSceneLayer, &mut cmd, &mut resources);
+
+        layer_stack.push(layer::PipelinesLayer, &mut cmd, &mut resources);
+
+        for push_layer in self.layers {
+            push_layer(&mut layer_stack, &mut cmd, &mut resources);
+        }
+
+        cmd.flush(&mut world, &mut resources);
+
+        layer_stack.run_startup(&mut world, &mut resources);
+
+        event_loop.run(move |event, _, control_flow| {
+            use winit::event::Event;
+
+            match event {
+                Event::MainEventsCleared => {
+                    if resources.get_mut::<layer::Redraw>().unwrap().take_request() {
+                        window.request_redraw();
+                    }
+                }
+                // frames requested while drawing this one keep the loop from waiting for events
+                Event::RedrawEventsCleared => {
+                    if *control_flow != ControlFlow::Exit {
+                        *control_flow = resources.get::<layer::Redraw>().unwrap().control_flow();
+                    }
+                }
+                Event::RedrawRequested(window_id) if window_id == window.id() => {
+                    layer_stack.execute(&mut world, &mut resources);
+
+                    if resources.get::<layer::AppExit>().unwrap().requested {
+                        *control_flow = ControlFlow::Exit;
+                    }
+                }
+                // emitted once after ControlFlow::Exit is set
+                Event::LoopDestroyed => {
+                    layer_stack.shutdown(&mut resources);
+                }
+                Event::DeviceEvent { ref event, .. } => {
+                    let input_event = match event {
+                        DeviceEvent::Key(KeyboardInput {
+                            state,
+                            virtual_keycode: Some(keycode),
+                            ..
+                        }) => input::Key::from_virtual_keycode(*keycode).map(|key| {
+                            input::InputEvent::Key(input::KeyEvent {
+                                key,
+                                state: input::KeyState::from(*state),
+                            })
+                        }),
+                        DeviceEvent::Button {
+                            button: 1, // left mouse button
+                            state,
+                        } => Some(input::InputEvent::Key(input::KeyEvent {
+                            key: input::Key::LMouseButton,
+                            state: input::KeyState::from(*state),
+                        })),
+                        DeviceEvent::MouseMotion { delta } => {
+                            Some(input::InputEvent::MouseMotion(*delta))
+                        }
+                        _ => None,
+                    };
+
+                    // mouse motion is sent for the whole screen, a held button keeps redrawing
+                    // instead
+                    if matches!(input_event, Some(input::InputEvent::Key(_))) {
+                        resources.get_mut::<layer::Redraw>().unwrap().request();
+                    }
+
+                    if let Some(input_event) = input_event {
+                        resources
+                            .get_mut::<events::Events<input::InputEvent>>()
+                            .unwrap()
+                            .send(input_event);
+                    }
+                }
+                Event::WindowEvent {
+                    ref event,
+                    window_id,
+                } if window_id == window.id() => {
+                    resources.get_mut::<layer::Redraw>().unwrap().request();
+
+                    match event {
+                        //
+                        WindowEvent::CloseRequested
+                        | WindowEvent::KeyboardInput {
+                            input:
+                                KeyboardInput {
+                                    state: ElementState::Pressed,
+                                    virtual_keycode: Some(VirtualKeyCode::Escape),
+                                    ..
+                                },
+                            ..
+                        } => *control_flow = ControlFlow::Exit,
+                        //
+                        WindowEvent::KeyboardInput {
+                            input:
+                                KeyboardInput {
+                                    state: ElementState::Pressed,
+                                    virtual_keycode: Some(VirtualKeyCode::F12),
+                                    ..
+                                },
+                            ..
+                        } => {
+                            let mut frame_capture =
+                                resources.get_mut::<layer::FrameCapture>().unwrap();
+                            frame_capture.request();
+                        }
+                        // toggles the scene's animations
+                        WindowEvent::KeyboardInput {
+                            input:
+                                KeyboardInput {
+                                    state: ElementState::Pressed,
+                                    virtual_keycode: Some(VirtualKeyCode::F2),
+                                    ..
+                                },
+                            ..
+                        } => {
+                            let enabled = !layer_stack.is_enabled::<layer::SceneLayer>();
+                            layer_stack.set_enabled::<layer::SceneLayer>(enabled);
+                            log::info!("scene layer enabled: {}", enabled);
+                        }
+                        // splits the window between the main camera and a camera on an entity
+                        WindowEvent::KeyboardInput {
+                            input:
+                                KeyboardInput {
+                                    state: ElementState::Pressed,
+                                    virtual_keycode: Some(VirtualKeyCode::F3),
+                                    ..
+                                },
+                            ..
+                        } => {
+                            let split = layer::toggle_split_screen(&mut world, &resources);
+                            log::info!("split screen: {}", split);
+                        }
+                        // toggles the depth prepass
+                        WindowEvent::KeyboardInput {
+                            input:
+                                KeyboardInput {
+                                    state: ElementState::Pressed,
+                                    virtual_keycode: Some(VirtualKeyCode::F4),
+                                    ..
+                                },
+                            ..
+                        } => {
+                            let mut settings = resources.get_mut::<DepthPrepassSettings>().unwrap();
+                            settings.enabled = !settings.enabled;
+                            log::info!("depth prepass enabled: {}", settings.enabled);
+                        }
+                        //
+                        WindowEvent::MouseWheel { delta, .. } => {
+                            resources
+                                .get_mut::<events::Events<input::InputEvent>>()
+                                .unwrap()
+                                .send(input::InputEvent::mouse_scroll(*delta));
+                        }
+                        WindowEvent::CursorMoved { position, .. } => {
+                            resources
+                                .get_mut::<events::Events<input::InputEvent>>()
+                                .unwrap()
+                                .send(input::InputEvent::CursorMoved((position.x, position.y)));
+                        }
+                        WindowEvent::MouseInput { button, state, .. } => {
+                            resources
+                                .get_mut::<events::Events<input::InputEvent>>()
+                                .unwrap()
+                                .send(input::InputEvent::MouseButton(input::MouseButtonEvent {
+                                    button: input::MouseButton::from(*button),
+                                    state: input::KeyState::from(*state),
+                                }));
+                        }
+                        WindowEvent::Resized(physical_size) => {
+                            let mut context = resources.get_mut::<GraphicsContext>().unwrap();
+                            context.on_resize(*physical_size, None);
+
+                            resources
+                                .get_mut::<events::Events<events::event::WindowResizeEvent>>()
+                                .unwrap()
+                                .send(events::event::WindowResizeEvent {
+                                    size: *physical_size,
+                                    scale_factor: None,
+                                });
+                        }
+                        WindowEvent::ScaleFactorChanged {
+                            scale_factor,
+                            new_inner_size,
+                        } => {
+                            let mut context = resources.get_mut::<GraphicsContext>().unwrap();
+                            context.on_resize(**new_inner_size, Some(*scale_factor as _));
+
+                            resources
+                                .get_mut::<events::Events<events::event::WindowResizeEvent>>()
+                                .unwrap()
+                                .send(events::event::WindowResizeEvent {
+                                    size: **new_inner_size,
+                                    scale_factor: Some(*scale_factor),
+                                });
+                        }
+                        _ => {}
+                    }
+                }
+
+                _ => {}
+            }
+        })
+    }
+}
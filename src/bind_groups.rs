@@ -31,6 +31,84 @@ pub mod layout_entry {
             }
         }
 
+        pub fn depth_texture_2d(
+            binding: u32,
+            visibility: wgpu::ShaderStages,
+        ) -> wgpu::BindGroupLayoutEntry {
+            wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Depth,
+                },
+                count: None,
+            }
+        }
+
+        /// Depth texture read as unfilterable floats, e.g. with textureLoad in post-processing.
+        pub fn depth_texture_2d_float(
+            binding: u32,
+            visibility: wgpu::ShaderStages,
+        ) -> wgpu::BindGroupLayoutEntry {
+            wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                },
+                count: None,
+            }
+        }
+
+        pub fn comparison_sampler(
+            binding: u32,
+            visibility: wgpu::ShaderStages,
+        ) -> wgpu::BindGroupLayoutEntry {
+            wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                count: None,
+            }
+        }
+
+        /// Sampler for unfilterable textures, such as depth_texture_2d_float. The sampler bound to
+        /// it must use FilterMode::Nearest and no compare function.
+        pub fn non_filtering_sampler(
+            binding: u32,
+            visibility: wgpu::ShaderStages,
+        ) -> wgpu::BindGroupLayoutEntry {
+            wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                count: None,
+            }
+        }
+
+        /// Texture that's read/written directly in a shader without a sampler.
+        pub fn storage_texture_2d(
+            binding: u32,
+            visibility: wgpu::ShaderStages,
+            format: wgpu::TextureFormat,
+            access: wgpu::StorageTextureAccess,
+        ) -> wgpu::BindGroupLayoutEntry {
+            wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility,
+                ty: wgpu::BindingType::StorageTexture {
+                    access,
+                    format,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            }
+        }
+
         #[allow(unused)]
         pub fn texture_2d_array(
             binding: u32,
@@ -44,6 +122,24 @@ pub mod layout_entry {
                 count: std::num::NonZeroU32::new(array_len),
             }
         }
+
+        /// A single texture with multiple layers, texture_2d_array in WGSL. Unlike an array of
+        /// texture bindings, this requires no extra features, but all layers have the same size.
+        pub fn layered_texture_2d(
+            binding: u32,
+            visibility: wgpu::ShaderStages,
+        ) -> wgpu::BindGroupLayoutEntry {
+            wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2Array,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            }
+        }
     }
 }
 
@@ -96,6 +192,20 @@ impl<const COUNT: usize> BindGroupLayoutBuilder<COUNT> {
         self
     }
 
+    pub fn uniform_buffer_dynamic(
+        mut self,
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+        min_binding_size: Option<wgpu::BufferSize>,
+    ) -> Self {
+        self.data.push(uniform_buffer_dynamic_layout_entry(
+            binding,
+            visibility,
+            min_binding_size,
+        ));
+        self
+    }
+
     pub fn storage_buffer(
         mut self,
         binding: u32,
@@ -131,6 +241,27 @@ impl<const COUNT: usize> BindGroupLayoutBuilder<COUNT> {
         self
     }
 
+    pub fn layered_texture_2d(mut self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+        self.data.push(layout_entry::texture::layered_texture_2d(
+            binding, visibility,
+        ));
+        self
+    }
+
+    pub fn texture_cube(mut self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+        self.data.push(wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::Cube,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        });
+        self
+    }
+
     pub fn sampler(mut self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
         self.data.push(wgpu::BindGroupLayoutEntry {
             binding,
@@ -141,7 +272,68 @@ impl<const COUNT: usize> BindGroupLayoutBuilder<COUNT> {
         self
     }
 
+    pub fn depth_texture_2d(mut self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+        self.data.push(wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Depth,
+            },
+            count: None,
+        });
+        self
+    }
+
+    pub fn comparison_sampler(mut self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+        self.data.push(wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+            count: None,
+        });
+        self
+    }
+
+    pub fn depth_texture_2d_float(mut self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+        self.data
+            .push(layout_entry::texture::depth_texture_2d_float(
+                binding, visibility,
+            ));
+        self
+    }
+
+    pub fn non_filtering_sampler(mut self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+        self.data.push(layout_entry::texture::non_filtering_sampler(
+            binding, visibility,
+        ));
+        self
+    }
+
+    pub fn storage_texture_2d(
+        mut self,
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+        format: wgpu::TextureFormat,
+        access: wgpu::StorageTextureAccess,
+    ) -> Self {
+        self.data.push(layout_entry::texture::storage_texture_2d(
+            binding, visibility, format, access,
+        ));
+        self
+    }
+
+    /// Panics in debug builds if the builder doesn't have COUNT entries with the bindings
+    /// 0..COUNT.
     pub fn build(self, device: &wgpu::Device, label: Option<&str>) -> wgpu::BindGroupLayout {
+        debug_assert_bindings(
+            self.data.iter().map(|entry| entry.binding),
+            COUNT,
+            "bind group layout",
+            label,
+        );
+
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label,
             entries: &self.data,
@@ -168,6 +360,17 @@ impl<'a, const COUNT: usize> BindGroupBuilder<'a, COUNT> {
         self.insert(binding, buffer.as_entire_binding())
     }
 
+    /// Binds a size large window into the buffer, that's moved with the dynamic offset given to
+    /// set_bind_group.
+    pub fn buffer_dynamic(
+        mut self,
+        binding: u32,
+        buffer: &'a wgpu::Buffer,
+        size: wgpu::BufferSize,
+    ) -> Self {
+        self.insert(binding, dynamic_buffer_binding(buffer, size))
+    }
+
     pub fn texture_view(mut self, binding: u32, texture_view: &'a wgpu::TextureView) -> Self {
         self.insert(binding, wgpu::BindingResource::TextureView(texture_view))
     }
@@ -176,12 +379,21 @@ impl<'a, const COUNT: usize> BindGroupBuilder<'a, COUNT> {
         self.insert(binding, wgpu::BindingResource::Sampler(sampler))
     }
 
+    /// Panics in debug builds if the builder doesn't have COUNT entries with the bindings
+    /// 0..COUNT.
     pub fn build(
         self,
         device: &wgpu::Device,
         label: Option<&str>,
         layout: &wgpu::BindGroupLayout,
     ) -> wgpu::BindGroup {
+        debug_assert_bindings(
+            self.data.iter().map(|entry| entry.binding),
+            COUNT,
+            "bind group",
+            label,
+        );
+
         device.create_bind_group(&wgpu::BindGroupDescriptor {
             label,
             layout,
@@ -190,6 +402,212 @@ impl<'a, const COUNT: usize> BindGroupBuilder<'a, COUNT> {
     }
 }
 
+/// Checks that there are expected_count bindings, and that they're 0..expected_count in any
+/// order.
+fn check_bindings(
+    bindings: impl Iterator<Item = u32>,
+    expected_count: usize,
+) -> Result<(), String> {
+    let mut bindings = bindings.collect::<Vec<_>>();
+
+    if bindings.len() != expected_count {
+        return Err(format!(
+            "expected {} entries, got {} (bindings {:?})",
+            expected_count,
+            bindings.len(),
+            bindings
+        ));
+    }
+
+    bindings.sort_unstable();
+
+    if let Some(pair) = bindings.windows(2).find(|pair| pair[0] == pair[1]) {
+        return Err(format!("binding {} is used more than once", pair[0]));
+    }
+
+    if let Some((expected, binding)) = bindings
+        .iter()
+        .enumerate()
+        .find(|(expected, binding)| **binding != *expected as u32)
+    {
+        return Err(format!(
+            "bindings aren't contiguous from 0, binding {} is missing (bindings {:?})",
+            expected, bindings
+        ));
+    }
+
+    Ok(())
+}
+
+/// Panics in debug builds if check_bindings fails, naming the bind group (layout).
+fn debug_assert_bindings(
+    bindings: impl Iterator<Item = u32>,
+    expected_count: usize,
+    kind: &str,
+    label: Option<&str>,
+) {
+    if cfg!(debug_assertions) {
+        if let Err(e) = check_bindings(bindings, expected_count) {
+            panic!("{} {:?}: {}", kind, label.unwrap_or("unlabeled"), e);
+        }
+    }
+}
+
+/// Builders backed by a Vec instead of a fixed size ArrayVec, for when the number of bindings isn't
+/// known at compile time (e.g. optional bindings).
+pub mod dynamic {
+    use super::{
+        dynamic_buffer_binding, layout_entry, storage_buffer_layout_entry,
+        uniform_buffer_dynamic_layout_entry, uniform_buffer_layout_entry,
+    };
+
+    #[derive(Default)]
+    pub struct BindGroupLayoutBuilder {
+        data: Vec<wgpu::BindGroupLayoutEntry>,
+    }
+    impl BindGroupLayoutBuilder {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn entry(mut self, entry: wgpu::BindGroupLayoutEntry) -> Self {
+            self.data.push(entry);
+            self
+        }
+
+        pub fn uniform_buffer(self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+            self.entry(uniform_buffer_layout_entry(binding, visibility))
+        }
+
+        pub fn uniform_buffer_dynamic(
+            self,
+            binding: u32,
+            visibility: wgpu::ShaderStages,
+            min_binding_size: Option<wgpu::BufferSize>,
+        ) -> Self {
+            self.entry(uniform_buffer_dynamic_layout_entry(
+                binding,
+                visibility,
+                min_binding_size,
+            ))
+        }
+
+        pub fn storage_buffer(
+            self,
+            binding: u32,
+            visibility: wgpu::ShaderStages,
+            read_only: bool,
+        ) -> Self {
+            self.entry(storage_buffer_layout_entry(binding, visibility, read_only))
+        }
+
+        pub fn texture_2d(self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+            self.entry(layout_entry::texture::texture_2d(binding, visibility))
+        }
+
+        pub fn sampler(self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+            self.entry(layout_entry::texture::sampler(binding, visibility))
+        }
+
+        pub fn depth_texture_2d(self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+            self.entry(layout_entry::texture::depth_texture_2d(binding, visibility))
+        }
+
+        pub fn comparison_sampler(self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+            self.entry(layout_entry::texture::comparison_sampler(
+                binding, visibility,
+            ))
+        }
+
+        pub fn depth_texture_2d_float(self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+            self.entry(layout_entry::texture::depth_texture_2d_float(
+                binding, visibility,
+            ))
+        }
+
+        pub fn non_filtering_sampler(self, binding: u32, visibility: wgpu::ShaderStages) -> Self {
+            self.entry(layout_entry::texture::non_filtering_sampler(
+                binding, visibility,
+            ))
+        }
+
+        pub fn storage_texture_2d(
+            self,
+            binding: u32,
+            visibility: wgpu::ShaderStages,
+            format: wgpu::TextureFormat,
+            access: wgpu::StorageTextureAccess,
+        ) -> Self {
+            self.entry(layout_entry::texture::storage_texture_2d(
+                binding, visibility, format, access,
+            ))
+        }
+
+        /// The entries added so far.
+        pub fn entries(&self) -> &[wgpu::BindGroupLayoutEntry] {
+            &self.data
+        }
+
+        pub fn build(self, device: &wgpu::Device, label: Option<&str>) -> wgpu::BindGroupLayout {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label,
+                entries: &self.data,
+            })
+        }
+    }
+
+    #[derive(Default)]
+    pub struct BindGroupBuilder<'a> {
+        data: Vec<wgpu::BindGroupEntry<'a>>,
+    }
+    impl<'a> BindGroupBuilder<'a> {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        fn insert(mut self, binding: u32, resource: wgpu::BindingResource<'a>) -> Self {
+            self.data.push(wgpu::BindGroupEntry { binding, resource });
+            self
+        }
+
+        pub fn buffer(self, binding: u32, buffer: &'a wgpu::Buffer) -> Self {
+            self.insert(binding, buffer.as_entire_binding())
+        }
+
+        /// Binds a size large window into the buffer, that's moved with the dynamic offset given
+        /// to set_bind_group.
+        pub fn buffer_dynamic(
+            self,
+            binding: u32,
+            buffer: &'a wgpu::Buffer,
+            size: wgpu::BufferSize,
+        ) -> Self {
+            self.insert(binding, dynamic_buffer_binding(buffer, size))
+        }
+
+        pub fn texture_view(self, binding: u32, texture_view: &'a wgpu::TextureView) -> Self {
+            self.insert(binding, wgpu::BindingResource::TextureView(texture_view))
+        }
+
+        pub fn sampler(self, binding: u32, sampler: &'a wgpu::Sampler) -> Self {
+            self.insert(binding, wgpu::BindingResource::Sampler(sampler))
+        }
+
+        pub fn build(
+            self,
+            device: &wgpu::Device,
+            label: Option<&str>,
+            layout: &wgpu::BindGroupLayout,
+        ) -> wgpu::BindGroup {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label,
+                layout,
+                entries: &self.data,
+            })
+        }
+    }
+}
+
 pub fn uniform_buffer_layout_entry(
     binding: u32,
     visibility: wgpu::ShaderStages,
@@ -205,6 +623,24 @@ pub fn uniform_buffer_layout_entry(
         count: None,
     }
 }
+/// Uniform buffer bound with a dynamic offset, so a single large buffer can be addressed per draw.
+/// min_binding_size is the size of the part of the buffer visible to the shader.
+pub fn uniform_buffer_dynamic_layout_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    min_binding_size: Option<wgpu::BufferSize>,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: true,
+            min_binding_size,
+        },
+        count: None,
+    }
+}
 pub fn storage_buffer_layout_entry(
     binding: u32,
     visibility: wgpu::ShaderStages,
@@ -222,6 +658,15 @@ pub fn storage_buffer_layout_entry(
     }
 }
 
+/// Binding of the first size bytes of the buffer, offset by the dynamic offset when bound.
+fn dynamic_buffer_binding(buffer: &wgpu::Buffer, size: wgpu::BufferSize) -> wgpu::BindingResource {
+    wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+        buffer,
+        offset: 0,
+        size: Some(size),
+    })
+}
+
 pub fn buffer_bind_group_entry(binding: u32, buffer: &wgpu::Buffer) -> wgpu::BindGroupEntry {
     wgpu::BindGroupEntry {
         binding,
@@ -279,3 +724,115 @@ pub fn buffer_bind_group_entry(binding: u32, buffer: &wgpu::Buffer) -> wgpu::Bin
 //     }
 // }
 //
+
+#[test]
+fn test_dynamic_bind_group_layout_builder() {
+    const FRAGMENT: wgpu::ShaderStages = wgpu::ShaderStages::FRAGMENT;
+
+    for with_shadow_map in [false, true] {
+        let mut builder = dynamic::BindGroupLayoutBuilder::new()
+            .texture_2d(0, FRAGMENT)
+            .sampler(1, FRAGMENT);
+
+        if with_shadow_map {
+            builder = builder
+                .depth_texture_2d(2, FRAGMENT)
+                .comparison_sampler(3, FRAGMENT);
+        }
+
+        let entries = builder.entries();
+        assert_eq!(entries.len(), if with_shadow_map { 4 } else { 2 });
+
+        for (i, entry) in entries.iter().enumerate() {
+            assert_eq!(entry.binding, i as u32);
+        }
+    }
+}
+
+#[test]
+fn test_depth_texture_layout_entries() {
+    const FRAGMENT: wgpu::ShaderStages = wgpu::ShaderStages::FRAGMENT;
+
+    // sampled with a comparison sampler, e.g. for shadows
+    let compare = dynamic::BindGroupLayoutBuilder::new()
+        .depth_texture_2d(0, FRAGMENT)
+        .comparison_sampler(1, FRAGMENT);
+    // read as raw depth values, e.g. for SSAO
+    let raw = dynamic::BindGroupLayoutBuilder::new()
+        .depth_texture_2d_float(0, FRAGMENT)
+        .non_filtering_sampler(1, FRAGMENT);
+
+    for (builder, sample_type, sampler_type) in [
+        (
+            compare,
+            wgpu::TextureSampleType::Depth,
+            wgpu::SamplerBindingType::Comparison,
+        ),
+        (
+            raw,
+            wgpu::TextureSampleType::Float { filterable: false },
+            wgpu::SamplerBindingType::NonFiltering,
+        ),
+    ] {
+        let entries = builder.entries();
+        assert_eq!(
+            check_bindings(entries.iter().map(|entry| entry.binding), 2),
+            Ok(())
+        );
+
+        match entries[0].ty {
+            wgpu::BindingType::Texture {
+                sample_type: ty, ..
+            } => assert_eq!(ty, sample_type),
+            _ => panic!("expected a texture binding"),
+        }
+        assert_eq!(entries[1].ty, wgpu::BindingType::Sampler(sampler_type));
+    }
+}
+
+#[test]
+fn test_uniform_buffer_dynamic_layout_entry() {
+    let min_binding_size = wgpu::BufferSize::new(64);
+
+    let entry =
+        uniform_buffer_dynamic_layout_entry(0, wgpu::ShaderStages::FRAGMENT, min_binding_size);
+
+    match entry.ty {
+        wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset,
+            min_binding_size: size,
+        } => {
+            assert_eq!(ty, wgpu::BufferBindingType::Uniform);
+            assert!(has_dynamic_offset);
+            assert_eq!(size, min_binding_size);
+        }
+        _ => panic!("expected a buffer binding"),
+    }
+
+    let builder = dynamic::BindGroupLayoutBuilder::new()
+        .uniform_buffer(0, wgpu::ShaderStages::VERTEX)
+        .uniform_buffer_dynamic(1, wgpu::ShaderStages::FRAGMENT, min_binding_size);
+    assert_eq!(builder.entries()[1], entry_with_binding(entry, 1));
+
+    fn entry_with_binding(
+        entry: wgpu::BindGroupLayoutEntry,
+        binding: u32,
+    ) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry { binding, ..entry }
+    }
+}
+
+#[test]
+fn test_check_bindings() {
+    assert!(check_bindings([0, 1, 2].into_iter(), 3).is_ok());
+    // order doesn't matter
+    assert!(check_bindings([2, 0, 1].into_iter(), 3).is_ok());
+
+    // a forgotten binding
+    assert!(check_bindings([0, 1].into_iter(), 3).is_err());
+    // a duplicated binding
+    assert!(check_bindings([0, 1, 1].into_iter(), 3).is_err());
+    // a gap
+    assert!(check_bindings([0, 1, 3].into_iter(), 3).is_err());
+}
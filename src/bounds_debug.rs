@@ -0,0 +1,246 @@
+use crate::render_scene::{RenderObjects, REMOVED_DRAW_COMMAND_INDEX};
+use crate::{bind_groups, camera, mesh, texture, GraphicsContext};
+use macaw as m;
+use penguin_util::handle::Handle;
+use penguin_util::{GpuBuffer, GpuBufferDeviceExt};
+use std::{mem, slice};
+use wgpu::util::DeviceExt;
+
+/// Line segments per circle of the bounds sphere.
+const SPHERE_SEGMENTS: usize = 32;
+
+/// Whether to draw the bounds of the render objects. Toggled from the editor.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct BoundsDebugSettings {
+    pub enabled: bool,
+}
+
+/// World space bounds of a render object, drawn as a scaled instance of the unit sphere.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BoundsInstance {
+    origin: m::Vec3,
+    radius: f32,
+}
+impl BoundsInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![1 => Float32x4];
+}
+
+/// Line list of three circles around the origin with radius 1, one in each of the xy, xz and yz
+/// planes.
+fn unit_sphere_lines(segments: usize) -> Vec<m::Vec3> {
+    let circle_point = |plane: usize, segment: usize| {
+        let angle = segment as f32 / segments as f32 * std::f32::consts::TAU;
+        let (sin, cos) = angle.sin_cos();
+
+        match plane {
+            0 => m::vec3(cos, sin, 0.0),
+            1 => m::vec3(cos, 0.0, sin),
+            _ => m::vec3(0.0, cos, sin),
+        }
+    };
+
+    (0..3)
+        .flat_map(|plane| {
+            (0..segments).flat_map(move |segment| {
+                [
+                    circle_point(plane, segment),
+                    circle_point(plane, segment + 1),
+                ]
+            })
+        })
+        .collect()
+}
+
+/// Draws the world space bounds of the render objects as wireframe spheres, transformed the same
+/// way as in the compute shader. Recorded after the scene in the main render pass.
+pub struct BoundsDebug {
+    camera_uniform_buffer: GpuBuffer<camera::CameraUniformData>,
+    vertex_buffer: wgpu::Buffer,
+    vertex_count: u32,
+    instance_buffer: GpuBuffer<BoundsInstance>,
+    instance_count: u32,
+    max_instances: usize,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+impl BoundsDebug {
+    /// Bounds are drawn for up to max_instances render objects.
+    pub fn init(
+        context: &GraphicsContext,
+        camera: &camera::MainCamera,
+        max_instances: usize,
+    ) -> Self {
+        let device = &context.device;
+
+        let camera_uniform_buffer = device.create_buffer_init_t::<camera::CameraUniformData>(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("bounds debug camera uniform buffer"),
+                contents: bytemuck::cast_slice(slice::from_ref(&camera.uniform_data)),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            },
+        );
+
+        let sphere_lines = unit_sphere_lines(SPHERE_SEGMENTS);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("bounds debug sphere vertex buffer"),
+            contents: bytemuck::cast_slice(&sphere_lines),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let instance_buffer = device.create_buffer_t::<BoundsInstance>(&wgpu::BufferDescriptor {
+            label: Some("bounds debug instance buffer"),
+            size: (mem::size_of::<BoundsInstance>() * max_instances) as _,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = bind_groups::BindGroupLayoutBuilder::<1>::builder()
+            .uniform_buffer(0, wgpu::ShaderStages::VERTEX)
+            .build(device, Some("bounds debug bind group layout"));
+
+        let bind_group = bind_groups::BindGroupBuilder::<1>::builder()
+            .buffer(0, &camera_uniform_buffer)
+            .build(device, Some("bounds debug bind group"), &bind_group_layout);
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("bounds debug shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/bounds_debug.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("bounds debug pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("bounds debug pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: mem::size_of::<m::Vec3>() as _,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: mem::size_of::<BoundsInstance>() as _,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &BoundsInstance::ATTRIBUTES,
+                    },
+                ],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            // hidden behind objects, but doesn't write depth
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: context.depth_mode.compare_function(),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                // must match the sample count of the main pass
+                count: context.sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format: context.config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            camera_uniform_buffer,
+            vertex_buffer,
+            vertex_count: sphere_lines.len() as _,
+            instance_buffer,
+            instance_count: 0,
+            max_instances,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    /// The most render objects bounds are drawn for.
+    pub fn max_instances(&self) -> usize {
+        self.max_instances
+    }
+
+    pub fn update_camera(
+        &self,
+        queue: &wgpu::Queue,
+        camera_uniform_data: &camera::CameraUniformData,
+    ) {
+        self.camera_uniform_buffer
+            .write(queue, 0, slice::from_ref(camera_uniform_data));
+    }
+
+    /// Writes the world space bounds of the render objects that haven't been removed.
+    pub fn update_bounds(&mut self, queue: &wgpu::Queue, render_objects: &RenderObjects) {
+        let instances = render_objects
+            .render_objects
+            .iter()
+            .enumerate()
+            .filter(|&(id, _)| {
+                render_objects.draw_commands[id].draw_command_index != REMOVED_DRAW_COMMAND_INDEX
+            })
+            .take(self.max_instances)
+            .map(|(id, _)| {
+                let mesh::RenderBounds { origin, radius } =
+                    render_objects.world_render_bounds(Handle::from(id));
+                BoundsInstance { origin, radius }
+            })
+            .collect::<Vec<_>>();
+
+        self.instance_count = instances.len() as _;
+        if !instances.is_empty() {
+            self.instance_buffer.write(queue, 0, &instances);
+        }
+    }
+
+    /// Draws the bounds. Should be recorded after the scene in the main render pass.
+    pub fn record<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.push_debug_group("bounds debug");
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..self.instance_count);
+        render_pass.pop_debug_group();
+    }
+}
+
+#[test]
+fn test_unit_sphere_lines() {
+    let lines = unit_sphere_lines(16);
+
+    // a pair of points per segment of each of the three circles
+    assert_eq!(lines.len(), 3 * 16 * 2);
+
+    for point in &lines {
+        assert!((point.length() - 1.0).abs() < 1e-5);
+    }
+
+    // the segments of each circle connect, and the circle closes
+    for circle in lines.chunks(16 * 2) {
+        for segments in circle.chunks(2).collect::<Vec<_>>().windows(2) {
+            assert!(segments[0][1].distance(segments[1][0]) < 1e-5);
+        }
+        assert!(circle[circle.len() - 1].distance(circle[0]) < 1e-5);
+    }
+}
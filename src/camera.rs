@@ -1,3 +1,4 @@
+use crate::graphics_context::DepthMode;
 use crate::{events, input};
 use legion::systems::CommandBuffer;
 use legion::{Entity, Resources};
@@ -6,15 +7,34 @@ use macaw as m;
 /// Tau / 4
 const FRAC_TAU_4: f32 = std::f32::consts::FRAC_PI_2;
 
+/// Minimum distance between an orbiting camera and its focus point.
+const MIN_ORBIT_DISTANCE: f32 = 0.5;
+
+/// How the main camera is controlled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Moves with WASD, looks around when dragging the mouse.
+    Fly,
+    /// Orbits around focus_point when dragging the mouse, scrolling changes the distance.
+    Orbit,
+}
+
 /// Data related to the editor camera
 pub struct MainCamera {
     camera: CameraLocationOrientation,
     pub projection: PerspectiveProjection,
     pub controller: CameraController,
     pub uniform_data: CameraUniformData,
+    pub mode: CameraMode,
+    /// The point the camera orbits around in orbit mode.
+    pub focus_point: m::Vec3,
+    /// Distance to the focus point in orbit mode.
+    pub distance: f32,
+    /// Area of the output the main camera renders into. Cameras on entities are drawn on top of it.
+    pub viewport: ViewportRect,
 }
 impl MainCamera {
-    pub fn init(config: &wgpu::SurfaceConfiguration) -> Self {
+    pub fn init(config: &wgpu::SurfaceConfiguration, depth_mode: DepthMode) -> Self {
         let camera = CameraLocationOrientation::new(
             (0.0, 5.0, 10.0).into(),
             f32::to_radians(-90.),
@@ -27,6 +47,7 @@ impl MainCamera {
             aspect: config.width as f32 / config.height as f32,
             z_near: 0.1,
             z_far: 100.0,
+            depth_mode,
         };
 
         let mut uniform_data = CameraUniformData::new();
@@ -37,15 +58,108 @@ impl MainCamera {
             projection,
             controller,
             uniform_data,
+            mode: CameraMode::Fly,
+            focus_point: m::Vec3::ZERO,
+            distance: 10.0,
+            viewport: ViewportRect::FULL,
         }
     }
 
+    /// Changes how the projection maps depth. The RenderScene loop switches the main pass to the
+    /// camera's depth mode.
+    pub fn set_depth_mode(&mut self, depth_mode: DepthMode) {
+        self.projection.depth_mode = depth_mode;
+        self.uniform_data
+            .update_view_proj(&self.camera, &self.projection);
+    }
+
+    /// Changes the viewport, keeping the aspect ratio of the projection in sync with it.
+    pub fn set_viewport(&mut self, viewport: ViewportRect, output_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.projection.resize(viewport.size_in_pixels(output_size));
+    }
+
     pub fn update(&mut self, dt: std::time::Duration) {
         // update camera data
-        self.controller.update_transform(&mut self.camera, dt);
+        match self.mode {
+            CameraMode::Fly => self.controller.update_transform(&mut self.camera, dt),
+            // moving the camera leaves orbit mode
+            CameraMode::Orbit if self.controller.has_movement_input() => {
+                self.mode = CameraMode::Fly;
+                self.controller.update_transform(&mut self.camera, dt);
+            }
+            CameraMode::Orbit => {
+                self.controller
+                    .update_orbit(&mut self.camera, &mut self.distance, dt);
+
+                // keep looking at the focus point from the orbit distance
+                self.camera.position = self.focus_point - self.camera.forward() * self.distance;
+            }
+        }
+
         self.uniform_data
             .update_view_proj(&self.camera, &self.projection);
     }
+
+    pub fn position(&self) -> m::Vec3 {
+        self.camera.position
+    }
+
+    /// The planes of the camera's view frustum.
+    pub fn frustum(&self) -> Frustum {
+        Frustum {
+            planes: self.projection.frustum_planes(&self.camera),
+        }
+    }
+
+    /// The camera's position, orientation and projection, for saving and restoring views.
+    pub fn view(&self) -> CameraView {
+        CameraView {
+            position: self.camera.position,
+            yaw: self.camera.yaw,
+            pitch: self.camera.pitch,
+            fov_y: self.projection.fov_y,
+            z_near: self.projection.z_near,
+            z_far: self.projection.z_far,
+        }
+    }
+
+    /// Moves the camera to a saved view, switching to fly mode. The aspect ratio is kept, as it
+    /// follows the viewport.
+    pub fn set_view(&mut self, view: &CameraView) {
+        self.camera = CameraLocationOrientation::new(view.position, view.yaw, view.pitch);
+        self.projection.fov_y = view.fov_y;
+        self.projection.z_near = view.z_near;
+        self.projection.z_far = view.z_far;
+        self.mode = CameraMode::Fly;
+
+        self.uniform_data
+            .update_view_proj(&self.camera, &self.projection);
+    }
+
+    /// Switches to orbit mode around the focus point, keeping the current orientation.
+    pub fn focus(&mut self, focus_point: m::Vec3) {
+        self.focus_point = focus_point;
+        self.distance = self
+            .camera
+            .position
+            .distance(focus_point)
+            .max(MIN_ORBIT_DISTANCE);
+        self.mode = CameraMode::Orbit;
+    }
+}
+
+/// A view of the main camera, saved with scene_file::save_camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraView {
+    pub position: m::Vec3,
+    /// Radians.
+    pub yaw: f32,
+    /// Radians.
+    pub pitch: f32,
+    pub fov_y: f32,
+    pub z_near: f32,
+    pub z_far: f32,
 }
 
 #[repr(C)]
@@ -72,6 +186,77 @@ impl CameraUniformData {
     }
 }
 
+/// A camera on an entity, rendering the scene into the entity's Viewport after the main camera.
+pub struct Camera {
+    pub location: CameraLocationOrientation,
+    pub projection: PerspectiveProjection,
+}
+impl Camera {
+    /// The camera's view projection, with the aspect ratio of the viewport it's rendered into.
+    pub fn uniform_data(
+        &self,
+        viewport: &ViewportRect,
+        output_size: (u32, u32),
+    ) -> CameraUniformData {
+        let mut projection = self.projection;
+        projection.resize(viewport.size_in_pixels(output_size));
+
+        let mut uniform_data = CameraUniformData::new();
+        uniform_data.update_view_proj(&self.location, &projection);
+        uniform_data
+    }
+}
+
+/// Where a Camera renders to. Viewports with a higher order are drawn later, on top of the ones
+/// before them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub rect: ViewportRect,
+    pub order: i32,
+}
+
+/// Area of the output, as fractions of the output size from the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewportRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+impl ViewportRect {
+    pub const FULL: Self = Self {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+    pub const LEFT_HALF: Self = Self {
+        width: 0.5,
+        ..Self::FULL
+    };
+    pub const RIGHT_HALF: Self = Self {
+        x: 0.5,
+        ..Self::LEFT_HALF
+    };
+
+    /// x, y, width and height in pixels, for RenderPass::set_viewport.
+    pub fn pixels(&self, (width, height): (u32, u32)) -> [f32; 4] {
+        let (width, height) = (width as f32, height as f32);
+        [
+            self.x * width,
+            self.y * height,
+            self.width * width,
+            self.height * height,
+        ]
+    }
+
+    /// Width and height in whole pixels.
+    pub fn size_in_pixels(&self, output_size: (u32, u32)) -> (u32, u32) {
+        let [_, _, width, height] = self.pixels(output_size);
+        (width.round() as u32, height.round() as u32)
+    }
+}
+
 pub struct CameraLocationOrientation {
     pub position: m::Vec3,
     yaw: f32,   // rads
@@ -86,28 +271,209 @@ impl CameraLocationOrientation {
         }
     }
 
+    /// Direction the camera is looking in.
+    pub fn forward(&self) -> m::Vec3 {
+        let (pitch_sin, pitch_cos) = self.pitch.sin_cos();
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+
+        m::vec3(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin)
+    }
+
+    /// Direction the camera is looking in, projected onto the horizontal plane.
+    pub fn horizontal_forward(&self) -> m::Vec3 {
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+        m::vec3(yaw_cos, 0.0, yaw_sin)
+    }
+
+    /// Direction to the right of the camera, always horizontal as the camera doesn't roll.
+    pub fn right(&self) -> m::Vec3 {
+        self.horizontal_forward().cross(m::Vec3::Y)
+    }
+
     pub fn view_matrix(&self) -> m::Mat4 {
-        m::Mat4::look_at_rh(
-            self.position,
-            self.position + m::vec3(self.yaw.cos(), self.pitch.sin(), self.yaw.sin()).normalize(),
-            m::Vec3::Y,
-        )
+        m::Mat4::look_at_rh(self.position, self.position + self.forward(), m::Vec3::Y)
     }
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct PerspectiveProjection {
     pub fov_y: f32,
     pub aspect: f32,
     pub z_near: f32,
     pub z_far: f32,
+    pub depth_mode: DepthMode,
 }
 impl PerspectiveProjection {
+    /// Takes the physical size. Both dimensions are multiplied by the scale factor, so the aspect
+    /// ratio is the same as that of the logical size. Zero sizes, sent when the window is
+    /// minimized, are ignored.
     pub fn resize(&mut self, (width, height): (u32, u32)) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
         self.aspect = width as f32 / height as f32;
     }
 
+    /// Maps the near plane to depth 0 and the far plane to depth 1, or the other way around with
+    /// reverse-Z.
     pub fn perspective_matrix(&self) -> m::Mat4 {
-        m::Mat4::perspective_rh(self.fov_y, self.aspect, self.z_near, self.z_far)
+        let (z_near, z_far) = match self.depth_mode {
+            DepthMode::Standard => (self.z_near, self.z_far),
+            DepthMode::ReverseZ => (self.z_far, self.z_near),
+        };
+        m::Mat4::perspective_rh(self.fov_y, self.aspect, z_near, z_far)
+    }
+
+    /// The planes of the view frustum, as seen from the camera.
+    pub fn frustum_planes(&self, camera: &CameraLocationOrientation) -> [m::Vec4; 6] {
+        Frustum::planes_from_view_proj(self.perspective_matrix() * camera.view_matrix())
+    }
+}
+
+/// View frustum, used for culling.
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far planes. xyz is the normal pointing into the frustum,
+    /// w is the distance from the origin.
+    pub planes: [m::Vec4; 6],
+}
+impl Frustum {
+    /// Extracts the frustum planes from a view projection matrix (Gribb-Hartmann), assuming wgpu's
+    /// 0..1 depth range. With reverse-Z, the near and far planes are swapped.
+    pub fn planes_from_view_proj(view_proj: m::Mat4) -> [m::Vec4; 6] {
+        let (row0, row1, row2, row3) = (
+            view_proj.row(0),
+            view_proj.row(1),
+            view_proj.row(2),
+            view_proj.row(3),
+        );
+
+        [
+            row3 + row0, // left
+            row3 - row0, // right
+            row3 + row1, // bottom
+            row3 - row1, // top
+            row2,        // near
+            row3 - row2, // far
+        ]
+        .map(|plane| plane / plane.truncate().length())
+    }
+
+    /// Whether a sphere is at least partially inside the frustum.
+    pub fn contains_sphere(&self, origin: m::Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(origin) + plane.w >= -radius)
+    }
+}
+
+/// A ray in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: m::Vec3,
+    /// Normalized.
+    pub direction: m::Vec3,
+}
+
+impl Ray {
+    /// Builds a ray from the camera through a point in normalized device coordinates.
+    pub fn from_ndc(ndc: m::Vec2, view_proj: m::Mat4, depth_mode: DepthMode) -> Option<Self> {
+        let inverse_view_proj = view_proj.inverse();
+
+        // points on the near and far planes
+        let near = inverse_view_proj.project_point3(ndc.extend(depth_mode.near_depth()));
+        let far = inverse_view_proj.project_point3(ndc.extend(depth_mode.far_depth()));
+
+        let direction = (far - near).try_normalize()?;
+
+        Some(Self {
+            origin: near,
+            direction,
+        })
+    }
+
+    /// Distance along the ray to the closest intersection with the sphere, if any.
+    pub fn intersect_sphere(&self, center: m::Vec3, radius: f32) -> Option<f32> {
+        let to_center = center - self.origin;
+        let projected = to_center.dot(self.direction);
+
+        let distance_sq = to_center.length_squared() - projected * projected;
+        let radius_sq = radius * radius;
+        if distance_sq > radius_sq {
+            return None;
+        }
+
+        let half_chord = (radius_sq - distance_sq).sqrt();
+
+        // the ray origin may be inside the sphere
+        let near = projected - half_chord;
+        let far = projected + half_chord;
+
+        if far < 0.0 {
+            None
+        } else if near < 0.0 {
+            Some(0.0)
+        } else {
+            Some(near)
+        }
+    }
+}
+
+/// A camera movement that can be bound to keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraAction {
+    Forward,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Keys bound to the camera movement actions. An action can be bound to several keys.
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: Vec<(input::Key, CameraAction)>,
+}
+
+impl Default for KeyBindings {
+    /// WASD or the arrow keys to move, E/Space and Q/LControl to move up and down.
+    fn default() -> Self {
+        use input::Key;
+
+        Self::builder()
+            .bind(CameraAction::Forward, &[Key::W, Key::Up])
+            .bind(CameraAction::Back, &[Key::S, Key::Down])
+            .bind(CameraAction::Left, &[Key::A, Key::Left])
+            .bind(CameraAction::Right, &[Key::D, Key::Right])
+            .bind(CameraAction::Up, &[Key::E, Key::Space])
+            .bind(CameraAction::Down, &[Key::Q, Key::LControl])
+    }
+}
+
+impl KeyBindings {
+    /// Bindings without any keys bound.
+    pub fn builder() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Binds the keys to the action, replacing the keys previously bound to it.
+    pub fn bind(mut self, action: CameraAction, keys: &[input::Key]) -> Self {
+        self.bindings
+            .retain(|(_, bound_action)| *bound_action != action);
+        self.bindings.extend(keys.iter().map(|key| (*key, action)));
+        self
+    }
+
+    /// The action the key is bound to, if any.
+    pub fn action(&self, key: input::Key) -> Option<CameraAction> {
+        self.bindings
+            .iter()
+            .find(|(bound_key, _)| *bound_key == key)
+            .map(|(_, action)| *action)
     }
 }
 
@@ -123,6 +489,9 @@ pub struct CameraController {
     speed: f32,
     sensitivity: f32,
     mouse_key_down: bool,
+    /// Lines scrolled since the last update.
+    scroll_lines: f32,
+    key_bindings: KeyBindings,
 }
 impl CameraController {
     pub fn new(speed: f32, sensitivity: f32) -> Self {
@@ -138,25 +507,53 @@ impl CameraController {
             speed,
             sensitivity,
             mouse_key_down: false,
+            scroll_lines: 0.0,
+            key_bindings: KeyBindings::default(),
         }
     }
 
+    pub fn with_key_bindings(mut self, key_bindings: KeyBindings) -> Self {
+        self.key_bindings = key_bindings;
+        self
+    }
+
     pub fn on_event(&mut self, event: &events::PenguinEvent) -> bool {
         match event {
-            events::PenguinEvent::Input(input_event) => match input_event {
-                input::InputEvent::Key(e) => self.process_key_events(e.key, e.state),
-                input::InputEvent::MouseMotion(delta) => {
-                    if self.mouse_key_down {
-                        self.process_mouse_delta_events(delta.0, delta.1);
-                    }
-                }
-            },
+            events::PenguinEvent::Input(input_event) => self.on_input_event(input_event),
             _ => {}
         }
 
         false
     }
 
+    pub fn on_input_event(&mut self, input_event: &input::InputEvent) {
+        match input_event {
+            input::InputEvent::Key(e) => self.process_key_events(e.key, e.state),
+            input::InputEvent::MouseMotion(delta) => {
+                if self.mouse_key_down {
+                    self.process_mouse_delta_events(delta.0, delta.1);
+                }
+            }
+            input::InputEvent::MouseScroll(lines) => self.scroll_lines += *lines,
+            input::InputEvent::CursorMoved(_) | input::InputEvent::MouseButton(_) => {}
+        }
+    }
+
+    /// Scrolling up increases the movement speed, scrolling down decreases it.
+    fn process_scroll_events(&mut self) {
+        let lines = std::mem::take(&mut self.scroll_lines);
+
+        const SPEED_CHANGE_PER_LINE: f32 = 1.1;
+        const MIN_SPEED: f32 = 0.1;
+        const MAX_SPEED: f32 = 100.0;
+
+        self.speed = f32::clamp(
+            self.speed * SPEED_CHANGE_PER_LINE.powf(lines),
+            MIN_SPEED,
+            MAX_SPEED,
+        );
+    }
+
     fn process_mouse_delta_events(&mut self, dx: f64, dy: f64) {
         self.yaw_amount = dx as _;
         self.pitch_amount = dy as _;
@@ -171,33 +568,18 @@ impl CameraController {
             0.0
         };
 
-        match key {
-            Key::A | Key::Left => {
-                self.left_amount = amount;
-            }
-            Key::D | Key::Right => {
-                self.right_amount = amount;
-            }
-            Key::W | Key::Up => {
-                self.forward_amount = amount;
-            }
-            Key::S | Key::Down => {
-                self.backward_amount = amount;
-            }
-            Key::E | Key::Space => {
-                self.up_amount = amount;
-            }
-            Key::Q | Key::LControl => {
-                self.down_amount = amount;
-            }
-            Key::LMouseButton => {
-                self.mouse_key_down = if state == crate::input::KeyState::Down {
-                    true
-                } else {
-                    false
-                };
-            }
-            _ => {}
+        match self.key_bindings.action(key) {
+            Some(CameraAction::Left) => self.left_amount = amount,
+            Some(CameraAction::Right) => self.right_amount = amount,
+            Some(CameraAction::Forward) => self.forward_amount = amount,
+            Some(CameraAction::Back) => self.backward_amount = amount,
+            Some(CameraAction::Up) => self.up_amount = amount,
+            Some(CameraAction::Down) => self.down_amount = amount,
+            None => {}
+        }
+
+        if key == Key::LMouseButton {
+            self.mouse_key_down = state == crate::input::KeyState::Down;
         }
     }
 
@@ -206,19 +588,55 @@ impl CameraController {
         camera: &mut CameraLocationOrientation,
         dt: std::time::Duration,
     ) {
+        self.process_scroll_events();
+
         let dt = dt.as_secs_f32();
 
         // Move forwards/backwards and left/right
-        let (yaw_sin, yaw_cos) = camera.yaw.sin_cos();
-        let forward = m::vec3(yaw_cos, 0.0, yaw_sin).normalize();
-        let right = m::vec3(-yaw_sin, 0.0, yaw_cos).normalize();
+        let forward = camera.horizontal_forward();
+        let right = camera.right();
         camera.position += forward * (self.forward_amount - self.backward_amount) * self.speed * dt;
         camera.position += right * (self.right_amount - self.left_amount) * self.speed * dt;
 
         // Move up/down (no roll)
         camera.position.y += (self.up_amount - self.down_amount) * self.speed * dt;
 
-        // Rotate
+        self.rotate(camera, dt);
+    }
+
+    /// Whether any of the movement keys are held.
+    pub fn has_movement_input(&self) -> bool {
+        [
+            self.left_amount,
+            self.right_amount,
+            self.forward_amount,
+            self.backward_amount,
+            self.up_amount,
+            self.down_amount,
+        ]
+        .iter()
+        .any(|amount| *amount != 0.0)
+    }
+
+    /// Rotates around the focus point with the mouse, and zooms in/out when scrolling.
+    fn update_orbit(
+        &mut self,
+        camera: &mut CameraLocationOrientation,
+        distance: &mut f32,
+        dt: std::time::Duration,
+    ) {
+        const DISTANCE_CHANGE_PER_LINE: f32 = 0.9;
+
+        let lines = std::mem::take(&mut self.scroll_lines);
+        *distance = f32::max(
+            *distance * DISTANCE_CHANGE_PER_LINE.powf(lines),
+            MIN_ORBIT_DISTANCE,
+        );
+
+        self.rotate(camera, dt.as_secs_f32());
+    }
+
+    fn rotate(&mut self, camera: &mut CameraLocationOrientation, dt: f32) {
         camera.yaw += f32::to_radians(self.yaw_amount) * self.sensitivity * dt;
         camera.pitch -= f32::to_radians(self.pitch_amount) * self.sensitivity * dt;
 
@@ -231,3 +649,212 @@ impl CameraController {
         camera.pitch = f32::clamp(camera.pitch, -safe_frac, safe_frac);
     }
 }
+
+#[test]
+fn test_frustum_contains_sphere() {
+    let view = m::Mat4::look_at_rh(m::vec3(0., 0., 5.), m::Vec3::ZERO, m::Vec3::Y);
+    let projection = m::Mat4::perspective_rh(f32::to_radians(90.), 1.0, 0.1, 100.0);
+
+    let frustum = Frustum {
+        planes: Frustum::planes_from_view_proj(projection * view),
+    };
+
+    // in front of the camera
+    assert!(frustum.contains_sphere(m::Vec3::ZERO, 1.0));
+
+    // behind the camera
+    assert!(!frustum.contains_sphere(m::vec3(0., 0., 10.), 1.0));
+}
+
+#[test]
+fn test_reverse_z_projection() {
+    let mut projection = PerspectiveProjection {
+        fov_y: f32::to_radians(45.0),
+        aspect: 1.0,
+        z_near: 0.1,
+        z_far: 100.0,
+        depth_mode: DepthMode::Standard,
+    };
+    let depth_at = |projection: &PerspectiveProjection, distance: f32| {
+        projection
+            .perspective_matrix()
+            .project_point3(m::vec3(0., 0., -distance))
+            .z
+    };
+
+    for depth_mode in [DepthMode::Standard, DepthMode::ReverseZ] {
+        projection.depth_mode = depth_mode;
+
+        assert!((depth_at(&projection, 0.1) - depth_mode.near_depth()).abs() < 1e-5);
+        assert!((depth_at(&projection, 100.0) - depth_mode.far_depth()).abs() < 1e-5);
+
+        // the compare function passes for the closer of two fragments
+        let (closer, further) = (depth_at(&projection, 10.0), depth_at(&projection, 20.0));
+        match depth_mode.compare_function() {
+            wgpu::CompareFunction::Less => assert!(closer < further),
+            wgpu::CompareFunction::Greater => assert!(closer > further),
+            _ => unreachable!(),
+        }
+
+        // culling doesn't depend on the depth mode
+        let camera = CameraLocationOrientation::new(m::vec3(0., 0., 5.), f32::to_radians(-90.), 0.);
+        let frustum = Frustum {
+            planes: projection.frustum_planes(&camera),
+        };
+        assert!(frustum.contains_sphere(m::Vec3::ZERO, 1.0));
+        assert!(!frustum.contains_sphere(m::vec3(0., 0., 10.), 1.0));
+        assert!(!frustum.contains_sphere(m::vec3(0., 0., -200.), 1.0));
+    }
+}
+
+#[test]
+fn test_split_screen_viewports() {
+    let output_size = (1280, 720);
+
+    let [lx, ly, lw, lh] = ViewportRect::LEFT_HALF.pixels(output_size);
+    let [rx, ry, rw, rh] = ViewportRect::RIGHT_HALF.pixels(output_size);
+
+    // the halves cover the output without overlapping
+    assert_eq!([lx, ly, lw, lh], [0.0, 0.0, 640.0, 720.0]);
+    assert_eq!([rx, ry, rw, rh], [640.0, 0.0, 640.0, 720.0]);
+
+    let camera = Camera {
+        location: CameraLocationOrientation::new(m::vec3(0., 0., 5.), f32::to_radians(-90.), 0.),
+        projection: PerspectiveProjection {
+            fov_y: f32::to_radians(45.0),
+            aspect: 1.0,
+            z_near: 0.1,
+            z_far: 100.0,
+            depth_mode: DepthMode::Standard,
+        },
+    };
+
+    // a square is half as wide on the half width viewport, relative to the viewport
+    let corner = m::vec3(1., 1., 0.);
+    let full = camera
+        .uniform_data(&ViewportRect::FULL, output_size)
+        .view_proj
+        .project_point3(corner);
+    let half = camera
+        .uniform_data(&ViewportRect::LEFT_HALF, output_size)
+        .view_proj
+        .project_point3(corner);
+
+    assert!((half.x - full.x * 2.0).abs() < 1e-5);
+    assert!((half.y - full.y).abs() < 1e-5);
+}
+
+#[test]
+fn test_key_bindings() {
+    use input::{Key, KeyState};
+
+    let mut controller = CameraController::new(1.0, 1.0)
+        .with_key_bindings(KeyBindings::default().bind(CameraAction::Forward, &[Key::I]));
+
+    controller.process_key_events(Key::W, KeyState::Down);
+    assert_eq!(controller.forward_amount, 0.0);
+
+    controller.process_key_events(Key::I, KeyState::Down);
+    assert_eq!(controller.forward_amount, 1.0);
+
+    // the other default bindings are kept
+    controller.process_key_events(Key::S, KeyState::Down);
+    assert_eq!(controller.backward_amount, 1.0);
+}
+
+#[test]
+fn test_camera_forward() {
+    for (yaw, pitch) in [(0.0, 0.0), (1.0, 0.5), (-2.0, -1.2), (3.0, 1.5)] {
+        let camera = CameraLocationOrientation::new(m::Vec3::ZERO, yaw, pitch);
+
+        let expected = m::vec3(
+            pitch.cos() * yaw.cos(),
+            pitch.sin(),
+            pitch.cos() * yaw.sin(),
+        )
+        .normalize();
+
+        let forward = camera.forward();
+        assert!((forward.length() - 1.0).abs() < 1e-5);
+        assert!(forward.abs_diff_eq(expected, 1e-5));
+
+        // moving forward follows the look direction, without the vertical part
+        let horizontal = m::vec3(forward.x, 0.0, forward.z).normalize();
+        assert!(camera.horizontal_forward().abs_diff_eq(horizontal, 1e-5));
+        assert!(camera.right().dot(forward).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_aspect_with_scale_factor() {
+    let mut projection = PerspectiveProjection {
+        fov_y: f32::to_radians(45.0),
+        aspect: 1.0,
+        z_near: 0.1,
+        z_far: 100.0,
+        depth_mode: DepthMode::Standard,
+    };
+
+    // an 800x600 window on a display with a scale factor of 2
+    let size = winit::dpi::LogicalSize::new(800, 600).to_physical::<u32>(2.0);
+    assert_eq!((size.width, size.height), (1600, 1200));
+
+    projection.resize((size.width, size.height));
+    assert!((projection.aspect - 4.0 / 3.0).abs() < 1e-6);
+
+    // the scale factor changing without the logical size changing keeps the aspect ratio
+    let size = winit::dpi::LogicalSize::new(800, 600).to_physical::<u32>(1.5);
+    projection.resize((size.width, size.height));
+    assert!((projection.aspect - 4.0 / 3.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_restore_camera_view() {
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: wgpu::TextureFormat::Bgra8UnormSrgb,
+        width: 800,
+        height: 600,
+        present_mode: wgpu::PresentMode::Fifo,
+    };
+    let mut camera = MainCamera::init(&config, DepthMode::Standard);
+    let saved = camera.view();
+    let view_proj = camera.uniform_data.view_proj;
+
+    // move and orbit somewhere else
+    camera.set_view(&CameraView {
+        position: m::vec3(3., -2., 1.),
+        yaw: 0.5,
+        pitch: 0.25,
+        fov_y: 1.0,
+        ..saved
+    });
+    camera.focus(m::Vec3::ZERO);
+    assert_ne!(camera.uniform_data.view_proj, view_proj);
+
+    camera.set_view(&saved);
+    assert_eq!(camera.view(), saved);
+    assert_eq!(camera.mode, CameraMode::Fly);
+    assert_eq!(camera.uniform_data.view_proj, view_proj);
+}
+
+#[test]
+fn test_set_depth_mode() {
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: wgpu::TextureFormat::Bgra8UnormSrgb,
+        width: 800,
+        height: 600,
+        present_mode: wgpu::PresentMode::Fifo,
+    };
+    let mut camera = MainCamera::init(&config, DepthMode::Standard);
+
+    // a point on the far plane, straight ahead of the camera
+    let far_point = camera.position() + camera.camera.forward() * camera.projection.z_far;
+    let far_depth = |camera: &MainCamera| camera.uniform_data.view_proj.project_point3(far_point).z;
+    assert!((far_depth(&camera) - 1.0).abs() < 1e-4);
+
+    // the view projection follows the depth mode without waiting for the next update
+    camera.set_depth_mode(DepthMode::ReverseZ);
+    assert!(far_depth(&camera).abs() < 1e-4);
+}
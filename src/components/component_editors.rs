@@ -1,5 +1,5 @@
 use super::*;
-use crate::editor::ComponentEditor;
+use crate::editor::{ComponentEditor, ComponentEditorContext, ComponentSnapshot, FieldsUi};
 
 mod entity_name {
     use super::*;
@@ -11,7 +11,12 @@ mod entity_name {
             ()
         }
 
-        fn penguin_editor(&mut self, ui: &mut Ui, _state: &mut Self::ComponentEditorState) {
+        fn penguin_editor(
+            &mut self,
+            ui: &mut Ui,
+            _state: &mut Self::ComponentEditorState,
+            _context: &ComponentEditorContext,
+        ) {
             ui.separator();
             ui.text_edit_singleline(&mut self.0);
         }
@@ -21,25 +26,14 @@ mod entity_name {
 mod translation {
     use super::*;
 
-    impl ComponentEditor for Translation {
-        type ComponentEditorState = ();
-
-        fn init_component_editor_state(&self) -> Self::ComponentEditorState {
-            ()
+    impl FieldsUi for Translation {
+        fn fields_mut(&mut self) -> Vec<(&'static str, &mut f32)> {
+            let m::Vec3 { x, y, z } = &mut self.0;
+            vec![("x", x), ("y", y), ("z", z)]
         }
 
-        fn penguin_editor(&mut self, ui: &mut Ui, _state: &mut Self::ComponentEditorState) {
-            egui::CollapsingHeader::new("Translation")
-                .default_open(true)
-                .show(ui, |ui| {
-                    ui.horizontal_wrapped(|ui| {
-                        ui.add(egui::DragValue::new(&mut self.x).speed(0.1));
-                        ui.separator();
-                        ui.add(egui::DragValue::new(&mut self.y).speed(0.1));
-                        ui.separator();
-                        ui.add(egui::DragValue::new(&mut self.z).speed(0.1));
-                    });
-                });
+        fn history_snapshot(&self) -> Option<ComponentSnapshot> {
+            Some(ComponentSnapshot::Translation(self.clone()))
         }
     }
 }
@@ -62,7 +56,12 @@ mod rotation {
             }
         }
 
-        fn penguin_editor(&mut self, ui: &mut Ui, state: &mut Self::ComponentEditorState) {
+        fn penguin_editor(
+            &mut self,
+            ui: &mut Ui,
+            state: &mut Self::ComponentEditorState,
+            _context: &ComponentEditorContext,
+        ) {
             fn drag_angle_tau(ui: &mut Ui, rads: &mut f32) {
                 use std::f32::consts::TAU;
 
@@ -122,28 +121,121 @@ mod rotation {
                 );
             }
         }
+
+        fn history_snapshot(&self) -> Option<ComponentSnapshot> {
+            Some(ComponentSnapshot::Rotation(self.clone()))
+        }
     }
 }
 
 mod scale {
     use super::*;
 
-    impl ComponentEditor for Scale {
+    impl FieldsUi for Scale {
+        fn fields_mut(&mut self) -> Vec<(&'static str, &mut f32)> {
+            let m::Vec3 { x, y, z } = &mut self.0;
+            vec![("x", x), ("y", y), ("z", z)]
+        }
+
+        fn history_snapshot(&self) -> Option<ComponentSnapshot> {
+            Some(ComponentSnapshot::Scale(self.clone()))
+        }
+    }
+}
+
+mod alpha_cutoff {
+    use super::*;
+
+    impl FieldsUi for AlphaCutoff {
+        const SPEED: f64 = 0.01;
+
+        fn fields_mut(&mut self) -> Vec<(&'static str, &mut f32)> {
+            vec![("cutoff", &mut self.0)]
+        }
+    }
+}
+
+mod color {
+    use super::*;
+
+    impl ComponentEditor for Color {
         type ComponentEditorState = ();
 
         fn init_component_editor_state(&self) -> Self::ComponentEditorState {
             ()
         }
 
-        fn penguin_editor(&mut self, ui: &mut Ui, _state: &mut Self::ComponentEditorState) {
-            egui::CollapsingHeader::new("Scale")
+        fn penguin_editor(
+            &mut self,
+            ui: &mut Ui,
+            _state: &mut Self::ComponentEditorState,
+            _context: &ComponentEditorContext,
+        ) {
+            egui::CollapsingHeader::new("Color")
                 .default_open(true)
                 .show(ui, |ui| {
-                    ui.horizontal_wrapped(|ui| {
-                        ui.add(egui::DragValue::new(&mut self.x).speed(0.1));
-                        ui.add(egui::DragValue::new(&mut self.y).speed(0.1));
-                        ui.add(egui::DragValue::new(&mut self.z).speed(0.1));
-                    });
+                    let mut rgba = self.0.to_array();
+
+                    if ui.color_edit_button_rgba_unmultiplied(&mut rgba).changed() {
+                        self.0 = m::Vec4::from(rgba);
+                    }
+                });
+        }
+    }
+}
+
+mod visible {
+    use super::*;
+
+    impl ComponentEditor for Visible {
+        type ComponentEditorState = ();
+
+        fn init_component_editor_state(&self) -> Self::ComponentEditorState {
+            ()
+        }
+
+        fn penguin_editor(
+            &mut self,
+            ui: &mut Ui,
+            _state: &mut Self::ComponentEditorState,
+            _context: &ComponentEditorContext,
+        ) {
+            ui.checkbox(&mut self.0, "Visible");
+        }
+    }
+}
+
+mod mesh {
+    use super::*;
+
+    impl ComponentEditor for MeshComponent {
+        type ComponentEditorState = ();
+
+        fn init_component_editor_state(&self) -> Self::ComponentEditorState {
+            ()
+        }
+
+        fn penguin_editor(
+            &mut self,
+            ui: &mut Ui,
+            _state: &mut Self::ComponentEditorState,
+            context: &ComponentEditorContext,
+        ) {
+            egui::CollapsingHeader::new("Mesh")
+                .default_open(true)
+                .show(ui, |ui| {
+                    let selected_mesh = context
+                        .mesh_assets
+                        .get(self.0)
+                        .map_or("None", String::as_str);
+
+                    egui::ComboBox::from_id_source("mesh component")
+                        .selected_text(selected_mesh)
+                        .show_ui(ui, |ui| {
+                            for (index, mesh_asset) in context.mesh_assets.iter().enumerate() {
+                                ui.selectable_value(&mut self.0, index, mesh_asset.as_str());
+                            }
+                        });
                 });
         }
     }
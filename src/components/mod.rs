@@ -4,6 +4,7 @@ use egui::Ui;
 use macaw as m;
 
 /// The name of an entity.
+#[derive(Default)]
 pub struct Name(pub String);
 impl From<&str> for Name {
     fn from(str: &str) -> Self {
@@ -36,7 +37,73 @@ mod transform {
     pub struct Scale(pub m::Vec3);
     impl_deref!(mut Scale, m::Vec3);
     impl_default!(Scale, Self(m::Vec3::ONE));
+
+    /// The entity's world matrix, built from its Translation, Rotation and Scale. Added to
+    /// entities with a Translation and kept up to date by the transform systems, read-only for
+    /// everything else.
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub struct WorldTransform(pub m::Mat4);
+    impl_deref!(mut WorldTransform, m::Mat4);
+    impl_default!(WorldTransform, Self(m::Mat4::IDENTITY));
+
+    impl WorldTransform {
+        pub fn new(
+            translation: &Translation,
+            rotation: Option<&Rotation>,
+            scale: Option<&Scale>,
+        ) -> Self {
+            Self(m::Mat4::from_scale_rotation_translation(
+                scale.map_or(m::Vec3::ONE, |scale| scale.0),
+                rotation.map_or(m::Quat::IDENTITY, |rotation| rotation.0),
+                translation.0,
+            ))
+        }
+
+        /// The entity's position in world space.
+        pub fn translation(&self) -> m::Vec3 {
+            self.0.w_axis.truncate()
+        }
+    }
 }
 
+/// Color that an entity's render object is tinted with.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Color(pub m::Vec4);
+penguin_util::impl_deref!(mut Color, m::Vec4);
+penguin_util::impl_default!(Color, Self(m::Vec4::ONE));
+
+/// Whether an entity's render object is drawn. Hidden entities keep their render object, they're
+/// just skipped when emitting draws.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Visible(pub bool);
+penguin_util::impl_default!(Visible, Self(true));
+
+/// A light shining in all directions from a point, fading out towards its radius.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PointLight {
+    pub position: m::Vec3,
+    pub color: m::Vec3,
+    /// Distance at which the light no longer has any effect.
+    pub radius: f32,
+}
+penguin_util::impl_default!(
+    PointLight,
+    Self {
+        position: m::Vec3::ZERO,
+        color: m::Vec3::ONE,
+        radius: 10.0,
+    }
+);
+
 type MeshAssetIndex = usize;
+#[derive(Default)]
 pub struct MeshComponent(pub MeshAssetIndex);
+
+/// Layer of the material texture array that an entity's render object samples.
+#[derive(Default)]
+pub struct MaterialComponent(pub u32);
+
+/// Texture alpha below which an entity's fragments are discarded, for cutout materials like
+/// foliage.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct AlphaCutoff(pub f32);
@@ -0,0 +1,156 @@
+use crate::graphics_context::{DebugLabels, DepthMode};
+use crate::{mesh, render_scene, texture, GraphicsContext, RenderInstance, Vertex, VertexArena};
+
+/// Whether to render the depth prepass. Toggled at runtime.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DepthPrepassSettings {
+    pub enabled: bool,
+}
+
+/// Depth-only pass that fills the depth buffer before the main pass, so that the main pass only
+/// shades the closest fragment of each pixel. Reuses the indirect draw buffers of the main pass.
+pub struct DepthPrepass {
+    pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+}
+impl DepthPrepass {
+    /// The vertex and fragment bind group layouts are shared with the main pass. The fragment
+    /// shader only samples the material textures, to discard alpha tested cutouts.
+    pub fn init(
+        device: &wgpu::Device,
+        context: &GraphicsContext,
+        vertex_bind_group_layout: &wgpu::BindGroupLayout,
+        fragment_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("depth prepass shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/depth_prepass.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("depth prepass pipeline layout"),
+            bind_group_layouts: &[vertex_bind_group_layout, fragment_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = Self::create_pipeline(context, &shader, &pipeline_layout);
+
+        Self {
+            pipeline,
+            pipeline_layout,
+            shader,
+        }
+    }
+
+    /// Creates the pipeline again for the context's depth mode, after it has changed.
+    pub fn set_depth_mode(&mut self, context: &GraphicsContext) {
+        self.pipeline = Self::create_pipeline(context, &self.shader, &self.pipeline_layout);
+    }
+
+    fn create_pipeline(
+        context: &GraphicsContext,
+        shader: &wgpu::ShaderModule,
+        pipeline_layout: &wgpu::PipelineLayout,
+    ) -> wgpu::RenderPipeline {
+        context
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("depth prepass pipeline"),
+                layout: Some(pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: "vs_main",
+                    buffers: &[
+                        mesh::MeshVertex::buffer_layout(),
+                        RenderInstance::buffer_layout(),
+                    ],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: texture::Texture::DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: context.depth_mode.compare_function(),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    // must match the sample count of the depth texture
+                    count: context.sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                // depth only, the fragment shader only discards cutouts
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: "fs_main",
+                    targets: &[],
+                }),
+                multiview: None,
+            })
+    }
+
+    /// Records the depth prepass, clearing the depth texture to the far depth of the depth mode.
+    /// The main pass should load the depth texture rather than clear it, and test with
+    /// DepthMode::prepass_compare_function, in the same viewport (x, y, width, height in pixels).
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &self,
+        cmd: &mut wgpu::CommandEncoder,
+        depth_mode: DepthMode,
+        depth_view: &wgpu::TextureView,
+        [x, y, width, height]: [f32; 4],
+        vertex_bind_group: &wgpu::BindGroup,
+        fragment_bind_group: &wgpu::BindGroup,
+        vertex_arena: &VertexArena,
+        instance_buffer: &wgpu::Buffer,
+        draw_commands_buffer: &wgpu::Buffer,
+        non_indexed_draw_commands_buffer: &wgpu::Buffer,
+        draw_count_buffer: &wgpu::Buffer,
+        max_draw_count: u32,
+        labels: &DebugLabels,
+    ) {
+        cmd.push_debug_group(&labels.depth_prepass);
+        {
+            let mut render_pass = cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(labels.depth_prepass.as_str()),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(depth_mode.far_depth()),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, vertex_bind_group, &[]);
+            render_pass.set_bind_group(1, fragment_bind_group, &[]);
+
+            render_pass.set_vertex_buffer(0, vertex_arena.vertices_slice());
+            render_pass.set_index_buffer(vertex_arena.indices_slice(), wgpu::IndexFormat::Uint32);
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+            render_scene::record_emitted_draws(
+                &mut render_pass,
+                draw_commands_buffer,
+                non_indexed_draw_commands_buffer,
+                draw_count_buffer,
+                max_draw_count,
+                labels.depth_prepass_draw_marker(),
+            );
+        }
+        cmd.pop_debug_group();
+    }
+}
@@ -1,3 +1,4 @@
+use super::history::{ComponentSnapshot, EditorHistory};
 use legion::EntityStore;
 
 mod leg {
@@ -5,6 +6,12 @@ mod leg {
     pub use legion::world::*;
 }
 
+/// Data outside of the entity's components that component editors may need.
+pub struct ComponentEditorContext<'a> {
+    /// Names of the loaded mesh assets, indexed by MeshComponent.
+    pub mesh_assets: &'a [String],
+}
+
 /// Trait to implement for components that should be editable in the editor.
 pub trait ComponentEditor
 where
@@ -13,12 +20,24 @@ where
     type ComponentEditorState: Sized + Default;
 
     fn init_component_editor_state(&self) -> Self::ComponentEditorState;
-    fn penguin_editor(&mut self, ui: &mut egui::Ui, state: &mut Self::ComponentEditorState);
+    fn penguin_editor(
+        &mut self,
+        ui: &mut egui::Ui,
+        state: &mut Self::ComponentEditorState,
+        context: &ComponentEditorContext,
+    );
+
+    /// Snapshot of the component's value, for components whose changes are recorded in the
+    /// editor history.
+    fn history_snapshot(&self) -> Option<ComponentSnapshot> {
+        None
+    }
 
     fn try_draw_editor_yeet(
         entry: &mut legion::world::EntryMut,
         ui: &mut egui::Ui,
         component_editor_state_storage: &std::cell::RefCell<ComponentEditorStateStorage>,
+        context: &ComponentEditorContext,
     ) -> bool {
         let component_type_id = leg::ComponentTypeId::of::<Self>();
 
@@ -45,31 +64,91 @@ where
             .downcast_mut::<Self::ComponentEditorState>()
             .unwrap();
 
-        component.penguin_editor(ui, &mut actual_state);
+        component.penguin_editor(ui, &mut actual_state, context);
 
         true
     }
 }
 
+/// Trait to implement for components that are edited with a DragValue per f32 field, instead of
+/// implementing ComponentEditor. Registered with EditorComponentStorage::register_auto_editor.
+pub trait FieldsUi
+where
+    Self: leg::Component,
+{
+    /// Drag speed of the fields' DragValues.
+    const SPEED: f64 = 0.1;
+
+    /// The component's fields, labelled with their names.
+    fn fields_mut(&mut self) -> Vec<(&'static str, &mut f32)>;
+
+    /// Same as ComponentEditor::history_snapshot.
+    fn history_snapshot(&self) -> Option<ComponentSnapshot> {
+        None
+    }
+}
+
+/// Name of the component type without its path, shown in the UI.
+fn component_name<T>() -> &'static str {
+    let type_name = std::any::type_name::<T>();
+    type_name.rsplit("::").next().unwrap_or(type_name)
+}
+
 use draw_function::*;
 mod draw_function {
     use super::*;
 
-    /// Function that draws the editor ui for a component.
+    /// Function that draws the editor ui for a component, and functions to add or remove the
+    /// component.
     pub(super) struct DrawComponentEditorFunc {
         component_type_id: leg::ComponentTypeId,
+        /// Name of the component type, shown in the UI.
+        pub name: &'static str,
         pub draw_func: fn(
             &mut legion::world::EntryMut,
             &mut egui::Ui,
             &std::cell::RefCell<ComponentEditorStateStorage>,
+            &ComponentEditorContext,
         ),
+        /// Takes a snapshot of the component for the editor history.
+        pub snapshot_func: fn(&legion::world::EntryMut) -> Option<ComponentSnapshot>,
+        /// Adds a default constructed component to an entity.
+        pub add_default_func: fn(&mut legion::systems::CommandBuffer, legion::Entity),
+        /// Removes the component from an entity.
+        pub remove_func: fn(&mut legion::systems::CommandBuffer, legion::Entity),
     }
 
     impl DrawComponentEditorFunc {
-        pub fn new<ComponentType: ComponentEditor>() -> Self {
+        pub fn new<ComponentType: ComponentEditor + Default>() -> Self {
             Self {
                 component_type_id: leg::ComponentTypeId::of::<ComponentType>(),
+                name: component_name::<ComponentType>(),
                 draw_func: Self::draw_editor::<ComponentType>,
+                snapshot_func: |entry| {
+                    entry
+                        .get_component::<ComponentType>()
+                        .ok()
+                        .and_then(ComponentType::history_snapshot)
+                },
+                add_default_func: |cmd, entity| cmd.add_component(entity, ComponentType::default()),
+                remove_func: |cmd, entity| cmd.remove_component::<ComponentType>(entity),
+            }
+        }
+
+        /// Draw function for a component that's edited through FieldsUi.
+        pub fn new_auto<ComponentType: FieldsUi + Default>() -> Self {
+            Self {
+                component_type_id: leg::ComponentTypeId::of::<ComponentType>(),
+                name: component_name::<ComponentType>(),
+                draw_func: Self::draw_auto_editor::<ComponentType>,
+                snapshot_func: |entry| {
+                    entry
+                        .get_component::<ComponentType>()
+                        .ok()
+                        .and_then(ComponentType::history_snapshot)
+                },
+                add_default_func: |cmd, entity| cmd.add_component(entity, ComponentType::default()),
+                remove_func: |cmd, entity| cmd.remove_component::<ComponentType>(entity),
             }
         }
 
@@ -82,6 +161,7 @@ mod draw_function {
             entry: &mut legion::world::EntryMut,
             ui: &mut egui::Ui,
             component_editor_state_storage: &std::cell::RefCell<ComponentEditorStateStorage>,
+            context: &ComponentEditorContext,
         ) where
             T: legion::storage::Component + ComponentEditor,
         {
@@ -108,7 +188,28 @@ mod draw_function {
 
             let mut actual_state = state.0.downcast_mut::<T::ComponentEditorState>().unwrap();
 
-            component.penguin_editor(ui, &mut actual_state);
+            component.penguin_editor(ui, &mut actual_state, context);
+        }
+
+        /// FieldsUi components don't have any editor state.
+        fn draw_auto_editor<T: FieldsUi>(
+            entry: &mut legion::world::EntryMut,
+            ui: &mut egui::Ui,
+            _component_editor_state_storage: &std::cell::RefCell<ComponentEditorStateStorage>,
+            _context: &ComponentEditorContext,
+        ) {
+            let component: &mut T = entry.get_component_mut::<T>().unwrap();
+
+            egui::CollapsingHeader::new(component_name::<T>())
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        for (name, value) in component.fields_mut() {
+                            ui.label(name);
+                            ui.add(egui::DragValue::new(value).speed(T::SPEED));
+                        }
+                    });
+                });
         }
     }
 }
@@ -140,12 +241,22 @@ mod component_editor_state {
     impl EditorComponentStorage {
         pub fn register_component_editor<ComponentType>(&mut self)
         where
-            ComponentType: ComponentEditor,
+            ComponentType: ComponentEditor + Default,
         {
             self.draw_funcs
                 .push(DrawComponentEditorFunc::new::<ComponentType>());
         }
 
+        /// Registers an editor for a component with a DragValue for each of the fields returned by
+        /// FieldsUi::fields_mut.
+        pub fn register_auto_editor<ComponentType>(&mut self)
+        where
+            ComponentType: FieldsUi + Default,
+        {
+            self.draw_funcs
+                .push(DrawComponentEditorFunc::new_auto::<ComponentType>());
+        }
+
         pub fn select_entity(&self, entity: legion::Entity) {
             if let Some(selected_entity) = self.selected_entity.get() {
                 // if still the same selected entity, don't change anything
@@ -161,13 +272,32 @@ mod component_editor_state {
             }
         }
 
-        /// Draw the components of a given entity in the UI, and provides mutable access to these components
+        /// Clears the components' editor states, so that they're reinitialized from the
+        /// components' values, e.g. after an undo.
+        pub fn reset_component_states(&self) {
+            self.ui_states.borrow_mut().0.clear();
+        }
+
+        /// Clears the selected entity and its components' states, e.g. when the entity is deleted.
+        pub fn deselect_entity(&self) {
+            self.ui_states.borrow_mut().0.clear();
+            self.selected_entity.set(None);
+        }
+
+        /// Draw the components of a given entity in the UI, and provides mutable access to these components.
+        /// Also draws the UI for adding and removing registered components.
         pub fn draw_entities_component_editors(
             &self,
             world: &mut legion::World,
             entity: legion::Entity,
             ui: &mut egui::Ui,
+            context: &ComponentEditorContext,
+            history: &mut EditorHistory,
         ) {
+            // adding or removing components moves the entity to another archetype, which can't be
+            // done while the entry is borrowed, so the changes are recorded and applied afterwards
+            let mut cmd = legion::systems::CommandBuffer::new(world);
+
             if let Ok(mut e) = world.entry_mut(entity) {
                 // get around the borrow checker
                 let component_type_ids = e
@@ -178,12 +308,31 @@ mod component_editor_state {
                     .map(|ty| *ty)
                     .collect::<Vec<_>>();
 
-                for component_type_id in component_type_ids {
+                for &component_type_id in &component_type_ids {
                     // find the draw function for this component (if any) and execute it
                     self.draw_funcs.iter().find(|draw_func| {
                         if draw_func.is_for_component(component_type_id) {
+                            ui.horizontal(|ui| {
+                                ui.label(draw_func.name);
+
+                                if ui.small_button("x").on_hover_text("Remove").clicked() {
+                                    (draw_func.remove_func)(&mut cmd, entity);
+                                }
+                            });
+
+                            let before = (draw_func.snapshot_func)(&e);
+
                             let func = draw_func.draw_func;
-                            func(&mut e, ui, &self.ui_states);
+                            func(&mut e, ui, &self.ui_states, context);
+
+                            // record edits in the history
+                            if let Some(before) = before {
+                                if let Some(after) = (draw_func.snapshot_func)(&e) {
+                                    if before != after {
+                                        history.record_change(entity, before, after);
+                                    }
+                                }
+                            }
 
                             true
                         } else {
@@ -191,7 +340,29 @@ mod component_editor_state {
                         }
                     });
                 }
+
+                ui.separator();
+
+                // registered components the entity doesn't have yet
+                let missing_components = self.draw_funcs.iter().filter(|draw_func| {
+                    !component_type_ids
+                        .iter()
+                        .any(|&component_type_id| draw_func.is_for_component(component_type_id))
+                });
+
+                egui::ComboBox::from_id_source("add component")
+                    .selected_text("Add component")
+                    .show_ui(ui, |ui| {
+                        for draw_func in missing_components {
+                            if ui.selectable_label(false, draw_func.name).clicked() {
+                                (draw_func.add_default_func)(&mut cmd, entity);
+                            }
+                        }
+                    });
             }
+
+            // the commands only add and remove components, so no resources are needed
+            cmd.flush(world, &mut legion::Resources::default());
         }
     }
 }
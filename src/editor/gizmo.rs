@@ -0,0 +1,207 @@
+use super::FrameData;
+use crate::components;
+use macaw as m;
+
+/// Length of the gizmo's axes, relative to the distance from the camera. Keeps the gizmo at a
+/// constant size on screen.
+const AXIS_LENGTH_SCALE: f32 = 0.15;
+
+/// Max distance in points from an axis for the pointer to hit it.
+const AXIS_HIT_DISTANCE: f32 = 6.0;
+
+const AXES: [(m::Vec3, egui::Color32); 3] = [
+    (m::Vec3::X, egui::Color32::from_rgb(230, 60, 60)),
+    (m::Vec3::Y, egui::Color32::from_rgb(60, 200, 60)),
+    (m::Vec3::Z, egui::Color32::from_rgb(60, 110, 240)),
+];
+
+/// Screen-space gizmo for translating the selected entity along one of its axes.
+#[derive(Default)]
+pub struct TranslateGizmo {
+    /// Index into AXES of the axis currently being dragged.
+    dragged_axis: Option<usize>,
+}
+
+/// An axis of the gizmo, projected onto the screen.
+struct ScreenAxis {
+    from: egui::Pos2,
+    to: egui::Pos2,
+    /// Length of the axis in world space.
+    world_length: f32,
+}
+
+impl TranslateGizmo {
+    pub fn is_dragging(&self) -> bool {
+        self.dragged_axis.is_some()
+    }
+
+    pub fn update(
+        &mut self,
+        context: &egui::CtxRef,
+        frame_data: &mut FrameData,
+        entity: legion::Entity,
+    ) {
+        // the gizmo follows the entity's world transform, dragging it moves the Translation
+        let translation = match frame_data.l_world.entry_ref(entity).ok().and_then(|entry| {
+            entry
+                .get_component::<components::WorldTransform>()
+                .ok()
+                .map(|world_transform| world_transform.translation())
+        }) {
+            Some(translation) => translation,
+            None => {
+                self.dragged_axis = None;
+                return;
+            }
+        };
+
+        let screen_rect = context.input().screen_rect();
+
+        let screen_axes = match Self::screen_axes(
+            translation,
+            frame_data.camera.uniform_data.view_proj,
+            screen_rect,
+        ) {
+            Some(screen_axes) => screen_axes,
+            // behind the camera
+            None => {
+                self.dragged_axis = None;
+                return;
+            }
+        };
+
+        // input
+        {
+            // don't start dragging when clicking on a panel
+            let is_pointer_over_area = context.is_pointer_over_area();
+
+            let input = context.input();
+            let pointer = &input.pointer;
+
+            if pointer.primary_pressed() && !is_pointer_over_area {
+                self.dragged_axis = pointer
+                    .interact_pos()
+                    .and_then(|pos| Self::hit_axis(&screen_axes, pos));
+            } else if !pointer.primary_down() {
+                self.dragged_axis = None;
+            }
+
+            if let Some(axis) = self.dragged_axis {
+                let screen_axis = &screen_axes[axis];
+                let screen_vec = screen_axis.to - screen_axis.from;
+                let screen_length = screen_vec.length();
+
+                if screen_length > f32::EPSILON {
+                    // move along the axis by the pointer movement projected onto the screen axis
+                    let screen_delta = pointer.delta().dot(screen_vec / screen_length);
+                    let world_delta = screen_delta / screen_length * screen_axis.world_length;
+
+                    if let Ok(mut entry) = frame_data.l_world.entry_mut(entity) {
+                        if let Ok(translation) =
+                            entry.get_component_mut::<components::Translation>()
+                        {
+                            translation.0 += AXES[axis].0 * world_delta;
+                        }
+                    }
+                }
+            }
+        }
+
+        // draw
+        let painter = context.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("translate gizmo"),
+        ));
+
+        for (index, screen_axis) in screen_axes.iter().enumerate() {
+            let (_, color) = AXES[index];
+
+            let width = if self.dragged_axis == Some(index) {
+                4.0
+            } else {
+                2.0
+            };
+
+            painter.arrow(
+                screen_axis.from,
+                screen_axis.to - screen_axis.from,
+                egui::Stroke::new(width, color),
+            );
+        }
+    }
+
+    /// Projects the gizmo's axes at the given world position onto the screen. Returns None if the
+    /// position is behind the camera.
+    fn screen_axes(
+        position: m::Vec3,
+        view_proj: m::Mat4,
+        screen_rect: egui::Rect,
+    ) -> Option<[ScreenAxis; 3]> {
+        let clip_w = (view_proj * position.extend(1.0)).w;
+        if clip_w <= 0.0 {
+            return None;
+        }
+
+        let world_length = AXIS_LENGTH_SCALE * clip_w;
+
+        let from = world_to_screen(position, view_proj, screen_rect)?;
+
+        let mut screen_axes = AXES.map(|_| ScreenAxis {
+            from,
+            to: from,
+            world_length,
+        });
+
+        for (screen_axis, (axis, _)) in screen_axes.iter_mut().zip(AXES) {
+            screen_axis.to =
+                world_to_screen(position + axis * world_length, view_proj, screen_rect)?;
+        }
+
+        Some(screen_axes)
+    }
+
+    /// Returns the index of the axis closest to the pointer, if any is close enough.
+    fn hit_axis(screen_axes: &[ScreenAxis; 3], pointer_pos: egui::Pos2) -> Option<usize> {
+        screen_axes
+            .iter()
+            .enumerate()
+            .map(|(index, screen_axis)| {
+                let distance = distance_to_segment(pointer_pos, screen_axis.from, screen_axis.to);
+                (index, distance)
+            })
+            .filter(|(_, distance)| *distance <= AXIS_HIT_DISTANCE)
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(index, _)| index)
+    }
+}
+
+/// Projects a world position to a position on the screen, in points.
+fn world_to_screen(
+    position: m::Vec3,
+    view_proj: m::Mat4,
+    screen_rect: egui::Rect,
+) -> Option<egui::Pos2> {
+    let clip = view_proj * position.extend(1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+
+    let ndc = clip.truncate() / clip.w;
+
+    Some(egui::pos2(
+        screen_rect.left() + (ndc.x + 1.0) * 0.5 * screen_rect.width(),
+        screen_rect.top() + (1.0 - ndc.y) * 0.5 * screen_rect.height(),
+    ))
+}
+
+fn distance_to_segment(point: egui::Pos2, from: egui::Pos2, to: egui::Pos2) -> f32 {
+    let segment = to - from;
+    let length_sq = segment.length_sq();
+
+    if length_sq <= f32::EPSILON {
+        return point.distance(from);
+    }
+
+    let t = ((point - from).dot(segment) / length_sq).clamp(0.0, 1.0);
+    point.distance(from + segment * t)
+}
@@ -0,0 +1,129 @@
+use crate::components::{Rotation, Scale, Translation};
+
+/// Value of a component at some point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComponentSnapshot {
+    Translation(Translation),
+    Rotation(Rotation),
+    Scale(Scale),
+}
+
+impl ComponentSnapshot {
+    /// Whether the snapshots are of the same component type.
+    fn is_same_component(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// Sets the entity's component to this snapshot's value.
+    fn apply(&self, world: &mut legion::World, entity: legion::Entity) {
+        let mut entry = match world.entry_mut(entity) {
+            Ok(entry) => entry,
+            // the entity has been deleted since
+            Err(_) => return,
+        };
+
+        match self {
+            Self::Translation(value) => {
+                if let Ok(component) = entry.get_component_mut::<Translation>() {
+                    *component = value.clone();
+                }
+            }
+            Self::Rotation(value) => {
+                if let Ok(component) = entry.get_component_mut::<Rotation>() {
+                    *component = value.clone();
+                }
+            }
+            Self::Scale(value) => {
+                if let Ok(component) = entry.get_component_mut::<Scale>() {
+                    *component = value.clone();
+                }
+            }
+        }
+    }
+}
+
+/// A change to a component made in the editor.
+struct ComponentChange {
+    entity: legion::Entity,
+    before: ComponentSnapshot,
+    after: ComponentSnapshot,
+}
+
+/// Undo and redo stacks of component changes made in the editor.
+#[derive(Default)]
+pub struct EditorHistory {
+    undo_stack: Vec<ComponentChange>,
+    redo_stack: Vec<ComponentChange>,
+    /// Change that is still being made, e.g. while a DragValue is being dragged.
+    pending: Option<ComponentChange>,
+}
+
+impl EditorHistory {
+    /// Records a change to a component. Consecutive changes to the same component are merged
+    /// until the change is committed.
+    pub fn record_change(
+        &mut self,
+        entity: legion::Entity,
+        before: ComponentSnapshot,
+        after: ComponentSnapshot,
+    ) {
+        if let Some(pending) = &mut self.pending {
+            if pending.entity == entity && pending.before.is_same_component(&after) {
+                pending.after = after;
+                return;
+            }
+        }
+
+        self.commit();
+        self.pending = Some(ComponentChange {
+            entity,
+            before,
+            after,
+        });
+    }
+
+    /// Pushes the pending change to the undo stack once the edit has completed, i.e. when the
+    /// pointer is no longer held down.
+    pub fn update(&mut self, is_pointer_down: bool) {
+        if !is_pointer_down {
+            self.commit();
+        }
+    }
+
+    fn commit(&mut self) {
+        if let Some(change) = self.pending.take() {
+            if change.before != change.after {
+                self.undo_stack.push(change);
+                self.redo_stack.clear();
+            }
+        }
+    }
+
+    /// Reverts the latest change. Returns true if there was a change to undo.
+    pub fn undo(&mut self, world: &mut legion::World) -> bool {
+        self.commit();
+
+        match self.undo_stack.pop() {
+            Some(change) => {
+                change.before.apply(world, change.entity);
+                self.redo_stack.push(change);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reapplies the latest undone change. Returns true if there was a change to redo.
+    pub fn redo(&mut self, world: &mut legion::World) -> bool {
+        self.commit();
+
+        match self.redo_stack.pop() {
+            Some(change) => {
+                change.after.apply(world, change.entity);
+                self.undo_stack.push(change);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -1,20 +1,51 @@
 mod component_editor;
+mod gizmo;
+mod history;
+mod picking;
 mod scene;
 mod stats;
 
-use crate::{events, input, time, GraphicsContext};
+use crate::bounds_debug::BoundsDebugSettings;
+use crate::depth_prepass::DepthPrepassSettings;
+use crate::graphics_context::{ClearColor, DebugLabels, DepthMode};
+use crate::grid::GridSettings;
+use crate::{camera, components, events, input, layer, render_scene, time, GraphicsContext};
+use penguin_util::handle::Handle;
 
 mod leg {
     pub use legion::storage::*;
     pub use legion::world::*;
 }
-pub use component_editor::{ComponentEditor, EditorComponentStorage};
+pub use component_editor::{
+    ComponentEditor, ComponentEditorContext, EditorComponentStorage, FieldsUi,
+};
+pub use history::{ComponentSnapshot, EditorHistory};
 
 /// Data that the UI needs every frame
 pub struct FrameData<'a> {
     pub clock: &'a time::Clock,
+    pub time_scale: &'a mut time::TimeScale,
     pub l_world: &'a mut legion::world::World,
     pub ui_storage: &'a component_editor::EditorComponentStorage,
+    /// Undo/redo history of component edits.
+    pub history: &'a mut history::EditorHistory,
+    pub render_stats: &'a render_scene::RenderStats,
+    /// Used to register and remove the render objects of entities added or deleted in the editor.
+    pub scene: &'a mut render_scene::RenderScene,
+    /// Names of the meshes loaded in the scene, indexed by MeshComponent. Meshes loaded from the
+    /// editor are added to it.
+    pub mesh_assets: &'a mut Vec<String>,
+    /// The main camera, used to draw gizmos in the viewport and to focus the selected entity.
+    pub camera: &'a mut camera::MainCamera,
+    /// Background color of the viewport.
+    pub clear_color: &'a mut ClearColor,
+    pub grid_settings: &'a mut GridSettings,
+    pub bounds_debug_settings: &'a mut BoundsDebugSettings,
+    pub depth_prepass_settings: &'a mut DepthPrepassSettings,
+    /// Labels of the passes in graphics debuggers, editable in the stats panel.
+    pub debug_labels: &'a mut DebugLabels,
+    /// When the event loop draws frames, switchable in the top bar.
+    pub redraw: &'a mut layer::Redraw,
 }
 
 /// Contains the necessary data for rendering and managing the editor and it's UI.
@@ -27,6 +58,8 @@ pub struct EditorState {
     // ----------
     panels: Panels,
     is_consuming_input: bool,
+    /// Render object tinted in the viewport as the selected entity's.
+    highlighted_render_object: Option<Handle<render_scene::RenderObject>>,
 }
 
 /// Contains all UI panels
@@ -34,6 +67,7 @@ pub struct EditorState {
 struct Panels {
     stats: stats::StatsPanel,
     scene: scene::ScenePanel,
+    translate_gizmo: gizmo::TranslateGizmo,
 }
 
 impl EditorState {
@@ -69,6 +103,7 @@ impl EditorState {
             screen_descriptor,
             panels: Panels::default(),
             is_consuming_input: false,
+            highlighted_render_object: None,
         }
     }
 
@@ -85,6 +120,10 @@ impl EditorState {
         if self.platform.context().is_pointer_over_area() {
             self.is_consuming_input = true;
         }
+
+        if self.panels.translate_gizmo.is_dragging() {
+            self.is_consuming_input = true;
+        }
     }
 
     /// Called on a PenguinEvent
@@ -107,6 +146,12 @@ impl EditorState {
             PenguinEvent::Input(input::InputEvent::Key(input::KeyEvent { .. })) => {
                 self.is_consuming_input
             }
+            // don't zoom the camera when scrolling in a panel
+            PenguinEvent::Input(input::InputEvent::MouseScroll(_)) => self.is_consuming_input,
+            // don't rotate the camera while dragging a gizmo
+            PenguinEvent::Input(input::InputEvent::MouseMotion(_)) => {
+                self.panels.translate_gizmo.is_dragging()
+            }
             _ => false,
         }
     }
@@ -122,9 +167,13 @@ impl EditorState {
             .update_time(frame_data.clock.start_time.elapsed().as_secs_f64());
         self.platform.begin_frame();
 
-        self.draw_ui(&self.platform.context(), frame_data);
+        self.draw_ui(&self.platform.context(), context, frame_data);
 
-        let (_output, paint_commands) = self.platform.end_frame(Some(window));
+        let (output, paint_commands) = self.platform.end_frame(Some(window));
+        // e.g. a hover animation
+        if output.needs_repaint {
+            frame_data.redraw.request();
+        }
         self.paint_jobs = self.platform.context().tessellate(paint_commands);
 
         {
@@ -175,19 +224,165 @@ impl EditorState {
 }
 
 impl EditorState {
-    fn draw_ui(&mut self, context: &egui::CtxRef, frame_data: &mut FrameData) {
-        Self::top_bar(context, &mut self.panels);
+    fn draw_ui(
+        &mut self,
+        context: &egui::CtxRef,
+        graphics_context: &GraphicsContext,
+        frame_data: &mut FrameData,
+    ) {
+        Self::undo_redo(context, frame_data);
+
+        Self::top_bar(context, &mut self.panels, frame_data);
 
         if self.panels.stats.enabled {
-            self.panels.stats.update(context, frame_data);
+            self.panels
+                .stats
+                .update(context, graphics_context, frame_data);
         }
 
         if self.panels.scene.enabled {
-            self.panels.scene.update(context, frame_data);
+            self.panels
+                .scene
+                .update(context, graphics_context, frame_data);
+        }
+
+        if let Some(entity) = self.panels.scene.selected_entity() {
+            self.panels
+                .translate_gizmo
+                .update(context, frame_data, entity);
         }
+
+        self.select_clicked_entity(context, frame_data);
+
+        Self::focus_selected_entity(context, frame_data, self.panels.scene.selected_entity());
+
+        self.highlight_selected_entity(frame_data);
+
+        // edits made this frame are committed to the history once the pointer is released
+        let is_pointer_down = context.input().pointer.any_down();
+        frame_data.history.update(is_pointer_down);
     }
 
-    fn top_bar(context: &egui::CtxRef, panels: &mut Panels) {
+    /// Left clicking in the viewport selects the entity under the pointer.
+    fn select_clicked_entity(&mut self, context: &egui::CtxRef, frame_data: &mut FrameData) {
+        // clicks on panels and gizmos are handled by them
+        if context.is_pointer_over_area() || self.panels.translate_gizmo.is_dragging() {
+            return;
+        }
+
+        let (pointer_pos, screen_rect) = {
+            let input = context.input();
+
+            if !input.pointer.primary_clicked() {
+                return;
+            }
+
+            match input.pointer.interact_pos() {
+                Some(pos) => (pos, input.screen_rect()),
+                None => return,
+            }
+        };
+
+        if let Some(entity) = picking::pick_entity(pointer_pos, screen_rect, frame_data) {
+            self.panels.scene.select_entity(entity, frame_data);
+        }
+    }
+
+    /// Tints the render object of the selected entity, and untints the previously selected one.
+    fn highlight_selected_entity(&mut self, frame_data: &mut FrameData) {
+        let render_object = self
+            .panels
+            .scene
+            .selected_entity()
+            .and_then(|entity| frame_data.l_world.entry_ref(entity).ok())
+            .and_then(|entry| {
+                entry
+                    .get_component::<Handle<render_scene::RenderObject>>()
+                    .ok()
+                    .copied()
+            });
+
+        let id = |render_object: Option<Handle<_>>| render_object.map(|handle| handle.id);
+        if id(render_object) == id(self.highlighted_render_object) {
+            return;
+        }
+
+        if let Some(previous) = self.highlighted_render_object {
+            frame_data.scene.objects.set_selected(previous, false);
+        }
+        if let Some(render_object) = render_object {
+            frame_data.scene.objects.set_selected(render_object, true);
+        }
+
+        self.highlighted_render_object = render_object;
+    }
+
+    /// F focuses the camera on the selected entity, switching it to orbit mode.
+    fn focus_selected_entity(
+        context: &egui::CtxRef,
+        frame_data: &mut FrameData,
+        selected_entity: Option<legion::Entity>,
+    ) {
+        if context.wants_keyboard_input() || !context.input().key_pressed(egui::Key::F) {
+            return;
+        }
+
+        let translation = selected_entity
+            .and_then(|entity| frame_data.l_world.entry_ref(entity).ok())
+            .and_then(|entry| {
+                entry
+                    .get_component::<components::Translation>()
+                    .ok()
+                    .map(|translation| translation.0)
+            });
+
+        if let Some(translation) = translation {
+            frame_data.camera.focus(translation);
+        }
+    }
+
+    /// Ctrl+Z undoes the latest component edit, Ctrl+Y redoes it.
+    fn undo_redo(context: &egui::CtxRef, frame_data: &mut FrameData) {
+        let (undo, redo) = {
+            let input = context.input();
+            let command = input.modifiers.command;
+
+            (
+                command && input.key_pressed(egui::Key::Z),
+                command && input.key_pressed(egui::Key::Y),
+            )
+        };
+
+        // let text fields handle their own undo
+        if context.wants_keyboard_input() {
+            return;
+        }
+
+        let changed = (undo && frame_data.history.undo(frame_data.l_world))
+            || (redo && frame_data.history.redo(frame_data.l_world));
+
+        if changed {
+            frame_data.ui_storage.reset_component_states();
+        }
+    }
+
+    /// Color picker for the background color, which takes effect on the next frame.
+    fn clear_color_picker(ui: &mut egui::Ui, clear_color: &mut ClearColor) {
+        let color = clear_color.0;
+        let mut rgb = [color.r as f32, color.g as f32, color.b as f32];
+
+        ui.label("Background");
+        if ui.color_edit_button_rgb(&mut rgb).changed() {
+            clear_color.0 = wgpu::Color {
+                r: rgb[0] as f64,
+                g: rgb[1] as f64,
+                b: rgb[2] as f64,
+                a: color.a,
+            };
+        }
+    }
+
+    fn top_bar(context: &egui::CtxRef, panels: &mut Panels, frame_data: &mut FrameData) {
         egui::TopBottomPanel::top("top menu").show(context, |ui| {
             egui::trace!(ui);
 
@@ -210,6 +405,64 @@ impl EditorState {
                 ui.checkbox(&mut panels.stats.enabled, "💻 Stats");
 
                 ui.checkbox(&mut panels.scene.enabled, "Scene");
+
+                ui.separator();
+
+                Self::clear_color_picker(ui, frame_data.clear_color);
+
+                ui.checkbox(&mut frame_data.grid_settings.enabled, "Grid");
+                ui.checkbox(&mut frame_data.bounds_debug_settings.enabled, "Show bounds");
+                ui.checkbox(
+                    &mut frame_data.depth_prepass_settings.enabled,
+                    "Depth prepass",
+                )
+                .on_hover_text("Fills the depth buffer first, so each pixel is shaded once.");
+
+                let mut reverse_z = frame_data.camera.projection.depth_mode == DepthMode::ReverseZ;
+                if ui
+                    .checkbox(&mut reverse_z, "Reverse-Z")
+                    .on_hover_text("Maps the far plane to depth 0, reducing z-fighting far away.")
+                    .changed()
+                {
+                    frame_data.camera.set_depth_mode(if reverse_z {
+                        DepthMode::ReverseZ
+                    } else {
+                        DepthMode::Standard
+                    });
+                }
+
+                let mut reactive = frame_data.redraw.mode == layer::RedrawMode::Reactive;
+                if ui
+                    .checkbox(&mut reactive, "Reactive")
+                    .on_hover_text("Only redraw on input. Pauses the scene animation.")
+                    .changed()
+                {
+                    frame_data.redraw.mode = if reactive {
+                        layer::RedrawMode::Reactive
+                    } else {
+                        layer::RedrawMode::Continuous
+                    };
+                }
+
+                ui.separator();
+
+                if ui.button("💾 Save").clicked() {
+                    panels.scene.save_scene(frame_data);
+                }
+
+                if ui.button("📂 Load").clicked() {
+                    panels.scene.load_scene(frame_data);
+                }
+
+                ui.separator();
+
+                if ui.button("📷 Copy camera").clicked() {
+                    panels.scene.copy_camera(ui, frame_data);
+                }
+
+                if ui.button("📷 Paste camera").clicked() {
+                    panels.scene.paste_camera(frame_data);
+                }
             });
         });
     }
@@ -0,0 +1,52 @@
+use super::FrameData;
+use crate::camera::Ray;
+use crate::graphics_context::DepthMode;
+use crate::render_scene;
+use legion::IntoQuery;
+use macaw as m;
+use penguin_util::handle::Handle;
+
+/// Builds a ray from the camera through a position on the screen, in points.
+fn ray_from_screen(
+    pos: egui::Pos2,
+    view_proj: m::Mat4,
+    depth_mode: DepthMode,
+    screen_rect: egui::Rect,
+) -> Option<Ray> {
+    let ndc_x = (pos.x - screen_rect.left()) / screen_rect.width() * 2.0 - 1.0;
+    let ndc_y = 1.0 - (pos.y - screen_rect.top()) / screen_rect.height() * 2.0;
+
+    Ray::from_ndc(m::vec2(ndc_x, ndc_y), view_proj, depth_mode)
+}
+
+/// Returns the entity whose render object's bounds are hit first by a ray from the camera through
+/// the pointer position. Hidden entities can't be picked.
+pub fn pick_entity(
+    pointer_pos: egui::Pos2,
+    screen_rect: egui::Rect,
+    frame_data: &FrameData,
+) -> Option<legion::Entity> {
+    let ray = ray_from_screen(
+        pointer_pos,
+        frame_data.camera.uniform_data.view_proj,
+        frame_data.camera.projection.depth_mode,
+        screen_rect,
+    )?;
+
+    let mut query = <(legion::Entity, &Handle<render_scene::RenderObject>)>::query();
+
+    query
+        .iter(frame_data.l_world)
+        .filter_map(|(entity, render_object)| {
+            if frame_data.scene.objects.render_objects[*render_object].visible == 0 {
+                return None;
+            }
+
+            let bounds = frame_data.scene.objects.world_render_bounds(*render_object);
+
+            ray.intersect_sphere(bounds.origin, bounds.radius)
+                .map(|distance| (*entity, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(entity, _)| entity)
+}
@@ -1,14 +1,40 @@
-use super::FrameData;
-use crate::components;
+use super::{ComponentEditorContext, FrameData};
+use crate::{components, mesh, render_scene, scene_file, GraphicsContext};
 use legion::IntoQuery;
+use macaw as m;
+use penguin_util::handle::Handle;
+
+/// File the scene is saved to and loaded from.
+const SCENE_FILE_PATH: &str = "scene.ron";
+const CAMERA_FILE_PATH: &str = "camera.ron";
 
 #[derive(Default)]
 pub struct ScenePanel {
     pub enabled: bool,
     l_selected_entity: Option<legion::Entity>,
+    /// Index of the mesh asset that new entities are created with.
+    new_entity_mesh: usize,
+    /// Name of the obj file in assets/meshes to load with the load mesh button.
+    mesh_file_name: String,
+    /// Number of entities added from the editor, used to give them unique names.
+    added_entity_count: usize,
 }
 impl ScenePanel {
-    pub fn update(&mut self, context: &egui::CtxRef, frame_data: &mut FrameData) {
+    pub fn selected_entity(&self) -> Option<legion::Entity> {
+        self.l_selected_entity
+    }
+
+    pub fn select_entity(&mut self, entity: legion::Entity, frame_data: &FrameData) {
+        self.l_selected_entity = Some(entity);
+        frame_data.ui_storage.select_entity(entity);
+    }
+
+    pub fn update(
+        &mut self,
+        context: &egui::CtxRef,
+        graphics_context: &GraphicsContext,
+        frame_data: &mut FrameData,
+    ) {
         egui::SidePanel::right("scene panel")
             .default_width(250.)
             .show(context, |ui| {
@@ -17,6 +43,11 @@ impl ScenePanel {
                     ui.separator();
                 });
 
+                self.add_entity_ui(ui, frame_data);
+                self.load_mesh_ui(ui, graphics_context, frame_data);
+
+                ui.separator();
+
                 let mut query = <(legion::Entity, &components::Name)>::query();
 
                 for (ent, name) in query.iter(frame_data.l_world) {
@@ -31,14 +62,207 @@ impl ScenePanel {
 
                 // draw entity ui if an entity is selected
                 if let Some(e) = self.l_selected_entity {
-                    frame_data.ui_storage.draw_entities_component_editors(
-                        &mut frame_data.l_world,
-                        e,
-                        ui,
-                    );
+                    ui.separator();
+
+                    if ui.button("🗑 Delete").clicked() {
+                        self.delete_entity(e, frame_data);
+                    } else {
+                        frame_data.ui_storage.draw_entities_component_editors(
+                            &mut frame_data.l_world,
+                            e,
+                            ui,
+                            &ComponentEditorContext {
+                                mesh_assets: frame_data.mesh_assets.as_slice(),
+                            },
+                            frame_data.history,
+                        );
+                    }
                 }
 
                 ui.separator();
             });
     }
+
+    fn add_entity_ui(&mut self, ui: &mut egui::Ui, frame_data: &mut FrameData) {
+        let mesh_assets = frame_data.mesh_assets.as_slice();
+        let new_entity_mesh = &mut self.new_entity_mesh;
+
+        let add_clicked = ui
+            .horizontal_wrapped(|ui| {
+                egui::ComboBox::from_id_source("new entity mesh")
+                    .selected_text(mesh_assets[*new_entity_mesh].as_str())
+                    .show_ui(ui, |ui| {
+                        for (index, mesh_asset) in mesh_assets.iter().enumerate() {
+                            ui.selectable_value(new_entity_mesh, index, mesh_asset.as_str());
+                        }
+                    });
+
+                ui.button("➕ Add entity").clicked()
+            })
+            .inner;
+
+        if add_clicked {
+            self.add_entity(frame_data);
+        }
+    }
+
+    /// Loads an obj file from assets/meshes into the scene, and adds an entity with it.
+    fn load_mesh_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        graphics_context: &GraphicsContext,
+        frame_data: &mut FrameData,
+    ) {
+        let mesh_file_name = &mut self.mesh_file_name;
+
+        let load_clicked = ui
+            .horizontal_wrapped(|ui| {
+                ui.add(egui::TextEdit::singleline(mesh_file_name).hint_text("mesh.obj"));
+
+                ui.button("📂 Load mesh").clicked()
+            })
+            .inner;
+
+        if load_clicked {
+            self.load_mesh(graphics_context, frame_data);
+        }
+    }
+
+    fn load_mesh(&mut self, graphics_context: &GraphicsContext, frame_data: &mut FrameData) {
+        let mesh_file_name = self.mesh_file_name.trim();
+
+        let mesh = match frame_data.scene.load_mesh_asset(
+            &graphics_context.device,
+            &graphics_context.queue,
+            mesh_file_name,
+        ) {
+            Ok(mesh) => mesh,
+            Err(e) => {
+                log::error!("failed to load mesh {}: {:?}", mesh_file_name, e);
+                return;
+            }
+        };
+
+        // mesh handles are mesh asset indices
+        debug_assert_eq!(mesh.id as usize, frame_data.mesh_assets.len());
+        frame_data.mesh_assets.push(mesh_file_name.to_owned());
+        log::info!("loaded mesh {}", mesh_file_name);
+
+        self.new_entity_mesh = mesh.id as usize;
+        self.add_entity(frame_data);
+    }
+
+    fn add_entity(&mut self, frame_data: &mut FrameData) {
+        let registered =
+            frame_data
+                .scene
+                .objects
+                .register_object(&render_scene::RenderObjectDescriptor {
+                    mesh_handle: Handle::from(self.new_entity_mesh),
+                    transform: m::Mat4::IDENTITY,
+                    render_bounds: mesh::RenderBounds {
+                        origin: m::Vec3::ZERO,
+                        radius: 3.0,
+                    },
+                    color: m::Vec4::ONE,
+                    texture_index: 0,
+                    alpha_cutoff: 0.0,
+                    draw_forward_pass: true,
+                    custom_passes: &[],
+                });
+        let render_object = match registered {
+            Ok(render_object) => render_object,
+            Err(e) => {
+                log::error!("failed to add entity: {:?}", e);
+                return;
+            }
+        };
+
+        let name = format!("Entity {}", self.added_entity_count);
+        self.added_entity_count += 1;
+
+        let entity = frame_data.l_world.push((
+            components::Name(name),
+            components::Translation::default(),
+            components::MeshComponent(self.new_entity_mesh),
+            render_object,
+        ));
+
+        self.l_selected_entity = Some(entity);
+        frame_data.ui_storage.select_entity(entity);
+    }
+
+    pub fn save_scene(&self, frame_data: &FrameData) {
+        match scene_file::save_scene(
+            frame_data.l_world,
+            frame_data.mesh_assets.as_slice(),
+            SCENE_FILE_PATH,
+        ) {
+            Ok(()) => log::info!("saved scene to {}", SCENE_FILE_PATH),
+            Err(e) => log::error!("failed to save scene: {:?}", e),
+        }
+    }
+
+    /// Saves the camera view to the camera file, and copies it to the clipboard for bug reports.
+    pub fn copy_camera(&self, ui: &egui::Ui, frame_data: &FrameData) {
+        match scene_file::camera_to_string(frame_data.camera) {
+            Ok(camera) => ui.output().copied_text = camera,
+            Err(e) => log::error!("failed to serialize camera: {:?}", e),
+        }
+
+        match scene_file::save_camera(frame_data.camera, CAMERA_FILE_PATH) {
+            Ok(()) => log::info!("saved camera to {}", CAMERA_FILE_PATH),
+            Err(e) => log::error!("failed to save camera: {:?}", e),
+        }
+    }
+
+    /// Moves the camera to the view in the camera file.
+    pub fn paste_camera(&self, frame_data: &mut FrameData) {
+        if let Err(e) = scene_file::load_camera(frame_data.camera, CAMERA_FILE_PATH) {
+            log::error!("failed to load camera: {:?}", e);
+        }
+    }
+
+    /// Replaces the entities in the world with the entities in the scene file.
+    pub fn load_scene(&mut self, frame_data: &mut FrameData) {
+        let mut cmd = legion::systems::CommandBuffer::new(frame_data.l_world);
+
+        if let Err(e) = scene_file::load_scene(&mut cmd, SCENE_FILE_PATH) {
+            log::error!("failed to load scene: {:?}", e);
+            return;
+        }
+
+        // remove the current entities and their render objects
+        let render_objects = <&Handle<render_scene::RenderObject>>::query()
+            .iter(frame_data.l_world)
+            .copied()
+            .collect::<Vec<_>>();
+        frame_data.scene.objects.remove_objects(&render_objects);
+        frame_data.l_world.clear();
+
+        // the commands only push entities, so no resources are needed
+        cmd.flush(frame_data.l_world, &mut legion::Resources::default());
+
+        self.l_selected_entity = None;
+        frame_data.ui_storage.deselect_entity();
+    }
+
+    /// Removes the entity from the world, and its render object from the scene.
+    fn delete_entity(&mut self, entity: legion::Entity, frame_data: &mut FrameData) {
+        let render_object = frame_data.l_world.entry_ref(entity).ok().and_then(|entry| {
+            entry
+                .get_component::<Handle<render_scene::RenderObject>>()
+                .ok()
+                .copied()
+        });
+
+        if let Some(render_object) = render_object {
+            frame_data.scene.objects.remove_object(render_object);
+        }
+
+        frame_data.l_world.remove(entity);
+
+        self.l_selected_entity = None;
+        frame_data.ui_storage.deselect_entity();
+    }
 }
@@ -1,5 +1,6 @@
 use super::FrameData;
-use crate::time;
+use crate::graphics_context::DebugLabels;
+use crate::{time, GraphicsContext};
 
 pub struct StatsPanel {
     pub enabled: bool,
@@ -30,7 +31,12 @@ mod panel {
     }
 
     impl StatsPanel {
-        pub fn update(&mut self, context: &egui::CtxRef, frame_data: &FrameData) {
+        pub fn update(
+            &mut self,
+            context: &egui::CtxRef,
+            graphics_context: &GraphicsContext,
+            frame_data: &mut FrameData,
+        ) {
             self.frame_time_history.update(frame_data.clock);
 
             context.request_repaint();
@@ -43,11 +49,111 @@ mod panel {
                 ui.separator();
 
                 self.frame_time_history.ui(ui);
+
+                let stats = frame_data.render_stats;
+                ui.label(format!(
+                    "Visible draws: {} / Total: {}",
+                    stats.visible_draw_count, stats.draw_count
+                ));
+                ui.label(format!("Triangles: {}", stats.triangle_count));
+                ui.label(format!("Vertices: {}", stats.vertex_count));
+
+                if let (Some(compute_time), Some(render_time)) =
+                    (stats.compute_time_ms, stats.render_time_ms)
+                {
+                    ui.label(format!(
+                        "Compute: {:.2} ms, Render: {:.2} ms",
+                        compute_time, render_time
+                    ));
+                }
+
+                ui.separator();
+
+                graphics_ui(ui, graphics_context);
+
+                ui.separator();
+
+                debug_labels_ui(ui, frame_data.debug_labels);
+
+                ui.separator();
+
+                time_scale_ui(ui, frame_data.time_scale);
             });
         }
     }
 }
 
+/// Adapter and surface configuration, for bug reports.
+fn graphics_ui(ui: &mut egui::Ui, context: &GraphicsContext) {
+    egui::CollapsingHeader::new("🖥 Graphics")
+        .default_open(true)
+        .show(ui, |ui| {
+            let adapter_info = &context.adapter_info;
+            ui.label(format!("Adapter: {}", adapter_info.name));
+            ui.label(format!("Backend: {:?}", adapter_info.backend));
+            ui.label(format!("Format: {:?}", context.config.format));
+            ui.label(format!(
+                "Present (requested): {:?}",
+                context.config.present_mode
+            ));
+            ui.label(format!(
+                "Resolution: {}x{}",
+                context.config.width, context.config.height
+            ));
+        });
+}
+
+/// Labels of the passes in graphics debuggers like RenderDoc.
+fn debug_labels_ui(ui: &mut egui::Ui, labels: &mut DebugLabels) {
+    egui::CollapsingHeader::new("🏷 Debug labels")
+        .default_open(false)
+        .show(ui, |ui| {
+            egui::Grid::new("debug labels grid").show(ui, |ui| {
+                ui.label("Compute pass");
+                ui.text_edit_singleline(&mut labels.compute_pass);
+                ui.end_row();
+
+                ui.label("Shadow pass");
+                ui.text_edit_singleline(&mut labels.shadow_pass);
+                ui.end_row();
+
+                ui.label("Depth prepass");
+                ui.text_edit_singleline(&mut labels.depth_prepass);
+                ui.end_row();
+
+                ui.label("Render pass");
+                ui.text_edit_singleline(&mut labels.render_pass);
+                ui.end_row();
+            });
+
+            ui.checkbox(&mut labels.draw_markers, "Draw markers")
+                .on_hover_text("Draws the batches one by one, each marked with its pass and slot.");
+        });
+}
+
+fn time_scale_ui(ui: &mut egui::Ui, time_scale: &mut time::TimeScale) {
+    egui::CollapsingHeader::new("⏱ Time")
+        .default_open(true)
+        .show(ui, |ui| {
+            ui.add(egui::Slider::new(&mut time_scale.time_scale, 0.0..=2.0).text("Time scale"));
+
+            ui.horizontal_wrapped(|ui| {
+                let pause_label = if time_scale.paused {
+                    "▶ Resume"
+                } else {
+                    "⏸ Pause"
+                };
+                if ui.button(pause_label).clicked() {
+                    time_scale.paused = !time_scale.paused;
+                }
+
+                if ui.button("Reset").clicked() {
+                    *time_scale = time::TimeScale::default();
+                }
+            });
+        });
+}
+
 mod frame_time_history {
     use super::*;
 
@@ -68,7 +174,7 @@ mod frame_time_history {
         pub fn update(&mut self, clock: &time::Clock) {
             self.frame_times.add(
                 clock.start_time.elapsed().as_secs_f64(),
-                clock.last_delta_time.as_secs_f32(),
+                clock.unclamped_delta_time.as_secs_f32(),
             );
         }
 
@@ -50,7 +50,9 @@ pub mod event {
     /// Event fired when the window is resized, or if the window's scale factor changes.
     #[derive(Debug)]
     pub struct WindowResizeEvent {
+        /// Physical size of the window. Changes along with the scale factor.
         pub size: winit::dpi::PhysicalSize<u32>,
+        /// The new scale factor, if it changed.
         pub scale_factor: Option<f64>,
     }
 
@@ -59,14 +61,8 @@ pub mod event {
 
 // new events --------------
 
-// Unique identifier for an event
-// todo: Just a wrapper used to debug the name and type, right?
-// #[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone)]
-// pub struct EventId<EventType> {
-//     pub id: usize,
-//     _marker: std::marker::PhantomData<EventType>,
-// }
-
+/// Unique identifier for an event, the number of events of the same type sent before it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct EventId(pub usize);
 
 pub struct Event<EventType> {
@@ -79,11 +75,12 @@ enum State {
     B,
 }
 
-pub struct EventWrites<EventType> {
-    writes: Vec<Event<EventType>>,
-}
-
-/// Resource containing events of type T
+/// Resource containing events of type T.
+///
+/// Events are double buffered: an event sent during one update is readable during that update
+/// and the next one, after which it's dropped. Readers keep track of which events they've
+/// already read, so each [EventReader] sees each event exactly once if it reads at least once
+/// every other update.
 pub struct Events<EventType> {
     events_a: Vec<Event<EventType>>,
     events_b: Vec<Event<EventType>>,
@@ -108,10 +105,7 @@ impl<T> Events<T> {
     pub fn send(&mut self, event: T) {
         let event_id = EventId(self.event_count);
 
-        let event_instance = Event {
-            event_id: event_id,
-            event,
-        };
+        let event_instance = Event { event_id, event };
 
         match self.state {
             State::A => self.events_a.push(event_instance),
@@ -120,33 +114,133 @@ impl<T> Events<T> {
 
         self.event_count += 1;
     }
+
+    /// Swaps the event buffers, dropping the events sent two updates ago.
+    /// Should be called once per frame.
+    pub fn update(&mut self) {
+        match self.state {
+            State::A => {
+                self.events_b.clear();
+                self.b_start_event_count = self.event_count;
+                self.state = State::B;
+            }
+            State::B => {
+                self.events_a.clear();
+                self.a_start_event_count = self.event_count;
+                self.state = State::A;
+            }
+        }
+    }
+
+    /// Iterates over the stored events with an id of at least last_event_count, oldest first.
+    fn iter_from(&self, last_event_count: usize) -> impl Iterator<Item = &Event<T>> {
+        let (older, older_start, newer, newer_start) = match self.state {
+            State::A => (
+                &self.events_b,
+                self.b_start_event_count,
+                &self.events_a,
+                self.a_start_event_count,
+            ),
+            State::B => (
+                &self.events_a,
+                self.a_start_event_count,
+                &self.events_b,
+                self.b_start_event_count,
+            ),
+        };
+
+        older
+            .iter()
+            .skip(last_event_count.saturating_sub(older_start))
+            .chain(
+                newer
+                    .iter()
+                    .skip(last_event_count.saturating_sub(newer_start)),
+            )
+    }
+}
+
+/// Reads events of type T. Meant to be used as system state (#[state]), so that each system
+/// keeps track of the events it has already read.
+pub struct EventReader<EventType> {
+    last_event_count: usize,
+    _marker: std::marker::PhantomData<fn() -> EventType>,
+}
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        Self {
+            last_event_count: 0,
+            _marker: std::marker::PhantomData,
+        }
+    }
 }
+impl<T> EventReader<T> {
+    /// Iterates over the events this reader hasn't read yet.
+    pub fn iter<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        let last_event_count = self.last_event_count;
+        self.last_event_count = events.event_count;
 
-// impl AtomicRefCell<>
+        events.iter_from(last_event_count).map(|e| &e.event)
+    }
+}
 
-// impl<T> DerefMut for Events<T> {
-//     fn deref_mut(&mut self) -> &mut Self::Target {
-//
-//         todo!()
-//     }
-// }
+/// Sends events of type T.
+pub struct EventWriter<'a, EventType> {
+    events: &'a mut Events<EventType>,
+}
+impl<'a, T> EventWriter<'a, T> {
+    pub fn new(events: &'a mut Events<T>) -> Self {
+        Self { events }
+    }
 
-// pub struct EventWriter<'a, EventType: ?Sized + 'a> {
-//     events: atomic_refcell::AtomicRefCell<EventType>,
-// }
+    pub fn send(&mut self, event: T) {
+        self.events.send(event);
+    }
 
-// pub struct EventWriter<EventType> {
-//     events: atomic_refcell::AtomicRefMut<Events<'a, EventType>>,
-//     // &'static mut Events<EventType>
-// }
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        events.into_iter().for_each(|event| self.events.send(event));
+    }
+}
 
-// pub struct EventWriter<'a, EventType, EventsWriter: Events<EventType>> {
-//
-// }
+/// Inserts an Events<T> resource.
+pub fn register_event_type<T: legion::systems::Resource>(r: &mut legion::Resources) {
+    r.insert(Events::<T>::default());
+}
 
-// pub struct EventWriter<EventType: legion::systems::Resource> {
-//     events: Events<EventType>,
-// }
+/// System that swaps the event buffers of Events<T> once per frame.
+pub fn update_events_system<T: legion::systems::Resource>() -> impl legion::systems::ParallelRunnable
+{
+    legion::SystemBuilder::new(format!("update_events<{}>", std::any::type_name::<T>()))
+        .write_resource::<Events<T>>()
+        .build(|_cmd, _world, events, _queries| events.update())
+}
 
 #[test]
-fn test_events() {}
+fn test_events() {
+    let mut events = Events::<u32>::default();
+    let mut reader_a = EventReader::<u32>::default();
+    let mut reader_b = EventReader::<u32>::default();
+
+    let mut read_a = Vec::new();
+    let mut read_b = Vec::new();
+
+    EventWriter::new(&mut events).send_batch([0, 1, 2]);
+
+    // tick 1
+    events.update();
+    read_a.extend(reader_a.iter(&events).copied());
+    read_b.extend(reader_b.iter(&events).copied());
+    read_a.extend(reader_a.iter(&events).copied());
+
+    // tick 2
+    events.update();
+    read_a.extend(reader_a.iter(&events).copied());
+    read_b.extend(reader_b.iter(&events).copied());
+
+    assert_eq!(read_a, vec![0, 1, 2]);
+    assert_eq!(read_b, vec![0, 1, 2]);
+
+    // events are dropped after being stored for two updates
+    let mut late_reader = EventReader::<u32>::default();
+    assert_eq!(late_reader.iter(&events).count(), 0);
+}
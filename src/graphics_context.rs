@@ -1,4 +1,5 @@
 use crate::{events, texture};
+use std::iter;
 
 /// Graphics API handles and window/surface size data.
 pub struct GraphicsContext {
@@ -6,6 +7,8 @@ pub struct GraphicsContext {
     pub surface: wgpu::Surface,
     /// Physical device, usually a dedicated gpu.
     pub adapter: wgpu::Adapter,
+    /// Name and backend of the adapter, queried once at startup.
+    pub adapter_info: wgpu::AdapterInfo,
     /// Logical device, a connection to physical device.
     pub device: wgpu::Device,
     /// Commands queue on the device
@@ -16,15 +19,215 @@ pub struct GraphicsContext {
     pub size: winit::dpi::PhysicalSize<u32>,
     /// Window scale factor.
     pub scale_factor: f64,
-    /// The depth texture.
+    /// Number of samples per pixel when rendering to the surface (1, 2, 4 or 8).
+    pub sample_count: u32,
+    /// How depth is mapped to the depth texture. Pipelines and the camera's projection must match.
+    pub depth_mode: DepthMode,
+    /// Whether the main pass derives the coverage of multisampled pixels from the fragment alpha,
+    /// smoothing the edges of alpha cutouts. Only enabled if sample_count is more than 1.
+    pub alpha_to_coverage: bool,
+    /// Multisampled color texture that is resolved into the surface texture. None if sample_count is 1.
+    pub msaa_texture: Option<texture::Texture>,
+    /// The depth texture. Has the same sample count as the msaa texture.
     pub depth_texture: texture::Texture,
+    /// True while the window has a zero size, such as when it's minimized. The surface can't be
+    /// configured with a zero size, so nothing should be rendered until the next non-zero resize.
+    pub is_minimized: bool,
+    /// Timestamps written around the GPU passes. None if the adapter doesn't support
+    /// Features::TIMESTAMP_QUERY.
+    pub timestamp_queries: Option<TimestampQueries>,
 }
-impl GraphicsContext {
-    pub async fn new(window: &winit::window::Window) -> Self {
-        let size = window.inner_size();
 
-        let instance = wgpu::Instance::new(wgpu::Backends::VULKAN);
-        let surface = unsafe { instance.create_surface(window) };
+/// GPU passes that are timed with timestamp queries.
+#[derive(Debug, Copy, Clone)]
+pub enum GpuPass {
+    Compute,
+    Render,
+}
+
+/// A pair of timestamp queries for each GpuPass, written at the start and end of the pass.
+pub struct TimestampQueries {
+    pub query_set: wgpu::QuerySet,
+    /// Nanoseconds per timestamp tick.
+    pub period: f32,
+}
+impl TimestampQueries {
+    /// Number of queries in the query set.
+    pub const COUNT: u32 = 4;
+
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("gpu pass timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: Self::COUNT,
+        });
+
+        Self {
+            query_set,
+            period: queue.get_timestamp_period(),
+        }
+    }
+
+    /// Index of the query written at the start of the pass. The end is written to the next one.
+    pub fn begin_index(pass: GpuPass) -> u32 {
+        pass as u32 * 2
+    }
+}
+
+/// Color the main pass clears the output to, in linear space.
+#[derive(Debug, Copy, Clone)]
+pub struct ClearColor(pub wgpu::Color);
+penguin_util::impl_default!(
+    ClearColor,
+    Self(wgpu::Color {
+        r: 0.1,
+        g: 0.2,
+        b: 0.3,
+        a: 1.0,
+    })
+);
+
+/// Labels of the passes' debug groups, shown in graphics debuggers like RenderDoc.
+#[derive(Debug, Clone)]
+pub struct DebugLabels {
+    pub compute_pass: String,
+    pub shadow_pass: String,
+    pub depth_prepass: String,
+    pub render_pass: String,
+    /// Draws the emitted draw commands one by one, each preceded by a debug marker, instead of in
+    /// a single multi-draw. Only on by default in debug builds, to keep the overhead out of release
+    /// builds.
+    pub draw_markers: bool,
+}
+penguin_util::impl_default!(
+    DebugLabels,
+    Self {
+        compute_pass: "compute pass".to_owned(),
+        shadow_pass: "shadow pass".to_owned(),
+        depth_prepass: "depth prepass".to_owned(),
+        render_pass: "render pass".to_owned(),
+        draw_markers: cfg!(debug_assertions),
+    }
+);
+impl DebugLabels {
+    /// The label to mark the shadow pass's draws with, if draw markers are on.
+    pub fn shadow_pass_draw_marker(&self) -> Option<&str> {
+        self.draw_markers.then(|| self.shadow_pass.as_str())
+    }
+
+    /// The label to mark the depth prepass's draws with, if draw markers are on.
+    pub fn depth_prepass_draw_marker(&self) -> Option<&str> {
+        self.draw_markers.then(|| self.depth_prepass.as_str())
+    }
+
+    /// The label to mark the render pass's draws with, if draw markers are on.
+    pub fn render_pass_draw_marker(&self) -> Option<&str> {
+        self.draw_markers.then(|| self.render_pass.as_str())
+    }
+}
+
+/// How the main pass maps depth to the depth texture.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DepthMode {
+    /// The near plane is at depth 0 and the far plane at depth 1.
+    Standard,
+    /// The near plane is at depth 1 and the far plane at depth 0. Float depth has the most
+    /// precision close to 0, which this spreads more evenly over distance, reducing z-fighting far
+    /// from the camera.
+    ReverseZ,
+}
+penguin_util::impl_default!(DepthMode, Self::Standard);
+impl DepthMode {
+    /// Depth of the near plane.
+    pub fn near_depth(self) -> f32 {
+        match self {
+            Self::Standard => 0.0,
+            Self::ReverseZ => 1.0,
+        }
+    }
+
+    /// Depth of the far plane, which the depth texture is cleared to.
+    pub fn far_depth(self) -> f32 {
+        match self {
+            Self::Standard => 1.0,
+            Self::ReverseZ => 0.0,
+        }
+    }
+
+    /// Depth test passing for fragments closer to the camera.
+    pub fn compare_function(self) -> wgpu::CompareFunction {
+        match self {
+            Self::Standard => wgpu::CompareFunction::Less,
+            Self::ReverseZ => wgpu::CompareFunction::Greater,
+        }
+    }
+
+    /// Depth test of the main pass after the depth prepass, passing for the fragments at the depth
+    /// the prepass wrote. Not Equal, as the prepass' vertex shader is a different shader, which
+    /// isn't guaranteed to compute the exact same depth.
+    pub fn prepass_compare_function(self) -> wgpu::CompareFunction {
+        match self {
+            Self::Standard => wgpu::CompareFunction::LessEqual,
+            Self::ReverseZ => wgpu::CompareFunction::GreaterEqual,
+        }
+    }
+}
+
+/// Options for creating the graphics context.
+#[derive(Debug, Copy, Clone)]
+pub struct GraphicsContextDescriptor {
+    /// Requested number of samples per pixel (1, 2, 4 or 8). Falls back to 1 if not supported.
+    pub sample_count: u32,
+    /// Present mode override. Mailbox if None. wgpu checks the mode against the modes the surface
+    /// supports when configuring it, and falls back to Fifo (always supported) with a warning.
+    /// wgpu 0.12 can't list the supported modes, so the config keeps the requested mode.
+    pub present_mode: Option<wgpu::PresentMode>,
+    /// Depth mode of the main pass.
+    pub depth_mode: DepthMode,
+    /// Alpha-to-coverage in the main pass, ignored without multisampling.
+    pub alpha_to_coverage: bool,
+    /// Features to request in addition to the ones the renderer requires.
+    pub extra_features: wgpu::Features,
+    /// Backends to request a gpu from. Overridden by the WGPU_BACKEND env var if set.
+    pub backends: wgpu::Backends,
+}
+penguin_util::impl_default!(
+    GraphicsContextDescriptor,
+    Self {
+        sample_count: 4,
+        present_mode: None,
+        depth_mode: DepthMode::Standard,
+        alpha_to_coverage: false,
+        extra_features: wgpu::Features::empty(),
+        backends: wgpu::Backends::PRIMARY,
+    }
+);
+impl GraphicsContextDescriptor {
+    /// Fifo if vsync is enabled, otherwise Immediate.
+    pub fn with_vsync(vsync: bool) -> Self {
+        Self {
+            present_mode: Some(match vsync {
+                true => wgpu::PresentMode::Fifo,
+                false => wgpu::PresentMode::Immediate,
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// Requests a gpu compatible with the surface from the given backends, or the backends in the
+/// WGPU_BACKEND env var (e.g. "vulkan", "metal", "dx12") if set. Falls back to any backend if no
+/// gpu is found. If the preferred gpu lacks the required features, another gpu that has them is
+/// used instead, e.g. the discrete gpu of a laptop that also has an integrated one.
+pub async fn request_adapter(
+    backends: wgpu::Backends,
+    create_surface: impl Fn(&wgpu::Instance) -> wgpu::Surface,
+) -> (wgpu::Instance, wgpu::Surface, wgpu::Adapter) {
+    let backends = wgpu::util::backend_bits_from_env().unwrap_or(backends);
+
+    for backends in [backends, wgpu::Backends::all()] {
+        let instance = wgpu::Instance::new(backends);
+        let surface = create_surface(&instance);
 
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
@@ -32,71 +235,382 @@ impl GraphicsContext {
                 force_fallback_adapter: false,
                 compatible_surface: Some(&surface),
             })
-            .await
-            .expect("no supported gpu");
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    features:
-                    //wgpu::Features::default(), // wgpu::Features::BUFFER_BINDING_ARRAY,
-                    //wgpu::Features::default(), // wgpu::Features::BUFFER_BINDING_ARRAY,
-                    // wgpu::Features::POLYGON_MODE_LINE |
-                    // allow non-zero value for first_instance field in draw calls
-                    //wgpu::Features::INDIRECT_FIRST_INSTANCE |
-                    //wgpu::Features::TEXTURE_BINDING_ARRAY |
-                    // wgpu::Features::STORAGE_RESOURCE_BINDING_ARRAY,
-                    wgpu::Features::all() ^ wgpu::Features::TEXTURE_COMPRESSION_ETC2 ^ wgpu::Features::TEXTURE_COMPRESSION_ASTC_LDR ^ wgpu::Features::VERTEX_ATTRIBUTE_64BIT,
-                    limits: wgpu::Limits::default(),
-                },
-                None,
-            )
-            .await
-            .expect("failed to init device, missing required features?");
+            .await;
+
+        match adapter {
+            Some(adapter) => {
+                let adapter = if adapter.features().contains(required_features()) {
+                    adapter
+                } else {
+                    instance
+                        .enumerate_adapters(backends)
+                        .find(|other| {
+                            other.features().contains(required_features())
+                                && other.is_surface_supported(&surface)
+                        })
+                        .unwrap_or(adapter)
+                };
+
+                log::info!("using gpu {:?}", adapter.get_info());
+                return (instance, surface, adapter);
+            }
+            None => log::warn!("no supported gpu found for backends {:?}", backends),
+        }
+    }
+
+    panic!("no supported gpu");
+}
+
+/// Requests a device with the required features without a window, for tests that run the shaders.
+/// Returns None if there's no gpu with the required features, e.g. on CI machines.
+#[cfg(test)]
+pub async fn request_headless_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let backends = wgpu::util::backend_bits_from_env().unwrap_or_else(wgpu::Backends::all);
+    let instance = wgpu::Instance::new(backends);
+
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        })
+        .await?;
+
+    if !adapter.features().contains(required_features()) {
+        log::warn!(
+            "gpu \"{}\" is missing required features, no headless device",
+            adapter.get_info().name
+        );
+        return None;
+    }
+
+    Some(request_device(&adapter, wgpu::Features::empty()).await)
+}
+
+/// Features the renderer can't run without.
+pub fn required_features() -> wgpu::Features {
+    // gpu-driven draw calls, the draw count is written by the compute shader
+    wgpu::Features::MULTI_DRAW_INDIRECT
+        | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT
+        // allow non-zero value for first_instance field in draw calls
+        | wgpu::Features::INDIRECT_FIRST_INSTANCE
+}
+
+/// Error message for a gpu that lacks some of the features. Explains what MULTI_DRAW_INDIRECT_COUNT
+/// is needed for if it's missing, since it's the one most often missing, e.g. on GL and Metal.
+fn missing_features_message(adapter: &wgpu::Adapter, missing_features: wgpu::Features) -> String {
+    let info = adapter.get_info();
+    let mut message = format!(
+        "gpu \"{}\" ({:?}) is missing required features: {:?}",
+        info.name, info.backend, missing_features
+    );
 
-        assert_ne!(size.width, 0);
-        assert_ne!(size.height, 0);
+    if missing_features.contains(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT) {
+        message.push_str(
+            "\nMULTI_DRAW_INDIRECT_COUNT is needed to draw the draw commands emitted by the \
+             compute shader without reading the draw count back. wgpu supports it on Vulkan \
+             (1.2 or VK_KHR_draw_indirect_count) and DX12, so try another backend with the \
+             WGPU_BACKEND env var (e.g. WGPU_BACKEND=vulkan) or another gpu.",
+        );
+    }
+
+    message
+}
+
+/// Requests a device with the required features and the extra features. Panics with a list of the
+/// missing features if the adapter doesn't support all of them.
+pub async fn request_device(
+    adapter: &wgpu::Adapter,
+    extra_features: wgpu::Features,
+) -> (wgpu::Device, wgpu::Queue) {
+    let features = required_features() | extra_features;
+
+    let missing_features = features - adapter.features();
+    if !missing_features.is_empty() {
+        panic!("{}", missing_features_message(adapter, missing_features));
+    }
+
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features,
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await
+        .unwrap_or_else(|e| panic!("failed to init device with features {:?}: {}", features, e))
+}
+
+impl GraphicsContext {
+    pub async fn new(window: &winit::window::Window, desc: &GraphicsContextDescriptor) -> Self {
+        let size = window.inner_size();
+
+        let (_instance, surface, adapter) = request_adapter(desc.backends, |instance| unsafe {
+            instance.create_surface(window)
+        })
+        .await;
+
+        // gpu pass timings are shown if supported, but aren't required
+        let optional_features = adapter.features() & wgpu::Features::TIMESTAMP_QUERY;
+
+        let (device, queue) =
+            request_device(&adapter, desc.extra_features | optional_features).await;
+
+        let timestamp_queries = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| TimestampQueries::new(&device, &queue));
+
+        let is_minimized = size.width == 0 || size.height == 0;
 
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            // copy src to allow capturing frames
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
             format: surface.get_preferred_format(&adapter).unwrap(),
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Mailbox,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: desc.present_mode.unwrap_or(wgpu::PresentMode::Mailbox),
         };
         surface.configure(&device, &config);
 
         let scale_factor = window.scale_factor();
 
-        let depth_texture = texture::Texture::create_depth_texture(&device, &config);
+        let sample_count = Self::supported_sample_count(&device, &config, desc.sample_count).await;
+
+        let msaa_texture = texture::Texture::create_msaa_texture(&device, &config, sample_count);
+        let depth_texture = texture::Texture::create_depth_texture(&device, &config, sample_count);
 
         Self {
             surface,
+            adapter_info: adapter.get_info(),
             adapter,
             device,
             queue,
             config,
             size,
             scale_factor,
+            sample_count,
+            depth_mode: desc.depth_mode,
+            alpha_to_coverage: desc.alpha_to_coverage && sample_count > 1,
+            msaa_texture,
             depth_texture,
+            is_minimized,
+            timestamp_queries,
+        }
+    }
+
+    /// Writes a timestamp at the start of the pass, if timestamp queries are supported.
+    pub fn begin_timestamp(&self, cmd: &mut wgpu::CommandEncoder, pass: GpuPass) {
+        if let Some(queries) = &self.timestamp_queries {
+            cmd.write_timestamp(&queries.query_set, TimestampQueries::begin_index(pass));
+        }
+    }
+
+    /// Writes a timestamp at the end of the pass, if timestamp queries are supported.
+    pub fn end_timestamp(&self, cmd: &mut wgpu::CommandEncoder, pass: GpuPass) {
+        if let Some(queries) = &self.timestamp_queries {
+            cmd.write_timestamp(&queries.query_set, TimestampQueries::begin_index(pass) + 1);
+        }
+    }
+
+    /// Returns the requested sample count if multisampled color and depth textures can be created
+    /// with it, otherwise 1.
+    async fn supported_sample_count(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        requested: u32,
+    ) -> u32 {
+        if requested <= 1 {
+            return 1;
+        }
+
+        if !matches!(requested, 2 | 4 | 8) {
+            log::warn!("unsupported msaa sample count {}, using 1", requested);
+            return 1;
+        }
+
+        // wgpu doesn't expose supported sample counts, so try creating the textures and check for
+        // validation errors
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let probe_config = wgpu::SurfaceConfiguration {
+            width: 1,
+            height: 1,
+            ..config.clone()
+        };
+        let _color = texture::Texture::create_msaa_texture(device, &probe_config, requested);
+        let _depth = texture::Texture::create_depth_texture(device, &probe_config, requested);
+
+        match device.pop_error_scope().await {
+            Some(e) => {
+                log::warn!(
+                    "msaa sample count {} not supported by the adapter, using 1: {}",
+                    requested,
+                    e
+                );
+                1
+            }
+            None => requested,
         }
     }
 
+    /// Color attachment that renders to the msaa texture and resolves into the output texture, or
+    /// renders directly to the output texture if multisampling is disabled.
+    pub fn color_attachment<'a>(
+        &'a self,
+        output_texture_view: &'a wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachment<'a> {
+        let (view, resolve_target) = match &self.msaa_texture {
+            Some(msaa_texture) => (&msaa_texture.view, Some(output_texture_view)),
+            None => (output_texture_view, None),
+        };
+
+        wgpu::RenderPassColorAttachment {
+            view,
+            // the texture that will receive the resolved output (used for multisampling)
+            resolve_target,
+            ops: wgpu::Operations {
+                load,
+                // store rendered results to output texture
+                store: true,
+            },
+        }
+    }
+
+    /// Reconfigures the surface for the new size. Zero sizes mark the context as minimized and
+    /// leave the surface as it is.
     pub fn on_resize(&mut self, size: winit::dpi::PhysicalSize<u32>, scale_factor: Option<f64>) {
-        assert_ne!(size.width, 0);
-        assert_ne!(size.height, 0);
+        if let Some(scale_factor) = scale_factor {
+            self.scale_factor = scale_factor;
+        }
+
+        self.is_minimized = size.width == 0 || size.height == 0;
+        if self.is_minimized {
+            return;
+        }
 
         self.size = size;
         self.config.width = size.width;
         self.config.height = size.height;
         self.surface.configure(&self.device, &self.config);
 
-        if let Some(scale_factor) = scale_factor {
-            self.scale_factor = scale_factor;
+        self.msaa_texture =
+            texture::Texture::create_msaa_texture(&self.device, &self.config, self.sample_count);
+        self.depth_texture =
+            texture::Texture::create_depth_texture(&self.device, &self.config, self.sample_count);
+    }
+
+    /// Panics if the msaa and depth textures weren't recreated along with the surface. Render
+    /// passes use them as attachments together with the surface texture, which is a validation
+    /// error if their sizes differ.
+    pub fn assert_attachments_match_surface(&self) {
+        let surface_size = (self.config.width, self.config.height);
+
+        let depth_size = self.depth_texture.size;
+        assert_eq!(
+            (depth_size.width, depth_size.height),
+            surface_size,
+            "the depth texture doesn't match the surface size"
+        );
+
+        if let Some(msaa_texture) = &self.msaa_texture {
+            assert_eq!(
+                (msaa_texture.size.width, msaa_texture.size.height),
+                surface_size,
+                "the msaa texture doesn't match the surface size"
+            );
         }
+    }
+
+    /// Copies a surface texture (before it's presented) into a mappable buffer and writes it to
+    /// the given path as an RGBA png.
+    pub fn capture_frame(
+        &self,
+        output_texture: &wgpu::Texture,
+        path: &std::path::Path,
+    ) -> anyhow::Result<()> {
+        let (width, height) = (self.config.width, self.config.height);
+
+        // rows in the copy destination buffer has to be aligned to 256 bytes
+        let unpadded_bytes_per_row = 4 * width as usize;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            + penguin_util::handle::calculate_padding(
+                unpadded_bytes_per_row,
+                wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as _,
+            );
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame capture buffer"),
+            size: (padded_bytes_per_row * height as usize) as _,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut cmd = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("frame capture encoder"),
+            });
+
+        cmd.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: output_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row as _),
+                    rows_per_image: std::num::NonZeroU32::new(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.queue.submit(iter::once(cmd.finish()));
+
+        let buffer_slice = buffer.slice(..);
+        let map_future = buffer_slice.map_async(wgpu::MapMode::Read);
+        self.device.poll(wgpu::Maintain::Wait);
+        penguin_util::pollster::block_on(map_future)?;
+
+        // the surface format is usually bgra, swizzle into rgba
+        let is_bgra = matches!(
+            self.config.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * height as usize);
+        {
+            let padded_data = buffer_slice.get_mapped_range();
+
+            for row in padded_data.chunks(padded_bytes_per_row) {
+                for pixel in row[..unpadded_bytes_per_row].chunks(4) {
+                    if is_bgra {
+                        pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+                    } else {
+                        pixels.extend_from_slice(pixel);
+                    }
+                }
+            }
+        }
+        buffer.unmap();
+
+        image::save_buffer(path, &pixels, width, height, image::ColorType::Rgba8)?;
+
+        log::info!("captured frame to {:?}", path);
 
-        self.depth_texture = texture::Texture::create_depth_texture(&self.device, &self.config);
+        Ok(())
     }
 
     pub fn on_event(&mut self, event: &events::PenguinEvent) -> bool {
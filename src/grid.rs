@@ -0,0 +1,144 @@
+use crate::{bind_groups, camera, texture, GraphicsContext};
+use macaw as m;
+use penguin_util::{GpuBuffer, GpuBufferDeviceExt};
+use std::slice;
+
+/// Distance from the camera at which the grid has faded out.
+const GRID_FADE_DISTANCE: f32 = 50.0;
+
+/// Whether to draw the ground grid. Toggled from the editor.
+#[derive(Debug, Copy, Clone)]
+pub struct GridSettings {
+    pub enabled: bool,
+}
+penguin_util::impl_default!(GridSettings, Self { enabled: true });
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct GridUniformData {
+    pub view_proj: m::Mat4,
+    /// w is the distance at which the grid has faded out.
+    pub camera_position: m::Vec4,
+}
+unsafe impl bytemuck::Pod for GridUniformData {}
+unsafe impl bytemuck::Zeroable for GridUniformData {}
+
+impl GridUniformData {
+    fn new(camera_uniform_data: &camera::CameraUniformData, camera_position: m::Vec3) -> Self {
+        Self {
+            view_proj: camera_uniform_data.view_proj,
+            camera_position: camera_position.extend(GRID_FADE_DISTANCE),
+        }
+    }
+}
+
+/// Ground grid at y = 0 for spatial reference, with a line per world unit. Drawn as a quad that
+/// follows the camera, after the scene in the main render pass so that it's hidden behind objects.
+pub struct Grid {
+    uniform_buffer: GpuBuffer<GridUniformData>,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+impl Grid {
+    pub fn init(context: &GraphicsContext, camera: &camera::MainCamera) -> Self {
+        let device = &context.device;
+
+        let uniform_buffer =
+            device.create_buffer_init_t::<GridUniformData>(&wgpu::util::BufferInitDescriptor {
+                label: Some("grid uniform buffer"),
+                contents: bytemuck::cast_slice(slice::from_ref(&GridUniformData::new(
+                    &camera.uniform_data,
+                    camera.position(),
+                ))),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let bind_group_layout = bind_groups::BindGroupLayoutBuilder::<1>::builder()
+            .uniform_buffer(0, wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT)
+            .build(device, Some("grid bind group layout"));
+
+        let bind_group = bind_groups::BindGroupBuilder::<1>::builder()
+            .buffer(0, &uniform_buffer)
+            .build(device, Some("grid bind group"), &bind_group_layout);
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("grid shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/grid.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("grid pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("grid pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                // visible from below as well
+                cull_mode: None,
+                ..Default::default()
+            },
+            // tested against the scene, but transparent, so it doesn't write depth
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: context.depth_mode.compare_function(),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                // must match the sample count of the main pass
+                count: context.sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format: context.config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            uniform_buffer,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    /// Writes the camera's view projection and position to the grid uniform buffer.
+    pub fn update_camera(
+        &self,
+        queue: &wgpu::Queue,
+        camera_uniform_data: &camera::CameraUniformData,
+        camera_position: m::Vec3,
+    ) {
+        self.uniform_buffer.write(
+            queue,
+            0,
+            slice::from_ref(&GridUniformData::new(camera_uniform_data, camera_position)),
+        );
+    }
+
+    /// Draws the grid. Should be recorded after the scene in the main render pass.
+    pub fn record<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.push_debug_group("grid");
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+        render_pass.pop_debug_group();
+    }
+}
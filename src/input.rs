@@ -1,8 +1,37 @@
+use macaw as m;
+use std::collections::HashSet;
+
 #[derive(Debug, Copy, Clone)]
 pub enum InputEvent {
     Key(KeyEvent),
     /// Contains mouse movement delta.
     MouseMotion((f64, f64)),
+    /// Contains the vertical mouse scroll delta, in lines. Positive when scrolling up/away from
+    /// the user.
+    MouseScroll(f32),
+    /// Contains the cursor position in physical pixels, from the top left corner of the window.
+    CursorMoved((f64, f64)),
+    MouseButton(MouseButtonEvent),
+}
+
+impl InputEvent {
+    /// Approximate amount of pixels that corresponds to one line for pixel-based scroll deltas
+    /// (touchpads).
+    const PIXELS_PER_SCROLL_LINE: f32 = 20.0;
+
+    /// Creates a MouseScroll event, normalizing pixel deltas to lines.
+    pub fn mouse_scroll(delta: winit::event::MouseScrollDelta) -> Self {
+        use winit::event::MouseScrollDelta;
+
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_x, y) => y,
+            MouseScrollDelta::PixelDelta(position) => {
+                position.y as f32 / Self::PIXELS_PER_SCROLL_LINE
+            }
+        };
+
+        Self::MouseScroll(lines)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -11,12 +40,145 @@ pub struct KeyEvent {
     pub state: KeyState,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MouseButtonEvent {
+    pub button: MouseButton,
+    pub state: KeyState,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u16),
+}
+
+impl From<winit::event::MouseButton> for MouseButton {
+    fn from(button: winit::event::MouseButton) -> Self {
+        match button {
+            winit::event::MouseButton::Left => Self::Left,
+            winit::event::MouseButton::Right => Self::Right,
+            winit::event::MouseButton::Middle => Self::Middle,
+            winit::event::MouseButton::Other(button) => Self::Other(button),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum KeyState {
     Down,
     Up,
 }
 
+/// Key, mouse button and cursor state that systems can poll, instead of reading InputEvents.
+/// Updated from the InputEvents once per frame.
+#[derive(Default)]
+pub struct Input {
+    /// Keys that are held down this frame.
+    pressed: HashSet<Key>,
+    /// Keys that were held down the previous frame.
+    previously_pressed: HashSet<Key>,
+    /// Mouse buttons that are held down this frame.
+    pressed_buttons: HashSet<MouseButton>,
+    /// Mouse buttons that were held down the previous frame.
+    previously_pressed_buttons: HashSet<MouseButton>,
+    /// Physical pixels from the top left corner of the window.
+    cursor_position: m::Vec2,
+    /// Mouse movement summed over the frame.
+    mouse_delta: m::Vec2,
+}
+
+impl Input {
+    /// Whether the key is held down.
+    pub fn pressed(&self, key: Key) -> bool {
+        self.pressed.contains(&key)
+    }
+
+    /// Whether any key or mouse button is held down.
+    pub fn any_pressed(&self) -> bool {
+        !self.pressed.is_empty() || !self.pressed_buttons.is_empty()
+    }
+
+    /// Whether the key was pressed this frame.
+    pub fn just_pressed(&self, key: Key) -> bool {
+        self.pressed.contains(&key) && !self.previously_pressed.contains(&key)
+    }
+
+    /// Whether the key was released this frame.
+    pub fn just_released(&self, key: Key) -> bool {
+        !self.pressed.contains(&key) && self.previously_pressed.contains(&key)
+    }
+
+    /// Whether the mouse button is held down.
+    pub fn button_pressed(&self, button: MouseButton) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
+    /// Whether the mouse button was pressed this frame.
+    pub fn button_just_pressed(&self, button: MouseButton) -> bool {
+        self.pressed_buttons.contains(&button) && !self.previously_pressed_buttons.contains(&button)
+    }
+
+    /// Whether the mouse button was released this frame.
+    pub fn button_just_released(&self, button: MouseButton) -> bool {
+        !self.pressed_buttons.contains(&button) && self.previously_pressed_buttons.contains(&button)
+    }
+
+    /// Cursor position in physical pixels, from the top left corner of the window.
+    pub fn cursor_position(&self) -> m::Vec2 {
+        self.cursor_position
+    }
+
+    /// Cursor position in normalized device coordinates of an area of the window, given as x, y,
+    /// width and height in pixels like ViewportRect::pixels returns. y points up.
+    pub fn cursor_ndc(&self, [x, y, width, height]: [f32; 4]) -> m::Vec2 {
+        m::vec2(
+            (self.cursor_position.x - x) / width * 2.0 - 1.0,
+            1.0 - (self.cursor_position.y - y) / height * 2.0,
+        )
+    }
+
+    /// Mouse movement since the previous frame.
+    pub fn mouse_delta(&self) -> m::Vec2 {
+        self.mouse_delta
+    }
+
+    /// Starts a new frame. Should be called before the frame's input events are handled.
+    pub fn begin_frame(&mut self) {
+        self.previously_pressed.clone_from(&self.pressed);
+        self.previously_pressed_buttons
+            .clone_from(&self.pressed_buttons);
+        self.mouse_delta = m::Vec2::ZERO;
+    }
+
+    pub fn on_input_event(&mut self, input_event: &InputEvent) {
+        match input_event {
+            InputEvent::Key(key_event) => self.on_key_event(*key_event),
+            InputEvent::MouseMotion((dx, dy)) => {
+                self.mouse_delta += m::vec2(*dx as f32, *dy as f32);
+            }
+            InputEvent::MouseScroll(_) => {}
+            InputEvent::CursorMoved((x, y)) => {
+                self.cursor_position = m::vec2(*x as f32, *y as f32);
+            }
+            InputEvent::MouseButton(MouseButtonEvent { button, state }) => {
+                match state {
+                    KeyState::Down => self.pressed_buttons.insert(*button),
+                    KeyState::Up => self.pressed_buttons.remove(button),
+                };
+            }
+        }
+    }
+
+    pub fn on_key_event(&mut self, key_event: KeyEvent) {
+        match key_event.state {
+            KeyState::Down => self.pressed.insert(key_event.key),
+            KeyState::Up => self.pressed.remove(&key_event.key),
+        };
+    }
+}
+
 impl From<winit::event::ElementState> for KeyState {
     fn from(element_state: winit::event::ElementState) -> Self {
         use winit::event::ElementState;
@@ -28,7 +190,7 @@ impl From<winit::event::ElementState> for KeyState {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Key {
     /// Left mouse button
     LMouseButton,
@@ -271,3 +433,64 @@ impl Key {
         }
     }
 }
+
+#[test]
+fn test_input_just_pressed() {
+    let mut input = Input::default();
+
+    // frame 1
+    input.begin_frame();
+    input.on_key_event(KeyEvent {
+        key: Key::W,
+        state: KeyState::Down,
+    });
+    assert!(input.pressed(Key::W));
+    assert!(input.just_pressed(Key::W));
+    assert!(!input.just_released(Key::W));
+
+    // frame 2, still held
+    input.begin_frame();
+    assert!(input.pressed(Key::W));
+    assert!(!input.just_pressed(Key::W));
+
+    // frame 3
+    input.begin_frame();
+    input.on_key_event(KeyEvent {
+        key: Key::W,
+        state: KeyState::Up,
+    });
+    assert!(!input.pressed(Key::W));
+    assert!(input.just_released(Key::W));
+}
+
+#[test]
+fn test_input_mouse_state() {
+    let mut input = Input::default();
+
+    // frame 1
+    input.begin_frame();
+    input.on_input_event(&InputEvent::CursorMoved((600.0, 150.0)));
+    input.on_input_event(&InputEvent::MouseMotion((2.0, -1.0)));
+    input.on_input_event(&InputEvent::MouseMotion((3.0, 0.5)));
+    input.on_input_event(&InputEvent::MouseButton(MouseButtonEvent {
+        button: MouseButton::Right,
+        state: KeyState::Down,
+    }));
+
+    assert_eq!(input.cursor_position(), m::vec2(600.0, 150.0));
+    assert_eq!(input.mouse_delta(), m::vec2(5.0, -0.5));
+    assert!(input.button_just_pressed(MouseButton::Right));
+    assert!(!input.button_pressed(MouseButton::Left));
+
+    // the center of the right half of an 800x600 window is at x = 0 in the half's NDC, the top
+    // quarter of the window is at y = 0.5
+    let ndc = input.cursor_ndc([400.0, 0.0, 400.0, 600.0]);
+    assert!(ndc.abs_diff_eq(m::vec2(0.0, 0.5), 1e-6));
+
+    // frame 2, the delta is reset and the button is still held
+    input.begin_frame();
+    assert_eq!(input.mouse_delta(), m::Vec2::ZERO);
+    assert_eq!(input.cursor_position(), m::vec2(600.0, 150.0));
+    assert!(input.button_pressed(MouseButton::Right));
+    assert!(!input.button_just_pressed(MouseButton::Right));
+}
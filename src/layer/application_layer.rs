@@ -1,10 +1,101 @@
+use crate::events::{self, event::InputEvent, event::WindowResizeEvent, EventReader, Events};
+use crate::input::Input;
 use crate::{GraphicsContext, Layer};
 use legion::system;
 use legion::systems::{CommandBuffer, Step};
-use legion::{Resources, Schedule};
+use legion::{Resources, Schedule, World};
+use std::time::{Duration, Instant};
+use winit::event_loop::ControlFlow;
 
 pub struct Time {
     clock: crate::time::Clock,
+    scale: crate::time::TimeScale,
+    /// Elapsed time, accumulated from scaled delta times.
+    scaled_elapsed: Duration,
+    /// Duration of a fixed time step.
+    fixed_delta_time: Duration,
+}
+
+/// Accumulates frame time to run fixed-step systems a whole number of times per frame.
+#[derive(Default)]
+pub struct FixedTime {
+    /// Frame time not yet consumed by fixed steps.
+    accumulator: Duration,
+}
+
+/// Upper limit of fixed steps per frame, to avoid falling further and further behind if the fixed
+/// steps take longer than the time they simulate.
+const MAX_FIXED_STEPS_PER_FRAME: u32 = 8;
+
+/// Caps the frame rate by waiting until the target frame time has passed since the previous frame
+/// started. Disabled by default.
+pub struct FrameLimiter {
+    /// Frames per second to limit to, or None to not limit the frame rate.
+    pub target_fps: Option<f64>,
+    frame_start: Instant,
+}
+
+/// How long before the end of the frame time the limiter stops sleeping and spins instead, since
+/// thread::sleep can overshoot by about a millisecond on most platforms.
+const FRAME_LIMITER_SPIN_DURATION: Duration = Duration::from_millis(1);
+
+/// Set to exit the application at the end of the frame.
+#[derive(Debug, Default)]
+pub struct AppExit {
+    pub requested: bool,
+}
+
+/// When the event loop draws frames.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RedrawMode {
+    /// Draws frames back to back.
+    Continuous,
+    /// Waits for events between frames, and only draws one after input, a window event, or a call
+    /// to Redraw::request. Idles while nothing changes. The scene animations are paused.
+    Reactive,
+}
+
+/// Decides when the event loop requests the next frame, see RedrawMode.
+#[derive(Debug)]
+pub struct Redraw {
+    pub mode: RedrawMode,
+    /// Whether a frame was requested since the last one was drawn.
+    requested: bool,
+}
+
+penguin_util::impl_default!(Redraw, Redraw::new(RedrawMode::Continuous));
+
+impl Redraw {
+    pub fn new(mode: RedrawMode) -> Self {
+        // the first frame is always drawn
+        Self {
+            mode,
+            requested: true,
+        }
+    }
+
+    /// Requests another frame in reactive mode. Systems that animate something call this every
+    /// frame they run.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Whether the next frame should be drawn, and clears the request. Called by the event loop.
+    pub fn take_request(&mut self) -> bool {
+        let redraw = self.mode == RedrawMode::Continuous || self.requested;
+        self.requested = false;
+        redraw
+    }
+
+    /// Control flow for the event loop at the end of an iteration: keep polling while frames are
+    /// wanted, otherwise sleep until the next event.
+    pub fn control_flow(&self) -> ControlFlow {
+        if self.mode == RedrawMode::Continuous || self.requested {
+            ControlFlow::Poll
+        } else {
+            ControlFlow::Wait
+        }
+    }
 }
 
 pub struct ApplicationLayer;
@@ -14,6 +105,14 @@ impl Layer for ApplicationLayer {
         log::warn!("INIT APPLICATION LAYER ----------------");
 
         r.insert(Time::default());
+        r.insert(FixedTime::default());
+        r.insert(FrameLimiter::default());
+        r.insert(Input::default());
+        r.insert(AppExit::default());
+        r.insert(Redraw::default());
+
+        events::register_event_type::<WindowResizeEvent>(r);
+        events::register_event_type::<InputEvent>(r);
     }
 
     fn startup_steps() -> Option<Vec<Step>> {
@@ -23,6 +122,13 @@ impl Layer for ApplicationLayer {
     fn run_steps() -> Option<Vec<Step>> {
         Some(
             Schedule::builder()
+                // waits before anything else in the frame, which paces frames the same as waiting
+                // at the end of the previous frame
+                .add_thread_local(limit_frame_rate_system())
+                .add_system(events::update_events_system::<WindowResizeEvent>())
+                .add_system(events::update_events_system::<InputEvent>())
+                .add_system(update_input_system(EventReader::default()))
+                .add_system(redraw_while_pressed_system())
                 .add_system(update_delta_time_system())
                 .build()
                 .into_vec(),
@@ -30,27 +136,121 @@ impl Layer for ApplicationLayer {
     }
 }
 
+#[system]
+fn update_input(
+    #[state] input_reader: &mut EventReader<InputEvent>,
+    #[resource] input_events: &Events<InputEvent>,
+    #[resource] input: &mut Input,
+) {
+    input.begin_frame();
+
+    for input_event in input_reader.iter(input_events) {
+        input.on_input_event(input_event);
+    }
+}
+
+/// Keeps drawing frames while a key or mouse button is held, e.g. to move the camera, since
+/// holding it down doesn't send any events.
+#[system]
+fn redraw_while_pressed(#[resource] input: &Input, #[resource] redraw: &mut Redraw) {
+    if input.any_pressed() {
+        redraw.request();
+    }
+}
+
+#[system]
+fn limit_frame_rate(#[resource] frame_limiter: &mut FrameLimiter) {
+    frame_limiter.wait();
+}
+
 #[system]
 fn update_delta_time(#[resource] dt: &mut Time) {
-    dt.clock.tick();
+    dt.tick();
 }
 
 penguin_util::impl_default!(
     Time,
     Self {
         clock: crate::time::Clock::start(),
+        scale: crate::time::TimeScale::default(),
+        scaled_elapsed: Duration::ZERO,
+        fixed_delta_time: Duration::from_secs_f64(1.0 / 60.0),
     }
 );
 impl Time {
+    pub fn fixed_delta_time(&self) -> Duration {
+        self.fixed_delta_time
+    }
+
+    pub fn set_fixed_delta_time(&mut self, fixed_delta_time: Duration) {
+        assert!(
+            !fixed_delta_time.is_zero(),
+            "fixed delta time can't be zero"
+        );
+        self.fixed_delta_time = fixed_delta_time;
+    }
+
+    /// Ticks the clock and advances the scaled elapsed time.
+    fn tick(&mut self) {
+        self.clock.tick();
+        self.scaled_elapsed += self.delta_time();
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.scale.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.scale.time_scale = time_scale;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.scale.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.scale.paused = paused;
+    }
+
+    pub fn scale_mut(&mut self) -> &mut crate::time::TimeScale {
+        &mut self.scale
+    }
+
+    /// Scaled delta time, zero when paused.
     pub fn delta_time(&self) -> std::time::Duration {
+        self.scale.apply(self.clock.last_delta_time)
+    }
+
+    /// Delta time unaffected by time scale and pausing.
+    pub fn unscaled_delta_time(&self) -> std::time::Duration {
         self.clock.last_delta_time
     }
 
+    /// Real duration of the previous frame. The other delta times are clamped to the max delta
+    /// time, so that stalls don't make animations jump.
+    pub fn unclamped_delta_time(&self) -> std::time::Duration {
+        self.clock.unclamped_delta_time
+    }
+
+    pub fn max_delta_time(&self) -> Duration {
+        self.clock.max_delta_time
+    }
+
+    pub fn set_max_delta_time(&mut self, max_delta_time: Duration) {
+        self.clock.max_delta_time = max_delta_time;
+    }
+
     pub fn start_time(&self) -> std::time::Instant {
         self.clock.start_time
     }
 
+    /// Scaled time elapsed since start.
     pub fn elapsed(&self) -> std::time::Duration {
+        self.scaled_elapsed
+    }
+
+    /// Time elapsed since start, unaffected by time scale and pausing.
+    pub fn unscaled_elapsed(&self) -> std::time::Duration {
         self.clock.start_time.elapsed()
     }
 
@@ -70,3 +270,156 @@ impl Time {
         self.delta_time().as_secs_f64()
     }
 }
+
+impl FixedTime {
+    /// Adds the frame's delta time to the accumulator and returns the number of fixed steps to run.
+    /// The remainder is kept for the next frame.
+    pub fn accumulate(&mut self, delta_time: Duration, fixed_delta_time: Duration) -> u32 {
+        self.accumulator += delta_time;
+
+        let mut steps = 0;
+        while self.accumulator >= fixed_delta_time {
+            self.accumulator -= fixed_delta_time;
+            steps += 1;
+
+            if steps == MAX_FIXED_STEPS_PER_FRAME {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+        }
+
+        steps
+    }
+
+    /// Accumulated time not yet consumed by fixed steps, as a fraction of the fixed delta time.
+    /// Useful for interpolating between fixed steps.
+    pub fn overstep_fraction(&self, fixed_delta_time: Duration) -> f32 {
+        self.accumulator.as_secs_f32() / fixed_delta_time.as_secs_f32()
+    }
+}
+
+penguin_util::impl_default!(
+    FrameLimiter,
+    Self {
+        target_fps: None,
+        frame_start: Instant::now(),
+    }
+);
+impl FrameLimiter {
+    /// Target duration of a frame, or None if the frame rate isn't limited.
+    pub fn target_frame_time(&self) -> Option<Duration> {
+        self.target_fps
+            .filter(|fps| *fps > 0.0)
+            .map(|fps| Duration::from_secs_f64(1.0 / fps))
+    }
+
+    /// Sleeps until shortly before the target frame time has passed since the previous call, then
+    /// spins for the rest, and starts the next frame. Returns immediately if the frame already took
+    /// longer.
+    pub fn wait(&mut self) {
+        if let Some(target_frame_time) = self.target_frame_time() {
+            let deadline = self.frame_start + target_frame_time;
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining > FRAME_LIMITER_SPIN_DURATION {
+                std::thread::sleep(remaining - FRAME_LIMITER_SPIN_DURATION);
+            }
+
+            while Instant::now() < deadline {
+                std::hint::spin_loop();
+            }
+        }
+
+        self.frame_start = Instant::now();
+    }
+}
+
+/// Runs the fixed-step schedule once for every fixed time step accumulated since the previous frame.
+/// Should be called once per frame, after Time has been updated.
+pub fn run_fixed_steps(
+    fixed_schedule: &mut Schedule,
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    let steps = {
+        let time = resources.get::<Time>().expect("Time resource");
+        let mut fixed_time = resources
+            .get_mut::<FixedTime>()
+            .expect("FixedTime resource");
+
+        fixed_time.accumulate(time.delta_time(), time.fixed_delta_time())
+    };
+
+    for _ in 0..steps {
+        fixed_schedule.execute(world, resources);
+    }
+}
+
+#[test]
+fn test_fixed_time() {
+    let mut fixed_time = FixedTime::default();
+    let fixed_delta_time = Duration::from_millis(16);
+
+    assert_eq!(
+        fixed_time.accumulate(Duration::from_millis(50), fixed_delta_time),
+        3
+    );
+    assert_eq!(fixed_time.accumulator, Duration::from_millis(2));
+
+    // the remainder carries over to the next frame
+    assert_eq!(
+        fixed_time.accumulate(Duration::from_millis(14), fixed_delta_time),
+        1
+    );
+    assert_eq!(fixed_time.accumulator, Duration::ZERO);
+}
+
+#[test]
+fn test_time_scale() {
+    let mut time = Time::default();
+    time.clock.last_delta_time = Duration::from_millis(100);
+
+    time.set_time_scale(0.5);
+    assert_eq!(time.delta_time(), Duration::from_millis(50));
+    assert_eq!(time.unscaled_delta_time(), Duration::from_millis(100));
+
+    time.set_paused(true);
+    assert_eq!(time.delta_time(), Duration::ZERO);
+    assert_eq!(time.unscaled_delta_time(), Duration::from_millis(100));
+}
+
+#[test]
+fn test_frame_limiter() {
+    let mut frame_limiter = FrameLimiter::default();
+    assert_eq!(frame_limiter.target_frame_time(), None);
+
+    frame_limiter.target_fps = Some(100.0);
+    let target_frame_time = frame_limiter.target_frame_time().unwrap();
+    assert!((target_frame_time.as_secs_f64() - 0.01).abs() < 1e-6);
+
+    frame_limiter.wait();
+    let frame_start = Instant::now();
+    frame_limiter.wait();
+    assert!(frame_start.elapsed() >= Duration::from_millis(9));
+}
+
+#[test]
+fn test_redraw() {
+    let mut redraw = Redraw::new(RedrawMode::Reactive);
+
+    // the first frame is drawn, then the loop waits until a frame is requested
+    assert!(redraw.take_request());
+    assert!(!redraw.take_request());
+    assert_eq!(redraw.control_flow(), ControlFlow::Wait);
+
+    redraw.request();
+    assert_eq!(redraw.control_flow(), ControlFlow::Poll);
+    assert!(redraw.take_request());
+    assert!(!redraw.take_request());
+
+    // continuous mode draws every frame
+    redraw.mode = RedrawMode::Continuous;
+    assert!(redraw.take_request());
+    assert!(redraw.take_request());
+    assert_eq!(redraw.control_flow(), ControlFlow::Poll);
+}
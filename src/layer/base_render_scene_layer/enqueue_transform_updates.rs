@@ -1,15 +1,24 @@
 ///! Systems to update cpu-side render objects data and mark the updated data as "should reupload to gpu memory".
 // todo: Separate model matrices from the render objects.
 use super::*;
-use crate::components::{Rotation, Scale, Translation};
+use crate::components::{
+    AlphaCutoff, Color, MeshComponent, Rotation, Scale, Translation, Visible, WorldTransform,
+};
 use legion::component;
 use legion::maybe_changed;
 
 pub fn steps() -> Vec<Step> {
     Schedule::builder()
+        .add_system(add_world_transform_system())
+        .flush()
         .add_system(translation_system())
         .add_system(translation_rotation_system())
         .add_system(translation_rotation_scale_system())
+        .add_system(apply_model_matrix_updates_system())
+        .add_system(color_system())
+        .add_system(alpha_cutoff_system())
+        .add_system(visible_system())
+        .add_system(mesh_system())
         .build()
         .into_vec()
 }
@@ -21,28 +30,44 @@ pub fn steps() -> Vec<Step> {
     & !component::<Scale >()
 )]
 fn translation(
-    render_obj: &Handle<RenderObject>,
+    render_obj: Option<&Handle<RenderObject>>,
+    sub_mesh_render_objs: Option<&SubMeshRenderObjects>,
+    world_transform: Option<&mut WorldTransform>,
     translation: &Translation,
-    #[resource] render_objs: &mut RenderObjects,
+    #[resource] updates: &mut ModelMatrixUpdates<TranslationUpdates>,
 ) {
-    render_objs.enqueue_model_matrix_update(*render_obj, m::Mat4::from_translation(translation.0));
+    let model_matrix = m::Mat4::from_translation(translation.0);
+
+    update_transform(
+        model_matrix,
+        render_obj,
+        sub_mesh_render_objs,
+        world_transform,
+        updates,
+    );
 }
 
 #[system(for_each)]
 #[filter(
-    maybe_changed::<Translation>()
-    | maybe_changed::<Rotation>()
+    (maybe_changed::<Translation>() | maybe_changed::<Rotation>())
     & !component::<Scale>()
 )]
 fn translation_rotation(
-    render_obj: &Handle<RenderObject>,
+    render_obj: Option<&Handle<RenderObject>>,
+    sub_mesh_render_objs: Option<&SubMeshRenderObjects>,
+    world_transform: Option<&mut WorldTransform>,
     translation: &Translation,
     rotation: &Rotation,
-    #[resource] render_objs: &mut RenderObjects,
+    #[resource] updates: &mut ModelMatrixUpdates<TranslationRotationUpdates>,
 ) {
-    render_objs.enqueue_model_matrix_update(
-        *render_obj,
-        m::Mat4::from_rotation_translation(rotation.0, translation.0),
+    let model_matrix = m::Mat4::from_rotation_translation(rotation.0, translation.0);
+
+    update_transform(
+        model_matrix,
+        render_obj,
+        sub_mesh_render_objs,
+        world_transform,
+        updates,
     );
 }
 
@@ -53,14 +78,257 @@ fn translation_rotation(
     | maybe_changed::<Scale>()
 )]
 fn translation_rotation_scale(
-    render_obj: &Handle<RenderObject>,
+    render_obj: Option<&Handle<RenderObject>>,
+    sub_mesh_render_objs: Option<&SubMeshRenderObjects>,
+    world_transform: Option<&mut WorldTransform>,
     translation: &Translation,
     rotation: &Rotation,
     scale: &Scale,
+    #[resource] updates: &mut ModelMatrixUpdates<TranslationRotationScaleUpdates>,
+) {
+    let model_matrix = m::Mat4::from_scale_rotation_translation(scale.0, rotation.0, translation.0);
+
+    update_transform(
+        model_matrix,
+        render_obj,
+        sub_mesh_render_objs,
+        world_transform,
+        updates,
+    );
+}
+
+/// Writes the entity's WorldTransform, and pushes the matrix as the model matrix of its render
+/// objects, if it has any.
+fn update_transform<S>(
+    model_matrix: m::Mat4,
+    render_obj: Option<&Handle<RenderObject>>,
+    sub_mesh_render_objs: Option<&SubMeshRenderObjects>,
+    world_transform: Option<&mut WorldTransform>,
+    updates: &mut ModelMatrixUpdates<S>,
+) {
+    if let Some(world_transform) = world_transform {
+        world_transform.0 = model_matrix;
+    }
+
+    if let Some(render_obj) = render_obj {
+        for render_obj in SubMeshRenderObjects::all(*render_obj, sub_mesh_render_objs) {
+            updates.push(render_obj, model_matrix);
+        }
+    }
+}
+
+/// Adds a WorldTransform to entities with a Translation that don't have one yet, such as entities
+/// spawned from a scene or in the editor. The transform systems keep it up to date afterwards.
+#[system(for_each)]
+#[filter(!component::<WorldTransform>())]
+fn add_world_transform(
+    cmd: &mut legion::systems::CommandBuffer,
+    entity: &Entity,
+    translation: &Translation,
+    rotation: Option<&Rotation>,
+    scale: Option<&Scale>,
+) {
+    cmd.add_component(*entity, WorldTransform::new(translation, rotation, scale));
+}
+
+/// Applies the model matrices pushed by the transform systems. Takes their ModelMatrixUpdates
+/// mutably, so it's scheduled after all of them.
+#[system]
+fn apply_model_matrix_updates(
+    #[resource] translation_updates: &mut ModelMatrixUpdates<TranslationUpdates>,
+    #[resource] translation_rotation_updates: &mut ModelMatrixUpdates<TranslationRotationUpdates>,
+    #[resource] translation_rotation_scale_updates: &mut ModelMatrixUpdates<
+        TranslationRotationScaleUpdates,
+    >,
+    #[resource] render_objs: &mut RenderObjects,
+) {
+    let updates = translation_updates
+        .drain()
+        .chain(translation_rotation_updates.drain())
+        .chain(translation_rotation_scale_updates.drain());
+
+    for (render_obj, model_matrix) in updates {
+        render_objs.enqueue_model_matrix_update(render_obj, model_matrix);
+    }
+}
+
+#[system(for_each)]
+#[filter(maybe_changed::<Color>())]
+fn color(
+    render_obj: &Handle<RenderObject>,
+    sub_mesh_render_objs: Option<&SubMeshRenderObjects>,
+    color: &Color,
+    mesh: &MeshComponent,
+    #[resource] render_objs: &mut RenderObjects,
+    #[resource] sub_meshes: &SubMeshes,
+) {
+    for (render_obj, sub_mesh) in
+        SubMeshRenderObjects::all(*render_obj, sub_mesh_render_objs).zip(&sub_meshes[mesh.0])
+    {
+        render_objs.enqueue_color_update(render_obj, color.0 * sub_mesh.diffuse_color);
+    }
+}
+
+#[system(for_each)]
+#[filter(maybe_changed::<AlphaCutoff>())]
+fn alpha_cutoff(
+    render_obj: &Handle<RenderObject>,
+    sub_mesh_render_objs: Option<&SubMeshRenderObjects>,
+    alpha_cutoff: &AlphaCutoff,
+    #[resource] render_objs: &mut RenderObjects,
+) {
+    for render_obj in SubMeshRenderObjects::all(*render_obj, sub_mesh_render_objs) {
+        render_objs.enqueue_alpha_cutoff_update(render_obj, alpha_cutoff.0);
+    }
+}
+
+#[system(for_each)]
+#[filter(maybe_changed::<Visible>())]
+fn visible(
+    render_obj: &Handle<RenderObject>,
+    sub_mesh_render_objs: Option<&SubMeshRenderObjects>,
+    visible: &Visible,
+    #[resource] render_objs: &mut RenderObjects,
+) {
+    for render_obj in SubMeshRenderObjects::all(*render_obj, sub_mesh_render_objs) {
+        render_objs.enqueue_visibility_update(render_obj, visible.0);
+    }
+}
+
+/// Updates the mesh of the entity's render object. Entities that have or get a mesh with more than
+/// one material have their render objects removed instead, and are registered again with a render
+/// object per material next frame.
+#[system(for_each)]
+#[filter(maybe_changed::<MeshComponent>())]
+fn mesh(
+    cmd: &mut legion::systems::CommandBuffer,
+    entity: &Entity,
+    render_obj: &Handle<RenderObject>,
+    sub_mesh_render_objs: Option<&SubMeshRenderObjects>,
+    mesh: &MeshComponent,
+    color: Option<&Color>,
     #[resource] render_objs: &mut RenderObjects,
+    #[resource] sub_meshes: &SubMeshes,
 ) {
-    render_objs.enqueue_model_matrix_update(
-        *render_obj,
-        m::Mat4::from_scale_rotation_translation(scale.0, rotation.0, translation.0),
+    let sub_meshes = &sub_meshes[mesh.0];
+
+    if sub_mesh_render_objs.is_none() && sub_meshes.len() == 1 {
+        let sub_mesh = &sub_meshes[0];
+
+        if render_objs.render_objects[*render_obj].mesh.id != sub_mesh.mesh.id {
+            render_objs.set_object_mesh(*render_obj, sub_mesh.mesh);
+
+            let color = color.map(|color| color.0).unwrap_or(m::Vec4::ONE);
+            render_objs.enqueue_color_update(*render_obj, color * sub_mesh.diffuse_color);
+        }
+        return;
+    }
+
+    let current_meshes = SubMeshRenderObjects::all(*render_obj, sub_mesh_render_objs)
+        .map(|render_obj| render_objs.render_objects[render_obj].mesh.id);
+    let meshes = sub_meshes.iter().map(|sub_mesh| sub_mesh.mesh.id);
+    if current_meshes.eq(meshes) {
+        return;
+    }
+
+    for render_obj in SubMeshRenderObjects::all(*render_obj, sub_mesh_render_objs) {
+        render_objs.remove_object(render_obj);
+    }
+    cmd.remove_component::<Handle<RenderObject>>(*entity);
+    cmd.remove_component::<SubMeshRenderObjects>(*entity);
+}
+
+#[test]
+fn test_translation_rotation_skips_scaled_entities() {
+    let mut world = legion::World::default();
+    let mut resources = legion::Resources::default();
+    resources.insert(ModelMatrixUpdates::<TranslationRotationUpdates>::default());
+
+    let mut schedule = Schedule::builder()
+        .add_system(translation_rotation_system())
+        .build();
+
+    let scaled = world.push((
+        Handle::<RenderObject>::from(0),
+        Translation(m::Vec3::ZERO),
+        Rotation(m::Quat::IDENTITY),
+        Scale(m::Vec3::splat(2.0)),
+    ));
+    let unscaled = world.push((
+        Handle::<RenderObject>::from(1),
+        Translation(m::Vec3::ZERO),
+        Rotation(m::Quat::IDENTITY),
+    ));
+
+    schedule.execute(&mut world, &mut resources);
+    resources
+        .get_mut::<ModelMatrixUpdates<TranslationRotationUpdates>>()
+        .unwrap()
+        .drain()
+        .for_each(drop);
+
+    for entity in [scaled, unscaled] {
+        world
+            .entry_mut(entity)
+            .unwrap()
+            .get_component_mut::<Translation>()
+            .unwrap()
+            .0 = m::vec3(1.0, 2.0, 3.0);
+    }
+    schedule.execute(&mut world, &mut resources);
+
+    // the scaled entity's matrix comes from the translation_rotation_scale system
+    let updated = resources
+        .get_mut::<ModelMatrixUpdates<TranslationRotationUpdates>>()
+        .unwrap()
+        .drain()
+        .map(|(render_obj, _)| render_obj.id)
+        .collect::<Vec<_>>();
+    assert_eq!(updated, vec![1]);
+}
+
+#[test]
+fn test_world_transform() {
+    let mut world = legion::World::default();
+    let mut resources = legion::Resources::default();
+    resources.insert(ModelMatrixUpdates::<TranslationRotationUpdates>::default());
+
+    let mut schedule = Schedule::builder()
+        .add_system(add_world_transform_system())
+        .flush()
+        .add_system(translation_rotation_system())
+        .build();
+
+    // entities without a render object get a world transform too
+    let entity = world.push((
+        Translation(m::vec3(1.0, 2.0, 3.0)),
+        Rotation(m::Quat::from_rotation_z(1.0)),
+    ));
+
+    let world_transform = |world: &legion::World| {
+        *world
+            .entry_ref(entity)
+            .unwrap()
+            .get_component::<WorldTransform>()
+            .unwrap()
+    };
+
+    schedule.execute(&mut world, &mut resources);
+    assert_eq!(
+        world_transform(&world).translation(),
+        m::vec3(1.0, 2.0, 3.0)
+    );
+
+    world
+        .entry_mut(entity)
+        .unwrap()
+        .get_component_mut::<Translation>()
+        .unwrap()
+        .0 = m::vec3(4.0, 5.0, 6.0);
+    schedule.execute(&mut world, &mut resources);
+
+    assert_eq!(
+        world_transform(&world).0,
+        m::Mat4::from_rotation_translation(m::Quat::from_rotation_z(1.0), m::vec3(4.0, 5.0, 6.0))
     );
 }
@@ -3,24 +3,22 @@ mod enqueue_transform_updates;
 use crate::layer::scene_layer;
 use crate::{
     bind_groups, mesh, render_scene, GraphicsContext, Layer, RenderObjectDescriptor, Vertex,
-    VertexArrayBuffer, MAX_DRAW_COMMANDS,
+    VertexArena, MATERIAL_TEXTURES,
 };
 use legion::systems::{CommandBuffer, Step};
 use legion::{Entity, Resources, Schedule};
-use penguin_util::handle::{Handle, HandleMap};
-use std::{mem, slice};
+use penguin_util::handle::Handle;
+use std::mem;
 
 use crate::components::Translation;
 use legion::system;
 use wgpu::{BindGroupLayoutEntry, ShaderStages};
 
 use crate::events::PenguinEventSender;
-use crate::render_scene::mesh_pass;
-use crate::render_scene::mesh_pass::{IndirectBatch, LegacyMeshPass, PassObject};
-use crate::render_scene::RenderObject;
+use crate::render_scene::{RenderLimits, RenderObject, RenderObjectDrawCommands};
 use crate::{events, DrawOutputInfo, RenderInstance};
 use macaw as m;
-use penguin_util::raw_gpu_types::{DrawIndexedIndirect, DrawIndirectCount};
+use penguin_util::raw_gpu_types::{DrawIndexedIndirect, DrawIndirect, DrawIndirectCount};
 use penguin_util::GpuBuffer;
 use penguin_util::GpuBufferDeviceExt;
 
@@ -37,13 +35,52 @@ mod resources {
     pub struct Meshes(pub Vec<mesh::Mesh>);
     impl_deref!(mut Meshes, Vec<mesh::Mesh>);
 
-    pub struct RenderObjects {
-        pub render_objects: HandleMap<RenderObject>,
-        pub should_rebuild_batches: bool,
-        pub render_objects_to_reupload: Vec<Handle<RenderObject>>,
-        pub forward_pass: mesh_pass::LegacyMeshPass,
+    /// The sub-meshes of each mesh asset, one per material. Indexed by MeshComponent.
+    pub struct SubMeshes(pub Vec<Vec<mesh::SubMesh>>);
+    impl_deref!(mut SubMeshes, Vec<Vec<mesh::SubMesh>>);
+
+    /// Texture assets loaded into the layers of the material texture array, in order. The fixed
+    /// MATERIAL_TEXTURES come first, then the diffuse textures of the mesh assets' materials,
+    /// which are absolute paths.
+    pub struct MaterialTextures(pub Vec<String>);
+    impl_deref!(MaterialTextures, Vec<String>);
+
+    pub use crate::render_scene::RenderObjects;
+
+    /// Model matrices computed by one of the transform systems this frame, applied to the
+    /// RenderObjects once they've all run. Each transform system has its own buffer, told apart by
+    /// the marker type S, so the systems can run in parallel without sharing one.
+    pub struct ModelMatrixUpdates<S> {
+        updates: Vec<(Handle<RenderObject>, m::Mat4)>,
+        _system: std::marker::PhantomData<S>,
+    }
+
+    impl<S> Default for ModelMatrixUpdates<S> {
+        fn default() -> Self {
+            Self {
+                updates: Vec::new(),
+                _system: std::marker::PhantomData,
+            }
+        }
     }
 
+    impl<S> ModelMatrixUpdates<S> {
+        pub fn push(&mut self, render_object: Handle<RenderObject>, model_matrix: m::Mat4) {
+            self.updates.push((render_object, model_matrix));
+        }
+
+        pub fn drain(&mut self) -> std::vec::Drain<(Handle<RenderObject>, m::Mat4)> {
+            self.updates.drain(..)
+        }
+    }
+
+    /// Marks the ModelMatrixUpdates of the translation system.
+    pub struct TranslationUpdates;
+    /// Marks the ModelMatrixUpdates of the translation_rotation system.
+    pub struct TranslationRotationUpdates;
+    /// Marks the ModelMatrixUpdates of the translation_rotation_scale system.
+    pub struct TranslationRotationScaleUpdates;
+
     /// The max value for possible draw commands (max draw count read from the draw count buffer)
     pub struct MaxDrawCount(pub u32);
     impl_deref!(mut MaxDrawCount, u32);
@@ -54,6 +91,8 @@ mod resources {
     /// The render_objects array in RenderObjects, uploaded to GPU memory.
     pub struct RenderObjectsBuffer {
         pub buffer: GpuBuffer<RenderObject>,
+        /// The draw_commands array in RenderObjects.
+        pub draw_commands_buffer: GpuBuffer<RenderObjectDrawCommands>,
     }
     // todo: Separate instances (model matrices) from the RenderObject buffer.
 
@@ -64,6 +103,10 @@ mod resources {
         pub clear_buffer: GpuBuffer<DrawIndexedIndirect>,
         /// Buffer that the compute shader fills with draw commands, and instance counts.
         pub out_buffer: GpuBuffer<DrawIndexedIndirect>,
+        /// Buffer that the compute shader fills with the non-indexed draw commands.
+        pub non_indexed_out_buffer: GpuBuffer<DrawIndirect>,
+        /// Zeroed buffer used to clear the out buffers when drawing them with markers.
+        pub zeroed_buffer: wgpu::Buffer,
     }
 
     /// Buffer that maps each instance index in the DrawCommandBuffers::out_buffer to a render object.
@@ -76,7 +119,7 @@ mod resources {
     pub struct DrawCountBuffers {
         /// Buffer with the draw count set to 0. Used to reset the buffer.
         pub clear_buffer: GpuBuffer<DrawIndirectCount>,
-        /// Buffer containing the draw count. Set by the compute shader.
+        /// Buffer containing the indexed and the non-indexed draw count. Set by the compute shader.
         pub buffer: GpuBuffer<DrawIndirectCount>,
     }
 
@@ -84,7 +127,6 @@ mod resources {
     pub struct ComputeShaderDataBuffers {
         pub clear_buffer: GpuBuffer<DrawOutputInfo>,
         pub buffer: GpuBuffer<DrawOutputInfo>,
-        pub buffer_size: usize,
     }
 
     pub struct RenderInstanceBuffer {
@@ -92,59 +134,179 @@ mod resources {
     }
 }
 
-pub struct BaseRenderSceneLayer<'a> {
-    pub window: &'a winit::window::Window,
-    pub mesh_assets: &'a [&'a str],
+/// The render objects of an entity's sub-meshes after the first, for meshes with more than one
+/// material. The first sub-mesh is drawn by the entity's Handle<RenderObject>.
+pub struct SubMeshRenderObjects(pub Vec<Handle<RenderObject>>);
+
+impl SubMeshRenderObjects {
+    /// All render objects of an entity, starting with its Handle<RenderObject>.
+    pub fn all(
+        render_obj: Handle<RenderObject>,
+        sub_mesh_render_objs: Option<&Self>,
+    ) -> impl Iterator<Item = Handle<RenderObject>> + '_ {
+        std::iter::once(render_obj).chain(
+            sub_mesh_render_objs
+                .into_iter()
+                .flat_map(|render_objs| render_objs.0.iter().copied()),
+        )
+    }
 }
 
-impl Layer for BaseRenderSceneLayer<'_> {
+/// Loads a mesh asset after startup into the VertexArena, next to the meshes that are already
+/// loaded, and adds its name to MeshAssets. Returns the index of the mesh asset, for MeshComponent.
+/// Entities with it are registered by register_render_objects like any other.
+pub fn load_mesh_asset(r: &Resources, mesh_asset_name: &str) -> anyhow::Result<usize> {
+    let context = r
+        .get::<GraphicsContext>()
+        .expect("GraphicsContextLayer should be pushed before BaseRenderSceneLayer");
+    let mut vertex_arena = r.get_mut::<VertexArena>().unwrap();
+    let mut meshes = r.get_mut::<Meshes>().unwrap();
+    let mut sub_meshes = r.get_mut::<SubMeshes>().unwrap();
+
+    let path = std::path::Path::new(env!("OUT_DIR"))
+        .join("assets/meshes")
+        .join(mesh_asset_name);
+    let asset_sub_meshes =
+        vertex_arena.load_mesh(&context.device, &context.queue, path, &mut meshes)?;
+
+    sub_meshes.push(asset_sub_meshes);
+    r.get_mut::<scene_layer::MeshAssets>()
+        .unwrap()
+        .push(mesh_asset_name.to_owned());
+
+    Ok(sub_meshes.len() - 1)
+}
+
+/// Uploads a mesh built at runtime into the VertexArena, as a mesh asset with a single untextured
+/// sub-mesh. Without indices the mesh is drawn non-indexed. Returns the index of the mesh asset,
+/// like load_mesh_asset.
+pub fn add_mesh(r: &Resources, vertices: &[mesh::MeshVertex], indices: &[u32]) -> usize {
+    let context = r
+        .get::<GraphicsContext>()
+        .expect("GraphicsContextLayer should be pushed before BaseRenderSceneLayer");
+    let mut vertex_arena = r.get_mut::<VertexArena>().unwrap();
+    let mut meshes = r.get_mut::<Meshes>().unwrap();
+    let mut sub_meshes = r.get_mut::<SubMeshes>().unwrap();
+
+    let mesh = vertex_arena.allocate(&context.device, &context.queue, vertices, indices);
+    meshes.push(mesh);
+
+    sub_meshes.push(vec![mesh::SubMesh {
+        mesh: Handle::from(meshes.len() - 1),
+        diffuse_color: m::Vec4::ONE,
+        diffuse_texture: None,
+        texture_index: None,
+    }]);
+    sub_meshes.len() - 1
+}
+
+/// Creates the render scene's buffers. Needs the GraphicsContext from GraphicsContextLayer.
+pub struct BaseRenderSceneLayer;
+
+impl Layer for BaseRenderSceneLayer {
     fn init(self, cmd: &mut CommandBuffer, r: &mut Resources) {
-        // todo: Move context to another layer, it doesn't make sense here
-        let context = penguin_util::pollster::block_on(GraphicsContext::new(&self.window));
+        if !r.contains::<RenderLimits>() {
+            r.insert(RenderLimits::default());
+        }
+        let limits = *r.get::<RenderLimits>().unwrap();
+
+        let context = r
+            .get::<GraphicsContext>()
+            .expect("GraphicsContextLayer should be pushed before BaseRenderSceneLayer");
         let device = &context.device;
 
-        let draw_commands = DrawCommandBuffers::init(device, MAX_DRAW_COMMANDS);
+        let draw_commands = DrawCommandBuffers::init(device, limits.max_draw_commands);
         let draw_counts = DrawCountBuffers::init(device);
+        let draw_count_readback =
+            render_scene::DrawCountReadback::init(device, limits.max_draw_commands);
+        let timestamp_readback = render_scene::TimestampReadback::init(device);
 
-        let instances = RenderInstanceBuffer::init(device, MAX_DRAW_COMMANDS);
-        let instances_to_render_objects = InstanceIndexToRenderObjectMapBuffer::init(device);
-        let local_shader_storage = ComputeShaderDataBuffers::init(device, MAX_DRAW_COMMANDS);
+        let instances = RenderInstanceBuffer::init(device, limits.max_instances);
+        let instances_to_render_objects =
+            InstanceIndexToRenderObjectMapBuffer::init(device, limits.max_instances);
+        let local_shader_storage = ComputeShaderDataBuffers::init(device, limits.max_draw_commands);
 
         // -------
         let mesh_assets = r.get::<scene_layer::MeshAssets>().unwrap();
-        let (vertex_array_buffer, meshes) =
-            mesh::VertexArrayBuffer::build_from_mesh_assets(device, &mesh_assets);
+        let mesh_asset_names = mesh_assets.iter().map(String::as_str).collect::<Vec<_>>();
+        let (vertex_arena, meshes, mut sub_meshes) =
+            mesh::VertexArena::build_from_mesh_assets(device, &mesh_asset_names);
         drop(mesh_assets);
-        r.remove::<scene_layer::MeshAssets>();
+
+        let mesh_textures =
+            mesh::assign_texture_layers(&mut sub_meshes, MATERIAL_TEXTURES.len() as u32);
+        let material_textures = MATERIAL_TEXTURES
+            .iter()
+            .map(|&asset_name| asset_name.to_owned())
+            .chain(
+                mesh_textures
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned()),
+            )
+            .collect::<Vec<_>>();
         // -----
 
-        let render_objects_buffer = RenderObjectsBuffer::init(device, MAX_DRAW_COMMANDS);
-        let render_objects = RenderObjects::default();
+        let render_objects_buffer = RenderObjectsBuffer::init(device, limits.max_instances);
+        let render_objects = RenderObjects::new(limits);
+
+        drop(context);
+        r.remove::<scene_layer::MeshAssets>();
 
         // base
-        r.insert(context);
         r.insert(draw_commands);
         r.insert(draw_counts);
         r.insert(MaxDrawCount(0));
+        r.insert(draw_count_readback);
+        r.insert(timestamp_readback);
+        r.insert(render_scene::RenderStats::default());
         r.insert(instances);
         r.insert(instances_to_render_objects);
         r.insert(local_shader_storage);
 
         // render objects
-        r.insert(vertex_array_buffer);
+        r.insert(vertex_arena);
         r.insert(Meshes(meshes));
+        r.insert(SubMeshes(sub_meshes));
+        r.insert(MaterialTextures(material_textures));
         r.insert(render_objects_buffer);
         r.insert(render_objects);
+        r.insert(ModelMatrixUpdates::<TranslationUpdates>::default());
+        r.insert(ModelMatrixUpdates::<TranslationRotationUpdates>::default());
+        r.insert(ModelMatrixUpdates::<TranslationRotationScaleUpdates>::default());
     }
 
     fn startup_steps() -> Option<Vec<Step>> {
         Some(startup::steps())
     }
 
+    fn order() -> i32 {
+        200
+    }
+
+    fn shutdown(r: &mut Resources) {
+        // submit pending queue writes and wait for the GPU to finish using the buffers
+        if let Some(context) = r.get::<GraphicsContext>() {
+            context.queue.submit(std::iter::empty());
+            context.device.poll(wgpu::Maintain::Wait);
+        }
+
+        // free the buffers before GraphicsContextLayer drops the graphics context
+        r.remove::<DrawCommandBuffers>();
+        r.remove::<DrawCountBuffers>();
+        r.remove::<render_scene::DrawCountReadback>();
+        r.remove::<render_scene::TimestampReadback>();
+        r.remove::<RenderInstanceBuffer>();
+        r.remove::<InstanceIndexToRenderObjectMapBuffer>();
+        r.remove::<ComputeShaderDataBuffers>();
+        r.remove::<VertexArena>();
+        r.remove::<RenderObjectsBuffer>();
+    }
+
     fn run_steps() -> Option<Vec<Step>> {
         Some(
-            enqueue_transform_updates::steps()
+            startup::steps()
                 .into_iter()
+                .chain(enqueue_transform_updates::steps())
                 .chain(
                     Schedule::builder()
                         .add_system(build_batches_system())
@@ -159,7 +321,9 @@ impl Layer for BaseRenderSceneLayer<'_> {
 
 mod startup {
     use super::*;
-    use crate::components::{MeshComponent, Rotation};
+    use crate::components::{
+        AlphaCutoff, Color, MaterialComponent, MeshComponent, Rotation, Visible,
+    };
     use legion::world::SubWorld;
     use legion::IntoQuery;
 
@@ -170,28 +334,71 @@ mod startup {
             .into_vec()
     }
 
+    /// Registers render objects for entities with a mesh that don't have one yet. Also runs every
+    /// frame, to register entities spawned after startup, such as from a loaded scene file.
     #[system(for_each)]
+    #[filter(!legion::component::<Handle<RenderObject>>())]
     fn register_render_objects(
         cmd: &mut legion::systems::CommandBuffer,
         entity: &Entity,
         mesh: &MeshComponent,
+        color: Option<&Color>,
+        material: Option<&MaterialComponent>,
+        alpha_cutoff: Option<&AlphaCutoff>,
+        visible: Option<&Visible>,
         #[resource] render_objects: &mut RenderObjects,
+        #[resource] sub_meshes: &SubMeshes,
     ) {
-        let render_obj_desc = RenderObjectDescriptor {
-            mesh_handle: Handle::from(mesh.0),
-            transform: m::Mat4::IDENTITY,
-            render_bounds: mesh::RenderBounds {
-                origin: m::Vec3::ZERO,
-                radius: 3.0,
-            },
-            draw_forward_pass: true,
-        };
-
-        let render_obj_handle = render_objects.register_object(&render_obj_desc);
+        let color = color.map(|color| color.0).unwrap_or(m::Vec4::ONE);
+
+        // one render object per material
+        let mut render_obj_handles = Vec::with_capacity(sub_meshes[mesh.0].len());
+        for sub_mesh in &sub_meshes[mesh.0] {
+            let render_obj_desc = RenderObjectDescriptor {
+                mesh_handle: sub_mesh.mesh,
+                transform: m::Mat4::IDENTITY,
+                render_bounds: mesh::RenderBounds {
+                    origin: m::Vec3::ZERO,
+                    radius: 3.0,
+                },
+                color: color * sub_mesh.diffuse_color,
+                // an explicit material overrides the texture of the mesh asset's material
+                texture_index: material
+                    .map(|material| material.0)
+                    .or(sub_mesh.texture_index)
+                    .unwrap_or_default(),
+                alpha_cutoff: alpha_cutoff.map(|cutoff| cutoff.0).unwrap_or_default(),
+                draw_forward_pass: true,
+                custom_passes: &[],
+            };
+
+            match render_objects.register_object(&render_obj_desc) {
+                Ok(render_obj_handle) => render_obj_handles.push(render_obj_handle),
+                Err(e) => {
+                    log::error!("failed to register render objects of {:?}: {:?}", entity, e);
+                    // don't leave the entity partially registered
+                    render_objects.remove_objects(&render_obj_handles);
+                    return;
+                }
+            }
+        }
+
+        // mesh assets always have at least one sub-mesh
+        let render_obj_handle = render_obj_handles[0];
+        let sub_mesh_render_objs = render_obj_handles[1..].to_vec();
+
+        if let Some(Visible(false)) = visible {
+            for &render_obj in std::iter::once(&render_obj_handle).chain(&sub_mesh_render_objs) {
+                render_objects.enqueue_visibility_update(render_obj, false);
+            }
+        }
 
         println!("registering render object {} for entity: {:?} --------------------------------------------------", render_obj_handle.id, entity);
 
         cmd.add_component(*entity, render_obj_handle);
+        if !sub_mesh_render_objs.is_empty() {
+            cmd.add_component(*entity, SubMeshRenderObjects(sub_mesh_render_objs));
+        }
     }
 }
 
@@ -204,49 +411,12 @@ fn build_batches(
     #[resource] max_draw_count: &mut MaxDrawCount,
     #[resource] meshes: &Meshes,
 ) {
-    let queue = &context.queue;
-
-    if render_objs
-        .forward_pass
-        .update_batches(&render_objs.render_objects)
-    {
-        println!("building batches.. ------------------------------------- ");
-
-        // create a draw call for each unique mesh + material combo
-        let indirect_commands = render_objs
-            .forward_pass
-            .indirect_batches
-            .iter()
-            .map(|batch: &IndirectBatch| {
-                let mesh = meshes[batch.mesh_h.id as usize];
-                println!("mesh: {:?}, max instance count: {}", mesh, batch.count);
-
-                let first_instance = batch.first as _;
-                let instance_count = 0; // set in compute shader
-                mesh.create_draw_command(first_instance, instance_count)
-            })
-            .collect::<Vec<_>>();
-
-        // assign draw commands to render objects
-        render_objs
-            .forward_pass
-            .objects
-            .inner
-            .iter()
-            .for_each(|pass_object: &PassObject| {
-                let render_object = pass_object.original_render_object;
-
-                render_objs.render_objects[render_object].draw_command_index =
-                    pass_object.draw_command_id;
-
-                render_objs.render_objects_to_reupload.push(render_object);
-            });
-
-        queue.write_buffer(
-            &draw_commands.clear_buffer,
-            0,
-            bytemuck::cast_slice(&indirect_commands),
-        );
+    if let Some(indirect_commands) = render_objs.build_batches(meshes) {
+        if !indirect_commands.is_empty() {
+            draw_commands
+                .clear_buffer
+                .write(&context.queue, 0, &indirect_commands);
+        }
 
         // update max draw count
         max_draw_count.0 = indirect_commands.len() as _;
@@ -264,57 +434,11 @@ fn reupload_updated_objects(
     #[resource] render_objects: &mut RenderObjects,
     #[resource] render_objects_buffer: &RenderObjectsBuffer,
 ) {
-    let queue = &context.queue;
-
-    while let Some(render_object_handle) = render_objects.render_objects_to_reupload.pop() {
-        let offset = mem::size_of::<RenderObject>() * render_object_handle.id as usize;
-        let render_object_data = render_objects.render_objects[render_object_handle];
-
-        queue.write_buffer(
-            &render_objects_buffer.buffer,
-            offset as _,
-            bytemuck::cast_slice(slice::from_ref(&render_object_data)),
-        );
-    }
-}
-
-impl Default for RenderObjects {
-    fn default() -> Self {
-        Self {
-            render_objects: HandleMap::new(),
-            should_rebuild_batches: true,
-            render_objects_to_reupload: Vec::new(),
-            forward_pass: mesh_pass::LegacyMeshPass::new(),
-        }
-    }
-}
-
-impl RenderObjects {
-    pub fn register_object(&mut self, desc: &RenderObjectDescriptor) -> Handle<RenderObject> {
-        let render_object: Handle<RenderObject> = self.render_objects.push(RenderObject {
-            mesh: desc.mesh_handle,
-            transform: desc.transform,
-            draw_command_index: 0,
-        });
-
-        if desc.draw_forward_pass {
-            self.forward_pass.unbatched_objects.push(render_object);
-        }
-
-        // this render object's data will need to be updated in GPU memory.
-        self.render_objects_to_reupload.push(render_object);
-
-        render_object
-    }
-
-    pub fn enqueue_model_matrix_update(
-        &mut self,
-        render_object: Handle<RenderObject>,
-        model_matrix: m::Mat4,
-    ) {
-        self.render_objects[render_object].transform = model_matrix;
-        self.render_objects_to_reupload.push(render_object);
-    }
+    render_objects.reupload(
+        &context.queue,
+        &render_objects_buffer.buffer,
+        &render_objects_buffer.draw_commands_buffer,
+    );
 }
 
 impl RenderObjectsBuffer {
@@ -327,8 +451,13 @@ impl RenderObjectsBuffer {
                 | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
+        let draw_commands_buffer =
+            render_scene::create_render_object_draw_commands_buffer(device, max_render_objects);
 
-        Self { buffer }
+        Self {
+            buffer,
+            draw_commands_buffer,
+        }
     }
 }
 
@@ -348,35 +477,58 @@ impl DrawCommandBuffers {
             mapped_at_creation: false,
         });
 
+        // copy src to allow reading back the emitted draw commands
         let buffer = device.create_buffer_t::<DrawIndexedIndirect>(&wgpu::BufferDescriptor {
             label: Some("draw indirect buffer"),
             size,
             usage: wgpu::BufferUsages::INDIRECT
                 | wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_DST,
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
             mapped_at_creation: false,
         });
 
         Self {
             clear_buffer,
             out_buffer: buffer,
+            non_indexed_out_buffer: render_scene::create_out_non_indexed_draw_commands_buffer(
+                device,
+                max_draw_commands,
+            ),
+            zeroed_buffer: render_scene::create_zeroed_draw_commands_buffer(
+                device,
+                max_draw_commands,
+            ),
         }
     }
+
+    /// Zeroes the out buffers, see render_scene::clear_emitted_draws.
+    pub fn reset(&self, cmd: &mut wgpu::CommandEncoder) {
+        render_scene::clear_emitted_draws(
+            cmd,
+            &self.zeroed_buffer,
+            &self.out_buffer,
+            &self.non_indexed_out_buffer,
+        );
+    }
 }
 
 impl DrawCountBuffers {
     pub fn init(device: &wgpu::Device) -> Self {
-        let contents = bytemuck::cast_slice(slice::from_ref(&DrawIndirectCount { count: 0 }));
+        // the indexed and the non-indexed draw count
+        let contents = bytemuck::cast_slice(&[DrawIndirectCount { count: 0 }; 2]);
 
+        // copy src to allow reading back the draw count
         let usage = wgpu::BufferUsages::INDIRECT
             | wgpu::BufferUsages::STORAGE
-            | wgpu::BufferUsages::COPY_DST;
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC;
 
         let clear_buffer =
             device.create_buffer_init_t::<DrawIndirectCount>(&wgpu::util::BufferInitDescriptor {
                 label: Some("draw indirect count buffer"),
                 contents,
-                usage: usage | wgpu::BufferUsages::COPY_SRC,
+                usage,
             });
 
         let buffer =
@@ -392,14 +544,20 @@ impl DrawCountBuffers {
         }
     }
 
-    /// Resets the buffer's draw count to 0.
+    /// Reads the current indexed draw count back to the CPU, blocking until the GPU is done with the
+    /// buffer. Prefer render_scene::DrawCountReadback for per-frame reads.
+    pub fn read_count(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> u32 {
+        self.buffer.read_all(device, queue)[0].count
+    }
+
+    /// Resets the buffer's draw counts to 0.
     pub fn reset(&self, cmd: &mut wgpu::CommandEncoder) {
         cmd.copy_buffer_to_buffer(
             &self.clear_buffer,
             0,
             &self.buffer,
             0,
-            mem::size_of::<DrawIndirectCount>() as _,
+            self.buffer.byte_len(),
         );
     }
 }
@@ -429,7 +587,6 @@ impl ComputeShaderDataBuffers {
         Self {
             clear_buffer,
             buffer,
-            buffer_size: std::mem::size_of::<DrawOutputInfo>() * max_draw_commands,
         }
     }
 
@@ -439,7 +596,7 @@ impl ComputeShaderDataBuffers {
             0,
             &self.buffer,
             0,
-            self.buffer_size as _,
+            self.buffer.byte_len(),
         );
     }
 }
@@ -466,12 +623,10 @@ impl RenderInstanceBuffer {
 }
 
 impl InstanceIndexToRenderObjectMapBuffer {
-    pub fn init(device: &wgpu::Device) -> Self {
+    pub fn init(device: &wgpu::Device, max_instances: usize) -> Self {
         let buffer = device.create_buffer_init_t::<u32>(&wgpu::util::BufferInitDescriptor {
             label: Some("final draw command indices"),
-            contents: bytemuck::cast_slice(
-                &(0..MAX_DRAW_COMMANDS).map(|_| 0_u32).collect::<Vec<_>>(),
-            ),
+            contents: bytemuck::cast_slice(&(0..max_instances).map(|_| 0_u32).collect::<Vec<_>>()),
             usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
         });
 
@@ -13,6 +13,10 @@ impl Layer for EditorLayer {
         None
     }
 
+    fn order() -> i32 {
+        400
+    }
+
     fn run_steps() -> Option<Vec<Step>> {
         None
     }
@@ -0,0 +1,43 @@
+use crate::{GraphicsContext, GraphicsContextDescriptor, Layer};
+use legion::systems::{CommandBuffer, Step};
+use legion::Resources;
+
+/// Creates the GraphicsContext, the single device and surface that the render layers pull from
+/// the resources. Pushed before them.
+pub struct GraphicsContextLayer<'a> {
+    pub window: &'a winit::window::Window,
+    pub context_descriptor: GraphicsContextDescriptor,
+}
+
+impl Layer for GraphicsContextLayer<'_> {
+    fn init(self, _cmd: &mut CommandBuffer, r: &mut Resources) {
+        if r.contains::<GraphicsContext>() {
+            log::warn!("a graphics context already exists, not creating another one");
+            return;
+        }
+
+        let context = penguin_util::pollster::block_on(GraphicsContext::new(
+            self.window,
+            &self.context_descriptor,
+        ));
+
+        r.insert(context);
+    }
+
+    fn startup_steps() -> Option<Vec<Step>> {
+        None
+    }
+
+    fn run_steps() -> Option<Vec<Step>> {
+        None
+    }
+
+    /// Before the render layers, so the context is dropped after they've freed their buffers.
+    fn order() -> i32 {
+        150
+    }
+
+    fn shutdown(r: &mut Resources) {
+        r.remove::<GraphicsContext>();
+    }
+}
@@ -0,0 +1,189 @@
+use super::{run_fixed_steps, Layer};
+use legion::systems::{CommandBuffer, Schedule, Step};
+use legion::{Resources, World};
+
+/// A layer in the LayerStack. Layers are consumed on init and their steps are associated
+/// functions, so the stack keeps the functions rather than the layers themselves.
+struct LayerEntry {
+    /// Type name of the layer, used to look it up.
+    name: &'static str,
+    order: i32,
+    enabled: bool,
+    startup_steps: fn() -> Option<Vec<Step>>,
+    run_steps: fn() -> Option<Vec<Step>>,
+    fixed_steps: fn() -> Option<Vec<Step>>,
+    shutdown: fn(&mut Resources),
+}
+
+/// The schedules built from the steps of the enabled layers.
+struct Schedules {
+    run: Schedule,
+    fixed: Schedule,
+}
+
+/// Holds the application's layers. Builds the schedules from the steps of the enabled layers,
+/// sorted by Layer::order, and rebuilds them when a layer is enabled or disabled.
+#[derive(Default)]
+pub struct LayerStack {
+    layers: Vec<LayerEntry>,
+    /// None if the schedules need to be rebuilt.
+    schedules: Option<Schedules>,
+}
+
+impl LayerStack {
+    /// Initializes the layer and adds it to the stack, enabled.
+    pub fn push<L: Layer>(&mut self, layer: L, cmd: &mut CommandBuffer, resources: &mut Resources) {
+        layer.init(cmd, resources);
+
+        self.layers.push(LayerEntry {
+            name: std::any::type_name::<L>(),
+            order: L::order(),
+            enabled: true,
+            startup_steps: L::startup_steps,
+            run_steps: L::run_steps,
+            fixed_steps: L::fixed_steps,
+            shutdown: L::shutdown,
+        });
+        self.schedules = None;
+    }
+
+    pub fn is_enabled<L: Layer>(&self) -> bool {
+        self.entry::<L>().map_or(false, |entry| entry.enabled)
+    }
+
+    /// Enables or disables the layer's steps. Returns false if the layer isn't in the stack.
+    pub fn set_enabled<L: Layer>(&mut self, enabled: bool) -> bool {
+        let name = std::any::type_name::<L>();
+
+        match self.layers.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => {
+                if entry.enabled != enabled {
+                    entry.enabled = enabled;
+                    self.schedules = None;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Runs the startup steps of the enabled layers once.
+    pub fn run_startup(&mut self, world: &mut World, resources: &mut Resources) {
+        let mut startup_schedule = self.build_schedule(|entry| entry.startup_steps);
+        startup_schedule.execute(world, resources);
+    }
+
+    /// Runs the steps of the enabled layers, followed by their fixed steps.
+    pub fn execute(&mut self, world: &mut World, resources: &mut Resources) {
+        if self.schedules.is_none() {
+            self.schedules = Some(Schedules {
+                run: self.build_schedule(|entry| entry.run_steps),
+                fixed: self.build_schedule(|entry| entry.fixed_steps),
+            });
+        }
+        let schedules = self.schedules.as_mut().unwrap();
+
+        schedules.run.execute(world, resources);
+        run_fixed_steps(&mut schedules.fixed, world, resources);
+    }
+
+    /// Shuts down all layers, including disabled ones, in the reverse order of Layer::order.
+    pub fn shutdown(&mut self, resources: &mut Resources) {
+        self.schedules = None;
+
+        let mut layers = self.layers.iter().collect::<Vec<_>>();
+        layers.sort_by_key(|entry| entry.order);
+
+        for entry in layers.into_iter().rev() {
+            log::info!("shutting down {}", entry.name);
+            (entry.shutdown)(resources);
+        }
+    }
+
+    fn entry<L: Layer>(&self) -> Option<&LayerEntry> {
+        let name = std::any::type_name::<L>();
+        self.layers.iter().find(|entry| entry.name == name)
+    }
+
+    fn build_schedule(
+        &self,
+        steps_func: impl Fn(&LayerEntry) -> fn() -> Option<Vec<Step>>,
+    ) -> Schedule {
+        let mut layers = self
+            .layers
+            .iter()
+            .filter(|entry| entry.enabled)
+            .collect::<Vec<_>>();
+        // stable, so layers with the same order keep the order they were pushed in
+        layers.sort_by_key(|entry| entry.order);
+
+        let steps = layers
+            .into_iter()
+            .flat_map(|entry| steps_func(entry)().unwrap_or_default())
+            .collect::<Vec<_>>();
+
+        Schedule::from(steps)
+    }
+}
+
+#[test]
+fn test_layer_shutdown() {
+    struct ShutdownOrder(Vec<&'static str>);
+
+    struct FirstLayer;
+    impl Layer for FirstLayer {
+        fn init(self, _cmd: &mut CommandBuffer, resources: &mut Resources) {
+            resources.insert(ShutdownOrder(Vec::new()));
+        }
+        fn startup_steps() -> Option<Vec<Step>> {
+            None
+        }
+        fn run_steps() -> Option<Vec<Step>> {
+            None
+        }
+        fn shutdown(resources: &mut Resources) {
+            resources
+                .get_mut::<ShutdownOrder>()
+                .unwrap()
+                .0
+                .push("first");
+        }
+    }
+
+    struct SecondLayer;
+    impl Layer for SecondLayer {
+        fn init(self, _cmd: &mut CommandBuffer, _resources: &mut Resources) {}
+        fn startup_steps() -> Option<Vec<Step>> {
+            None
+        }
+        fn run_steps() -> Option<Vec<Step>> {
+            None
+        }
+        fn order() -> i32 {
+            1
+        }
+        fn shutdown(resources: &mut Resources) {
+            resources
+                .get_mut::<ShutdownOrder>()
+                .unwrap()
+                .0
+                .push("second");
+        }
+    }
+
+    let world = World::default();
+    let mut resources = Resources::default();
+    let mut cmd = CommandBuffer::new(&world);
+
+    let mut layer_stack = LayerStack::default();
+    layer_stack.push(FirstLayer, &mut cmd, &mut resources);
+    layer_stack.push(SecondLayer, &mut cmd, &mut resources);
+    layer_stack.set_enabled::<SecondLayer>(false);
+
+    layer_stack.shutdown(&mut resources);
+
+    assert_eq!(
+        resources.get::<ShutdownOrder>().unwrap().0,
+        vec!["second", "first"]
+    );
+}
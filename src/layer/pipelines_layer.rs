@@ -1,11 +1,13 @@
-use crate::camera::{CameraUniformData, MainCamera};
-use crate::layer::application_layer::Time;
+use crate::camera::{Camera, CameraUniformData, MainCamera, Ray, Viewport, ViewportRect};
+use crate::graphics_context::{ClearColor, DebugLabels, GpuPass};
+use crate::layer::application_layer::{AppExit, Time};
 use crate::{
     camera, mesh, texture, DrawOutputInfo, GraphicsContext, Layer, RenderInstance, Vertex,
-    VertexArrayBuffer, MAX_DRAW_COMMANDS,
+    VertexArena,
 };
 use legion::systems::{CommandBuffer, Step};
-use legion::{Resources, Schedule};
+use legion::world::SubWorld;
+use legion::{IntoQuery, Resources, Schedule};
 use std::marker::PhantomData;
 use std::{iter, mem, slice};
 
@@ -13,13 +15,21 @@ use crate::bind_groups;
 use crate::bind_groups::{
     buffer_bind_group_entry, storage_buffer_layout_entry, uniform_buffer_layout_entry, DeviceExt,
 };
-use crate::components::Translation;
+use crate::components::{PointLight, Translation};
+use crate::depth_prepass::{DepthPrepass, DepthPrepassSettings};
+use crate::grid::{Grid, GridSettings};
 use crate::layer::base_render_scene_layer::{
     ComputeShaderDataBuffers, DrawCommandBuffers, DrawCountBuffers,
-    InstanceIndexToRenderObjectMapBuffer, MaxDrawCount, RenderInstanceBuffer, RenderObjects,
-    RenderObjectsBuffer,
+    InstanceIndexToRenderObjectMapBuffer, MaterialTextures, MaxDrawCount, RenderInstanceBuffer,
+    RenderObjects, RenderObjectsBuffer,
 };
-use crate::render_scene::RenderObject;
+use crate::point_light::{PointLightsBuffer, DEFAULT_MAX_POINT_LIGHTS};
+use crate::render_scene::compute_pipeline::{self, ComputeUniform, IndirectDispatch};
+use crate::render_scene::{
+    record_emitted_draws, DrawCountReadback, RenderObject, RenderStats, TimestampReadback,
+};
+use crate::shadow::{DirectionalLight, ShadowPass};
+use crate::skybox::Skybox;
 use legion::system;
 use penguin_util::handle::Handle;
 use penguin_util::raw_gpu_types::{DrawIndexedIndirect, DrawIndirectCount};
@@ -30,15 +40,163 @@ use wgpu::{BindGroup, BindGroupLayoutEntry, ShaderStages};
 
 /// Data related to a compute pass.
 struct Compute {
+    /// Culls render objects and counts the visible instances of each draw command.
     pub pipeline: wgpu::ComputePipeline,
-    pub bind_group: wgpu::BindGroup,
+    /// Writes the draw commands with visible instances to the output draw commands.
+    pub emit_pipeline: wgpu::ComputePipeline,
+    /// One per camera uniform buffer, indexed by UniformBuffer::current_index.
+    pub bind_groups: Vec<wgpu::BindGroup>,
+    pub uniform: ComputeUniform,
+    /// Workgroup count of the compute pass, computed on the GPU.
+    pub indirect_dispatch: IndirectDispatch,
+    /// Offsets for dynamic offset bindings in the bind group.
+    pub dynamic_offsets: Vec<wgpu::DynamicOffset>,
 }
 
 /// Data related to a render pass.
 struct Render {
     pub pipeline: wgpu::RenderPipeline,
-    pub vertex_shader_bind_group: wgpu::BindGroup,
+    /// Variant of the pipeline that only shades fragments at the depth written by the depth
+    /// prepass, without writing depth itself.
+    pub after_depth_prepass_pipeline: wgpu::RenderPipeline,
+    /// One per camera uniform buffer, indexed by UniformBuffer::current_index.
+    pub vertex_shader_bind_groups: Vec<wgpu::BindGroup>,
     pub fragment_shader_bind_group: wgpu::BindGroup,
+    /// Used to recreate the fragment shader bind group when the material textures have loaded.
+    pub fragment_shader_bind_group_layout: wgpu::BindGroupLayout,
+    pub normal_map: texture::Texture,
+    /// Offsets for dynamic offset bindings in the fragment shader bind group.
+    pub fragment_shader_dynamic_offsets: Vec<wgpu::DynamicOffset>,
+}
+
+/// Material textures being decoded off the main thread. Placeholders are drawn until all of them
+/// have loaded, then the texture array is swapped in.
+struct PendingMaterialTextures {
+    loads: Vec<texture::TextureLoad>,
+    layers: Vec<Option<texture::TexturePixels>>,
+}
+impl PendingMaterialTextures {
+    fn start(asset_names: &[String]) -> Self {
+        Self {
+            loads: asset_names
+                .iter()
+                .map(|asset_name| texture::Texture::load_async(asset_name))
+                .collect(),
+            layers: asset_names.iter().map(|_| None).collect(),
+        }
+    }
+
+    /// Array with a placeholder layer per material texture.
+    fn placeholder_array(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> texture::Texture {
+        let layers = self
+            .layers
+            .iter()
+            .map(|_| texture::TexturePixels::placeholder())
+            .collect::<Vec<_>>();
+
+        texture::Texture::array_from_pixels(
+            device,
+            queue,
+            &layers,
+            &texture::SamplerDescriptorConfig::default(),
+        )
+        .unwrap()
+    }
+
+    /// Takes the pixels of the loads that have finished. Returns the layers once all of them have
+    /// loaded, with placeholders for the ones that failed.
+    fn poll(&mut self) -> Option<Vec<texture::TexturePixels>> {
+        if self.loads.is_empty() {
+            return None;
+        }
+
+        for (load, layer) in self.loads.iter_mut().zip(self.layers.iter_mut()) {
+            if layer.is_some() {
+                continue;
+            }
+
+            *layer = load.try_take().map(|result| {
+                result.unwrap_or_else(|e| {
+                    log::error!("failed to load material texture: {:?}", e);
+                    texture::TexturePixels::placeholder()
+                })
+            });
+        }
+
+        if self.layers.iter().any(Option::is_none) {
+            return None;
+        }
+
+        self.loads.clear();
+        Some(self.layers.drain(..).flatten().collect())
+    }
+}
+
+/// Ray from the main camera through the cursor, updated after the camera each frame. None if the
+/// cursor position doesn't map to a ray.
+#[derive(Debug, Default)]
+pub struct CursorRay(pub Option<Ray>);
+
+/// Path to capture the next rendered frame to, if any.
+#[derive(Default)]
+pub struct FrameCapture {
+    pub path: Option<std::path::PathBuf>,
+}
+impl FrameCapture {
+    /// Requests the next frame to be written to a png named after the current time.
+    pub fn request(&mut self) {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        self.path = Some(format!("screenshot_{}.png", secs).into());
+    }
+}
+
+/// Marks the camera entity added by toggle_split_screen.
+struct SplitScreenCamera;
+
+/// Splits the window between the main camera on the left and a camera on an entity on the right,
+/// looking at the scene from the side. Called again, removes the camera and gives the main camera
+/// the whole window back. Returns whether the window is split.
+pub(crate) fn toggle_split_screen(world: &mut legion::World, r: &Resources) -> bool {
+    let context = r.get::<GraphicsContext>().unwrap();
+    let mut main_camera = r.get_mut::<MainCamera>().unwrap();
+    let output_size = (context.config.width, context.config.height);
+
+    let split_screen_cameras = <legion::Entity>::query()
+        .filter(legion::component::<SplitScreenCamera>())
+        .iter(world)
+        .copied()
+        .collect::<Vec<_>>();
+
+    if !split_screen_cameras.is_empty() {
+        for entity in split_screen_cameras {
+            world.remove(entity);
+        }
+        main_camera.set_viewport(ViewportRect::FULL, output_size);
+        return false;
+    }
+
+    main_camera.set_viewport(ViewportRect::LEFT_HALF, output_size);
+    world.push((
+        SplitScreenCamera,
+        Camera {
+            location: camera::CameraLocationOrientation::new(
+                (10.0, 5.0, 0.0).into(),
+                f32::to_radians(180.),
+                f32::to_radians(-20.),
+            ),
+            // the aspect ratio follows the viewport
+            projection: main_camera.projection,
+        },
+        Viewport {
+            rect: ViewportRect::RIGHT_HALF,
+            order: 0,
+        },
+    ));
+    true
 }
 
 pub struct PipelinesLayer;
@@ -52,61 +210,107 @@ impl Layer for PipelinesLayer {
         let config = &context.config;
 
         // --------
-        let main_camera = MainCamera::init(config); // todo: Maybe remake into an entity
+        let main_camera = MainCamera::init(config, context.depth_mode); // todo: Maybe remake into an entity
         let uniform_buffer = UniformBuffer::init(device, &main_camera.uniform_data);
+        let light = DirectionalLight::default();
+        let skybox = Skybox::init(&context, &main_camera.uniform_data);
+        let grid = Grid::init(&context, &main_camera);
+        let point_lights = PointLightsBuffer::init(device, DEFAULT_MAX_POINT_LIGHTS);
 
         // -------
         const READ: bool = true;
-        const READ_WRITE: bool = false;
         const VERTEX: wgpu::ShaderStages = wgpu::ShaderStages::VERTEX;
         const FRAGMENT: wgpu::ShaderStages = wgpu::ShaderStages::FRAGMENT;
-        const COMPUTE: wgpu::ShaderStages = wgpu::ShaderStages::COMPUTE;
         // -------
 
-        let (vertex_group, fragment_group, render_pipeline_layout) = {
+        // decoded off the main thread, placeholders are drawn until they've loaded
+        let pending_material_textures =
+            PendingMaterialTextures::start(&r.get::<MaterialTextures>().unwrap());
+
+        let (
+            vertex_groups,
+            (fragment_group_layout, fragment_group, normal_map),
+            render_pipeline_layout,
+            shadow_pass,
+            depth_prepass,
+        ) = {
             // vertex -----------
-            let (vertex_bind_group_layout, vertex_bind_group) = {
-                let vertex_bind_group_layout = bind_groups::BindGroupLayoutBuilder::<3>::builder()
+            let (vertex_bind_group_layout, vertex_bind_groups, shadow_pass) = {
+                let vertex_bind_group_layout = bind_groups::BindGroupLayoutBuilder::<4>::builder()
                     .uniform_buffer(0, VERTEX) // camera uniform
                     .storage_buffer(1, VERTEX, READ) // render objects
                     .storage_buffer(2, VERTEX, READ) // instance_index to render_object map
+                    .uniform_buffer(3, VERTEX | FRAGMENT) // light uniform
                     .build(device, Some("vertex bind group layout"));
 
                 let render_objects = r.get::<RenderObjectsBuffer>().unwrap();
                 let instance_map = r.get::<InstanceIndexToRenderObjectMapBuffer>().unwrap();
 
-                let vertex_bind_group = bind_groups::BindGroupBuilder::<3>::builder()
-                    .buffer(0, &uniform_buffer.buffer)
-                    .buffer(1, &render_objects.buffer)
-                    .buffer(2, &instance_map.buffer)
-                    .build(device, Some("vertex bind group"), &vertex_bind_group_layout);
+                let shadow_pass = ShadowPass::init(
+                    device,
+                    &light,
+                    &vertex_bind_group_layout,
+                    &render_objects.buffer,
+                    &instance_map.buffer,
+                );
+
+                let vertex_bind_groups = uniform_buffer
+                    .buffers()
+                    .iter()
+                    .map(|camera_uniform_buffer| {
+                        bind_groups::BindGroupBuilder::<4>::builder()
+                            .buffer(0, camera_uniform_buffer)
+                            .buffer(1, &render_objects.buffer)
+                            .buffer(2, &instance_map.buffer)
+                            .buffer(3, &shadow_pass.light_uniform_buffer)
+                            .build(device, Some("vertex bind group"), &vertex_bind_group_layout)
+                    })
+                    .collect::<Vec<_>>();
 
-                (vertex_bind_group_layout, vertex_bind_group)
+                (vertex_bind_group_layout, vertex_bind_groups, shadow_pass)
             };
 
             // fragment ------------
-            let (fragment_bind_group_layout, fragment_bind_group) = {
-                let cube_texture =
-                    texture::Texture::from_asset(device, queue, "cube-diffuse.jpg").unwrap();
+            let (fragment_bind_group_layout, fragment_bind_group, cube_normal_map) = {
+                let material_textures = pending_material_textures.placeholder_array(device, queue);
+                let cube_normal_map =
+                    texture::Texture::normal_map_from_asset(device, queue, "cube-normal.png");
 
                 let fragment_bind_group_layout =
-                    bind_groups::BindGroupLayoutBuilder::<2>::builder()
-                        .texture_2d(0, FRAGMENT)
+                    bind_groups::BindGroupLayoutBuilder::<8>::builder()
+                        .layered_texture_2d(0, FRAGMENT) // material textures
                         .sampler(1, FRAGMENT)
+                        .depth_texture_2d(2, FRAGMENT) // shadow map
+                        .comparison_sampler(3, FRAGMENT)
+                        .texture_2d(4, FRAGMENT) // normal map
+                        .sampler(5, FRAGMENT)
+                        .storage_buffer(6, FRAGMENT, READ) // point lights
+                        .uniform_buffer(7, FRAGMENT) // point light count
                         .build(device, Some("fragment bind group layout"));
 
-                let fragment_bind_group = bind_groups::BindGroupBuilder::<2>::builder()
-                    .texture_view(0, &cube_texture.view)
-                    .sampler(1, &cube_texture.sampler)
-                    .build(
-                        device,
-                        Some("fragment bind group"),
-                        &fragment_bind_group_layout,
-                    );
+                let fragment_bind_group = create_fragment_bind_group(
+                    device,
+                    &fragment_bind_group_layout,
+                    &material_textures,
+                    &cube_normal_map,
+                    &shadow_pass,
+                    &point_lights,
+                );
 
-                (fragment_bind_group_layout, fragment_bind_group)
+                (
+                    fragment_bind_group_layout,
+                    fragment_bind_group,
+                    cube_normal_map,
+                )
             };
 
+            let depth_prepass = DepthPrepass::init(
+                device,
+                &context,
+                &vertex_bind_group_layout,
+                &fragment_bind_group_layout,
+            );
+
             // render pipeline layout -----------
             let render_pipeline_layout =
                 device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -119,23 +323,23 @@ impl Layer for PipelinesLayer {
                 });
 
             (
-                vertex_bind_group,
-                fragment_bind_group,
+                vertex_bind_groups,
+                (
+                    fragment_bind_group_layout,
+                    fragment_bind_group,
+                    cube_normal_map,
+                ),
                 render_pipeline_layout,
+                shadow_pass,
+                depth_prepass,
             )
         };
 
         // compute
-        let (compute_group, compute_pipeline_layout) = {
-            let compute_bind_group_layout = bind_groups::BindGroupLayoutBuilder::<7>::builder()
-                .uniform_buffer(0, COMPUTE)
-                .storage_buffer(1, COMPUTE, READ)
-                .storage_buffer(2, COMPUTE, READ)
-                .storage_buffer(3, COMPUTE, READ_WRITE)
-                .storage_buffer(4, COMPUTE, READ_WRITE)
-                .storage_buffer(5, COMPUTE, READ_WRITE)
-                .storage_buffer(6, COMPUTE, READ_WRITE)
-                .build(device, Some("compute bind group layout"));
+        let compute_uniform = ComputeUniform::init(device);
+        let indirect_dispatch = IndirectDispatch::init(device, &compute_uniform.buffer);
+        let (compute_groups, compute_pipeline_layout) = {
+            let compute_bind_group_layout = compute_pipeline::create_bind_group_layout(device);
 
             let draw_commands = r.get::<DrawCommandBuffers>().unwrap();
             let render_objects = r.get::<RenderObjectsBuffer>().unwrap();
@@ -143,19 +347,28 @@ impl Layer for PipelinesLayer {
             let draw_count = r.get::<DrawCountBuffers>().unwrap();
             let instance_map = r.get::<InstanceIndexToRenderObjectMapBuffer>().unwrap();
 
-            let compute_bind_group = bind_groups::BindGroupBuilder::<7>::builder()
-                .buffer(0, &uniform_buffer.buffer)
-                .buffer(1, &draw_commands.clear_buffer)
-                .buffer(2, &render_objects.buffer)
-                .buffer(3, &shader_local.buffer)
-                .buffer(4, &draw_count.buffer)
-                .buffer(5, &draw_commands.out_buffer)
-                .buffer(6, &instance_map.buffer)
-                .build(
-                    device,
-                    Some("compute bind group"),
-                    &compute_bind_group_layout,
-                );
+            let compute_bind_groups = uniform_buffer
+                .buffers()
+                .iter()
+                .map(|camera_uniform_buffer| {
+                    compute_pipeline::create_bind_group(
+                        device,
+                        &compute_bind_group_layout,
+                        &compute_pipeline::ComputeBindGroupBuffers {
+                            camera_uniform: camera_uniform_buffer,
+                            draw_commands: &draw_commands.clear_buffer,
+                            render_objects: &render_objects.buffer,
+                            compute_local_data: &shader_local.buffer,
+                            draw_count: &draw_count.buffer,
+                            out_draw_commands: &draw_commands.out_buffer,
+                            instance_index_to_render_object_map: &instance_map.buffer,
+                            compute_uniform: &compute_uniform.buffer,
+                            render_object_draw_commands: &render_objects.draw_commands_buffer,
+                            out_non_indexed_draw_commands: &draw_commands.non_indexed_out_buffer,
+                        },
+                    )
+                })
+                .collect::<Vec<_>>();
 
             let compute_pipeline_layout =
                 device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -164,87 +377,136 @@ impl Layer for PipelinesLayer {
                     push_constant_ranges: &[],
                 });
 
-            (compute_bind_group, compute_pipeline_layout)
+            (compute_bind_groups, compute_pipeline_layout)
         };
 
-        let render_pipeline = {
+        let (render_pipeline, after_depth_prepass_render_pipeline) = {
             // --------
             let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
                 label: Some("shader"),
                 source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/vert_frag.wgsl").into()),
             });
 
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("render pipeline"),
-                layout: Some(&render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    buffers: &[
-                        mesh::MeshVertex::buffer_layout(),
-                        RenderInstance::buffer_layout(),
-                    ],
-                },
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: texture::Texture::DEPTH_FORMAT,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,                         // all
-                    alpha_to_coverage_enabled: false, // related to anti-aliasing
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    targets: &[wgpu::ColorTargetState {
-                        format: context.config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }],
-                }),
-                multiview: None, // related to rendering to array textures
-            })
+            let create_pipeline = |label, depth_write_enabled, depth_compare| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&render_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[
+                            mesh::MeshVertex::buffer_layout(),
+                            RenderInstance::buffer_layout(),
+                        ],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: Some(wgpu::Face::Back),
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: texture::Texture::DEPTH_FORMAT,
+                        depth_write_enabled,
+                        depth_compare,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        // must match the sample count of the msaa and depth textures
+                        count: context.sample_count,
+                        mask: !0, // all
+                        alpha_to_coverage_enabled: context.alpha_to_coverage,
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_main",
+                        targets: &[wgpu::ColorTargetState {
+                            format: context.config.format,
+                            blend: Some(wgpu::BlendState::REPLACE),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        }],
+                    }),
+                    multiview: None, // related to rendering to array textures
+                })
+            };
+
+            (
+                create_pipeline(
+                    "render pipeline",
+                    true,
+                    context.depth_mode.compare_function(),
+                ),
+                // the depth prepass has already written the depth of the closest fragments
+                create_pipeline(
+                    "render pipeline after depth prepass",
+                    false,
+                    context.depth_mode.prepass_compare_function(),
+                ),
+            )
         };
 
-        let compute_pipeline = {
+        let (compute_pipeline, compute_emit_pipeline) = {
             let compute_shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
                 label: Some("compute shader"),
-                source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/compute.wgsl").into()),
+                source: wgpu::ShaderSource::Wgsl(compute_pipeline::compute_shader_source().into()),
             });
 
-            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                label: Some("compute pipeline"),
-                layout: Some(&compute_pipeline_layout),
-                module: &compute_shader,
-                entry_point: "cs_main",
-            })
+            let compute_pipeline =
+                device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some("compute pipeline"),
+                    layout: Some(&compute_pipeline_layout),
+                    module: &compute_shader,
+                    entry_point: "cs_main",
+                });
+
+            let compute_emit_pipeline =
+                device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some("compute emit pipeline"),
+                    layout: Some(&compute_pipeline_layout),
+                    module: &compute_shader,
+                    entry_point: "cs_emit",
+                });
+
+            (compute_pipeline, compute_emit_pipeline)
         };
 
         drop(context);
 
         r.insert(main_camera);
+        r.insert(CursorRay::default());
         r.insert(uniform_buffer);
+        r.insert(light);
+        r.insert(shadow_pass);
+        r.insert(depth_prepass);
+        r.insert(DepthPrepassSettings::default());
+        r.insert(skybox);
+        r.insert(grid);
+        r.insert(GridSettings::default());
+        r.insert(point_lights);
+        r.insert(FrameCapture::default());
+        r.insert(pending_material_textures);
+        r.insert(ClearColor::default());
+        r.insert(DebugLabels::default());
         r.insert(Render {
             pipeline: render_pipeline,
-            vertex_shader_bind_group: vertex_group,
+            after_depth_prepass_pipeline: after_depth_prepass_render_pipeline,
+            vertex_shader_bind_groups: vertex_groups,
             fragment_shader_bind_group: fragment_group,
+            fragment_shader_bind_group_layout: fragment_group_layout,
+            normal_map,
+            fragment_shader_dynamic_offsets: vec![],
         });
         r.insert(Compute {
             pipeline: compute_pipeline,
-            bind_group: compute_group,
+            emit_pipeline: compute_emit_pipeline,
+            bind_groups: compute_groups,
+            uniform: compute_uniform,
+            indirect_dispatch,
+            dynamic_offsets: vec![],
         });
     }
 
@@ -252,12 +514,44 @@ impl Layer for PipelinesLayer {
         None
     }
 
+    fn order() -> i32 {
+        300
+    }
+
+    fn shutdown(r: &mut Resources) {
+        // wait for the GPU to finish using the pipelines and their buffers
+        if let Some(context) = r.get::<GraphicsContext>() {
+            context.queue.submit(iter::empty());
+            context.device.poll(wgpu::Maintain::Wait);
+        }
+
+        // runs before BaseRenderSceneLayer frees the scene buffers the bind groups point to
+        r.remove::<MainCamera>();
+        r.remove::<CursorRay>();
+        r.remove::<UniformBuffer>();
+        r.remove::<DirectionalLight>();
+        r.remove::<ShadowPass>();
+        r.remove::<DepthPrepass>();
+        r.remove::<DepthPrepassSettings>();
+        r.remove::<Skybox>();
+        r.remove::<Grid>();
+        r.remove::<GridSettings>();
+        r.remove::<PointLightsBuffer>();
+        r.remove::<FrameCapture>();
+        r.remove::<PendingMaterialTextures>();
+        r.remove::<ClearColor>();
+        r.remove::<DebugLabels>();
+        r.remove::<Render>();
+        r.remove::<Compute>();
+    }
+
     fn run_steps() -> Option<Vec<Step>> {
         Some(
             uniform_buffer::steps()
                 .into_iter()
                 .chain(
                     Schedule::builder()
+                        .add_system(swap_in_material_textures_system())
                         .add_system(compute_commands_system())
                         .add_system(render_commands_system())
                         .build()
@@ -271,100 +565,352 @@ impl Layer for PipelinesLayer {
 use uniform_buffer::*;
 mod uniform_buffer {
     use super::*;
+    use crate::events::{event::InputEvent, event::WindowResizeEvent, EventReader, Events};
+    use crate::input::Input;
+    use legion::world::SubWorld;
+    use legion::IntoQuery;
     use macaw as m;
 
+    /// Number of frames that may be in flight at once.
+    pub const FRAMES_IN_FLIGHT: usize = 3;
+
+    /// Ring of camera uniform buffers, one per frame in flight, so that writing the camera of the
+    /// next frame doesn't touch the buffer that an in-flight frame is still reading.
     pub struct UniformBuffer {
-        pub buffer: GpuBuffer<CameraUniformData>,
+        buffers: Vec<GpuBuffer<CameraUniformData>>,
+        /// Index of the buffer used by the current frame.
+        current: usize,
     }
 
     pub fn steps() -> Vec<Step> {
         Schedule::builder()
-            .add_system(update_main_camera_system())
+            .add_system(resize_main_camera_system(EventReader::default()))
+            .add_system(update_main_camera_system(EventReader::default()))
+            .add_system(update_cursor_ray_system())
             .add_system(enqueue_uniform_buffer_write_system())
+            .add_system(enqueue_light_uniform_write_system())
+            .add_system(enqueue_point_lights_write_system())
             .build()
             .into_vec()
     }
 
+    /// Keeps the aspect ratio of the camera's projection in sync with the window.
     #[system]
-    fn update_main_camera(#[resource] main_camera: &mut MainCamera, #[resource] dt: &Time) {
-        main_camera.update(dt.delta_time());
+    fn resize_main_camera(
+        #[state] resize_reader: &mut EventReader<WindowResizeEvent>,
+        #[resource] resize_events: &Events<WindowResizeEvent>,
+        #[resource] main_camera: &mut MainCamera,
+    ) {
+        for WindowResizeEvent { size, .. } in resize_reader.iter(resize_events) {
+            let viewport_size = main_camera
+                .viewport
+                .size_in_pixels((size.width, size.height));
+            main_camera.projection.resize(viewport_size);
+        }
+    }
+
+    #[system]
+    fn update_main_camera(
+        #[state] input_reader: &mut EventReader<InputEvent>,
+        #[resource] input_events: &Events<InputEvent>,
+        #[resource] main_camera: &mut MainCamera,
+        #[resource] dt: &Time,
+    ) {
+        for input_event in input_reader.iter(input_events) {
+            main_camera.controller.on_input_event(input_event);
+        }
+
+        main_camera.update(dt.unscaled_delta_time());
+    }
+
+    /// Casts a ray from the main camera through the cursor, for picking.
+    #[system]
+    fn update_cursor_ray(
+        #[resource] context: &GraphicsContext,
+        #[resource] input: &Input,
+        #[resource] main_camera: &MainCamera,
+        #[resource] cursor_ray: &mut CursorRay,
+    ) {
+        let output_size = (context.config.width, context.config.height);
+        let ndc = input.cursor_ndc(main_camera.viewport.pixels(output_size));
+
+        cursor_ray.0 = Ray::from_ndc(
+            ndc,
+            main_camera.uniform_data.view_proj,
+            main_camera.projection.depth_mode,
+        );
     }
 
     #[system]
     fn enqueue_uniform_buffer_write(
         #[resource] context: &GraphicsContext,
-        #[resource] uniform_buffer: &UniformBuffer,
+        #[resource] uniform_buffer: &mut UniformBuffer,
         #[resource] editor_camera: &MainCamera,
+        #[resource] skybox: &Skybox,
+        #[resource] grid: &Grid,
     ) {
         let queue = &context.queue;
 
-        queue.write_buffer(
-            &uniform_buffer.buffer,
-            0,
-            bytemuck::cast_slice(slice::from_ref(&editor_camera.uniform_data)),
-        );
+        uniform_buffer.rotate();
+        uniform_buffer
+            .current()
+            .write(queue, 0, slice::from_ref(&editor_camera.uniform_data));
+        skybox.update_camera(queue, &editor_camera.uniform_data);
+        grid.update_camera(queue, &editor_camera.uniform_data, editor_camera.position());
+    }
+
+    #[system]
+    fn enqueue_light_uniform_write(
+        #[resource] context: &GraphicsContext,
+        #[resource] shadow_pass: &ShadowPass,
+        #[resource] light: &DirectionalLight,
+    ) {
+        shadow_pass.update_light(&context.queue, light);
+    }
+
+    #[system]
+    #[read_component(PointLight)]
+    fn enqueue_point_lights_write(
+        world: &SubWorld,
+        #[resource] context: &GraphicsContext,
+        #[resource] point_lights: &PointLightsBuffer,
+    ) {
+        let mut query = <&PointLight>::query();
+        point_lights.update(&context.queue, query.iter(world));
     }
 
     impl UniformBuffer {
         pub fn init(device: &wgpu::Device, camera_uniform_data: &CameraUniformData) -> Self {
-            let buffer = device.create_buffer_init_t::<camera::CameraUniformData>(
-                &wgpu::util::BufferInitDescriptor {
-                    label: Some("camera uniform buffer"),
-                    contents: bytemuck::cast_slice(slice::from_ref(camera_uniform_data)),
-                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-                },
-            );
+            let buffers = (0..FRAMES_IN_FLIGHT)
+                .map(|_| {
+                    device.create_buffer_init_t::<camera::CameraUniformData>(
+                        &wgpu::util::BufferInitDescriptor {
+                            label: Some("camera uniform buffer"),
+                            contents: bytemuck::cast_slice(slice::from_ref(camera_uniform_data)),
+                            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        },
+                    )
+                })
+                .collect();
+
+            Self {
+                buffers,
+                current: 0,
+            }
+        }
+
+        /// The buffer used by the current frame.
+        pub fn current(&self) -> &GpuBuffer<CameraUniformData> {
+            &self.buffers[self.current]
+        }
 
-            Self { buffer }
+        /// Index of the current frame's buffer, for selecting the bind groups that bind it.
+        pub fn current_index(&self) -> usize {
+            self.current
+        }
+
+        pub fn buffers(&self) -> &[GpuBuffer<CameraUniformData>] {
+            &self.buffers
+        }
+
+        /// Moves on to the next frame's buffer.
+        pub fn rotate(&mut self) {
+            self.current = (self.current + 1) % self.buffers.len();
         }
     }
 }
 
+fn create_fragment_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    material_textures: &texture::Texture,
+    normal_map: &texture::Texture,
+    shadow_pass: &ShadowPass,
+    point_lights: &PointLightsBuffer,
+) -> wgpu::BindGroup {
+    bind_groups::BindGroupBuilder::<8>::builder()
+        .texture_view(0, &material_textures.view)
+        .sampler(1, &material_textures.sampler)
+        .texture_view(2, &shadow_pass.shadow_map.view)
+        .sampler(3, &shadow_pass.shadow_map.sampler)
+        .texture_view(4, &normal_map.view)
+        .sampler(5, &normal_map.sampler)
+        .buffer(6, &point_lights.buffer)
+        .buffer(7, &point_lights.count_buffer)
+        .build(device, Some("fragment bind group"), layout)
+}
+
+/// Replaces the placeholder material textures once all of them have been decoded.
+#[system]
+fn swap_in_material_textures(
+    #[resource] context: &GraphicsContext,
+    #[resource] pending_material_textures: &mut PendingMaterialTextures,
+    #[resource] render: &mut Render,
+    #[resource] shadow_pass: &ShadowPass,
+    #[resource] point_lights: &PointLightsBuffer,
+) {
+    let layers = match pending_material_textures.poll() {
+        Some(layers) => layers,
+        None => return,
+    };
+
+    let material_textures = match texture::Texture::array_from_pixels(
+        &context.device,
+        &context.queue,
+        &layers,
+        &texture::SamplerDescriptorConfig::default(),
+    ) {
+        Ok(material_textures) => material_textures,
+        Err(e) => {
+            log::error!("failed to create material texture array: {:?}", e);
+            return;
+        }
+    };
+
+    render.fragment_shader_bind_group = create_fragment_bind_group(
+        &context.device,
+        &render.fragment_shader_bind_group_layout,
+        &material_textures,
+        &render.normal_map,
+        shadow_pass,
+        point_lights,
+    );
+}
+
 #[system]
 fn compute_commands(
     #[resource] context: &GraphicsContext,
     #[resource] compute_local: &ComputeShaderDataBuffers,
     #[resource] draw_counts: &DrawCountBuffers,
-    #[resource] compute: &Compute,
+    #[resource] draw_commands: &DrawCommandBuffers,
+    #[resource] max_draw_count: &MaxDrawCount,
+    #[resource] draw_count_readback: &mut DrawCountReadback,
+    #[resource] timestamp_readback: &mut TimestampReadback,
+    #[resource] render_stats: &mut RenderStats,
+    #[resource] compute: &mut Compute,
     #[resource] render_objs: &RenderObjects,
+    #[resource] uniform_buffer: &UniformBuffer,
+    #[resource] labels: &DebugLabels,
 ) {
     let device = &context.device;
     let queue = &context.queue;
 
+    // read back the stats of earlier frames whose copies the GPU has finished
+    draw_count_readback.read_ready(device, render_stats);
+    timestamp_readback.read_ready(context, render_stats);
+    render_stats.draw_count = max_draw_count.0;
+
+    let object_count = render_objs.render_objects.inner.len() as u32;
+    let compute_uniform_changed = compute.uniform.update(
+        queue,
+        object_count,
+        render_objs.first_non_indexed_draw_command,
+    );
+
     let mut cmd = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
         label: Some("compute commands encoder"),
     });
 
-    cmd.push_debug_group("compute pass");
+    // the dispatch args of the compute pass only change with the object count
+    if compute_uniform_changed {
+        compute.indirect_dispatch.record(&mut cmd);
+    }
+
+    context.begin_timestamp(&mut cmd, GpuPass::Compute);
+
+    record_compute_pass(
+        &mut cmd,
+        compute,
+        compute_local,
+        draw_counts,
+        max_draw_count,
+        draw_commands,
+        &compute.bind_groups[uniform_buffer.current_index()],
+        labels,
+    );
+
+    context.end_timestamp(&mut cmd, GpuPass::Compute);
+
+    draw_count_readback.copy(&mut cmd, &draw_counts.buffer, &draw_commands.out_buffer);
+
+    queue.submit(iter::once(cmd.finish()));
+    draw_count_readback.map_copied();
+}
+
+/// Culls the render objects and picks their LOD levels for the camera bound by the bind group,
+/// writing the draw commands and draw count of the render pass.
+#[allow(clippy::too_many_arguments)]
+fn record_compute_pass(
+    cmd: &mut wgpu::CommandEncoder,
+    compute: &Compute,
+    compute_local: &ComputeShaderDataBuffers,
+    draw_counts: &DrawCountBuffers,
+    max_draw_count: &MaxDrawCount,
+    draw_commands: &DrawCommandBuffers,
+    bind_group: &wgpu::BindGroup,
+    labels: &DebugLabels,
+) {
+    cmd.push_debug_group(&labels.compute_pass);
     {
-        compute_local.reset(&mut cmd);
-        draw_counts.reset(&mut cmd);
+        compute_local.reset(cmd);
+        draw_counts.reset(cmd);
+        // the draw commands are drawn one by one with markers, including the stale ones past the
+        // draw count
+        if labels.draw_markers {
+            draw_commands.reset(cmd);
+        }
 
         let mut compute_pass = cmd.begin_compute_pass(&wgpu::ComputePassDescriptor {
-            label: Some("compute pass"),
+            label: Some(labels.compute_pass.as_str()),
         });
         compute_pass.set_pipeline(&compute.pipeline);
-        compute_pass.set_bind_group(0, &compute.bind_group, &[]);
-        compute_pass.dispatch(render_objs.render_objects.inner.len() as _, 1, 1);
+        compute_pass.set_bind_group(0, bind_group, &compute.dynamic_offsets);
+        compute_pass.dispatch_indirect(&compute.indirect_dispatch.args_buffer, 0);
+
+        compute_pass.set_pipeline(&compute.emit_pipeline);
+        compute_pass.dispatch(
+            compute_pipeline::dispatch_group_count(max_draw_count.0),
+            1,
+            1,
+        );
     }
     cmd.pop_debug_group();
-
-    queue.submit(iter::once(cmd.finish()));
 }
 
+/// Renders the main camera, then the cameras on entities on top of it in the order of their
+/// viewports.
 #[system]
+#[read_component(Camera)]
+#[read_component(Viewport)]
 fn render_commands(
+    world: &SubWorld,
     #[resource] context: &GraphicsContext,
+    #[resource] main_camera: &MainCamera,
+    #[resource] compute: &Compute,
+    #[resource] compute_local: &ComputeShaderDataBuffers,
     #[resource] render: &Render,
-    #[resource] vertex_array_buffer: &VertexArrayBuffer,
+    #[resource] vertex_arena: &VertexArena,
     #[resource] instances: &RenderInstanceBuffer,
     #[resource] draw_commands: &DrawCommandBuffers,
     #[resource] draw_counts: &DrawCountBuffers,
     #[resource] max_draw_count: &MaxDrawCount,
+    #[resource] shadow_pass: &ShadowPass,
+    #[resource] depth_prepass: &DepthPrepass,
+    #[resource] depth_prepass_settings: &DepthPrepassSettings,
+    #[resource] skybox: &Skybox,
+    #[resource] grid: &Grid,
+    #[resource] grid_settings: &GridSettings,
+    #[resource] clear_color: &ClearColor,
+    #[resource] frame_capture: &mut FrameCapture,
+    #[resource] uniform_buffer: &UniformBuffer,
+    #[resource] timestamp_readback: &mut TimestampReadback,
+    #[resource] app_exit: &mut AppExit,
+    #[resource] labels: &DebugLabels,
 ) {
     /// Access the output view texture to submit render commands.
     fn render_func<OutputTextureFunc: FnOnce(&wgpu::TextureView)>(
         context: &GraphicsContext,
+        frame_capture: &mut FrameCapture,
         f: OutputTextureFunc,
     ) -> Result<(), wgpu::SurfaceError> {
         let output_texture = context.surface.get_current_texture()?;
@@ -374,76 +920,202 @@ fn render_commands(
 
         f(&output_texture_view);
 
+        if let Some(path) = frame_capture.path.take() {
+            if let Err(e) = context.capture_frame(&output_texture.texture, &path) {
+                log::error!("failed to capture frame: {:?}", e);
+            }
+        }
+
         output_texture.present();
 
         Ok(())
     }
 
+    // nothing to render to until the window is restored
+    if context.is_minimized {
+        return;
+    }
+    context.assert_attachments_match_surface();
+
     let device = &context.device;
     let queue = &context.queue;
+    let output_size = (context.config.width, context.config.height);
 
-    // todo: Respond to result, reconfigure surface if needed.
-    let _render_result = render_func(&context, |output| {
-        let mut cmd = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("compute commands encoder"),
-        });
+    let mut cameras = <(&Camera, &Viewport)>::query()
+        .iter(world)
+        .map(|(camera, viewport)| {
+            (
+                camera.uniform_data(&viewport.rect, output_size),
+                camera.location.position,
+                *viewport,
+            )
+        })
+        .collect::<Vec<_>>();
+    cameras.sort_by_key(|(_, _, viewport)| viewport.order);
+
+    let render_result = render_func(&context, frame_capture, |output| {
+        let vertex_bind_group = &render.vertex_shader_bind_groups[uniform_buffer.current_index()];
+        let compute_bind_group = &compute.bind_groups[uniform_buffer.current_index()];
+
+        // records the passes of the camera whose uniforms are written before the next submit
+        let record_camera = |cmd: &mut wgpu::CommandEncoder,
+                             viewport: &ViewportRect,
+                             color_load: wgpu::LoadOp<wgpu::Color>| {
+            let viewport = viewport.pixels(output_size);
 
-        cmd.push_debug_group("render pass");
-        {
-            let mut render_pass = cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view: &output,
-                    // the texture that will receive the resolved output (used for multisampling)
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
+            if depth_prepass_settings.enabled {
+                depth_prepass.record(
+                    cmd,
+                    context.depth_mode,
+                    &context.depth_texture.view,
+                    viewport,
+                    vertex_bind_group,
+                    &render.fragment_shader_bind_group,
+                    vertex_arena,
+                    &instances.buffer,
+                    &draw_commands.out_buffer,
+                    &draw_commands.non_indexed_out_buffer,
+                    &draw_counts.buffer,
+                    max_draw_count.0,
+                    labels,
+                );
+            }
+
+            // keep the depth written by the prepass
+            let (depth_load, pipeline) = match depth_prepass_settings.enabled {
+                true => (wgpu::LoadOp::Load, &render.after_depth_prepass_pipeline),
+                false => (
+                    wgpu::LoadOp::Clear(context.depth_mode.far_depth()),
+                    &render.pipeline,
+                ),
+            };
+
+            cmd.push_debug_group(&labels.render_pass);
+            {
+                let mut render_pass = cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some(labels.render_pass.as_str()),
+                    color_attachments: &[context.color_attachment(output, color_load)],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &context.depth_texture.view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: depth_load,
+                            store: true,
                         }),
-                        // store rendered results to output texture
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &context.depth_texture.view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
+                        stencil_ops: None,
                     }),
-                    stencil_ops: None,
-                }),
-            });
+                });
 
-            // set render pipeline
-            render_pass.set_pipeline(&render.pipeline);
+                let [x, y, width, height] = viewport;
+                render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
 
-            // set bind groups
-            render_pass.set_bind_group(0, &render.vertex_shader_bind_group, &[]);
-            render_pass.set_bind_group(1, &render.fragment_shader_bind_group, &[]);
+                skybox.record(&mut render_pass);
 
-            // set vertex/index buffer
-            render_pass.set_vertex_buffer(0, vertex_array_buffer.vertices_slice());
-            render_pass.set_index_buffer(
-                vertex_array_buffer.indices_slice(),
-                wgpu::IndexFormat::Uint32,
-            );
-            // set instance buffer
-            render_pass.set_vertex_buffer(1, instances.buffer.slice(..));
-
-            // draw
-            render_pass.multi_draw_indexed_indirect_count(
-                &draw_commands.out_buffer,
-                0,
-                &draw_counts.buffer,
-                0,
-                max_draw_count.0,
+                // set render pipeline
+                render_pass.set_pipeline(pipeline);
+
+                // set bind groups
+                render_pass.set_bind_group(0, vertex_bind_group, &[]);
+                render_pass.set_bind_group(
+                    1,
+                    &render.fragment_shader_bind_group,
+                    &render.fragment_shader_dynamic_offsets,
+                );
+
+                // set vertex/index buffer
+                render_pass.set_vertex_buffer(0, vertex_arena.vertices_slice());
+                render_pass
+                    .set_index_buffer(vertex_arena.indices_slice(), wgpu::IndexFormat::Uint32);
+                // set instance buffer
+                render_pass.set_vertex_buffer(1, instances.buffer.slice(..));
+
+                // draw
+                record_emitted_draws(
+                    &mut render_pass,
+                    &draw_commands.out_buffer,
+                    &draw_commands.non_indexed_out_buffer,
+                    &draw_counts.buffer,
+                    max_draw_count.0,
+                    labels.render_pass_draw_marker(),
+                );
+
+                if grid_settings.enabled {
+                    grid.record(&mut render_pass);
+                }
+            }
+            cmd.pop_debug_group();
+        };
+
+        let mut cmd = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("render commands encoder"),
+        });
+
+        context.begin_timestamp(&mut cmd, GpuPass::Render);
+
+        shadow_pass.record(
+            &mut cmd,
+            vertex_arena,
+            &instances.buffer,
+            &draw_commands.out_buffer,
+            &draw_commands.non_indexed_out_buffer,
+            &draw_counts.buffer,
+            max_draw_count.0,
+            labels,
+        );
+
+        record_camera(
+            &mut cmd,
+            &main_camera.viewport,
+            wgpu::LoadOp::Clear(clear_color.0),
+        );
+
+        for (camera_uniform_data, camera_position, viewport) in &cameras {
+            // queue writes land before the next submit, so the previous camera's commands are
+            // submitted before its uniforms are overwritten
+            queue.submit(iter::once(cmd.finish()));
+
+            uniform_buffer
+                .current()
+                .write(queue, 0, slice::from_ref(camera_uniform_data));
+            skybox.update_camera(queue, camera_uniform_data);
+            grid.update_camera(queue, camera_uniform_data, *camera_position);
+
+            cmd = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("render commands encoder"),
+            });
+
+            // the draw commands were culled for the previous camera
+            record_compute_pass(
+                &mut cmd,
+                compute,
+                compute_local,
+                draw_counts,
+                max_draw_count,
+                draw_commands,
+                compute_bind_group,
+                labels,
             );
+            record_camera(&mut cmd, &viewport.rect, wgpu::LoadOp::Load);
         }
-        cmd.pop_debug_group();
+
+        context.end_timestamp(&mut cmd, GpuPass::Render);
+        timestamp_readback.copy(&mut cmd, context);
 
         queue.submit(iter::once(cmd.finish()));
+        timestamp_readback.map_copied();
     });
+
+    match render_result {
+        Ok(()) => {}
+        // e.g. after waking from sleep, the surface has to be configured again before it can be
+        // rendered to
+        Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+            log::warn!("surface lost or outdated, reconfiguring");
+            context.surface.configure(device, &context.config);
+        }
+        Err(wgpu::SurfaceError::OutOfMemory) => {
+            log::error!("out of memory, exiting");
+            app_exit.requested = true;
+        }
+        Err(e) => log::warn!("surface error: {:?}", e),
+    }
 }
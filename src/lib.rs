@@ -0,0 +1,1491 @@
+mod app;
+mod bind_groups;
+mod bounds_debug;
+mod camera;
+mod components;
+mod depth_prepass;
+mod editor;
+mod events;
+mod graphics_context;
+mod grid;
+mod input;
+pub mod layer;
+mod mesh;
+pub mod new_bevy_ecs;
+mod point_light;
+mod render_scene;
+mod scene_file;
+mod shadow;
+mod skybox;
+mod texture;
+mod time;
+
+pub use app::App;
+pub use graphics_context::DepthMode;
+use graphics_context::{
+    ClearColor, DebugLabels, GpuPass, GraphicsContext, GraphicsContextDescriptor,
+};
+
+/// Textures of the materials, loaded into the layers of a texture array. Render objects select a
+/// layer with their texture index.
+const MATERIAL_TEXTURES: &[&str] = &["cube-diffuse.jpg", "tree.png"];
+
+use crate::events::PenguinEvent;
+
+use crate::{
+    mesh::{Vertex, VertexArena},
+    render_scene::{DrawOutputInfo, RenderObjectDescriptor},
+};
+
+use legion::{maybe_changed, IntoQuery, Resources};
+use macaw as m;
+use penguin_util::{handle::Handle, GpuBuffer, GpuBufferDeviceExt};
+
+use crate::bind_groups::DeviceExt;
+use crate::layer::Layer;
+use std::mem::transmute;
+use std::{iter, mem, slice};
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::WindowBuilder,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct RenderInstance {
+    pub render_object_id: Handle<render_scene::RenderObject>,
+}
+unsafe impl bytemuck::Pod for RenderInstance {}
+unsafe impl bytemuck::Zeroable for RenderInstance {}
+
+impl RenderInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![
+        5 => Uint32,
+    ];
+
+    fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<Self>() as _,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Temporary variable that increases with a value each frame.
+static mut TIME_STATE: f32 = 0.0_f32;
+
+/// Data related to a compute pass.
+pub struct Compute {
+    /// Culls render objects and counts the visible instances of each draw command.
+    pub pipeline: wgpu::ComputePipeline,
+    /// Writes the draw commands with visible instances to the output draw commands.
+    pub emit_pipeline: wgpu::ComputePipeline,
+    pub bind_group: wgpu::BindGroup,
+    pub uniform: render_scene::compute_pipeline::ComputeUniform,
+    /// Workgroup count of the compute pass, computed on the GPU.
+    pub indirect_dispatch: render_scene::compute_pipeline::IndirectDispatch,
+}
+
+/// Data related to a render pass.
+pub struct Render {
+    pub pipeline: wgpu::RenderPipeline,
+    /// Variant of the pipeline that only shades fragments at the depth written by the depth
+    /// prepass, without writing depth itself.
+    pub after_depth_prepass_pipeline: wgpu::RenderPipeline,
+    /// Layout of the pipelines, kept to create them again when the depth mode changes.
+    pub pipeline_layout: wgpu::PipelineLayout,
+    pub vertex_shader_bind_group: wgpu::BindGroup,
+    pub fragment_shader_bind_group: wgpu::BindGroup,
+}
+
+struct LegionECSData {
+    world: legion::World,
+    resources: legion::Resources,
+    #[allow(unused)]
+    entities: Vec<legion::Entity>,
+}
+
+struct Yeet {
+    renderer_layer: legion::systems::Schedule,
+}
+
+/// State with data necessary to render.
+pub struct RendererState {
+    /// Compute pass data.
+    compute: Compute,
+    /// Render pass data.
+    render: Render,
+    // Material textures.
+    _material_textures: texture::Texture,
+    _cube_normal_map: texture::Texture,
+    /// Editor camera data.
+    camera: camera::MainCamera,
+    /// Uniform buffer.
+    uniform_buffer: GpuBuffer<camera::CameraUniformData>,
+    /// The currently loaded RenderScene.
+    scene: render_scene::RenderScene,
+    /// Renders the shadow map for the directional light.
+    shadow_pass: shadow::ShadowPass,
+    /// Cubemap background.
+    skybox: skybox::Skybox,
+    /// Ground grid, toggled from the editor.
+    grid: grid::Grid,
+    grid_settings: grid::GridSettings,
+    /// Wireframe spheres at the bounds of the render objects, toggled from the editor.
+    bounds_debug: bounds_debug::BoundsDebug,
+    bounds_debug_settings: bounds_debug::BoundsDebugSettings,
+    /// Fills the depth texture before the main pass, toggled from the editor.
+    depth_prepass: depth_prepass::DepthPrepass,
+    depth_prepass_settings: depth_prepass::DepthPrepassSettings,
+    /// Point lights gathered from the point light entities each frame.
+    point_lights: point_light::PointLightsBuffer,
+    /// Reads back the number of draws the compute shader emitted.
+    draw_count_readback: render_scene::DrawCountReadback,
+    /// Reads back the GPU time of the compute and render passes.
+    timestamp_readback: render_scene::TimestampReadback,
+    /// Statistics shown in the editor.
+    render_stats: render_scene::RenderStats,
+    /// Background color, editable in the editor.
+    clear_color: ClearColor,
+    /// Labels of the passes in graphics debuggers.
+    debug_labels: DebugLabels,
+    /// Names of the mesh assets loaded into the scene, including meshes loaded from the editor.
+    mesh_assets: Vec<String>,
+    /// ECS data.
+    ecs: LegionECSData,
+}
+
+/// Helper struct when creating texture-related data.
+struct Textures {
+    bind_group_layout: wgpu::BindGroupLayout,
+    //
+    material_textures: texture::Texture,
+    cube_normal_map: texture::Texture,
+    cube_texture_bind_group: wgpu::BindGroup,
+}
+impl RendererState {
+    fn init_textures(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shadow_map: &texture::Texture,
+        point_lights: &point_light::PointLightsBuffer,
+    ) -> Textures {
+        let material_textures = texture::Texture::array_from_assets(
+            device,
+            queue,
+            MATERIAL_TEXTURES,
+            &texture::SamplerDescriptorConfig::default(),
+        )
+        .unwrap();
+        let cube_normal_map =
+            texture::Texture::normal_map_from_asset(device, queue, "cube-normal.png");
+
+        use bind_groups::layout_entry;
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("texture bind group layout"),
+                entries: &[
+                    layout_entry::texture::layered_texture_2d(0, wgpu::ShaderStages::FRAGMENT),
+                    layout_entry::texture::sampler(1, wgpu::ShaderStages::FRAGMENT),
+                    layout_entry::texture::depth_texture_2d(2, wgpu::ShaderStages::FRAGMENT),
+                    layout_entry::texture::comparison_sampler(3, wgpu::ShaderStages::FRAGMENT),
+                    layout_entry::texture::texture_2d(4, wgpu::ShaderStages::FRAGMENT),
+                    layout_entry::texture::sampler(5, wgpu::ShaderStages::FRAGMENT),
+                    bind_groups::storage_buffer_layout_entry(6, wgpu::ShaderStages::FRAGMENT, true),
+                    bind_groups::uniform_buffer_layout_entry(7, wgpu::ShaderStages::FRAGMENT),
+                ],
+            });
+
+        let cube_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("cube diffuse bind group"),
+            layout: &texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&material_textures.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&material_textures.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&shadow_map.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&shadow_map.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&cube_normal_map.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Sampler(&cube_normal_map.sampler),
+                },
+                bind_groups::buffer_bind_group_entry(6, &point_lights.buffer),
+                bind_groups::buffer_bind_group_entry(7, &point_lights.count_buffer),
+            ],
+        });
+
+        Textures {
+            bind_group_layout: texture_bind_group_layout,
+            material_textures,
+            cube_normal_map,
+            cube_texture_bind_group,
+        }
+    }
+
+    /// The main pass pipeline, and its variant drawn after the depth prepass. Their depth test
+    /// depends on the context's depth mode, so they're created again when the depth mode changes.
+    fn create_render_pipelines(
+        context: &GraphicsContext,
+        layout: &wgpu::PipelineLayout,
+    ) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+        let shader = context
+            .device
+            .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: Some("shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("shaders/vert_frag.wgsl").into()),
+            });
+
+        let create_pipeline = |label, depth_write_enabled, depth_compare| {
+            context
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[
+                            mesh::MeshVertex::buffer_layout(),
+                            RenderInstance::buffer_layout(),
+                        ],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: Some(wgpu::Face::Back),
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: texture::Texture::DEPTH_FORMAT,
+                        depth_write_enabled,
+                        depth_compare,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        // must match the sample count of the msaa and depth textures
+                        count: context.sample_count,
+                        mask: !0, // all
+                        alpha_to_coverage_enabled: context.alpha_to_coverage,
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_main",
+                        targets: &[wgpu::ColorTargetState {
+                            format: context.config.format,
+                            blend: Some(wgpu::BlendState::REPLACE),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        }],
+                    }),
+                    multiview: None, // related to rendering to array textures
+                })
+        };
+
+        (
+            create_pipeline(
+                "render pipeline",
+                true,
+                context.depth_mode.compare_function(),
+            ),
+            // the depth prepass has already written the depth of the closest fragments
+            create_pipeline(
+                "render pipeline after depth prepass",
+                false,
+                context.depth_mode.prepass_compare_function(),
+            ),
+        )
+    }
+}
+
+#[derive(Default)]
+struct RenderObjectStorage {
+    render_objects: Vec<render_scene::RenderObject>,
+}
+penguin_util::impl_deref!(
+    mut RenderObjectStorage,
+    render_objects,
+    Vec<render_scene::RenderObject>
+);
+
+/// Vertices of a triangle facing +z, with its base centered on the origin.
+fn triangle_vertices() -> [mesh::MeshVertex; 3] {
+    let vertex = |position: m::Vec3, uv: m::Vec2| mesh::MeshVertex {
+        position,
+        normal: m::Vec3::Z,
+        uv,
+        tangent: m::vec4(1.0, 0.0, 0.0, 1.0),
+    };
+
+    [
+        vertex(m::vec3(-1.0, 0.0, 0.0), m::vec2(0.0, 1.0)),
+        vertex(m::vec3(1.0, 0.0, 0.0), m::vec2(1.0, 1.0)),
+        vertex(m::vec3(0.0, 1.5, 0.0), m::vec2(0.5, 0.0)),
+    ]
+}
+
+impl RendererState {
+    fn new(context: &GraphicsContext) -> Self {
+        let mut l_world = legion::World::default();
+
+        let mut l_resources = legion::Resources::default();
+        l_resources.insert(RenderObjectStorage::default());
+
+        // editor
+        let components_ui_storage = {
+            use components::*;
+            let mut s = editor::EditorComponentStorage::default();
+            s.register_component_editor::<Name>();
+            s.register_auto_editor::<Translation>();
+            s.register_component_editor::<Rotation>();
+            s.register_auto_editor::<Scale>();
+            s.register_component_editor::<Color>();
+            s.register_component_editor::<Visible>();
+            s.register_component_editor::<MeshComponent>();
+            s
+        };
+        l_resources.insert(components_ui_storage);
+        l_resources.insert(editor::EditorHistory::default());
+
+        let mut cmd = legion::systems::CommandBuffer::new(&l_world);
+
+        // ------------
+
+        // the startup scene, with the demos that aren't described by scene files added after it
+        let mut startup_scene = scene_file::SceneFile::read_startup();
+        let mut mesh_assets = std::mem::take(&mut startup_scene.mesh_assets);
+
+        /// Index of the mesh asset, which is also its mesh handle and MeshComponent. Added after
+        /// the scene's mesh assets if the scene doesn't use it.
+        fn mesh_asset_index(mesh_assets: &mut Vec<String>, asset: &str) -> usize {
+            mesh_assets
+                .iter()
+                .position(|mesh_asset| mesh_asset == asset)
+                .unwrap_or_else(|| {
+                    mesh_assets.push(asset.to_owned());
+                    mesh_assets.len() - 1
+                })
+        }
+        let grid_cube_mesh = mesh_asset_index(&mut mesh_assets, "cube.obj");
+        let lod_sphere_meshes = ["sphere_lod0.obj", "sphere_lod1.obj", "sphere_lod2.obj"]
+            .map(|asset| Handle::from(mesh_asset_index(&mut mesh_assets, asset)));
+
+        let render_limits = render_scene::RenderLimits::default();
+
+        let (scene, entities) = {
+            let mesh_asset_names = mesh_assets.iter().map(String::as_str).collect::<Vec<_>>();
+            let mut scene =
+                render_scene::RenderScene::new(&context.device, &mesh_asset_names, render_limits);
+
+            // a triangle without indices, drawn non-indexed next to the indexed meshes. it isn't
+            // loaded from a file, but it's selectable like the mesh assets
+            let triangle_mesh =
+                scene.add_mesh(&context.device, &context.queue, &triangle_vertices(), &[]);
+            debug_assert_eq!(triangle_mesh.id as usize, mesh_assets.len());
+            mesh_assets.push("triangle".to_owned());
+
+            // register the render objects of the demos. the render objects of the scene's
+            // entities are registered on the first update, like those of loaded scenes
+            //
+            let mut render_obj_desc = RenderObjectDescriptor {
+                mesh_handle: Handle::from(grid_cube_mesh),
+                transform: m::Mat4::IDENTITY,
+                render_bounds: mesh::RenderBounds {
+                    origin: m::Vec3::ZERO,
+                    radius: 3.0,
+                },
+                color: m::Vec4::ONE,
+                texture_index: 0,
+                alpha_cutoff: 0.0,
+                draw_forward_pass: true,
+                custom_passes: &[],
+            };
+
+            // a static grid of cubes, drawn with a single instanced draw command
+            let grid_transforms = (0..1000)
+                .map(|i| {
+                    let cell = m::vec3((i % 10) as f32, (i / 10 % 10) as f32, (i / 100) as f32);
+                    m::Mat4::from_scale_rotation_translation(
+                        m::Vec3::splat(0.25),
+                        m::Quat::IDENTITY,
+                        m::vec3(20.0, 0.0, 0.0) + cell * 1.5,
+                    )
+                })
+                .collect::<Vec<_>>();
+            scene
+                .objects
+                .register_instances(&render_obj_desc, &grid_transforms);
+
+            // a sphere that switches to coarser meshes as the camera moves away from it
+            render_obj_desc.render_bounds.radius = 1.0;
+            let lod_sphere_object = scene
+                .objects
+                .register_object_with_lods(&render_obj_desc, &lod_sphere_meshes)
+                .expect("the LOD sphere doesn't fit in the render limits");
+            render_obj_desc.render_bounds.radius = 3.0;
+
+            render_obj_desc.mesh_handle = triangle_mesh;
+            let triangle_object = scene
+                .objects
+                .register_object(&render_obj_desc)
+                .expect("the triangle doesn't fit in the render limits");
+
+            scene.build_batches(&context.queue);
+
+            // construct entities
+            let mut entities = startup_scene.spawn(&mut cmd);
+            entities.extend([
+                // no MeshComponent, setting one would replace the LOD levels with a single mesh
+                cmd.push((
+                    components::Name::from("LOD Sphere"),
+                    lod_sphere_object,
+                    components::Translation(m::vec3(-6.0, 1.0, 0.0)),
+                )),
+                cmd.push((
+                    components::Name::from("Triangle"),
+                    triangle_object,
+                    components::Translation(m::vec3(-3.0, 0.0, 2.0)),
+                    components::MeshComponent(triangle_mesh.id as usize),
+                )),
+            ]);
+
+            (scene, entities)
+        };
+
+        cmd.flush(&mut l_world, &mut l_resources);
+
+        let camera = camera::MainCamera::init(&context.config, context.depth_mode);
+
+        let skybox = skybox::Skybox::init(context, &camera.uniform_data);
+        let grid = grid::Grid::init(context, &camera);
+        let bounds_debug =
+            bounds_debug::BoundsDebug::init(context, &camera, render_limits.max_instances);
+
+        let uniform_buffer = context
+            .device
+            .create_buffer_init_t::<camera::CameraUniformData>(&wgpu::util::BufferInitDescriptor {
+                label: Some("camera uniform buffer"),
+                contents: bytemuck::cast_slice(slice::from_ref(&camera.uniform_data)),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        const VERTEX: wgpu::ShaderStages = wgpu::ShaderStages::VERTEX;
+        const FRAGMENT: wgpu::ShaderStages = wgpu::ShaderStages::FRAGMENT;
+        const READ: bool = true;
+
+        let vertex_shader_bind_group_layout = bind_groups::BindGroupLayoutBuilder::<4>::builder()
+            .uniform_buffer(0, VERTEX) // camera uniform
+            .storage_buffer(1, VERTEX, READ) // render objects
+            .storage_buffer(2, VERTEX, READ) // instance_index to render_object map
+            .uniform_buffer(3, VERTEX | FRAGMENT) // light uniform
+            .build(&context.device, Some("vertex bind group layout"));
+
+        let shadow_pass = shadow::ShadowPass::init(
+            &context.device,
+            &shadow::DirectionalLight::default(),
+            &vertex_shader_bind_group_layout,
+            &scene.render_objects_buffer,
+            &scene.instance_index_to_render_object_map,
+        );
+
+        let point_lights = point_light::PointLightsBuffer::init(
+            &context.device,
+            point_light::DEFAULT_MAX_POINT_LIGHTS,
+        );
+
+        let Textures {
+            bind_group_layout: texture_bind_group_layout,
+            material_textures,
+            cube_normal_map,
+            cube_texture_bind_group,
+        } = Self::init_textures(
+            &context.device,
+            &context.queue,
+            &shadow_pass.shadow_map,
+            &point_lights,
+        );
+
+        let camera_bind_group = bind_groups::BindGroupBuilder::<4>::builder()
+            .buffer(0, &uniform_buffer)
+            .buffer(1, &scene.render_objects_buffer)
+            .buffer(2, &scene.instance_index_to_render_object_map)
+            .buffer(3, &shadow_pass.light_uniform_buffer)
+            .build(
+                &context.device,
+                Some("vertex bind group"),
+                &vertex_shader_bind_group_layout,
+            );
+
+        let render_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("render pipeline layout"),
+                    bind_group_layouts: &[
+                        &vertex_shader_bind_group_layout, // group 0
+                        &texture_bind_group_layout,       // group 1
+                    ],
+                    push_constant_ranges: &[],
+                });
+        let (render_pipeline, after_depth_prepass_render_pipeline) =
+            Self::create_render_pipelines(context, &render_pipeline_layout);
+
+        let depth_prepass = depth_prepass::DepthPrepass::init(
+            &context.device,
+            context,
+            &vertex_shader_bind_group_layout,
+            &texture_bind_group_layout,
+        );
+
+        let render = Render {
+            pipeline: render_pipeline,
+            after_depth_prepass_pipeline: after_depth_prepass_render_pipeline,
+            pipeline_layout: render_pipeline_layout,
+            vertex_shader_bind_group: camera_bind_group,
+            fragment_shader_bind_group: cube_texture_bind_group,
+        };
+
+        let compute_shader = context
+            .device
+            .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: Some("compute shader"),
+                source: wgpu::ShaderSource::Wgsl(
+                    render_scene::compute_pipeline::compute_shader_source().into(),
+                ),
+            });
+
+        let compute_bind_group_layout =
+            render_scene::compute_pipeline::create_bind_group_layout(&context.device);
+
+        let compute_uniform = render_scene::compute_pipeline::ComputeUniform::init(&context.device);
+
+        let compute_bind_group = render_scene::compute_pipeline::create_bind_group(
+            &context.device,
+            &compute_bind_group_layout,
+            &render_scene::compute_pipeline::ComputeBindGroupBuffers {
+                camera_uniform: &uniform_buffer,
+                draw_commands: &scene.draw_commands_buffer,
+                render_objects: &scene.render_objects_buffer,
+                compute_local_data: &scene.compute_shader_local_data_buffer,
+                draw_count: &scene.draw_count_buffer,
+                out_draw_commands: &scene.out_draw_commands_buffer,
+                instance_index_to_render_object_map: &scene.instance_index_to_render_object_map,
+                compute_uniform: &compute_uniform.buffer,
+                render_object_draw_commands: &scene.render_object_draw_commands_buffer,
+                out_non_indexed_draw_commands: &scene.out_non_indexed_draw_commands_buffer,
+            },
+        );
+
+        let compute_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("compute pipeline layout"),
+                    bind_group_layouts: &[&compute_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let compute_pipeline =
+            context
+                .device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some("compute pipeline"),
+                    layout: Some(&compute_pipeline_layout),
+                    module: &compute_shader,
+                    entry_point: "cs_main",
+                });
+
+        let compute_emit_pipeline =
+            context
+                .device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some("compute emit pipeline"),
+                    layout: Some(&compute_pipeline_layout),
+                    module: &compute_shader,
+                    entry_point: "cs_emit",
+                });
+
+        let indirect_dispatch = render_scene::compute_pipeline::IndirectDispatch::init(
+            &context.device,
+            &compute_uniform.buffer,
+        );
+
+        let compute = Compute {
+            pipeline: compute_pipeline,
+            emit_pipeline: compute_emit_pipeline,
+            bind_group: compute_bind_group,
+            uniform: compute_uniform,
+            indirect_dispatch,
+        };
+
+        Self {
+            compute,
+            render,
+            _material_textures: material_textures,
+            _cube_normal_map: cube_normal_map,
+            camera,
+            uniform_buffer,
+            scene,
+            shadow_pass,
+            skybox,
+            grid,
+            grid_settings: grid::GridSettings::default(),
+            bounds_debug,
+            bounds_debug_settings: bounds_debug::BoundsDebugSettings::default(),
+            depth_prepass,
+            depth_prepass_settings: depth_prepass::DepthPrepassSettings::default(),
+            point_lights,
+            draw_count_readback: render_scene::DrawCountReadback::init(
+                &context.device,
+                render_limits.max_draw_commands,
+            ),
+            timestamp_readback: render_scene::TimestampReadback::init(&context.device),
+            render_stats: render_scene::RenderStats::default(),
+            clear_color: ClearColor::default(),
+            debug_labels: DebugLabels::default(),
+            mesh_assets,
+            ecs: LegionECSData {
+                world: l_world,
+                resources: l_resources,
+                entities,
+            },
+        }
+    }
+
+    fn on_event(&mut self, event: &events::PenguinEvent) -> bool {
+        if self.camera.controller.on_event(&event) {
+            return true;
+        }
+
+        match event {
+            events::PenguinEvent::Window(events::event::WindowResizeEvent { size, .. }) => {
+                self.camera.projection.resize((size.width, size.height));
+
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Schedules the writes of the camera's view projection to the uniform buffers of the passes.
+    fn write_camera_uniforms(&self, context: &GraphicsContext) {
+        self.uniform_buffer.write(
+            &context.queue,
+            0,
+            slice::from_ref(&self.camera.uniform_data),
+        );
+        self.skybox
+            .update_camera(&context.queue, &self.camera.uniform_data);
+        self.grid.update_camera(
+            &context.queue,
+            &self.camera.uniform_data,
+            self.camera.position(),
+        );
+        self.bounds_debug
+            .update_camera(&context.queue, &self.camera.uniform_data);
+    }
+
+    /// Switches the main pass to the depth mode of the camera's projection, which the editor
+    /// changes. The pipelines depth testing against the main pass' depth texture are created again
+    /// with the new compare function, and the depth texture is cleared to the new far depth.
+    fn apply_camera_depth_mode(&mut self, context: &mut GraphicsContext) {
+        let depth_mode = self.camera.projection.depth_mode;
+        if depth_mode == context.depth_mode {
+            return;
+        }
+        context.depth_mode = depth_mode;
+
+        let (pipeline, after_depth_prepass_pipeline) =
+            Self::create_render_pipelines(context, &self.render.pipeline_layout);
+        self.render.pipeline = pipeline;
+        self.render.after_depth_prepass_pipeline = after_depth_prepass_pipeline;
+        self.depth_prepass.set_depth_mode(context);
+        self.grid = grid::Grid::init(context, &self.camera);
+        self.bounds_debug = bounds_debug::BoundsDebug::init(
+            context,
+            &self.camera,
+            self.bounds_debug.max_instances(),
+        );
+        self.skybox.set_depth_mode(depth_mode);
+
+        // the camera's view projection changed with the projection's depth mode
+        self.write_camera_uniforms(context);
+    }
+
+    /// Called each frame.
+    /// `scene_dt` is the scaled delta time used for animating the scene, while the camera uses the
+    /// unscaled `dt` so that it can still be moved around while the scene is paused.
+    fn update_camera_and_scene(
+        &mut self,
+        context: &GraphicsContext,
+        dt: std::time::Duration,
+        scene_dt: std::time::Duration,
+    ) {
+        // update camera data
+        self.camera.update(dt);
+        self.write_camera_uniforms(context);
+
+        let mut point_lights_query = <&components::PointLight>::query();
+        self.point_lights
+            .update(&context.queue, point_lights_query.iter(&self.ecs.world));
+
+        let compute_uniform_changed = self.compute.uniform.update(
+            &context.queue,
+            self.scene.objects.render_objects.inner.len() as _,
+            self.scene.objects.first_non_indexed_draw_command,
+        );
+        if compute_uniform_changed {
+            // the dispatch args of the compute pass only change with the object count
+            let mut cmd = context
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("dispatch args encoder"),
+                });
+            self.compute.indirect_dispatch.record(&mut cmd);
+            context.queue.submit(iter::once(cmd.finish()));
+        }
+
+        let (_x, y) = unsafe {
+            TIME_STATE += scene_dt.as_secs_f32() * 2.;
+            (f32::cos(TIME_STATE), f32::sin(TIME_STATE))
+        };
+
+        // legion ecs ------------------------
+        let _world = &mut self.ecs.world;
+
+        // register render objects for entities added after startup, such as from a loaded scene
+        {
+            let mut query = <(
+                legion::Entity,
+                &components::MeshComponent,
+                Option<&components::Color>,
+                Option<&components::MaterialComponent>,
+                Option<&components::AlphaCutoff>,
+            )>::query()
+            .filter(!legion::component::<Handle<render_scene::RenderObject>>());
+
+            let unregistered = query
+                .iter(&self.ecs.world)
+                .map(|(entity, mesh, color, material, alpha_cutoff)| {
+                    (
+                        *entity,
+                        mesh.0,
+                        color.map_or(m::Vec4::ONE, |color| color.0),
+                        material.map_or(0, |material| material.0),
+                        alpha_cutoff.map_or(0.0, |cutoff| cutoff.0),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            for (entity, mesh, color, texture_index, alpha_cutoff) in unregistered {
+                let registered = self.scene.objects.register_object(&RenderObjectDescriptor {
+                    mesh_handle: Handle::from(mesh),
+                    transform: m::Mat4::IDENTITY,
+                    render_bounds: mesh::RenderBounds {
+                        origin: m::Vec3::ZERO,
+                        radius: 3.0,
+                    },
+                    color,
+                    texture_index,
+                    alpha_cutoff,
+                    draw_forward_pass: true,
+                    custom_passes: &[],
+                });
+                let render_object = match registered {
+                    Ok(render_object) => render_object,
+                    Err(e) => {
+                        log::error!("failed to register render object: {:?}", e);
+                        break;
+                    }
+                };
+
+                if let Some(mut entry) = self.ecs.world.entry(entity) {
+                    entry.add_component(render_object);
+                }
+            }
+        }
+
+        let mut translation_query =
+            <(&mut components::Translation, &mut components::Rotation)>::query();
+        for (mut translation, _rotation) in translation_query.iter_mut(&mut self.ecs.world) {
+            translation.0 = m::vec3(4.1, 4. + y, 0.);
+        }
+
+        // world transforms, added to entities with a Translation and updated when it changes
+        {
+            let mut query = <(
+                legion::Entity,
+                &components::Translation,
+                Option<&components::Rotation>,
+                Option<&components::Scale>,
+            )>::query()
+            .filter(!legion::component::<components::WorldTransform>());
+
+            let missing = query
+                .iter(&self.ecs.world)
+                .map(|(entity, translation, rotation, scale)| {
+                    (
+                        *entity,
+                        components::WorldTransform::new(translation, rotation, scale),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            for (entity, world_transform) in missing {
+                if let Some(mut entry) = self.ecs.world.entry(entity) {
+                    entry.add_component(world_transform);
+                }
+            }
+
+            let mut query = <(
+                &mut components::WorldTransform,
+                &components::Translation,
+                Option<&components::Rotation>,
+                Option<&components::Scale>,
+            )>::query()
+            .filter(
+                maybe_changed::<components::Translation>()
+                    | maybe_changed::<components::Rotation>()
+                    | maybe_changed::<components::Scale>(),
+            );
+
+            for (world_transform, translation, rotation, scale) in
+                query.iter_mut(&mut self.ecs.world)
+            {
+                *world_transform = components::WorldTransform::new(translation, rotation, scale);
+            }
+        }
+
+        use components::*;
+        use legion::component;
+
+        {
+            type TransQuery = (
+                &'static Handle<render_scene::RenderObject>,
+                &'static Translation,
+            );
+
+            let mut translation_query = <TransQuery>::query().filter(
+                !component::<components::Rotation>()
+                    & !component::<Scale>()
+                    & maybe_changed::<Translation>(),
+            );
+
+            for (render_obj, translation) in translation_query.iter(&self.ecs.world) {
+                self.scene.objects.enqueue_model_matrix_update(
+                    *render_obj,
+                    m::Mat4::from_translation(translation.0),
+                );
+            }
+        }
+
+        {
+            type TransRotQuery = (
+                &'static Handle<render_scene::RenderObject>,
+                &'static Translation,
+                &'static Rotation,
+            );
+
+            let mut query = <TransRotQuery>::query().filter(
+                !component::<Scale>()
+                    & (maybe_changed::<Translation>() | maybe_changed::<Rotation>()),
+            );
+
+            for (render_obj, trans, rot) in query.iter(&self.ecs.world) {
+                //let rot = m::Quat::from_euler(m::EulerRot::XYZ, rot.x, rot.y, rot.z);
+                self.scene.objects.enqueue_model_matrix_update(
+                    *render_obj,
+                    m::Mat4::from_rotation_translation(rot.0, trans.0),
+                );
+            }
+        }
+
+        {
+            type TransRotScaleQuery = (
+                &'static Handle<render_scene::RenderObject>,
+                &'static components::Translation,
+                &'static components::Rotation,
+                &'static components::Scale,
+            );
+
+            let mut query = <TransRotScaleQuery>::query().filter(
+                maybe_changed::<components::Translation>()
+                    | maybe_changed::<Rotation>()
+                    | maybe_changed::<Scale>(),
+            );
+
+            for (render_obj, trans, rot, scale) in query.iter(&self.ecs.world) {
+                self.scene.objects.enqueue_model_matrix_update(
+                    *render_obj,
+                    m::Mat4::from_scale_rotation_translation(scale.0, rot.0, trans.0),
+                );
+            }
+        }
+
+        {
+            let mut query = <(&Handle<render_scene::RenderObject>, &Color)>::query()
+                .filter(maybe_changed::<Color>());
+
+            for (render_obj, color) in query.iter(&self.ecs.world) {
+                self.scene
+                    .objects
+                    .enqueue_color_update(*render_obj, color.0);
+            }
+        }
+
+        {
+            type MeshQuery = (
+                &'static Handle<render_scene::RenderObject>,
+                &'static MeshComponent,
+            );
+
+            let mut query = <MeshQuery>::query().filter(maybe_changed::<MeshComponent>());
+
+            for (render_obj, mesh) in query.iter(&self.ecs.world) {
+                self.scene
+                    .objects
+                    .set_object_mesh(*render_obj, Handle::from(mesh.0));
+            }
+        }
+
+        {
+            let mut query = <(&Handle<render_scene::RenderObject>, &Visible)>::query()
+                .filter(maybe_changed::<Visible>());
+
+            for (render_obj, visible) in query.iter(&self.ecs.world) {
+                self.scene
+                    .objects
+                    .enqueue_visibility_update(*render_obj, visible.0);
+            }
+        }
+
+        // update scene
+        self.scene.build_batches(&context.queue);
+        self.scene.update(&context.queue);
+
+        if self.bounds_debug_settings.enabled {
+            self.bounds_debug
+                .update_bounds(&context.queue, &self.scene.objects);
+        }
+    }
+
+    /// Access the output view texture to submit render commands.
+    fn render<OutputTextureFunc: FnOnce(&wgpu::TextureView)>(
+        &self,
+        context: &GraphicsContext,
+        f: OutputTextureFunc,
+    ) -> Result<(), wgpu::SurfaceError> {
+        let output_texture = context.surface.get_current_texture()?;
+        let output_texture_view = output_texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        f(&output_texture_view);
+
+        output_texture.present();
+
+        Ok(())
+    }
+
+    /// Compute commands.
+    fn compute_commands(
+        &self,
+        device: &wgpu::Device,
+        encoder: Option<wgpu::CommandEncoder>,
+        dynamic_offsets: &[wgpu::DynamicOffset],
+    ) -> wgpu::CommandEncoder {
+        let mut cmd = match encoder {
+            Some(encoder) => encoder,
+            None => device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("compute commands encoder"),
+            }),
+        };
+
+        cmd.push_debug_group(&self.debug_labels.compute_pass);
+        {
+            // clear local compute commands buffer
+            cmd.copy_buffer_to_buffer(
+                &self.scene.clear_compute_shader_local_data_buffer,
+                0,
+                &self.scene.compute_shader_local_data_buffer,
+                0,
+                self.scene.compute_shader_local_data_buffer.byte_len(),
+            );
+
+            // clear draw count buffer
+            cmd.copy_buffer_to_buffer(
+                &self.scene.clear_draw_count_buffer,
+                0,
+                &self.scene.draw_count_buffer,
+                0,
+                self.scene.draw_count_buffer.byte_len(),
+            );
+
+            // the draw commands are drawn one by one with markers, including the stale ones
+            // past the draw count
+            if self.debug_labels.draw_markers {
+                render_scene::clear_emitted_draws(
+                    &mut cmd,
+                    &self.scene.zeroed_draw_commands_buffer,
+                    &self.scene.out_draw_commands_buffer,
+                    &self.scene.out_non_indexed_draw_commands_buffer,
+                );
+            }
+
+            let mut compute_pass = cmd.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some(self.debug_labels.compute_pass.as_str()),
+            });
+            compute_pass.set_pipeline(&self.compute.pipeline);
+            compute_pass.set_bind_group(0, &self.compute.bind_group, dynamic_offsets);
+            compute_pass.dispatch_indirect(&self.compute.indirect_dispatch.args_buffer, 0);
+
+            compute_pass.set_pipeline(&self.compute.emit_pipeline);
+            compute_pass.dispatch(
+                render_scene::compute_pipeline::dispatch_group_count(
+                    self.scene.limits.max_draw_commands as _,
+                ),
+                1,
+                1,
+            );
+        }
+        cmd.pop_debug_group();
+
+        cmd
+    }
+
+    /// The dynamic offsets are used for the fragment shader bind group.
+    fn render_commands(
+        &self,
+        context: &GraphicsContext,
+        output_texture_view: &wgpu::TextureView,
+        encoder: Option<wgpu::CommandEncoder>,
+        dynamic_offsets: &[wgpu::DynamicOffset],
+    ) -> wgpu::CommandEncoder {
+        let mut cmd = match encoder {
+            Some(encoder) => encoder,
+            None => context
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render commands encoder"),
+                }),
+        };
+
+        context.assert_attachments_match_surface();
+
+        self.shadow_pass.record(
+            &mut cmd,
+            &self.scene.vertex_arena,
+            &self.scene.instance_buffer,
+            &self.scene.out_draw_commands_buffer,
+            &self.scene.out_non_indexed_draw_commands_buffer,
+            &self.scene.draw_count_buffer,
+            self.scene.max_draw_count as _,
+            &self.debug_labels,
+        );
+
+        if self.depth_prepass_settings.enabled {
+            self.depth_prepass.record(
+                &mut cmd,
+                context.depth_mode,
+                &context.depth_texture.view,
+                self.camera
+                    .viewport
+                    .pixels((context.config.width, context.config.height)),
+                &self.render.vertex_shader_bind_group,
+                &self.render.fragment_shader_bind_group,
+                &self.scene.vertex_arena,
+                &self.scene.instance_buffer,
+                &self.scene.out_draw_commands_buffer,
+                &self.scene.out_non_indexed_draw_commands_buffer,
+                &self.scene.draw_count_buffer,
+                self.scene.max_draw_count as _,
+                &self.debug_labels,
+            );
+        }
+
+        // keep the depth written by the prepass
+        let (depth_load, pipeline) = match self.depth_prepass_settings.enabled {
+            true => (
+                wgpu::LoadOp::Load,
+                &self.render.after_depth_prepass_pipeline,
+            ),
+            false => (
+                wgpu::LoadOp::Clear(context.depth_mode.far_depth()),
+                &self.render.pipeline,
+            ),
+        };
+
+        cmd.push_debug_group(&self.debug_labels.render_pass);
+        {
+            let mut render_pass = cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(self.debug_labels.render_pass.as_str()),
+                color_attachments: &[context.color_attachment(
+                    output_texture_view,
+                    wgpu::LoadOp::Clear(self.clear_color.0),
+                )],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &context.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: depth_load,
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            self.skybox.record(&mut render_pass);
+
+            // set render pipeline
+            render_pass.set_pipeline(pipeline);
+
+            // set bind groups
+            render_pass.set_bind_group(0, &self.render.vertex_shader_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.render.fragment_shader_bind_group, dynamic_offsets);
+
+            // set vertex/index buffer
+            render_pass.set_vertex_buffer(0, self.scene.vertex_arena.vertices_slice());
+            render_pass.set_index_buffer(
+                self.scene.vertex_arena.indices_slice(),
+                wgpu::IndexFormat::Uint32,
+            );
+            // set instance buffer
+            render_pass.set_vertex_buffer(1, self.scene.instance_buffer.slice(..));
+
+            // draw
+            render_scene::record_emitted_draws(
+                &mut render_pass,
+                &self.scene.out_draw_commands_buffer,
+                &self.scene.out_non_indexed_draw_commands_buffer,
+                &self.scene.draw_count_buffer,
+                self.scene.max_draw_count as _,
+                self.debug_labels.render_pass_draw_marker(),
+            );
+
+            if self.bounds_debug_settings.enabled {
+                self.bounds_debug.record(&mut render_pass);
+            }
+
+            if self.grid_settings.enabled {
+                self.grid.record(&mut render_pass);
+            }
+        }
+        cmd.pop_debug_group();
+
+        cmd
+    }
+}
+
+/// Entry point of the renderer before layers. Kept until the layer path has an editor.
+pub fn main_without_layers() {
+    env_logger::init();
+    let event_loop = EventLoop::with_user_event();
+    let window = WindowBuilder::new()
+        .with_title("Penguin engine")
+        .build(&event_loop)
+        .unwrap();
+
+    let mut context = penguin_util::pollster::block_on(GraphicsContext::new(
+        &window,
+        &GraphicsContextDescriptor::default(),
+    ));
+
+    // base render layer --------
+    let mut state = RendererState::new(&context);
+
+    // egui -------
+    let mut editor = editor::EditorState::new(&context);
+
+    // clock for calculating delta time -----
+    let mut clock = time::Clock::start();
+    let mut time_scale = time::TimeScale::default();
+    let event_sender = events::PenguinEventSender::init(event_loop.create_proxy());
+    // continuous unless switched to reactive in the editor's top bar
+    let mut redraw = layer::Redraw::default();
+
+    event_loop.run(move |event, _, control_flow| {
+        // pass winit events to editor layer
+        editor.handle_platform_event(&event);
+
+        match event {
+            winit::event::Event::UserEvent(penguin_event) => {
+                // mouse motion is sent for the whole screen, moving the mouse over the window
+                // sends window events instead
+                if !matches!(
+                    penguin_event,
+                    PenguinEvent::Input(input::InputEvent::MouseMotion(_))
+                ) {
+                    redraw.request();
+                }
+
+                #[allow(unused)]
+                let mut event_consumed = false;
+
+                event_consumed = context.on_event(&penguin_event);
+
+                if !event_consumed {
+                    event_consumed = editor.on_event(&penguin_event);
+                }
+
+                if !event_consumed {
+                    event_consumed = state.on_event(&penguin_event);
+                }
+            }
+            //
+            winit::event::Event::DeviceEvent { ref event, .. } => {
+                match event {
+                    winit::event::DeviceEvent::Key(KeyboardInput {
+                        scancode: _,
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    }) => {
+                        let key = input::Key::from_virtual_keycode(*keycode);
+
+                        if let Some(key) = key {
+                            event_sender.send_event(events::PenguinEvent::Input(
+                                input::InputEvent::Key(input::KeyEvent {
+                                    key,
+                                    state: input::KeyState::from(*state),
+                                }),
+                            ));
+                        }
+                    }
+                    winit::event::DeviceEvent::Button {
+                        button: 1, // left mouse button
+                        state,
+                    } => {
+                        event_sender.send_event(events::PenguinEvent::Input(
+                            input::InputEvent::Key(input::KeyEvent {
+                                key: input::Key::LMouseButton,
+                                state: input::KeyState::from(*state),
+                            }),
+                        ));
+                    }
+                    winit::event::DeviceEvent::MouseMotion { delta } => {
+                        event_sender.send_event(events::PenguinEvent::Input(
+                            input::InputEvent::MouseMotion(*delta),
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+            //
+            winit::event::Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                redraw.request();
+
+                match event {
+                    //
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    //
+                    WindowEvent::Resized(physical_size) => event_sender.send_event(
+                        events::PenguinEvent::Window(events::event::WindowResizeEvent {
+                            size: *physical_size,
+                            scale_factor: None,
+                        }),
+                    ),
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    } => event_sender.send_event(events::PenguinEvent::Window(
+                        events::event::WindowResizeEvent {
+                            size: **new_inner_size,
+                            scale_factor: Some(*scale_factor),
+                        },
+                    )),
+                    WindowEvent::MouseWheel { delta, .. } => event_sender.send_event(
+                        events::PenguinEvent::Input(input::InputEvent::mouse_scroll(*delta)),
+                    ),
+                    _ => {}
+                }
+            }
+            //
+            winit::event::Event::MainEventsCleared => {
+                if redraw.take_request() {
+                    window.request_redraw();
+                }
+            }
+            // frames requested while drawing this one keep the loop from waiting for events
+            winit::event::Event::RedrawEventsCleared => {
+                if *control_flow != ControlFlow::Exit {
+                    *control_flow = redraw.control_flow();
+                }
+            }
+            //
+            winit::event::Event::RedrawRequested(window_id) if window_id == window.id() => {
+                let dt = clock.tick();
+
+                // update
+                {
+                    // the scene animation is paused in reactive mode, so an idle scene stops drawing
+                    let scene_dt = match redraw.mode {
+                        layer::RedrawMode::Continuous => time_scale.apply(dt),
+                        layer::RedrawMode::Reactive => std::time::Duration::ZERO,
+                    };
+                    state.update_camera_and_scene(&context, dt, scene_dt);
+
+                    // keep drawing while the camera moves
+                    if state.camera.controller.has_movement_input() {
+                        redraw.request();
+                    }
+
+                    let ui_storage = state
+                        .ecs
+                        .resources
+                        .get::<editor::EditorComponentStorage>()
+                        .expect("ui storage");
+                    let mut history = state
+                        .ecs
+                        .resources
+                        .get_mut::<editor::EditorHistory>()
+                        .expect("editor history");
+
+                    editor.update(
+                        &context,
+                        &window,
+                        &mut editor::FrameData {
+                            clock: &clock,
+                            time_scale: &mut time_scale,
+                            l_world: &mut state.ecs.world,
+                            ui_storage: &ui_storage,
+                            history: &mut history,
+                            render_stats: &state.render_stats,
+                            scene: &mut state.scene,
+                            mesh_assets: &mut state.mesh_assets,
+                            camera: &mut state.camera,
+                            clear_color: &mut state.clear_color,
+                            grid_settings: &mut state.grid_settings,
+                            bounds_debug_settings: &mut state.bounds_debug_settings,
+                            depth_prepass_settings: &mut state.depth_prepass_settings,
+                            debug_labels: &mut state.debug_labels,
+                            redraw: &mut redraw,
+                        },
+                    );
+                }
+
+                // the depth mode can be switched in the editor's top bar
+                state.apply_camera_depth_mode(&mut context);
+
+                // nothing to render to until the window is restored
+                if context.is_minimized {
+                    return;
+                }
+
+                // compute commands
+                {
+                    // read back the stats of earlier frames whose copies the GPU has finished
+                    state
+                        .draw_count_readback
+                        .read_ready(&context.device, &mut state.render_stats);
+                    state
+                        .timestamp_readback
+                        .read_ready(&context, &mut state.render_stats);
+                    state.render_stats.draw_count = state.scene.max_draw_count;
+
+                    let mut cmd =
+                        context
+                            .device
+                            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                                label: Some("compute commands encoder"),
+                            });
+
+                    context.begin_timestamp(&mut cmd, GpuPass::Compute);
+                    let mut cmd = state.compute_commands(&context.device, Some(cmd), &[]);
+                    context.end_timestamp(&mut cmd, GpuPass::Compute);
+                    state.draw_count_readback.copy(
+                        &mut cmd,
+                        &state.scene.draw_count_buffer,
+                        &state.scene.out_draw_commands_buffer,
+                    );
+
+                    context.queue.submit(iter::once(cmd.finish()));
+                    state.draw_count_readback.map_copied();
+                }
+
+                // render commands
+                {
+                    // get frame surface texture to render to
+                    let render_result = state.render(&context, |output| {
+                        let mut cmd = context.device.create_command_encoder(
+                            &wgpu::CommandEncoderDescriptor {
+                                label: Some("Render commands encoder"),
+                            },
+                        );
+
+                        context.begin_timestamp(&mut cmd, GpuPass::Render);
+                        let mut cmd = state.render_commands(&context, output, Some(cmd), &[]);
+                        context.end_timestamp(&mut cmd, GpuPass::Render);
+
+                        let cmd = editor.render_commands(&context.device, output, Some(cmd));
+
+                        context.queue.submit(iter::once(cmd.finish()));
+                    });
+
+                    // resolve the pass timestamps after the render commands are submitted
+                    let mut cmd =
+                        context
+                            .device
+                            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                                label: Some("timestamp readback encoder"),
+                            });
+                    state.timestamp_readback.copy(&mut cmd, &context);
+                    context.queue.submit(iter::once(cmd.finish()));
+                    state.timestamp_readback.map_copied();
+
+                    match render_result {
+                        Ok(_) => {}
+                        Err(wgpu::SurfaceError::Lost) => {
+                            println!("Surface lost. Reconfiguring");
+
+                            // reconfigure
+                            event_sender.send_event(PenguinEvent::Window(
+                                events::event::WindowResizeEvent {
+                                    size: context.size,
+                                    scale_factor: Some(context.scale_factor),
+                                },
+                            ));
+                        }
+                        Err(wgpu::SurfaceError::OutOfMemory) => {
+                            eprintln!("Out of memory. Exiting");
+                            *control_flow = ControlFlow::Exit;
+                        }
+                        Err(e) => eprintln!("Surface error: {:?}", e),
+                    };
+                }
+            }
+            //
+            _ => {}
+        }
+    });
+}
@@ -1,7 +1,9 @@
 use anyhow::*;
 use macaw as m;
-use penguin_util::raw_gpu_types::DrawIndexedIndirect;
+use penguin_util::handle::Handle;
+use penguin_util::raw_gpu_types::{DrawIndexedIndirect, DrawIndirect};
 use std::mem;
+use std::ops::Range;
 use std::result::Result::Ok;
 use wgpu::util::DeviceExt;
 
@@ -15,15 +17,18 @@ pub struct MeshVertex {
     pub position: m::Vec3,
     pub normal: m::Vec3,
     pub uv: m::Vec2,
+    /// Tangent in the direction of increasing u. w is the handedness of the bitangent (1 or -1).
+    pub tangent: m::Vec4,
 }
 unsafe impl bytemuck::Pod for MeshVertex {}
 unsafe impl bytemuck::Zeroable for MeshVertex {}
 
 impl MeshVertex {
-    const ATTRIBUTES: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
+    const ATTRIBUTES: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
         0 => Float32x3,
         1 => Float32x3,
         2 => Float32x2,
+        3 => Float32x4,
     ];
 }
 impl Vertex for MeshVertex {
@@ -40,13 +45,14 @@ impl Vertex for MeshVertex {
 
 /// todo: Bounds of a mesh used for culling in a compute shader.
 #[repr(C)]
-#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct RenderBounds {
     pub origin: m::Vec3,
     pub radius: f32,
 }
 
-/// Ranges in a vertex array buffer's vertices and indices section that represents a mesh.
+/// Ranges in a vertex arena's vertices and indices that represents a mesh. A mesh without indices
+/// is non-indexed, its vertices are drawn in order.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Mesh {
@@ -56,112 +62,554 @@ pub struct Mesh {
     pub index_count: u32,
 }
 impl Mesh {
-    /// Creates a draw command using this mesh.
-    pub fn create_draw_command(
-        &self,
-        first_instance: u32,
-        instance_count: u32,
-    ) -> DrawIndexedIndirect {
-        DrawIndexedIndirect {
-            index_count: self.index_count,
-            instance_count,
-            first_index: self.first_index,
-            base_vertex: self.first_vertex,
-            first_instance,
+    pub fn is_indexed(&self) -> bool {
+        self.index_count > 0
+    }
+
+    /// Creates a draw command using this mesh, indexed if the mesh has indices.
+    pub fn create_draw_command(&self, first_instance: u32, instance_count: u32) -> DrawCommand {
+        if self.is_indexed() {
+            DrawCommand::Indexed(DrawIndexedIndirect {
+                index_count: self.index_count,
+                instance_count,
+                first_index: self.first_index,
+                base_vertex: self.first_vertex,
+                first_instance,
+            })
+        } else {
+            DrawCommand::NonIndexed(DrawIndirect {
+                vertex_count: self.vertex_count,
+                instance_count,
+                first_vertex: self.first_vertex,
+                first_instance,
+            })
+        }
+    }
+}
+
+/// A draw command for an indexed or a non-indexed mesh.
+#[derive(Copy, Clone, Debug)]
+pub enum DrawCommand {
+    Indexed(DrawIndexedIndirect),
+    NonIndexed(DrawIndirect),
+}
+
+impl DrawCommand {
+    /// The draw command as stored in the draw commands buffer, which holds both kinds. Non-indexed
+    /// draw commands store the vertex count and first vertex in place of the index count and first
+    /// index, the compute shader turns them back into DrawIndirect.
+    pub fn batched(self) -> DrawIndexedIndirect {
+        match self {
+            DrawCommand::Indexed(draw_command) => draw_command,
+            DrawCommand::NonIndexed(draw_command) => DrawIndexedIndirect {
+                index_count: draw_command.vertex_count,
+                instance_count: draw_command.instance_count,
+                first_index: draw_command.first_vertex,
+                base_vertex: 0,
+                first_instance: draw_command.first_instance,
+            },
         }
     }
 }
 
 // -----------------
 
-pub struct VertexArrayBuffer {
-    pub buffer: wgpu::Buffer,
-    vertices_byte_range: u64,
+/// Sub-allocates ranges of elements in a buffer. New ranges are bumped off the end, freed ranges
+/// are kept in a free list and reused first-fit.
+#[derive(Default, Debug)]
+pub struct RangeAllocator {
+    /// End of the allocated ranges.
+    end: u32,
+    /// Freed ranges before the end, sorted and not adjacent to each other.
+    free_ranges: Vec<Range<u32>>,
 }
-impl VertexArrayBuffer {
-    /// Returns the slice of the vertex array buffer that contains the vertices.
+impl RangeAllocator {
+    /// An allocator with the range ..end already allocated.
+    pub fn with_allocated(end: u32) -> Self {
+        Self {
+            end,
+            free_ranges: Vec::new(),
+        }
+    }
+
+    /// End of the allocated ranges, the size the buffer needs to be.
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    pub fn allocate(&mut self, count: u32) -> Range<u32> {
+        if let Some(index) = self
+            .free_ranges
+            .iter()
+            .position(|free_range| free_range.len() as u32 >= count)
+        {
+            let start = self.free_ranges[index].start;
+            self.free_ranges[index].start += count;
+            if self.free_ranges[index].is_empty() {
+                self.free_ranges.remove(index);
+            }
+            return start..start + count;
+        }
+
+        let start = self.end;
+        self.end += count;
+        start..self.end
+    }
+
+    pub fn free(&mut self, range: Range<u32>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let index = self
+            .free_ranges
+            .partition_point(|free_range| free_range.start < range.start);
+        self.free_ranges.insert(index, range);
+
+        // merge with the next and previous free ranges
+        if index + 1 < self.free_ranges.len()
+            && self.free_ranges[index].end == self.free_ranges[index + 1].start
+        {
+            self.free_ranges[index].end = self.free_ranges.remove(index + 1).end;
+        }
+        if index > 0 && self.free_ranges[index - 1].end == self.free_ranges[index].start {
+            self.free_ranges[index - 1].end = self.free_ranges.remove(index).end;
+        }
+
+        // a free range at the end goes back to the bump allocation
+        if let Some(last) = self.free_ranges.last() {
+            if last.end == self.end {
+                self.end = last.start;
+                self.free_ranges.pop();
+            }
+        }
+    }
+}
+
+/// Vertex and index buffers that meshes are sub-allocated from. The buffers grow when they're
+/// full, copying the existing meshes over, so meshes can be loaded at runtime without touching the
+/// ones already loaded.
+pub struct VertexArena {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    /// Capacity of the vertex buffer, in vertices.
+    vertex_capacity: u32,
+    /// Capacity of the index buffer, in indices.
+    index_capacity: u32,
+    vertices: RangeAllocator,
+    indices: RangeAllocator,
+}
+impl VertexArena {
+    const VERTEX_BUFFER_USAGE: wgpu::BufferUsages = wgpu::BufferUsages::from_bits_truncate(
+        wgpu::BufferUsages::VERTEX.bits()
+            | wgpu::BufferUsages::COPY_SRC.bits()
+            | wgpu::BufferUsages::COPY_DST.bits(),
+    );
+    const INDEX_BUFFER_USAGE: wgpu::BufferUsages = wgpu::BufferUsages::from_bits_truncate(
+        wgpu::BufferUsages::INDEX.bits()
+            | wgpu::BufferUsages::COPY_SRC.bits()
+            | wgpu::BufferUsages::COPY_DST.bits(),
+    );
+
+    /// Creates empty buffers with room for the given number of vertices and indices.
+    pub fn new(device: &wgpu::Device, vertex_capacity: u32, index_capacity: u32) -> Self {
+        Self {
+            vertex_buffer: Self::create_vertex_buffer(device, vertex_capacity),
+            index_buffer: Self::create_index_buffer(device, index_capacity),
+            vertex_capacity,
+            index_capacity,
+            vertices: RangeAllocator::default(),
+            indices: RangeAllocator::default(),
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: u32) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("vertex arena vertex buffer"),
+            size: (capacity as usize * mem::size_of::<MeshVertex>()) as _,
+            usage: Self::VERTEX_BUFFER_USAGE,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_index_buffer(device: &wgpu::Device, capacity: u32) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("vertex arena index buffer"),
+            size: (capacity as usize * mem::size_of::<u32>()) as _,
+            usage: Self::INDEX_BUFFER_USAGE,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Returns the slice of the vertex buffer that contains the vertices.
     pub fn vertices_slice(&self) -> wgpu::BufferSlice {
-        self.buffer.slice(..self.vertices_byte_range as u64)
+        self.vertex_buffer.slice(..)
     }
 
-    /// Returns the slice of the vertex array buffer that contains the indices.
+    /// Returns the slice of the index buffer that contains the indices.
     pub fn indices_slice(&self) -> wgpu::BufferSlice {
-        self.buffer.slice(self.vertices_byte_range as u64..)
+        self.index_buffer.slice(..)
+    }
+
+    #[allow(unused)]
+    pub fn vertex_buffer(&self) -> &wgpu::Buffer {
+        &self.vertex_buffer
+    }
+
+    #[allow(unused)]
+    pub fn index_buffer(&self) -> &wgpu::Buffer {
+        &self.index_buffer
+    }
+
+    /// Allocates ranges for the vertices and indices and uploads them. The indices are relative to
+    /// the first vertex, which is the mesh's base vertex. Without indices the mesh is non-indexed.
+    pub fn allocate(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vertices: &[MeshVertex],
+        indices: &[u32],
+    ) -> Mesh {
+        let vertex_range = self.vertices.allocate(vertices.len() as _);
+        let index_range = self.indices.allocate(indices.len() as _);
+        self.grow(device, queue);
+
+        queue.write_buffer(
+            &self.vertex_buffer,
+            (vertex_range.start as usize * mem::size_of::<MeshVertex>()) as _,
+            bytemuck::cast_slice(vertices),
+        );
+        if !indices.is_empty() {
+            queue.write_buffer(
+                &self.index_buffer,
+                (index_range.start as usize * mem::size_of::<u32>()) as _,
+                bytemuck::cast_slice(indices),
+            );
+        }
+
+        Mesh {
+            first_vertex: vertex_range.start,
+            vertex_count: vertices.len() as _,
+            first_index: index_range.start,
+            index_count: indices.len() as _,
+        }
+    }
+
+    /// Frees the mesh's vertex and index ranges, to be reused by meshes allocated later. Meshes
+    /// for the sub-meshes of a mesh share its ranges, only the whole mesh should be freed.
+    pub fn free(&mut self, mesh: &Mesh) {
+        self.vertices
+            .free(mesh.first_vertex..mesh.first_vertex + mesh.vertex_count);
+        self.indices
+            .free(mesh.first_index..mesh.first_index + mesh.index_count);
+    }
+
+    /// Grows the buffers to fit the allocated ranges, at least doubling their capacity, and copies
+    /// the existing vertices and indices to the new buffers.
+    fn grow(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.vertices.end() <= self.vertex_capacity && self.indices.end() <= self.index_capacity
+        {
+            return;
+        }
+
+        let mut cmd = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("vertex arena grow encoder"),
+        });
+        // the old buffers are dropped after the copies are submitted
+        let mut old_buffers = Vec::new();
+
+        if self.vertices.end() > self.vertex_capacity {
+            let capacity = self.vertices.end().max(self.vertex_capacity * 2);
+            let buffer = Self::create_vertex_buffer(device, capacity);
+            cmd.copy_buffer_to_buffer(
+                &self.vertex_buffer,
+                0,
+                &buffer,
+                0,
+                (self.vertex_capacity as usize * mem::size_of::<MeshVertex>()) as _,
+            );
+
+            old_buffers.push(mem::replace(&mut self.vertex_buffer, buffer));
+            self.vertex_capacity = capacity;
+        }
+
+        if self.indices.end() > self.index_capacity {
+            let capacity = self.indices.end().max(self.index_capacity * 2);
+            let buffer = Self::create_index_buffer(device, capacity);
+            cmd.copy_buffer_to_buffer(
+                &self.index_buffer,
+                0,
+                &buffer,
+                0,
+                (self.index_capacity as usize * mem::size_of::<u32>()) as _,
+            );
+
+            old_buffers.push(mem::replace(&mut self.index_buffer, buffer));
+            self.index_capacity = capacity;
+        }
+
+        queue.submit(std::iter::once(cmd.finish()));
+        drop(old_buffers);
     }
 
-    /// Takes a list of mesh asset names and uploads their vertices and indices into a single,
-    /// continuous, gpu buffer. Returns a handle to the allocated buffer and an array of meshes.
+    /// Loads an obj file and uploads it to the arena. Pushes its mesh, and a mesh for each of its
+    /// sub-meshes if it has more than one material, to meshes. Returns its sub-meshes.
+    ///
+    /// The sub-meshes' diffuse textures aren't assigned a layer of the material texture array,
+    /// which is created on startup. A warning is logged for each of them, and they are drawn with
+    /// the diffuse color only.
+    pub fn load_mesh<P: AsRef<std::path::Path>>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: P,
+        meshes: &mut Vec<Mesh>,
+    ) -> Result<Vec<SubMesh>> {
+        let path = path.as_ref();
+        let asset = MeshAsset::load_obj(path)?;
+
+        let mesh = self.allocate(device, queue, &asset.vertices, &asset.indices);
+        log::debug!("loaded mesh {}: {:?}", path.display(), mesh);
+
+        let mesh_handle = Handle::from(meshes.len());
+        meshes.push(mesh);
+
+        let sub_meshes = if asset.sub_meshes.len() <= 1 {
+            let material = asset
+                .sub_meshes
+                .first()
+                .and_then(|sub_mesh| asset.sub_mesh_material(sub_mesh));
+
+            vec![SubMesh::new(mesh_handle, material)]
+        } else {
+            asset
+                .sub_meshes
+                .iter()
+                .map(|sub_mesh| {
+                    let handle = Handle::from(meshes.len());
+
+                    meshes.push(Mesh {
+                        first_index: mesh.first_index + sub_mesh.first_index,
+                        index_count: sub_mesh.index_count,
+                        ..mesh
+                    });
+
+                    SubMesh::new(handle, asset.sub_mesh_material(sub_mesh))
+                })
+                .collect()
+        };
+
+        for texture in sub_meshes.iter().filter_map(|s| s.diffuse_texture.as_ref()) {
+            log::warn!(
+                "{}: the diffuse texture {} isn't loaded, textures are only loaded on startup",
+                path.display(),
+                texture.display()
+            );
+        }
+
+        Ok(sub_meshes)
+    }
+
+    /// Takes a list of mesh asset names and uploads their vertices and indices into a new arena,
+    /// sized to fit them. Returns the arena, an array of meshes and the sub-meshes of each mesh
+    /// asset.
     ///
     /// The location of each mesh in the returned array corresponds to the location of the mesh
-    /// asset name in the input mesh_asset_names array.
+    /// asset name in the input mesh_asset_names array. Meshes for the sub-meshes of assets with
+    /// more than one material are placed after them.
     pub fn build_from_mesh_assets(
         device: &wgpu::Device,
         mesh_asset_names: &[&str],
-    ) -> (Self, Vec<Mesh>) {
+    ) -> (Self, Vec<Mesh>, Vec<Vec<SubMesh>>) {
         let assets_dir = std::path::Path::new(env!("OUT_DIR")).join("assets/meshes");
 
         let mut next_first_vertex = 0;
         let mut next_first_index = 0;
 
         let mut meshes = Vec::with_capacity(mesh_asset_names.len());
+        let mut material_meshes = Vec::new();
+        let mut sub_meshes = Vec::with_capacity(mesh_asset_names.len());
 
-        println!("loading meshes...");
-        let (vertices, indices): (Vec<Vec<MeshVertex>>, Vec<Vec<u32>>) = mesh_asset_names
-            .iter()
-            .map(|mesh_name| {
-                let MeshAsset { vertices, indices } =
-                    MeshAsset::load_obj(assets_dir.join(mesh_name))
-                        .expect(&format!("failed to load {}", mesh_name));
-
-                let mesh = Mesh {
-                    first_vertex: next_first_vertex,
-                    vertex_count: vertices.len() as _,
-                    first_index: next_first_index,
-                    index_count: indices.len() as _,
-                };
-                println!("loaded mesh: {:?}", mesh);
-                meshes.push(mesh);
+        let mut vertices: Vec<Vec<MeshVertex>> = Vec::with_capacity(mesh_asset_names.len());
+        let mut indices: Vec<Vec<u32>> = Vec::with_capacity(mesh_asset_names.len());
 
-                next_first_vertex += vertices.len() as u32;
-                next_first_index += indices.len() as u32;
+        log::debug!("loading meshes...");
+        let assets = MeshAsset::load_objs(
+            mesh_asset_names
+                .iter()
+                .map(|mesh_name| assets_dir.join(mesh_name)),
+        );
 
-                (vertices, indices)
-            })
-            .unzip();
-        println!("\n");
+        for (mesh_name, asset) in mesh_asset_names.iter().zip(assets) {
+            let asset = asset.expect(&format!("failed to load {}", mesh_name));
+
+            let mesh = Mesh {
+                first_vertex: next_first_vertex,
+                vertex_count: asset.vertices.len() as _,
+                first_index: next_first_index,
+                index_count: asset.indices.len() as _,
+            };
+            log::debug!("loaded mesh {}: {:?}", mesh_name, mesh);
+
+            let asset_sub_meshes = if asset.sub_meshes.len() <= 1 {
+                // a single material, draw the whole mesh
+                let material = asset
+                    .sub_meshes
+                    .first()
+                    .and_then(|sub_mesh| asset.sub_mesh_material(sub_mesh));
+
+                vec![SubMesh::new(Handle::from(meshes.len()), material)]
+            } else {
+                asset
+                    .sub_meshes
+                    .iter()
+                    .map(|sub_mesh| {
+                        let handle = Handle::from(mesh_asset_names.len() + material_meshes.len());
+
+                        material_meshes.push(Mesh {
+                            first_index: mesh.first_index + sub_mesh.first_index,
+                            index_count: sub_mesh.index_count,
+                            ..mesh
+                        });
+
+                        SubMesh::new(handle, asset.sub_mesh_material(sub_mesh))
+                    })
+                    .collect()
+            };
+
+            meshes.push(mesh);
+            sub_meshes.push(asset_sub_meshes);
+
+            next_first_vertex += asset.vertices.len() as u32;
+            next_first_index += asset.indices.len() as u32;
+
+            vertices.push(asset.vertices);
+            indices.push(asset.indices);
+        }
+        meshes.extend(material_meshes);
 
         let vertices = vertices.into_iter().flatten().collect::<Vec<_>>();
         let indices = indices.into_iter().flatten().collect::<Vec<_>>();
 
-        let vertices_bytes: &[u8] = bytemuck::cast_slice(&vertices);
-        let indices_bytes: &[u8] = bytemuck::cast_slice(&indices);
-        let vertices_byte_range = vertices_bytes.len();
-
-        let vertex_array_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("vertex index buffer"),
-            contents: &[vertices_bytes, indices_bytes].concat(),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::INDEX,
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("vertex arena vertex buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: Self::VERTEX_BUFFER_USAGE,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("vertex arena index buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: Self::INDEX_BUFFER_USAGE,
         });
 
         (
             Self {
-                buffer: vertex_array_buffer,
-                vertices_byte_range: vertices_byte_range as u64,
+                vertex_buffer,
+                index_buffer,
+                vertex_capacity: vertices.len() as _,
+                index_capacity: indices.len() as _,
+                vertices: RangeAllocator::with_allocated(vertices.len() as _),
+                indices: RangeAllocator::with_allocated(indices.len() as _),
             },
             meshes,
+            sub_meshes,
         )
     }
 }
 
+/// A part of a mesh asset that's drawn with one material.
+#[derive(Clone, Debug)]
+pub struct SubMesh {
+    pub mesh: Handle<Mesh>,
+    /// Diffuse color of the material, white if the sub-mesh has no material.
+    pub diffuse_color: m::Vec4,
+    pub diffuse_texture: Option<std::path::PathBuf>,
+    /// Layer of the material texture array with the diffuse texture. Assigned by
+    /// assign_texture_layers.
+    pub texture_index: Option<u32>,
+}
+impl SubMesh {
+    fn new(mesh: Handle<Mesh>, material: Option<&MaterialAsset>) -> Self {
+        Self {
+            mesh,
+            diffuse_color: material
+                .map_or(m::Vec4::ONE, |material| material.diffuse_color.extend(1.0)),
+            diffuse_texture: material.and_then(|material| material.diffuse_texture.clone()),
+            texture_index: None,
+        }
+    }
+}
+
+/// Assigns the sub-meshes with a diffuse texture the layers of the material texture array after
+/// the first first_layer layers. Sub-meshes with the same texture share a layer. Returns the paths
+/// of the textures to load into the assigned layers, in order.
+pub fn assign_texture_layers(
+    sub_meshes: &mut [Vec<SubMesh>],
+    first_layer: u32,
+) -> Vec<std::path::PathBuf> {
+    let mut textures: Vec<std::path::PathBuf> = Vec::new();
+
+    for sub_mesh in sub_meshes.iter_mut().flatten() {
+        if let Some(texture) = &sub_mesh.diffuse_texture {
+            let index = match textures.iter().position(|t| t == texture) {
+                Some(index) => index,
+                None => {
+                    textures.push(texture.clone());
+                    textures.len() - 1
+                }
+            };
+
+            sub_mesh.texture_index = Some(first_layer + index as u32);
+        }
+    }
+
+    textures
+}
+
+/// Material loaded from an obj file's mtl file.
+#[derive(Clone, Debug)]
+pub struct MaterialAsset {
+    pub name: String,
+    pub diffuse_color: m::Vec3,
+    /// Path to the diffuse texture. Relative to the mtl file until MeshAsset::load_obj resolves it
+    /// against the obj file's directory.
+    pub diffuse_texture: Option<std::path::PathBuf>,
+}
+impl From<tobj::Material> for MaterialAsset {
+    fn from(material: tobj::Material) -> Self {
+        Self {
+            name: material.name,
+            diffuse_color: m::Vec3::from(material.diffuse),
+            diffuse_texture: (!material.diffuse_texture.is_empty())
+                .then(|| std::path::PathBuf::from(material.diffuse_texture)),
+        }
+    }
+}
+
+/// Range in a MeshAsset's indices that uses one material.
+#[derive(Copy, Clone, Debug)]
+pub struct SubMeshAsset {
+    pub first_index: u32,
+    pub index_count: u32,
+    /// Index into MeshAsset::materials, None if the shapes have no material.
+    pub material: Option<usize>,
+}
+
 /// Mesh data loaded into memory (CPU-side memory / RAM).
 pub struct MeshAsset {
     pub vertices: Vec<MeshVertex>,
     pub indices: Vec<u32>,
+    /// The indices grouped by material.
+    pub sub_meshes: Vec<SubMeshAsset>,
+    pub materials: Vec<MaterialAsset>,
 }
 impl MeshAsset {
-    /// Loads an obj file's vertices and indices into memory.
+    /// Loads an obj file's vertices, indices and materials into memory. An obj file without
+    /// materials, or with an mtl file that fails to load, gets a single sub-mesh without material.
+    /// Shapes without normals get flat normals, and shapes without texture coordinates get zeroed
+    /// uvs.
     pub fn load_obj<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
-        let (shapes, _materials) = tobj::load_obj(
+        let (mut shapes, materials) = tobj::load_obj(
             path.as_ref(),
             &tobj::LoadOptions {
                 single_index: true,
@@ -171,29 +619,83 @@ impl MeshAsset {
             },
         )?;
 
+        // the mtl file is looked up next to the obj file, so its texture paths are relative to it
+        let mesh_dir = path
+            .as_ref()
+            .parent()
+            .unwrap_or_else(|| std::path::Path::new(""));
+
+        let materials = match materials {
+            Ok(materials) => materials
+                .into_iter()
+                .map(MaterialAsset::from)
+                .map(|mut material| {
+                    material.diffuse_texture = material
+                        .diffuse_texture
+                        .map(|diffuse_texture| mesh_dir.join(diffuse_texture));
+                    material
+                })
+                .collect(),
+            Err(e) => {
+                log::warn!(
+                    "failed to load materials of {}: {}",
+                    path.as_ref().display(),
+                    e
+                );
+                Vec::new()
+            }
+        };
+
+        // shapes with the same material end up next to each other, in one sub-mesh
+        let shape_material = |shape: &tobj::Model| {
+            shape
+                .mesh
+                .material_id
+                .filter(|material_id| *material_id < materials.len())
+        };
+        shapes.sort_by_key(shape_material);
+
         let mut vertices: Vec<Vec<MeshVertex>> = Vec::new();
         let mut indices: Vec<Vec<u32>> = Vec::new();
+        let mut sub_meshes: Vec<SubMeshAsset> = Vec::new();
 
         let mut next_vertex_index_begin = 0;
+        let mut next_first_index = 0;
 
         for shape in shapes.iter() {
-            let shape_verts = (0..shape.mesh.positions.len() / 3)
+            let has_normals = !shape.mesh.normals.is_empty();
+            let has_uvs = !shape.mesh.texcoords.is_empty();
+
+            let mut shape_verts = (0..shape.mesh.positions.len() / 3)
                 .map(|vertex_index| MeshVertex {
                     position: m::Vec3::from_slice(
                         &shape.mesh.positions[vertex_index * 3..=vertex_index * 3 + 2],
                     ),
-                    normal: m::Vec3::from_slice(
-                        &shape.mesh.normals[vertex_index * 3..=vertex_index * 3 + 2],
-                    ),
-                    uv: m::Vec2::from_slice(
-                        &shape.mesh.texcoords[vertex_index * 2..=vertex_index * 2 + 1],
-                    ),
+                    normal: if has_normals {
+                        m::Vec3::from_slice(
+                            &shape.mesh.normals[vertex_index * 3..=vertex_index * 3 + 2],
+                        )
+                    } else {
+                        m::Vec3::ZERO
+                    },
+                    uv: if has_uvs {
+                        m::Vec2::from_slice(
+                            &shape.mesh.texcoords[vertex_index * 2..=vertex_index * 2 + 1],
+                        )
+                    } else {
+                        m::Vec2::ZERO
+                    },
+                    tangent: m::Vec4::ZERO,
                 })
                 .collect::<Vec<_>>();
+            let mut shape_local_inds = shape.mesh.indices.clone();
 
-            let shape_inds = shape
-                .mesh
-                .indices
+            if !has_normals {
+                Self::compute_flat_normals(&mut shape_verts, &mut shape_local_inds);
+            }
+            Self::compute_tangents(&mut shape_verts, &shape_local_inds);
+
+            let shape_inds = shape_local_inds
                 .iter()
                 .map(|index| next_vertex_index_begin + index)
                 .collect::<Vec<_>>();
@@ -208,7 +710,20 @@ impl MeshAsset {
             //     next_vertex_index_begin
             // );
 
-            next_vertex_index_begin += shape.mesh.positions.len() as u32;
+            let material = shape_material(shape);
+            match sub_meshes.last_mut() {
+                Some(sub_mesh) if sub_mesh.material == material => {
+                    sub_mesh.index_count += shape_inds.len() as u32;
+                }
+                _ => sub_meshes.push(SubMeshAsset {
+                    first_index: next_first_index,
+                    index_count: shape_inds.len() as u32,
+                    material,
+                }),
+            }
+            next_first_index += shape_inds.len() as u32;
+
+            next_vertex_index_begin += shape_verts.len() as u32;
 
             vertices.push(shape_verts);
             indices.push(shape_inds);
@@ -217,6 +732,404 @@ impl MeshAsset {
         let vertices = vertices.into_iter().flatten().collect::<Vec<MeshVertex>>();
         let indices = indices.into_iter().flatten().collect::<Vec<u32>>();
 
-        Ok(Self { vertices, indices })
+        Ok(Self {
+            vertices,
+            indices,
+            sub_meshes,
+            materials,
+        })
+    }
+
+    /// Loads obj files in parallel, each on its own thread. The results are in the same order as
+    /// the paths.
+    pub fn load_objs(paths: impl IntoIterator<Item = std::path::PathBuf>) -> Vec<Result<Self>> {
+        let threads = paths
+            .into_iter()
+            .map(|path| std::thread::spawn(move || Self::load_obj(path)))
+            .collect::<Vec<_>>();
+
+        threads
+            .into_iter()
+            .map(|thread| {
+                thread
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    }
+
+    fn sub_mesh_material(&self, sub_mesh: &SubMeshAsset) -> Option<&MaterialAsset> {
+        sub_mesh
+            .material
+            .map(|material_index| &self.materials[material_index])
+    }
+
+    /// Gives every triangle its own vertices, with the triangle's face normal. Degenerate triangles
+    /// get an up normal.
+    fn compute_flat_normals(vertices: &mut Vec<MeshVertex>, indices: &mut Vec<u32>) {
+        *vertices = indices
+            .iter()
+            .map(|&index| vertices[index as usize])
+            .collect();
+        *indices = (0..vertices.len() as u32).collect();
+
+        for triangle in vertices.chunks_exact_mut(3) {
+            let edge1 = triangle[1].position - triangle[0].position;
+            let edge2 = triangle[2].position - triangle[0].position;
+            let normal = edge1.cross(edge2).try_normalize().unwrap_or(m::Vec3::Y);
+
+            for vertex in triangle {
+                vertex.normal = normal;
+            }
+        }
+    }
+
+    /// Calculates per-vertex tangents from the positions and uvs of the triangles, for normal
+    /// mapping. The tangents are averaged over the triangles sharing a vertex and made orthogonal
+    /// to the vertex normal.
+    fn compute_tangents(vertices: &mut [MeshVertex], indices: &[u32]) {
+        let mut tangents = vec![m::Vec3::ZERO; vertices.len()];
+        let mut bitangents = vec![m::Vec3::ZERO; vertices.len()];
+
+        for triangle in indices.chunks_exact(3) {
+            let [i0, i1, i2] = [
+                triangle[0] as usize,
+                triangle[1] as usize,
+                triangle[2] as usize,
+            ];
+            let (v0, v1, v2) = (&vertices[i0], &vertices[i1], &vertices[i2]);
+
+            let edge1 = v1.position - v0.position;
+            let edge2 = v2.position - v0.position;
+            let delta_uv1 = v1.uv - v0.uv;
+            let delta_uv2 = v2.uv - v0.uv;
+
+            let determinant = delta_uv1.x * delta_uv2.y - delta_uv2.x * delta_uv1.y;
+            if determinant.abs() <= f32::EPSILON {
+                // degenerate uvs
+                continue;
+            }
+            let r = 1.0 / determinant;
+
+            let tangent = (edge1 * delta_uv2.y - edge2 * delta_uv1.y) * r;
+            let bitangent = (edge2 * delta_uv1.x - edge1 * delta_uv2.x) * r;
+
+            for index in [i0, i1, i2] {
+                tangents[index] += tangent;
+                bitangents[index] += bitangent;
+            }
+        }
+
+        for (vertex, (tangent, bitangent)) in vertices
+            .iter_mut()
+            .zip(tangents.into_iter().zip(bitangents))
+        {
+            let normal = vertex.normal;
+
+            // gram-schmidt orthogonalize, falling back to any vector orthogonal to the normal
+            let tangent = (tangent - normal * normal.dot(tangent))
+                .try_normalize()
+                .unwrap_or_else(|| {
+                    let axis = if normal.x.abs() > 0.9 {
+                        m::Vec3::Y
+                    } else {
+                        m::Vec3::X
+                    };
+                    normal.cross(axis).normalize()
+                });
+
+            let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+
+            vertex.tangent = tangent.extend(handedness);
+        }
+    }
+}
+
+#[test]
+fn test_assign_texture_layers() {
+    let sub_mesh = |diffuse_texture: Option<&str>| SubMesh {
+        mesh: Handle::from(0),
+        diffuse_color: m::Vec4::ONE,
+        diffuse_texture: diffuse_texture.map(std::path::PathBuf::from),
+        texture_index: None,
+    };
+
+    let mut sub_meshes = vec![
+        vec![sub_mesh(Some("/bark.png")), sub_mesh(None)],
+        vec![sub_mesh(Some("/leaves.png")), sub_mesh(Some("/bark.png"))],
+    ];
+
+    let textures = assign_texture_layers(&mut sub_meshes, 2);
+
+    // shared textures are only loaded once
+    assert_eq!(
+        textures,
+        vec![
+            std::path::PathBuf::from("/bark.png"),
+            std::path::PathBuf::from("/leaves.png")
+        ]
+    );
+
+    let texture_indices = sub_meshes
+        .iter()
+        .flatten()
+        .map(|sub_mesh| sub_mesh.texture_index)
+        .collect::<Vec<_>>();
+    assert_eq!(texture_indices, vec![Some(2), None, Some(3), Some(2)]);
+}
+
+#[test]
+fn test_load_obj_without_normals_or_uvs() {
+    let path = std::env::temp_dir().join("penguin_test_positions_only.obj");
+    std::fs::write(
+        &path,
+        "v 0 0 0\n\
+         v 1 0 0\n\
+         v 1 1 0\n\
+         v 0 1 0\n\
+         f 1 2 3 4\n",
+    )
+    .unwrap();
+
+    let mesh = MeshAsset::load_obj(&path).unwrap();
+
+    // the quad is triangulated, each triangle with its own vertices
+    assert_eq!(mesh.vertices.len(), 6);
+    assert_eq!(mesh.indices, (0..6).collect::<Vec<u32>>());
+    for vertex in &mesh.vertices {
+        assert!((vertex.normal - m::Vec3::Z).length() < 1e-5);
+        assert_eq!(vertex.uv, m::Vec2::ZERO);
+        assert!(vertex.tangent.truncate().is_normalized());
     }
 }
+
+#[test]
+fn test_load_obj_with_two_materials() {
+    let dir = std::env::temp_dir().join("penguin_test_two_materials");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("two_materials.mtl"),
+        "newmtl red\n\
+         Kd 1 0 0\n\
+         map_Kd red.png\n\
+         newmtl blue\n\
+         Kd 0 0 1\n",
+    )
+    .unwrap();
+
+    // the shapes of the red material are split by a blue quad
+    let path = dir.join("two_materials.obj");
+    std::fs::write(
+        &path,
+        "mtllib two_materials.mtl\n\
+         v 0 0 0\n\
+         v 1 0 0\n\
+         v 1 1 0\n\
+         v 0 1 0\n\
+         o red_triangle\n\
+         usemtl red\n\
+         f 1 2 3\n\
+         o blue_quad\n\
+         usemtl blue\n\
+         f 1 2 3 4\n\
+         o red_triangle2\n\
+         usemtl red\n\
+         f 1 3 4\n",
+    )
+    .unwrap();
+
+    let mesh = MeshAsset::load_obj(&path).unwrap();
+
+    let names = mesh
+        .materials
+        .iter()
+        .map(|material| material.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["red", "blue"]);
+    assert_eq!(mesh.materials[0].diffuse_color, m::vec3(1., 0., 0.));
+    assert_eq!(mesh.materials[1].diffuse_color, m::vec3(0., 0., 1.));
+    // resolved against the obj file's directory
+    assert_eq!(mesh.materials[0].diffuse_texture, Some(dir.join("red.png")));
+    assert_eq!(mesh.materials[1].diffuse_texture, None);
+
+    // the red triangles are grouped into one sub-mesh, before the blue quad's two triangles
+    let sub_meshes = mesh
+        .sub_meshes
+        .iter()
+        .map(|sub_mesh| {
+            (
+                sub_mesh.first_index,
+                sub_mesh.index_count,
+                sub_mesh.material,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(sub_meshes, vec![(0, 6, Some(0)), (6, 6, Some(1))]);
+    assert_eq!(mesh.indices.len(), 12);
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn test_load_objs_keeps_order() {
+    let paths = (1..=4)
+        .map(|triangle_count| {
+            let path =
+                std::env::temp_dir().join(format!("penguin_test_load_objs_{}.obj", triangle_count));
+            let mut obj = String::from("v 0 0 0\nv 1 0 0\nv 0 1 0\n");
+            obj.push_str(&"f 1 2 3\n".repeat(triangle_count));
+            std::fs::write(&path, obj).unwrap();
+            path
+        })
+        .collect::<Vec<_>>();
+
+    let index_counts = MeshAsset::load_objs(paths)
+        .into_iter()
+        .map(|asset| asset.unwrap().indices.len())
+        .collect::<Vec<_>>();
+
+    assert_eq!(index_counts, vec![3, 6, 9, 12]);
+}
+
+#[test]
+fn test_range_allocator() {
+    let mut allocator = RangeAllocator::default();
+
+    let a = allocator.allocate(4);
+    let b = allocator.allocate(2);
+    let c = allocator.allocate(3);
+    assert_eq!((a.clone(), b.clone(), c.clone()), (0..4, 4..6, 6..9));
+
+    // freed ranges are reused first-fit
+    allocator.free(a);
+    assert_eq!(allocator.allocate(1), 0..1);
+    assert_eq!(allocator.allocate(5), 9..14);
+
+    // adjacent free ranges merge, and give the end back to the bump allocation
+    allocator.free(b);
+    allocator.free(c);
+    allocator.free(9..14);
+    assert_eq!(allocator.end(), 1);
+    assert_eq!(allocator.allocate(3), 1..4);
+}
+
+/// Needs a gpu with the required features, run with `cargo test -- --ignored`.
+#[test]
+#[ignore]
+fn test_vertex_arena_grows() {
+    let (device, queue) =
+        penguin_util::pollster::block_on(crate::graphics_context::request_headless_device())
+            .expect("no gpu with the required features");
+
+    let triangle = |x: f32| {
+        [0.0, 1.0, 2.0].map(|y| MeshVertex {
+            position: m::vec3(x, y, 0.0),
+            normal: m::Vec3::Z,
+            uv: m::Vec2::ZERO,
+            tangent: m::Vec4::X,
+        })
+    };
+
+    let mut arena = VertexArena::new(&device, 4, 4);
+    let first = arena.allocate(&device, &queue, &triangle(1.0), &[0, 1, 2]);
+    // doesn't fit, the buffers grow
+    let second = arena.allocate(&device, &queue, &triangle(2.0), &[2, 1, 0]);
+
+    assert_eq!((first.first_vertex, first.first_index), (0, 0));
+    assert_eq!((second.first_vertex, second.first_index), (3, 3));
+
+    // the first mesh is copied over to the grown buffers
+    let read_buffer = |buffer: &wgpu::Buffer, size: usize| {
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("test readback buffer"),
+            size: size as _,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut cmd = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("test readback encoder"),
+        });
+        cmd.copy_buffer_to_buffer(buffer, 0, &staging_buffer, 0, size as _);
+        queue.submit(std::iter::once(cmd.finish()));
+
+        let buffer_slice = staging_buffer.slice(..);
+        let map_future = buffer_slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        penguin_util::pollster::block_on(map_future).unwrap();
+
+        let data = buffer_slice.get_mapped_range().to_vec();
+        staging_buffer.unmap();
+        data
+    };
+
+    let vertices = read_buffer(arena.vertex_buffer(), 6 * mem::size_of::<MeshVertex>());
+    let positions = bytemuck::cast_slice::<u8, MeshVertex>(&vertices)
+        .iter()
+        .map(|vertex| vertex.position.x)
+        .collect::<Vec<_>>();
+    assert_eq!(positions, vec![1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+
+    let indices = read_buffer(arena.index_buffer(), 6 * mem::size_of::<u32>());
+    assert_eq!(
+        bytemuck::cast_slice::<u8, u32>(&indices),
+        &[0, 1, 2, 2, 1, 0]
+    );
+
+    // freed ranges are reused without growing
+    arena.free(&first);
+    let third = arena.allocate(&device, &queue, &triangle(3.0), &[0, 1, 2]);
+    assert_eq!((third.first_vertex, third.first_index), (0, 0));
+    assert_eq!(arena.vertex_capacity, 8);
+}
+
+#[test]
+fn test_create_draw_command() {
+    let indexed = Mesh {
+        first_vertex: 3,
+        vertex_count: 24,
+        first_index: 6,
+        index_count: 36,
+    };
+    let non_indexed = Mesh {
+        first_vertex: 27,
+        vertex_count: 3,
+        first_index: 42,
+        index_count: 0,
+    };
+
+    assert!(matches!(
+        indexed.create_draw_command(5, 0),
+        DrawCommand::Indexed(DrawIndexedIndirect {
+            index_count: 36,
+            first_index: 6,
+            base_vertex: 3,
+            first_instance: 5,
+            ..
+        })
+    ));
+    assert!(matches!(
+        non_indexed.create_draw_command(5, 0),
+        DrawCommand::NonIndexed(DrawIndirect {
+            vertex_count: 3,
+            first_vertex: 27,
+            first_instance: 5,
+            ..
+        })
+    ));
+
+    // stored with the vertex range in place of the index range
+    let batched = non_indexed.create_draw_command(5, 0).batched();
+    assert_eq!(
+        (
+            batched.index_count,
+            batched.first_index,
+            batched.base_vertex
+        ),
+        (3, 27, 0)
+    );
+}
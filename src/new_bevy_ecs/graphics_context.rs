@@ -1,4 +1,4 @@
-use crate::texture;
+use crate::{graphics_context, texture};
 use bevy_app::{App, Plugin};
 use bevy_ecs::prelude::*;
 use bevy_window::{WindowResized, Windows};
@@ -19,38 +19,18 @@ pub fn init_graphics_context(mut cmd: Commands, windows: Res<Windows>) {
     let window = windows
         .get_primary()
         .expect("Failed to get window. You're probably missing WinitPlugin.");
-    let instance = wgpu::Instance::new(wgpu::Backends::VULKAN);
+    let (instance, surface, adapter) = pollster::block_on(graphics_context::request_adapter(
+        wgpu::Backends::PRIMARY,
+        |instance| unsafe {
+            let raw_window_handle = window.raw_window_handle().get_handle();
+            instance.create_surface(&raw_window_handle)
+        },
+    ));
 
-    let surface = unsafe {
-        let raw_window_handle = window.raw_window_handle().get_handle();
-        instance.create_surface(&raw_window_handle)
-    };
-
-    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::HighPerformance,
-        force_fallback_adapter: false,
-        compatible_surface: Some(&surface),
-    }))
-    .expect("no supported gpu");
-
-    let (device, queue) = pollster::block_on(adapter
-        .request_device(
-            &wgpu::DeviceDescriptor {
-                label: None,
-                features:
-                //wgpu::Features::default(), // wgpu::Features::BUFFER_BINDING_ARRAY,
-                //wgpu::Features::default(), // wgpu::Features::BUFFER_BINDING_ARRAY,
-                // wgpu::Features::POLYGON_MODE_LINE |
-                // allow non-zero value for first_instance field in draw calls
-                //wgpu::Features::INDIRECT_FIRST_INSTANCE |
-                //wgpu::Features::TEXTURE_BINDING_ARRAY |
-                // wgpu::Features::STORAGE_RESOURCE_BINDING_ARRAY,
-                wgpu::Features::all() ^ wgpu::Features::TEXTURE_COMPRESSION_ETC2 ^ wgpu::Features::TEXTURE_COMPRESSION_ASTC_LDR ^ wgpu::Features::VERTEX_ATTRIBUTE_64BIT,
-                limits: wgpu::Limits::default(),
-            },
-            None,
-        ))
-        .expect("failed to init device, missing required features?");
+    let (device, queue) = pollster::block_on(graphics_context::request_device(
+        &adapter,
+        wgpu::Features::empty(),
+    ));
 
     let config = wgpu::SurfaceConfiguration {
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -59,9 +39,10 @@ pub fn init_graphics_context(mut cmd: Commands, windows: Res<Windows>) {
         height: window.physical_height(),
         present_mode: wgpu::PresentMode::Mailbox,
     };
+    // falls back to Fifo if the surface doesn't support Mailbox
     surface.configure(&device, &config);
 
-    let depth_texture = DepthTexture(texture::Texture::create_depth_texture(&device, &config));
+    let depth_texture = DepthTexture(texture::Texture::create_depth_texture(&device, &config, 1));
 
     cmd.insert_resource(instance);
     cmd.insert_resource(surface);
@@ -24,6 +24,9 @@ mod time {
 
     pub struct Time {
         clock: crate::time::Clock,
+        scale: crate::time::TimeScale,
+        /// Elapsed time, accumulated from scaled delta times.
+        scaled_elapsed: std::time::Duration,
     }
 
     pub struct TimePlugin;
@@ -40,7 +43,7 @@ mod time {
 
     fn update_delta_time(e: EventReader<RequestRedraw>, mut time: ResMut<Time>) {
         if !e.is_empty() {
-            time.clock.tick();
+            time.tick();
         }
     }
 
@@ -48,11 +51,45 @@ mod time {
         Time,
         Self {
             clock: crate::time::Clock::start(),
+            scale: crate::time::TimeScale::default(),
+            scaled_elapsed: std::time::Duration::ZERO,
         }
     );
 
     impl Time {
+        /// Ticks the clock and advances the scaled elapsed time.
+        fn tick(&mut self) {
+            self.clock.tick();
+            self.scaled_elapsed += self.delta_time();
+        }
+
+        pub fn time_scale(&self) -> f32 {
+            self.scale.time_scale
+        }
+
+        pub fn set_time_scale(&mut self, time_scale: f32) {
+            self.scale.time_scale = time_scale;
+        }
+
+        pub fn paused(&self) -> bool {
+            self.scale.paused
+        }
+
+        pub fn set_paused(&mut self, paused: bool) {
+            self.scale.paused = paused;
+        }
+
+        pub fn scale_mut(&mut self) -> &mut crate::time::TimeScale {
+            &mut self.scale
+        }
+
+        /// Scaled delta time, zero when paused.
         pub fn delta_time(&self) -> std::time::Duration {
+            self.scale.apply(self.clock.last_delta_time)
+        }
+
+        /// Delta time unaffected by time scale and pausing.
+        pub fn unscaled_delta_time(&self) -> std::time::Duration {
             self.clock.last_delta_time
         }
 
@@ -60,7 +97,13 @@ mod time {
             self.clock.start_time
         }
 
+        /// Scaled time elapsed since start.
         pub fn elapsed(&self) -> std::time::Duration {
+            self.scaled_elapsed
+        }
+
+        /// Time elapsed since start, unaffected by time scale and pausing.
+        pub fn unscaled_elapsed(&self) -> std::time::Duration {
             self.clock.start_time.elapsed()
         }
 
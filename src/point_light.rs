@@ -0,0 +1,102 @@
+use crate::components::PointLight;
+use macaw as m;
+use penguin_util::{GpuBuffer, GpuBufferDeviceExt};
+use std::slice;
+
+/// Default max number of point lights sent to the GPU each frame. Lights beyond the max are
+/// ignored, since the fragment shader loops over every light for every fragment.
+pub const DEFAULT_MAX_POINT_LIGHTS: u32 = 16;
+
+/// A point light as laid out in the fragment shader's point lights storage buffer.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct PointLightRaw {
+    pub position: m::Vec3,
+    pub radius: f32,
+    /// w is unused.
+    pub color: m::Vec4,
+}
+unsafe impl bytemuck::Pod for PointLightRaw {}
+unsafe impl bytemuck::Zeroable for PointLightRaw {}
+
+impl From<&PointLight> for PointLightRaw {
+    fn from(light: &PointLight) -> Self {
+        Self {
+            position: light.position,
+            radius: light.radius,
+            color: light.color.extend(0.0),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PointLightCountUniformData {
+    pub count: u32,
+    _padding: [u32; 3],
+}
+unsafe impl bytemuck::Pod for PointLightCountUniformData {}
+unsafe impl bytemuck::Zeroable for PointLightCountUniformData {}
+
+/// The point lights in the scene, gathered into a storage buffer every frame, together with a
+/// uniform containing the number of lights in the buffer.
+pub struct PointLightsBuffer {
+    pub buffer: GpuBuffer<PointLightRaw>,
+    pub count_buffer: GpuBuffer<PointLightCountUniformData>,
+    max_count: u32,
+}
+impl PointLightsBuffer {
+    /// Allocates space for max_count lights.
+    pub fn init(device: &wgpu::Device, max_count: u32) -> Self {
+        assert!(max_count > 0, "storage buffers can't be empty");
+
+        let buffer = device.create_buffer_t::<PointLightRaw>(&wgpu::BufferDescriptor {
+            label: Some("point lights buffer"),
+            size: (std::mem::size_of::<PointLightRaw>() * max_count as usize) as _,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let count_buffer = device.create_buffer_init_t::<PointLightCountUniformData>(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("point light count uniform buffer"),
+                contents: bytemuck::cast_slice(slice::from_ref(
+                    &PointLightCountUniformData::default(),
+                )),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            },
+        );
+
+        Self {
+            buffer,
+            count_buffer,
+            max_count,
+        }
+    }
+
+    /// Writes the lights to the GPU. Lights beyond the max count are ignored.
+    pub fn update<'a>(
+        &self,
+        queue: &wgpu::Queue,
+        lights: impl IntoIterator<Item = &'a PointLight>,
+    ) {
+        let lights = lights
+            .into_iter()
+            .take(self.max_count as usize)
+            .map(PointLightRaw::from)
+            .collect::<Vec<_>>();
+
+        if !lights.is_empty() {
+            self.buffer.write(queue, 0, &lights);
+        }
+
+        self.count_buffer.write(
+            queue,
+            0,
+            slice::from_ref(&PointLightCountUniformData {
+                count: lights.len() as u32,
+                ..Default::default()
+            }),
+        );
+    }
+}
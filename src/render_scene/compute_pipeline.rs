@@ -1,132 +1,557 @@
-use crate::{camera, render_scene};
-use std::mem;
-
-pub const BIND_GROUP_LAYOUT_DESC: wgpu::BindGroupLayoutDescriptor =
-    wgpu::BindGroupLayoutDescriptor {
-        label: Some("compute bind group layout"),
-        entries: &[
-            // camera uniform
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: wgpu::BufferSize::new(
-                        mem::size_of::<camera::CameraUniformData>() as _,
-                    ),
-                },
-                count: None,
-            },
-            // draw commands buffer (draw indirect buffer)
-            wgpu::BindGroupLayoutEntry {
-                binding: 1,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: true },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-            // render objects buffer
-            wgpu::BindGroupLayoutEntry {
-                binding: 2,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: true },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-            // compute local data buffer
-            wgpu::BindGroupLayoutEntry {
-                binding: 3,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-            // draw count buffer
-            wgpu::BindGroupLayoutEntry {
-                binding: 4,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
+use crate::bind_groups;
+use penguin_util::raw_gpu_types::DispatchIndirect;
+use penguin_util::{GpuBuffer, GpuBufferDeviceExt};
+
+/// Number of invocations per workgroup in the compute shader entry points. cs_main has one
+/// invocation per render object and cs_emit one per draw command.
+pub const WORKGROUP_SIZE: u32 = 64;
+
+/// The culling compute shader, with its workgroup size set to WORKGROUP_SIZE.
+pub fn compute_shader_source() -> String {
+    with_workgroup_size(include_str!("../shaders/compute.wgsl"))
+}
+
+/// The shader writing the dispatch args of the culling compute pass, dividing by WORKGROUP_SIZE.
+fn dispatch_args_shader_source() -> String {
+    with_workgroup_size(include_str!("../shaders/dispatch_args.wgsl"))
+}
+
+fn with_workgroup_size(source: &str) -> String {
+    source.replace("WORKGROUP_SIZE", &WORKGROUP_SIZE.to_string())
+}
+
+/// Number of workgroups to dispatch to get an invocation for each of `invocation_count` render
+/// objects or draw commands. The dispatch args shader computes the same for the render objects on
+/// the GPU.
+pub fn dispatch_group_count(invocation_count: u32) -> u32 {
+    (invocation_count + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ComputeUniformData {
+    /// Number of render objects. Invocations past this return early.
+    pub object_count: u32,
+    /// Draw commands from this index on are non-indexed.
+    pub first_non_indexed_draw_command: u32,
+    _padding: [u32; 2],
+}
+unsafe impl bytemuck::Pod for ComputeUniformData {}
+unsafe impl bytemuck::Zeroable for ComputeUniformData {}
+
+impl ComputeUniformData {
+    pub fn new(object_count: u32, first_non_indexed_draw_command: u32) -> Self {
+        Self {
+            object_count,
+            first_non_indexed_draw_command,
+            _padding: [0; 2],
+        }
+    }
+
+    pub fn create_buffer(device: &wgpu::Device) -> GpuBuffer<Self> {
+        device.create_buffer_init_t::<Self>(&wgpu::util::BufferInitDescriptor {
+            label: Some("compute uniform buffer"),
+            contents: bytemuck::cast_slice(std::slice::from_ref(&Self::new(0, 0))),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+}
+
+/// The compute uniform buffer along with the values last written to it. The values only change
+/// when render objects are added or the batches are rebuilt, so most frames write nothing.
+pub struct ComputeUniform {
+    pub buffer: GpuBuffer<ComputeUniformData>,
+    written: ComputeUniformData,
+}
+
+impl ComputeUniform {
+    pub fn init(device: &wgpu::Device) -> Self {
+        Self {
+            buffer: ComputeUniformData::create_buffer(device),
+            written: ComputeUniformData::new(0, 0),
+        }
+    }
+
+    /// Writes the object count and the index of the first non-indexed draw command if either
+    /// differs from what the buffer holds. Returns whether it wrote, in which case the dispatch
+    /// args have to be recomputed.
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        object_count: u32,
+        first_non_indexed_draw_command: u32,
+    ) -> bool {
+        let data = ComputeUniformData::new(object_count, first_non_indexed_draw_command);
+        let changed = data != self.written;
+        if changed {
+            self.buffer.write(queue, 0, std::slice::from_ref(&data));
+            self.written = data;
+        }
+        changed
+    }
+}
+
+/// Computes the workgroup count of the culling compute pass on the GPU from the object count in
+/// the compute uniform buffer, so the pass can be dispatched with dispatch_indirect.
+pub struct IndirectDispatch {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    /// Dispatch args of the culling compute pass.
+    pub args_buffer: GpuBuffer<DispatchIndirect>,
+}
+
+impl IndirectDispatch {
+    pub fn init(
+        device: &wgpu::Device,
+        compute_uniform_buffer: &GpuBuffer<ComputeUniformData>,
+    ) -> Self {
+        let args_buffer =
+            device.create_buffer_init_t::<DispatchIndirect>(&wgpu::util::BufferInitDescriptor {
+                label: Some("compute dispatch args buffer"),
+                contents: bytemuck::cast_slice(std::slice::from_ref(&DispatchIndirect {
+                    x: 0,
+                    y: 1,
+                    z: 1,
+                })),
+                usage: wgpu::BufferUsages::INDIRECT
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC,
+            });
+
+        const COMPUTE: wgpu::ShaderStages = wgpu::ShaderStages::COMPUTE;
+
+        let bind_group_layout = bind_groups::BindGroupLayoutBuilder::<2>::builder()
+            .uniform_buffer(0, COMPUTE) // object count
+            .storage_buffer(1, COMPUTE, false) // dispatch args
+            .build(device, Some("dispatch args bind group layout"));
+
+        let bind_group = bind_groups::BindGroupBuilder::<2>::builder()
+            .buffer(0, compute_uniform_buffer)
+            .buffer(1, &args_buffer)
+            .build(device, Some("dispatch args bind group"), &bind_group_layout);
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("dispatch args shader"),
+            source: wgpu::ShaderSource::Wgsl(dispatch_args_shader_source().into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("dispatch args pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("dispatch args pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+            args_buffer,
+        }
+    }
+
+    /// Records the pass that writes the dispatch args. The args buffer is bound as storage here,
+    /// so this has to be its own pass, recorded before the pass that dispatches from it. The args
+    /// persist, so this is only needed after ComputeUniform::update wrote new values.
+    pub fn record(&self, cmd: &mut wgpu::CommandEncoder) {
+        let mut compute_pass = cmd.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("dispatch args pass"),
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch(1, 1, 1);
+    }
+}
+
+/// Buffers bound to the culling compute shader, in binding order.
+pub struct ComputeBindGroupBuffers<'a> {
+    pub camera_uniform: &'a wgpu::Buffer,
+    /// Batched draw commands with instance count set to 0.
+    pub draw_commands: &'a wgpu::Buffer,
+    pub render_objects: &'a wgpu::Buffer,
+    pub compute_local_data: &'a wgpu::Buffer,
+    /// The indexed and the non-indexed draw count.
+    pub draw_count: &'a wgpu::Buffer,
+    /// Indexed draw commands with instance counts, written by the compute shader.
+    pub out_draw_commands: &'a wgpu::Buffer,
+    pub instance_index_to_render_object_map: &'a wgpu::Buffer,
+    pub compute_uniform: &'a wgpu::Buffer,
+    /// The draw commands of each render object.
+    pub render_object_draw_commands: &'a wgpu::Buffer,
+    /// Non-indexed draw commands with instance counts, written by the compute shader.
+    pub out_non_indexed_draw_commands: &'a wgpu::Buffer,
+}
+
+/// The layout of the culling compute shader's bind group. The single definition of it, shared by
+/// the RenderScene and the pipelines layer, matching create_bind_group.
+pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    const COMPUTE: wgpu::ShaderStages = wgpu::ShaderStages::COMPUTE;
+    const READ: bool = true;
+    const READ_WRITE: bool = false;
+
+    bind_groups::BindGroupLayoutBuilder::<10>::builder()
+        .uniform_buffer(0, COMPUTE) // camera uniform
+        .storage_buffer(1, COMPUTE, READ) // draw commands
+        .storage_buffer(2, COMPUTE, READ) // render objects
+        .storage_buffer(3, COMPUTE, READ_WRITE) // compute local data
+        .storage_buffer(4, COMPUTE, READ_WRITE) // draw count
+        .storage_buffer(5, COMPUTE, READ_WRITE) // out draw commands
+        .storage_buffer(6, COMPUTE, READ_WRITE) // instance index to render object map
+        .uniform_buffer(7, COMPUTE) // compute uniform (object count)
+        .storage_buffer(8, COMPUTE, READ) // render object draw commands
+        .storage_buffer(9, COMPUTE, READ_WRITE) // out non-indexed draw commands
+        .build(device, Some("compute bind group layout"))
+}
+
+/// Creates a bind group for the layout from create_bind_group_layout.
+pub fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffers: &ComputeBindGroupBuffers,
+) -> wgpu::BindGroup {
+    bind_groups::BindGroupBuilder::<10>::builder()
+        .buffer(0, buffers.camera_uniform)
+        .buffer(1, buffers.draw_commands)
+        .buffer(2, buffers.render_objects)
+        .buffer(3, buffers.compute_local_data)
+        .buffer(4, buffers.draw_count)
+        .buffer(5, buffers.out_draw_commands)
+        .buffer(6, buffers.instance_index_to_render_object_map)
+        .buffer(7, buffers.compute_uniform)
+        .buffer(8, buffers.render_object_draw_commands)
+        .buffer(9, buffers.out_non_indexed_draw_commands)
+        .build(device, Some("compute bind group"), layout)
+}
+
+#[test]
+fn test_dispatch_group_count() {
+    assert_eq!(dispatch_group_count(0), 0);
+    assert_eq!(dispatch_group_count(1), 1);
+    assert_eq!(dispatch_group_count(WORKGROUP_SIZE), 1);
+    assert_eq!(dispatch_group_count(WORKGROUP_SIZE + 1), 2);
+    assert_eq!(
+        dispatch_group_count(1000),
+        (1000 + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE
+    );
+}
+
+#[test]
+fn test_shader_workgroup_size() {
+    let size = format!("workgroup_size({})", WORKGROUP_SIZE);
+    assert!(compute_shader_source().contains(&size));
+    assert!(!compute_shader_source().contains("WORKGROUP_SIZE"));
+    assert!(!dispatch_args_shader_source().contains("WORKGROUP_SIZE"));
+}
+
+/// Needs a gpu with the required features, run with `cargo test -- --ignored`.
+#[test]
+#[ignore]
+fn test_compute_shader_emits_instances() {
+    use super::{
+        DrawOutputInfo, RenderObject, RenderObjectDrawCommands, DEFAULT_LOD_SCREEN_SIZES,
+        REMOVED_DRAW_COMMAND_INDEX,
+    };
+    use crate::camera::CameraUniformData;
+    use crate::mesh::RenderBounds;
+    use macaw as m;
+    use penguin_util::handle::Handle;
+    use penguin_util::raw_gpu_types::{DrawIndexedIndirect, DrawIndirect, DrawIndirectCount};
+    use wgpu::util::DeviceExt;
+
+    let (device, queue) =
+        penguin_util::pollster::block_on(crate::graphics_context::request_headless_device())
+            .expect("no gpu with the required features");
+
+    /// Copies the buffer into a mappable buffer and reads it back.
+    fn read_buffer<T: bytemuck::Pod>(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        buffer: &wgpu::Buffer,
+        len: usize,
+    ) -> Vec<T> {
+        let size = (std::mem::size_of::<T>() * len) as wgpu::BufferAddress;
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("test readback buffer"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut cmd = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("test readback encoder"),
+        });
+        cmd.copy_buffer_to_buffer(buffer, 0, &staging_buffer, 0, size);
+        queue.submit(std::iter::once(cmd.finish()));
+
+        let buffer_slice = staging_buffer.slice(..);
+        let map_future = buffer_slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        penguin_util::pollster::block_on(map_future).unwrap();
+
+        let data = buffer_slice.get_mapped_range();
+        bytemuck::cast_slice::<u8, T>(&data).to_vec()
+    }
+
+    let create_buffer = |label, contents: &[u8], usage| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage: usage | wgpu::BufferUsages::COPY_SRC,
+        })
+    };
+
+    // camera at z = 2 looking at the origin
+    let view_proj = m::Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 1000.0)
+        * m::Mat4::look_at_rh(m::vec3(0.0, 0.0, 2.0), m::Vec3::ZERO, m::Vec3::Y);
+
+    // draw command 0 and 1 are the LOD levels of the same mesh, 2 is another mesh, 3 is a
+    // non-indexed triangle
+    let mut draw_commands = [0, 2, 3, 4].map(|first_instance| DrawIndexedIndirect {
+        index_count: 36,
+        instance_count: 0,
+        first_index: 0,
+        base_vertex: 0,
+        first_instance,
+    });
+    draw_commands[3].index_count = 3;
+    draw_commands[3].first_index = 24;
+    let first_non_indexed_draw_command = 3;
+
+    let render_object_at = |translation: m::Vec3| RenderObject {
+        mesh: Handle::from(0),
+        transform: m::Mat4::from_translation(translation),
+        texture_index: 0,
+        selected: 0,
+        alpha_cutoff: 0.0,
+        color: m::Vec4::ONE,
+        render_bounds: RenderBounds {
+            origin: m::Vec3::ZERO,
+            radius: 1.0,
+        },
+        lod_count: 2,
+        lod_screen_sizes: DEFAULT_LOD_SCREEN_SIZES,
+        visible: 1,
+    };
+    let lod_draw_commands = |draw_command_index: u32| RenderObjectDrawCommands {
+        draw_command_index,
+        lod_draw_command_indices: [draw_command_index, draw_command_index + 1, 0, 0],
+    };
+
+    let (render_objects, render_object_draw_commands): (Vec<_>, Vec<_>) = [
+        // close, LOD 0
+        (render_object_at(m::Vec3::ZERO), lod_draw_commands(0)),
+        // far away, LOD 1
+        (
+            render_object_at(m::vec3(0.0, 0.0, -50.0)),
+            lod_draw_commands(0),
+        ),
+        // removed
+        (
+            render_object_at(m::Vec3::ZERO),
+            lod_draw_commands(REMOVED_DRAW_COMMAND_INDEX),
+        ),
+        // close, LOD 0
+        (
+            render_object_at(m::vec3(1.0, 0.0, 0.0)),
+            lod_draw_commands(0),
+        ),
+        // non-indexed
+        (
+            RenderObject {
+                lod_count: 1,
+                ..render_object_at(m::vec3(-1.0, 0.0, 0.0))
             },
-            // out draw commands
-            wgpu::BindGroupLayoutEntry {
-                binding: 5,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
+            lod_draw_commands(3),
+        ),
+        // close, but hidden
+        (
+            RenderObject {
+                visible: 0,
+                ..render_object_at(m::Vec3::ZERO)
             },
-            // instance index to render object map
-            wgpu::BindGroupLayoutEntry {
-                binding: 6,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
+            lod_draw_commands(0),
+        ),
+        // behind the camera, frustum culled
+        (
+            render_object_at(m::vec3(0.0, 0.0, 10.0)),
+            lod_draw_commands(0),
+        ),
+        // beside the view, frustum culled
+        (
+            render_object_at(m::vec3(20.0, 0.0, 0.0)),
+            lod_draw_commands(0),
+        ),
+        // past the object count
+        (
+            RenderObject {
+                lod_count: 1,
+                ..render_object_at(m::Vec3::ZERO)
             },
-        ],
-    };
+            lod_draw_commands(2),
+        ),
+    ]
+    .into_iter()
+    .unzip();
+    let object_count = 8;
 
-pub fn bind_group_entries<'a>(
-    uniform_buffer: &'a wgpu::Buffer,
-    scene: &'a render_scene::RenderScene,
-) -> [wgpu::BindGroupEntry<'a>; 7] {
-    [
-        // camera
-        wgpu::BindGroupEntry {
-            binding: 0,
-            resource: uniform_buffer.as_entire_binding(),
-        },
-        // draw commands
-        wgpu::BindGroupEntry {
-            binding: 1,
-            resource: scene.draw_commands_buffer.as_entire_binding(),
-        },
-        wgpu::BindGroupEntry {
-            binding: 2,
-            resource: scene.render_objects_buffer.as_entire_binding(),
-        },
-        // compute shader local data
-        wgpu::BindGroupEntry {
-            binding: 3,
-            resource: scene.compute_shader_local_data_buffer.as_entire_binding(),
-        },
-        // draw count
-        wgpu::BindGroupEntry {
-            binding: 4,
-            resource: scene.draw_count_buffer.as_entire_binding(),
-        },
-        // out draw commands
-        wgpu::BindGroupEntry {
-            binding: 5,
-            resource: scene.out_draw_commands_buffer.as_entire_binding(),
-        },
-        // instance index to render object map
-        wgpu::BindGroupEntry {
-            binding: 6,
-            resource: scene
-                .instance_index_to_render_object_map
-                .as_entire_binding(),
+    let camera_uniform = create_buffer(
+        "test camera uniform",
+        bytemuck::bytes_of(&CameraUniformData { view_proj }),
+        wgpu::BufferUsages::UNIFORM,
+    );
+    let draw_commands_buffer = create_buffer(
+        "test draw commands",
+        bytemuck::cast_slice(&draw_commands),
+        wgpu::BufferUsages::STORAGE,
+    );
+    let render_objects_buffer = create_buffer(
+        "test render objects",
+        bytemuck::cast_slice(&render_objects),
+        wgpu::BufferUsages::STORAGE,
+    );
+    let render_object_draw_commands_buffer = create_buffer(
+        "test render object draw commands",
+        bytemuck::cast_slice(&render_object_draw_commands),
+        wgpu::BufferUsages::STORAGE,
+    );
+    let compute_local_data = create_buffer(
+        "test compute local data",
+        bytemuck::cast_slice(&[DrawOutputInfo::default(); 4]),
+        wgpu::BufferUsages::STORAGE,
+    );
+    let draw_count = create_buffer(
+        "test draw count",
+        bytemuck::cast_slice(&[DrawIndirectCount { count: 0 }; 2]),
+        wgpu::BufferUsages::STORAGE,
+    );
+    let out_draw_commands = create_buffer(
+        "test out draw commands",
+        bytemuck::cast_slice(&draw_commands),
+        wgpu::BufferUsages::STORAGE,
+    );
+    let out_non_indexed_draw_commands = create_buffer(
+        "test out non-indexed draw commands",
+        bytemuck::cast_slice(
+            &[DrawIndirect {
+                vertex_count: 0,
+                instance_count: 0,
+                first_vertex: 0,
+                first_instance: 0,
+            }; 4],
+        ),
+        wgpu::BufferUsages::STORAGE,
+    );
+    let instance_index_to_render_object_map = create_buffer(
+        "test instance index to render object map",
+        bytemuck::cast_slice(&[u32::MAX; 5]),
+        wgpu::BufferUsages::STORAGE,
+    );
+
+    let mut compute_uniform = ComputeUniform::init(&device);
+    assert!(compute_uniform.update(&queue, object_count, first_non_indexed_draw_command));
+    assert!(!compute_uniform.update(&queue, object_count, first_non_indexed_draw_command));
+    let indirect_dispatch = IndirectDispatch::init(&device, &compute_uniform.buffer);
+
+    let bind_group_layout = create_bind_group_layout(&device);
+    let bind_group = create_bind_group(
+        &device,
+        &bind_group_layout,
+        &ComputeBindGroupBuffers {
+            camera_uniform: &camera_uniform,
+            draw_commands: &draw_commands_buffer,
+            render_objects: &render_objects_buffer,
+            compute_local_data: &compute_local_data,
+            draw_count: &draw_count,
+            out_draw_commands: &out_draw_commands,
+            instance_index_to_render_object_map: &instance_index_to_render_object_map,
+            compute_uniform: &compute_uniform.buffer,
+            render_object_draw_commands: &render_object_draw_commands_buffer,
+            out_non_indexed_draw_commands: &out_non_indexed_draw_commands,
         },
-    ]
+    );
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("test compute pipeline layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+        label: Some("compute shader"),
+        source: wgpu::ShaderSource::Wgsl(compute_shader_source().into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("test compute pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "cs_main",
+    });
+    let emit_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("test compute emit pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "cs_emit",
+    });
+
+    let mut cmd = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("test compute encoder"),
+    });
+    indirect_dispatch.record(&mut cmd);
+    {
+        let mut compute_pass = cmd.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("test compute pass"),
+        });
+        compute_pass.set_pipeline(&pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        compute_pass.dispatch_indirect(&indirect_dispatch.args_buffer, 0);
+        compute_pass.set_pipeline(&emit_pipeline);
+        compute_pass.dispatch(dispatch_group_count(draw_commands.len() as u32), 1, 1);
+    }
+    queue.submit(std::iter::once(cmd.finish()));
+
+    let dispatch_args =
+        read_buffer::<DispatchIndirect>(&device, &queue, &indirect_dispatch.args_buffer, 1)[0];
+    assert_eq!(
+        (dispatch_args.x, dispatch_args.y, dispatch_args.z),
+        (dispatch_group_count(object_count), 1, 1)
+    );
+
+    // only the draw commands with instances are emitted, the non-indexed one counted on its own
+    let counts = read_buffer::<DrawIndirectCount>(&device, &queue, &draw_count, 2);
+    let count = counts[0].count;
+    assert_eq!((count, counts[1].count), (2, 1));
+
+    let mut emitted = read_buffer::<DrawIndexedIndirect>(&device, &queue, &out_draw_commands, 4)
+        [..count as usize]
+        .iter()
+        .map(|draw_command| (draw_command.first_instance, draw_command.instance_count))
+        .collect::<Vec<_>>();
+    emitted.sort_unstable();
+    assert_eq!(emitted, vec![(0, 2), (2, 1)]);
+
+    let non_indexed =
+        read_buffer::<DrawIndirect>(&device, &queue, &out_non_indexed_draw_commands, 1)[0];
+    assert_eq!(
+        (
+            non_indexed.vertex_count,
+            non_indexed.instance_count,
+            non_indexed.first_vertex,
+            non_indexed.first_instance
+        ),
+        (3, 1, 24, 4)
+    );
+
+    let instances = read_buffer::<u32>(&device, &queue, &instance_index_to_render_object_map, 5);
+    let mut lod_0_instances = instances[0..2].to_vec();
+    lod_0_instances.sort_unstable();
+    assert_eq!(lod_0_instances, vec![0, 3]);
+    assert_eq!(instances[2], 1);
+    // the removed object, the hidden object, the culled objects and the object past the object
+    // count aren't drawn
+    assert_eq!(instances[3], u32::MAX);
+    assert_eq!(instances[4], 4);
 }
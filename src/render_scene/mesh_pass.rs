@@ -32,6 +32,15 @@ pub struct PassMaterial {
     material_h: Handle<Material>,
 }
 
+/// Computes the key that pass objects are sorted and batched by. Pass objects with the same key are
+/// drawn with one instanced draw command, so the key has to at least tell meshes apart.
+pub type BatchSortKeyFn = fn(Handle<mesh::Mesh>, PassMaterial) -> u64;
+
+/// Batches by mesh and material.
+pub fn batch_sort_key(mesh_h: Handle<mesh::Mesh>, material: PassMaterial) -> u64 {
+    (mesh_h.id as u64) | ((material.material_h.id as u64) << 32)
+}
+
 /// Reference to the related data of a RenderObject in a RenderScene.
 #[derive(Copy, Clone)]
 pub struct PassObject {
@@ -39,6 +48,8 @@ pub struct PassObject {
     mesh_h: Handle<mesh::Mesh>,
     /// The RenderObject this PassObject was created from.
     pub original_render_object: Handle<render_scene::RenderObject>,
+    /// LOD level of the render object drawn with this PassObject's mesh.
+    pub lod: u32,
     // ID to draw command in indirect_batches.
     pub draw_command_id: u32,
 }
@@ -55,6 +66,9 @@ pub struct LegacyMeshPass {
     pub objects: HandleMap<PassObject>,
     /// Render objects pending addition
     pub unbatched_objects: Vec<Handle<render_scene::RenderObject>>,
+    /// Set when objects have been removed, forcing the batches to be rebuilt.
+    needs_rebuild: bool,
+    batch_sort_key: BatchSortKeyFn,
 }
 
 impl LegacyMeshPass {
@@ -64,18 +78,86 @@ impl LegacyMeshPass {
             sorted_render_batches: Vec::new(),
             objects: HandleMap::new(),
             unbatched_objects: Vec::new(),
+            needs_rebuild: false,
+            batch_sort_key,
+        }
+    }
+
+    /// A mesh pass that batches pass objects by the given sort key instead of by mesh and
+    /// material.
+    pub fn with_batch_sort_key(batch_sort_key: BatchSortKeyFn) -> Self {
+        Self {
+            batch_sort_key,
+            ..Self::new()
         }
     }
 
-    /// Updates the mesh pass
+    /// Whether the render object was added to the pass, batched or not.
+    pub fn contains_object(&self, render_object: Handle<render_scene::RenderObject>) -> bool {
+        self.unbatched_objects
+            .iter()
+            .any(|unbatched| unbatched.id == render_object.id)
+            || self
+                .objects
+                .iter()
+                .any(|pass_object| pass_object.original_render_object.id == render_object.id)
+    }
+
+    /// Removes a render object from the pass. The batches are rebuilt on the next update.
+    pub fn remove_object(&mut self, render_object: Handle<render_scene::RenderObject>) {
+        self.remove_objects(std::slice::from_ref(&render_object));
+    }
+
+    /// Removes render objects from the pass, rebuilding the pass once for all of them. The batches
+    /// are rebuilt on the next update.
+    pub fn remove_objects(&mut self, render_objects: &[Handle<render_scene::RenderObject>]) {
+        let is_removed = |render_object: &Handle<render_scene::RenderObject>| {
+            render_objects
+                .iter()
+                .any(|removed| removed.id == render_object.id)
+        };
+
+        self.unbatched_objects
+            .retain(|unbatched| !is_removed(unbatched));
+
+        // pass objects are referenced by index from the render batches, so rather than patching
+        // the handles, rebuild the pass from the remaining objects
+        let remaining_objects = self
+            .objects
+            .iter()
+            // render objects have a pass object per LOD level
+            .filter(|pass_object| pass_object.lod == 0)
+            .map(|pass_object| pass_object.original_render_object)
+            .filter(|original| !is_removed(original))
+            .collect::<Vec<_>>();
+
+        self.unbatched_objects.extend(remaining_objects);
+        self.objects.clear();
+        self.sorted_render_batches.clear();
+        self.indirect_batches.clear();
+
+        self.needs_rebuild = true;
+    }
+
+    /// Updates the mesh pass. Returns true if the batches were rebuilt. lod_meshes holds the mesh
+    /// of each LOD level of each render object, each level is batched as a separate pass object.
+    ///
+    /// At most RenderLimits::max_draw_commands batches are kept, the size of the draw commands
+    /// buffers, and only batches whose instances fit in RenderLimits::max_instances, the size of
+    /// the instance buffers. If there are more, the rest are dropped and their objects aren't
+    /// drawn, and an error is returned. The kept batches are still valid and should be uploaded
+    /// like on a successful rebuild.
     pub fn update_batches(
         &mut self,
-        render_objects: &HandleMap<render_scene::RenderObject>,
-    ) -> bool {
-        // only rebuild if there are new objects to add
+        lod_meshes: &[Vec<Handle<mesh::Mesh>>],
+        limits: &render_scene::RenderLimits,
+    ) -> anyhow::Result<bool> {
+        // only rebuild if there are new objects to add, or objects have been removed
         if self.unbatched_objects.is_empty() {
-            return false;
+            // every object was removed, the batches are already cleared
+            return Ok(std::mem::take(&mut self.needs_rebuild));
         }
+        self.needs_rebuild = false;
 
         // add new pass objects to the pass objects array and create new render batches from them
         //
@@ -88,20 +170,25 @@ impl LegacyMeshPass {
                 .unbatched_objects
                 .clone()
                 .into_iter()
-                .map(|render_obj_to_add| {
-                    let render_object: &super::RenderObject = &render_objects[render_obj_to_add];
-
+                .flat_map(|render_obj_to_add| {
+                    lod_meshes[render_obj_to_add.id as usize]
+                        .iter()
+                        .enumerate()
+                        .map(move |(lod, mesh_h)| (render_obj_to_add, lod, *mesh_h))
+                })
+                .map(|(render_obj_to_add, lod, mesh_h)| {
                     let pass_object = PassObject {
                         pass_material: PassMaterial::default(), // todo
-                        mesh_h: render_object.mesh,
+                        mesh_h,
                         original_render_object: render_obj_to_add,
+                        lod: lod as _,
                         draw_command_id: 0,
                     };
 
                     let pass_object_h = self.objects.push(pass_object);
 
-                    let sort_key = (pass_object.mesh_h.id as u64)
-                        | ((pass_object.pass_material.material_h.id as u64) << 32);
+                    let sort_key =
+                        (self.batch_sort_key)(pass_object.mesh_h, pass_object.pass_material);
                     println!("RenderObject {}: sort_key = {}", index, sort_key);
 
                     index += 1;
@@ -120,16 +207,17 @@ impl LegacyMeshPass {
             new_render_batches
         };
 
-        // add new render batches to the render batches array and sort it by mesh and material
+        // add new render batches to the render batches array and sort it by sort key.
+        // ties are broken by pass object, so that objects keep their order between rebuilds
         //
         let render_batches: &Vec<RenderBatch> = {
             self.sorted_render_batches.extend(new_render_batches);
             self.sorted_render_batches
-                .sort_by(|a, b| a.sort_key.cmp(&b.sort_key));
+                .sort_by_key(|batch| (batch.sort_key, batch.pass_object_h.id));
             &self.sorted_render_batches
         };
 
-        // group render batches with the same mesh and material into instanced indirect draw commands
+        // group render batches with the same sort key into instanced indirect draw commands
         //
         let indirect_batches: Vec<IndirectBatch> = {
             let first_pass_object: PassObject = self.objects[render_batches[0].pass_object_h];
@@ -157,10 +245,10 @@ impl LegacyMeshPass {
 
                 let mut previous: &mut IndirectBatch = indirect_batches.last_mut().unwrap();
 
-                let same_mesh_as_previous = mesh_h.id == previous.mesh_h.id;
-                let same_material_as_previous = material == previous.pass_material;
+                let same_sort_key_as_previous =
+                    render_batch.sort_key == render_batches[previous.first as usize].sort_key;
 
-                if same_mesh_as_previous && same_material_as_previous {
+                if same_sort_key_as_previous {
                     // if the batch can be instanced, just increase the max instance count
                     // (this count isn't used for anything currently, just storing it in case
                     // I need it for something later)
@@ -185,6 +273,173 @@ impl LegacyMeshPass {
 
         self.indirect_batches = indirect_batches;
 
-        return true;
+        let batch_count = self.indirect_batches.len();
+        let max_draw_commands = limits.max_draw_commands;
+
+        // batches are laid out back to back in the instance buffers, so the kept batches are a
+        // prefix of them
+        let kept_count = self
+            .indirect_batches
+            .iter()
+            .take(max_draw_commands)
+            .take_while(|batch| (batch.first + batch.count) as usize <= limits.max_instances)
+            .count();
+
+        if kept_count < batch_count {
+            self.indirect_batches.truncate(kept_count);
+
+            // skip the objects of the dropped batches in the compute shader
+            for pass_object in self.objects.iter_mut() {
+                if pass_object.draw_command_id as usize >= kept_count {
+                    pass_object.draw_command_id = render_scene::REMOVED_DRAW_COMMAND_INDEX;
+                }
+            }
+
+            if batch_count > max_draw_commands {
+                anyhow::bail!(
+                    "mesh pass has {} batches, but only {} (RenderLimits::max_draw_commands) fit in the draw \
+                    commands buffer. The objects of the remaining batches won't be drawn",
+                    batch_count,
+                    max_draw_commands
+                );
+            }
+
+            anyhow::bail!(
+                "mesh pass has {} instances, but only {} (RenderLimits::max_instances) fit in the \
+                instance buffers. The objects of the remaining batches won't be drawn",
+                self.objects.len(),
+                limits.max_instances
+            );
+        }
+
+        Ok(true)
     }
 }
+
+/// Render limits with the given max draw commands, and room for plenty of instances.
+#[cfg(test)]
+fn limits(max_draw_commands: usize) -> render_scene::RenderLimits {
+    render_scene::RenderLimits {
+        max_draw_commands,
+        max_instances: 100,
+    }
+}
+
+#[test]
+fn test_lod_batches() {
+    // indexed by render object
+    let lod_meshes = vec![
+        vec![Handle::from(0), Handle::from(1)],
+        vec![Handle::from(0)],
+    ];
+    let (first, second) = (Handle::from(0), Handle::from(1));
+
+    let mut pass = LegacyMeshPass::new();
+    pass.unbatched_objects.extend([first, second]);
+
+    assert!(pass.update_batches(&lod_meshes, &limits(10)).unwrap());
+
+    // mesh 0 is shared by the first LOD level of both objects
+    assert_eq!(pass.indirect_batches.len(), 2);
+    assert_eq!(pass.objects.len(), 3);
+
+    // removing an object keeps a single pass object per LOD level of the others
+    pass.remove_object(second);
+    assert!(pass.update_batches(&lod_meshes, &limits(10)).unwrap());
+    assert_eq!(pass.objects.len(), 2);
+    assert_eq!(pass.indirect_batches.len(), 2);
+}
+
+#[test]
+fn test_deterministic_batch_order() {
+    // objects sharing meshes, so that they have identical sort keys
+    let lod_meshes = [0, 1, 0, 0, 1].map(|mesh_id| vec![Handle::from(mesh_id)]);
+    let objects = (0..lod_meshes.len()).map(Handle::from).collect::<Vec<_>>();
+
+    let build = || {
+        let mut pass = LegacyMeshPass::new();
+        pass.unbatched_objects.extend_from_slice(&objects);
+        pass.update_batches(&lod_meshes, &limits(10)).unwrap();
+
+        pass.sorted_render_batches
+            .iter()
+            .map(|batch| (batch.sort_key, batch.pass_object_h.id))
+            .collect::<Vec<_>>()
+    };
+
+    let first = build();
+    assert_eq!(first, build());
+
+    // objects with the same mesh are ordered by pass object
+    let mut sorted = first.clone();
+    sorted.sort();
+    assert_eq!(first, sorted);
+}
+
+#[test]
+fn test_batches_past_render_limits() {
+    // two objects of mesh 0, one of mesh 1 and one of mesh 2
+    let lod_meshes = [0, 0, 1, 2].map(|mesh_id| vec![Handle::from(mesh_id)]);
+    let objects = (0..lod_meshes.len()).map(Handle::from).collect::<Vec<_>>();
+
+    let kept_batches = |limits: render_scene::RenderLimits| {
+        let mut pass = LegacyMeshPass::new();
+        pass.unbatched_objects.extend_from_slice(&objects);
+        assert!(pass.update_batches(&lod_meshes, &limits).is_err());
+
+        // the objects of the dropped batches are skipped
+        let drawn_count = pass
+            .objects
+            .iter()
+            .filter(|object| object.draw_command_id != render_scene::REMOVED_DRAW_COMMAND_INDEX)
+            .count();
+        (pass.indirect_batches.len(), drawn_count)
+    };
+
+    assert_eq!(kept_batches(limits(2)), (2, 3));
+    assert_eq!(
+        kept_batches(render_scene::RenderLimits {
+            max_draw_commands: 10,
+            max_instances: 2,
+        }),
+        (1, 2)
+    );
+}
+
+#[test]
+fn test_batch_sort_key() {
+    let mesh_h = Handle::from(3);
+    let (material, other_material) = (
+        PassMaterial {
+            material_h: Handle::from(0),
+        },
+        PassMaterial {
+            material_h: Handle::from(1),
+        },
+    );
+    assert_ne!(
+        batch_sort_key(mesh_h, material),
+        batch_sort_key(mesh_h, other_material)
+    );
+
+    // a depth pass batches objects with the same mesh together, whatever their material
+    fn mesh_sort_key(mesh_h: Handle<mesh::Mesh>, _material: PassMaterial) -> u64 {
+        mesh_h.id as u64
+    }
+
+    let lod_meshes = [0, 1, 0].map(|mesh_id| vec![Handle::from(mesh_id)]);
+    let mut depth_pass = LegacyMeshPass::with_batch_sort_key(mesh_sort_key);
+    depth_pass
+        .unbatched_objects
+        .extend((0..lod_meshes.len()).map(Handle::from));
+    depth_pass.update_batches(&lod_meshes, &limits(10)).unwrap();
+
+    assert_eq!(
+        depth_pass
+            .indirect_batches
+            .iter()
+            .map(|batch| (batch.mesh_h.id, batch.count))
+            .collect::<Vec<_>>(),
+        vec![(0, 2), (1, 1)]
+    );
+}
@@ -1,9 +1,13 @@
 ///! This module contains structs that stores the data and handles to GPU data that is used to render a scene.
 pub mod compute_pipeline;
 pub(crate) mod mesh_pass;
+mod render_objects;
+mod render_stats;
 
-use crate::render_scene::mesh_pass::{IndirectBatch, PassObject};
-use crate::{mesh, GraphicsContext, RenderInstance, VertexArrayBuffer};
+pub use render_objects::{CustomMeshPass, RenderObjects};
+pub use render_stats::{DrawCountReadback, RenderStats, TimestampReadback};
+
+use crate::{mesh, GraphicsContext, RenderInstance, VertexArena};
 use legion::systems::{CommandBuffer, Step};
 use legion::Resources;
 use macaw as m;
@@ -11,11 +15,13 @@ use penguin_util as util;
 use penguin_util::handle::HandleMap;
 use penguin_util::{
     handle::Handle,
-    raw_gpu_types::{DrawIndexedIndirect, DrawIndirectCount},
+    raw_gpu_types::{DrawIndexedIndirect, DrawIndirect, DrawIndirectCount},
     GpuBufferDeviceExt,
 };
-use std::{mem, slice};
+use std::mem;
+use std::ops::Range;
 use util::GpuBuffer;
+use wgpu::util::DeviceExt;
 
 /// Describes a render object entry to add to the render scene.
 pub struct RenderObjectDescriptor {
@@ -27,10 +33,17 @@ pub struct RenderObjectDescriptor {
     pub transform: m::Mat4,
     /// The render bounds of this object (for culling).
     pub render_bounds: mesh::RenderBounds,
+    /// Color the object's texture is multiplied with.
+    pub color: m::Vec4,
+    /// Layer of the material texture array the object samples.
+    pub texture_index: u32,
+    /// Texture alpha below which fragments are discarded. 0.0 for opaque objects.
+    pub alpha_cutoff: f32,
     /// Weather this mesh object should be drawn in the forward rendering mesh pass.
     pub draw_forward_pass: bool,
-    // other mesh pass..
-    // other mesh pass..
+    /// Names of the custom mesh passes, registered with RenderObjects::register_mesh_pass, this
+    /// object should be drawn in.
+    pub custom_passes: &'static [&'static str],
 }
 
 /// Data for an object in the scene.
@@ -40,13 +53,104 @@ pub struct RenderObject {
     pub mesh: Handle<mesh::Mesh>,
     // material: usize,
     pub transform: m::Mat4,
-    // pub render_bounds: mesh::RenderBounds,
-    pub(crate) draw_command_index: u32, // todo Should actually just be in PassObject
+    /// Layer of the material texture array the object samples.
+    pub texture_index: u32,
+    /// Nonzero if the object is selected in the editor, which tints it. A u32 rather than a bool
+    /// to match the shaders.
+    pub selected: u32,
+    /// Fragments whose texture alpha is below the cutoff are discarded, for cutout materials like
+    /// foliage. 0.0 for opaque objects.
+    pub alpha_cutoff: f32,
+    /// Per-object tint, multiplied with the sampled texture color.
+    pub color: m::Vec4,
+    /// Bounds in object space. Used for picking and LOD selection.
+    pub render_bounds: mesh::RenderBounds,
+    /// Number of LOD levels, at least 1.
+    pub lod_count: u32,
+    /// The object switches to the next LOD level when the fraction of the screen height covered
+    /// by its bounds falls below the threshold at the current level.
+    pub lod_screen_sizes: [f32; MAX_LODS - 1],
+    /// Nonzero if the object is drawn. Hidden objects are skipped by the compute pass. A u32
+    /// rather than a bool to match the shaders.
+    pub visible: u32,
 }
 unsafe impl bytemuck::Pod for RenderObject {}
 unsafe impl bytemuck::Zeroable for RenderObject {}
 
-pub const MAX_DRAW_COMMANDS: usize = 100;
+impl RenderObject {
+    /// Picks the LOD level to draw the object with. CPU reference of the LOD selection in the
+    /// compute shader, keep in sync with selectLod in compute.wgsl.
+    #[cfg(test)]
+    pub fn select_lod(&self, view_proj: m::Mat4) -> usize {
+        let center = self.transform.transform_point3(self.render_bounds.origin);
+        let max_scale = self
+            .transform
+            .x_axis
+            .truncate()
+            .length()
+            .max(self.transform.y_axis.truncate().length())
+            .max(self.transform.z_axis.truncate().length());
+        let radius = self.render_bounds.radius * max_scale;
+
+        let clip_w = (view_proj * center.extend(1.0)).w;
+
+        // the camera is inside the bounds
+        if clip_w <= radius {
+            return 0;
+        }
+
+        // the y scale of the projection, the length of the view matrix's rotation row is 1
+        let projection_scale = view_proj.row(1).truncate().length();
+        let screen_size = radius * projection_scale / clip_w;
+
+        let mut lod = 0;
+        while lod + 1 < self.lod_count as usize && screen_size < self.lod_screen_sizes[lod] {
+            lod += 1;
+        }
+        lod
+    }
+}
+
+/// The draw commands of a render object in the forward pass. Kept out of RenderObject in a buffer
+/// of their own, since they're the only data that changes for every object when the batches are
+/// rebuilt.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct RenderObjectDrawCommands {
+    /// REMOVED_DRAW_COMMAND_INDEX if the render object is removed, otherwise the same as the first
+    /// LOD level's.
+    pub draw_command_index: u32,
+    /// Draw command of each LOD level.
+    pub lod_draw_command_indices: [u32; MAX_LODS],
+}
+
+/// Max number of LOD levels of a render object.
+pub const MAX_LODS: usize = 4;
+
+/// Default screen size thresholds for switching to the next LOD level.
+pub const DEFAULT_LOD_SCREEN_SIZES: [f32; MAX_LODS - 1] = [0.3, 0.1, 0.03];
+
+/// Sizes of the buffers of the render scene, set once at startup. In the layer path it's a
+/// resource, which BaseRenderSceneLayer inserts with the default limits if it's missing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RenderLimits {
+    /// Max number of draw commands, one per batch of render objects with the same mesh.
+    pub max_draw_commands: usize,
+    /// Max number of render objects, and of instances drawn in a frame. Draw commands are
+    /// instanced, so this can be much larger than max_draw_commands.
+    pub max_instances: usize,
+}
+util::impl_default!(
+    RenderLimits,
+    Self {
+        max_draw_commands: 100,
+        max_instances: 10_000,
+    }
+);
+
+/// Draw command index of a removed render object. Render objects with this index are skipped in
+/// the compute shader.
+pub const REMOVED_DRAW_COMMAND_INDEX: u32 = u32::MAX;
 
 /// Stores the data, and handles to GPU data, that is used to render a scene.
 /// All mesh passes will keep the same object data for culling and object transform.
@@ -54,22 +158,21 @@ pub struct RenderScene {
     //
     // ----- Mesh data ----------------------
     //
-    /// Vertex array buffer containing all of the meshes vertices and indices.
-    pub vertex_array_buffer: VertexArrayBuffer,
-    /// Representation of each mesh in the vertex array buffer.
+    /// Vertex and index buffers containing all of the meshes vertices and indices.
+    pub vertex_arena: VertexArena,
+    /// Representation of each mesh in the vertex arena.
     meshes: Vec<mesh::Mesh>,
     // --------------------------------------
     //
     //
     // ---- Object data --------------------------------
     //
-    /// Data for each render object that doesn't change per mesh pass, such as the transform and
-    /// render bounds for culling.
-    pub render_objects: HandleMap<RenderObject>,
+    /// The render objects and their batches in the mesh passes.
+    pub objects: RenderObjects,
     /// The render objects array in GPU-memory.
     pub render_objects_buffer: GpuBuffer<RenderObject>,
-    /// Render objects that need to be reuploaded to the GPU.
-    render_objects_to_update: Vec<Handle<RenderObject>>,
+    /// The draw commands of each render object in GPU-memory.
+    pub render_object_draw_commands_buffer: GpuBuffer<RenderObjectDrawCommands>,
     //
     pub instance_buffer: GpuBuffer<RenderInstance>,
     // --------------------------------------
@@ -81,9 +184,14 @@ pub struct RenderScene {
     pub draw_commands_buffer: GpuBuffer<DrawIndexedIndirect>,
     /// Buffer of final draw commands needed for the frame (set by the compute shader).
     pub out_draw_commands_buffer: GpuBuffer<DrawIndexedIndirect>,
+    /// Buffer of final non-indexed draw commands needed for the frame (set by the compute shader).
+    pub out_non_indexed_draw_commands_buffer: GpuBuffer<DrawIndirect>,
+    /// Zeroed buffer used to clear the out draw command buffers when drawing them with markers.
+    pub zeroed_draw_commands_buffer: wgpu::Buffer,
     /// Buffer with draw_count set to 0, used to reset draw_count_buffer.
     pub clear_draw_count_buffer: GpuBuffer<DrawIndirectCount>,
-    /// Buffer containing the number of draw commands to issue this frame (filled by the compute shader).
+    /// Buffer containing the number of indexed and non-indexed draw commands to issue this frame
+    /// (filled by the compute shader).
     pub draw_count_buffer: GpuBuffer<DrawIndirectCount>,
     // ---------------------------------------
     //
@@ -94,43 +202,42 @@ pub struct RenderScene {
     //
     pub clear_compute_shader_local_data_buffer: GpuBuffer<DrawOutputInfo>,
     pub compute_shader_local_data_buffer: GpuBuffer<DrawOutputInfo>,
-
-    /// Mesh pass for forward rendering.
-    forward_pass: mesh_pass::LegacyMeshPass,
+    /// Sizes of the buffers.
+    pub limits: RenderLimits,
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-/// Data local to the compute shader helping to determine where an invoked draw command should
-/// be placed in the output draw commands buffer.
+#[derive(Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+/// Data local to the compute shader. The culling entry point counts the visible instances of
+/// each draw command here, and the emit entry point then gives every draw command with a
+/// nonzero count a slot in the output draw commands buffer.
+///
+/// Only draw commands with visible instances are given a slot, so the output draw commands are
+/// compacted: batches whose instances are all culled, removed or drawn at another LOD level are
+/// left out of the draw count.
 pub struct DrawOutputInfo {
-    /// Weather the draw command at this index has been given an output draw command slot.
-    /// A value of 0 indicates false.
-    has_output_slot: u32,
-    /// The slot the draw command at this index has been assigned to in the output draw commands
-    /// buffer. A value of u32::MAX (4294967295_u32) indicates that the output slot is unset.
-    output_slot: u32,
-}
-impl std::default::Default for DrawOutputInfo {
-    fn default() -> Self {
-        Self {
-            has_output_slot: 0,
-            output_slot: u32::MAX,
-        }
-    }
+    /// Number of visible instances of the draw command at this index.
+    instance_count: u32,
 }
 
 impl RenderScene {
-    /// Creates a new render scene with the specified mesh assets.
-    pub fn new(device: &wgpu::Device, mesh_assets: &[&str]) -> Self {
+    /// Creates a new render scene with the specified mesh assets, with buffers sized by the limits.
+    pub fn new(device: &wgpu::Device, mesh_assets: &[&str], limits: RenderLimits) -> Self {
         // mesh data buffers --------------
-        let (vertex_array_buffer, meshes) =
-            mesh::VertexArrayBuffer::build_from_mesh_assets(&device, mesh_assets);
+        let (vertex_arena, mut meshes, _sub_meshes) =
+            mesh::VertexArena::build_from_mesh_assets(&device, mesh_assets);
+        // each mesh asset is drawn whole, so the meshes of the sub-meshes of assets with more than
+        // one material aren't needed, and mesh handles stay mesh asset indices
+        meshes.truncate(mesh_assets.len());
 
         // draw indirect buffers ---------------
         //
         let (draw_commands_buffer, out_draw_commands_buffer) =
-            create_draw_indirect_buffers(&device, MAX_DRAW_COMMANDS);
+            create_draw_indirect_buffers(&device, limits.max_draw_commands);
+        let out_non_indexed_draw_commands_buffer =
+            create_out_non_indexed_draw_commands_buffer(device, limits.max_draw_commands);
+        let zeroed_draw_commands_buffer =
+            create_zeroed_draw_commands_buffer(device, limits.max_draw_commands);
 
         // draw count buffers -----------------
         //
@@ -138,131 +245,99 @@ impl RenderScene {
 
         // render object buffer -------------------
         //
-        let render_objects_buffer = create_render_objects_buffer(device, MAX_DRAW_COMMANDS);
+        let render_objects_buffer = create_render_objects_buffer(device, limits.max_instances);
+        let render_object_draw_commands_buffer =
+            create_render_object_draw_commands_buffer(device, limits.max_instances);
 
         // instance buffers -------------------
         //
-        let instance_buffer = create_instance_buffer(device, MAX_DRAW_COMMANDS);
+        let instance_buffer = create_instance_buffer(device, limits.max_instances);
 
         // ----------------
         let instance_index_to_render_object_map =
             device.create_buffer_init_t::<u32>(&wgpu::util::BufferInitDescriptor {
                 label: Some("final draw command indices"),
                 contents: bytemuck::cast_slice(
-                    &(0..MAX_DRAW_COMMANDS).map(|_| 0_u32).collect::<Vec<_>>(),
+                    &(0..limits.max_instances).map(|_| 0_u32).collect::<Vec<_>>(),
                 ),
                 usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
             });
 
         let (clear_compute_shader_local_data_buffer, compute_shader_local_data_buffer) =
-            create_compute_shader_local_data_buffers(device, MAX_DRAW_COMMANDS);
+            create_compute_shader_local_data_buffers(device, limits.max_draw_commands);
 
         Self {
-            vertex_array_buffer,
+            vertex_arena,
             draw_commands_buffer,
             out_draw_commands_buffer,
+            out_non_indexed_draw_commands_buffer,
+            zeroed_draw_commands_buffer,
             clear_draw_count_buffer,
             draw_count_buffer,
             meshes,
-            render_objects: HandleMap::new(),
+            objects: RenderObjects::new(limits),
             render_objects_buffer,
-            render_objects_to_update: Vec::new(),
-            forward_pass: mesh_pass::LegacyMeshPass::new(),
+            render_object_draw_commands_buffer,
             max_draw_count: 0,
             instance_buffer,
             instance_index_to_render_object_map,
             clear_compute_shader_local_data_buffer,
             compute_shader_local_data_buffer,
+            limits,
         }
     }
 
-    /// Adds a RenderObject to the scene and adds it to the listed mesh passes.
-    pub fn register_object(&mut self, desc: &RenderObjectDescriptor) -> Handle<RenderObject> {
-        // let mesh_handle = if self.meshes.get(desc.mesh_id).is_some() {
-        //     Handle::<mesh::Mesh>::from(desc.mesh_id)
-        // } else {
-        //     panic!("no mesh with id {} in the render scene", desc.mesh_id)
-        // };
-
-        let render_object: Handle<RenderObject> = self.render_objects.push(RenderObject {
-            mesh: desc.mesh_handle,
-            transform: desc.transform,
-            draw_command_index: 0,
-        });
-
-        if desc.draw_forward_pass {
-            self.forward_pass.unbatched_objects.push(render_object);
-        }
-
-        // this render object's data will need to be updated in GPU memory.
-        self.render_objects_to_update.push(render_object);
-
-        render_object
+    /// Update GPU memory with any newly submitted render object data.
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.objects.reupload(
+            queue,
+            &self.render_objects_buffer,
+            &self.render_object_draw_commands_buffer,
+        );
     }
 
-    pub fn update_transform_model_matrix(
+    /// Loads a mesh asset after creation into the vertex arena, next to the meshes that are already
+    /// loaded. Returns the handle of its mesh, which is the index of the next mesh asset.
+    pub fn load_mesh_asset(
         &mut self,
-        render_object: Handle<RenderObject>,
-        transform: m::Mat4,
-    ) {
-        // todo RwLock?
-        self.render_objects[render_object].transform = transform;
-
-        self.render_objects_to_update.push(render_object);
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mesh_asset_name: &str,
+    ) -> anyhow::Result<Handle<mesh::Mesh>> {
+        let path = std::path::Path::new(env!("OUT_DIR"))
+            .join("assets/meshes")
+            .join(mesh_asset_name);
+
+        let mut asset_meshes = Vec::new();
+        self.vertex_arena
+            .load_mesh(device, queue, path, &mut asset_meshes)?;
+
+        // the first mesh covers the whole asset, see RenderScene::new
+        self.meshes.push(asset_meshes[0]);
+        Ok(Handle::from(self.meshes.len() - 1))
     }
 
-    /// Update GPU memory with any newly submitted render object data.
-    pub fn update(&mut self, queue: &wgpu::Queue) {
-        while let Some(render_object) = self.render_objects_to_update.pop() {
-            let offset = mem::size_of::<RenderObject>() * render_object.id as usize;
-            let render_object_data = self.render_objects[render_object];
-
-            queue.write_buffer(
-                &self.render_objects_buffer,
-                offset as _,
-                bytemuck::cast_slice(slice::from_ref(&render_object_data)),
-            );
-        }
+    /// Uploads a mesh built at runtime into the vertex arena. Without indices the mesh is drawn
+    /// non-indexed. Returns the handle of the mesh, which is the index of the next mesh asset.
+    pub fn add_mesh(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vertices: &[mesh::MeshVertex],
+        indices: &[u32],
+    ) -> Handle<mesh::Mesh> {
+        let mesh = self.vertex_arena.allocate(device, queue, vertices, indices);
+        self.meshes.push(mesh);
+        Handle::from(self.meshes.len() - 1)
     }
 
+    /// Rebuilds the batches if needed and uploads the draw commands.
     pub fn build_batches(&mut self, queue: &wgpu::Queue) {
-        if self.forward_pass.update_batches(&self.render_objects) {
-            println!("building batches..");
-
-            // create a draw call for each unique mesh + material combo
-            let indirect_commands = self
-                .forward_pass
-                .indirect_batches
-                .iter()
-                .map(|batch: &IndirectBatch| {
-                    let mesh = self.meshes[batch.mesh_h.id as usize];
-                    println!("mesh: {:?}, max instance count: {}", mesh, batch.count);
-
-                    let first_instance = batch.first as _;
-                    let instance_count = 0; // set in compute shader
-                    mesh.create_draw_command(first_instance, instance_count)
-                })
-                .collect::<Vec<_>>();
-
-            // assign draw commands to render objects
-            self.forward_pass
-                .objects
-                .inner
-                .iter()
-                .for_each(|pass_object: &PassObject| {
-                    let render_object = pass_object.original_render_object;
-
-                    self.render_objects[render_object].draw_command_index =
-                        pass_object.draw_command_id;
-
-                    self.render_objects_to_update.push(render_object);
-                });
-
-            queue.write_buffer(
-                &self.draw_commands_buffer,
-                0,
-                bytemuck::cast_slice(&indirect_commands),
-            );
+        if let Some(indirect_commands) = self.objects.build_batches(&self.meshes) {
+            if !indirect_commands.is_empty() {
+                self.draw_commands_buffer
+                    .write(queue, 0, &indirect_commands);
+            }
 
             // update max draw count
             self.max_draw_count = indirect_commands.len() as _;
@@ -270,6 +345,47 @@ impl RenderScene {
     }
 }
 
+/// Sorts and deduplicates the render objects to reupload, and coalesces them into ranges of
+/// contiguous ids. Leaves the list empty.
+pub fn coalesce_reuploads(to_reupload: &mut Vec<Handle<RenderObject>>) -> Vec<Range<usize>> {
+    to_reupload.sort_unstable_by_key(|render_object| render_object.id);
+    to_reupload.dedup_by_key(|render_object| render_object.id);
+
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+
+    for render_object in to_reupload.drain(..) {
+        let id = render_object.id as usize;
+
+        match ranges.last_mut() {
+            Some(range) if range.end == id => range.end += 1,
+            _ => ranges.push(id..id + 1),
+        }
+    }
+
+    ranges
+}
+
+/// Writes the render objects to reupload to the render objects buffer, and the draw commands to
+/// reupload to the render object draw commands buffer, with one write per run of contiguous render
+/// objects.
+pub fn reupload_render_objects(
+    queue: &wgpu::Queue,
+    render_objects_buffer: &GpuBuffer<RenderObject>,
+    draw_commands_buffer: &GpuBuffer<RenderObjectDrawCommands>,
+    render_objects: &HandleMap<RenderObject>,
+    draw_commands: &[RenderObjectDrawCommands],
+    to_reupload: &mut Vec<Handle<RenderObject>>,
+    draw_commands_to_reupload: &mut Vec<Handle<RenderObject>>,
+) {
+    for range in coalesce_reuploads(to_reupload) {
+        render_objects_buffer.write(queue, range.start, &render_objects[range.clone()]);
+    }
+
+    for range in coalesce_reuploads(draw_commands_to_reupload) {
+        draw_commands_buffer.write(queue, range.start, &draw_commands[range.clone()]);
+    }
+}
+
 fn create_draw_indirect_buffers(
     device: &wgpu::Device,
     max_draw_commands: usize,
@@ -291,26 +407,137 @@ fn create_draw_indirect_buffers(
             mapped_at_creation: false,
         });
 
+    // copy src to allow reading back the emitted draw commands
     let out_draw_commands_buffer =
         device.create_buffer_t::<DrawIndexedIndirect>(&wgpu::BufferDescriptor {
             label: Some("draw indirect buffer"),
             size,
             usage: wgpu::BufferUsages::INDIRECT
                 | wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_DST,
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
             mapped_at_creation: false,
         });
 
     (draw_indirect_buffer, out_draw_commands_buffer)
 }
 
+/// Creates the buffer the compute shader writes the non-indexed draw commands to.
+pub fn create_out_non_indexed_draw_commands_buffer(
+    device: &wgpu::Device,
+    max_draw_commands: usize,
+) -> GpuBuffer<DrawIndirect> {
+    device.create_buffer_t::<DrawIndirect>(&wgpu::BufferDescriptor {
+        label: Some("non-indexed draw indirect buffer"),
+        size: (mem::size_of::<DrawIndirect>() * max_draw_commands) as _,
+        usage: wgpu::BufferUsages::INDIRECT
+            | wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Creates a zeroed buffer as large as the out draw command buffers, to clear them with. The
+/// compute shader only writes the draw commands below the draw count, the ones past it are left
+/// over from earlier frames.
+pub fn create_zeroed_draw_commands_buffer(
+    device: &wgpu::Device,
+    max_draw_commands: usize,
+) -> wgpu::Buffer {
+    let size = mem::size_of::<DrawIndexedIndirect>() * max_draw_commands;
+
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("zeroed draw indirect buffer"),
+        contents: &vec![0_u8; size],
+        usage: wgpu::BufferUsages::COPY_SRC,
+    })
+}
+
+/// Zeroes the out draw command buffers, so that the draw commands past the draw count draw no
+/// instances. Needed before the compute pass when the draws are recorded with markers.
+pub fn clear_emitted_draws(
+    cmd: &mut wgpu::CommandEncoder,
+    zeroed_draw_commands_buffer: &wgpu::Buffer,
+    out_draw_commands_buffer: &GpuBuffer<DrawIndexedIndirect>,
+    out_non_indexed_draw_commands_buffer: &GpuBuffer<DrawIndirect>,
+) {
+    cmd.copy_buffer_to_buffer(
+        zeroed_draw_commands_buffer,
+        0,
+        out_draw_commands_buffer,
+        0,
+        out_draw_commands_buffer.byte_len(),
+    );
+    cmd.copy_buffer_to_buffer(
+        zeroed_draw_commands_buffer,
+        0,
+        out_non_indexed_draw_commands_buffer,
+        0,
+        out_non_indexed_draw_commands_buffer.byte_len(),
+    );
+}
+
+/// Byte offset of the non-indexed draw count in a draw count buffer, after the indexed one.
+pub const NON_INDEXED_DRAW_COUNT_OFFSET: wgpu::BufferAddress =
+    mem::size_of::<DrawIndirectCount>() as _;
+
+/// Records the draws of the draw commands emitted by the compute shader, the indexed and then the
+/// non-indexed ones. The vertex, index and instance buffers should be set.
+///
+/// Given a draw marker label, each draw command is drawn on its own and preceded by a debug marker
+/// with its slot, so graphics debuggers can tell the batches apart. The out draw command buffers
+/// must then have been cleared with clear_emitted_draws before the compute pass.
+pub fn record_emitted_draws<'a>(
+    render_pass: &mut wgpu::RenderPass<'a>,
+    out_draw_commands_buffer: &'a wgpu::Buffer,
+    out_non_indexed_draw_commands_buffer: &'a wgpu::Buffer,
+    draw_count_buffer: &'a wgpu::Buffer,
+    max_draw_count: u32,
+    draw_marker: Option<&str>,
+) {
+    if let Some(label) = draw_marker {
+        let stride = mem::size_of::<DrawIndexedIndirect>() as wgpu::BufferAddress;
+        for slot in 0..max_draw_count {
+            render_pass.insert_debug_marker(&format!("{}: indexed draw {}", label, slot));
+            render_pass.draw_indexed_indirect(out_draw_commands_buffer, slot as u64 * stride);
+        }
+
+        let stride = mem::size_of::<DrawIndirect>() as wgpu::BufferAddress;
+        for slot in 0..max_draw_count {
+            render_pass.insert_debug_marker(&format!("{}: non-indexed draw {}", label, slot));
+            render_pass.draw_indirect(out_non_indexed_draw_commands_buffer, slot as u64 * stride);
+        }
+        return;
+    }
+
+    render_pass.multi_draw_indexed_indirect_count(
+        out_draw_commands_buffer,
+        0,
+        draw_count_buffer,
+        0,
+        max_draw_count,
+    );
+
+    render_pass.multi_draw_indirect_count(
+        out_non_indexed_draw_commands_buffer,
+        0,
+        draw_count_buffer,
+        NON_INDEXED_DRAW_COUNT_OFFSET,
+        max_draw_count,
+    );
+}
+
 fn create_draw_count_buffers(
     device: &wgpu::Device,
 ) -> (GpuBuffer<DrawIndirectCount>, GpuBuffer<DrawIndirectCount>) {
-    let contents = bytemuck::cast_slice(slice::from_ref(&DrawIndirectCount { count: 0 }));
+    // the indexed and the non-indexed draw count
+    let contents = bytemuck::cast_slice(&[DrawIndirectCount { count: 0 }; 2]);
 
-    let usage =
-        wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST;
+    // copy src to allow reading back the draw count
+    let usage = wgpu::BufferUsages::INDIRECT
+        | wgpu::BufferUsages::STORAGE
+        | wgpu::BufferUsages::COPY_DST
+        | wgpu::BufferUsages::COPY_SRC;
 
     let clear_draw_count_buffer =
         device.create_buffer_init_t::<DrawIndirectCount>(&wgpu::util::BufferInitDescriptor {
@@ -328,6 +555,19 @@ fn create_draw_count_buffers(
     (clear_draw_count_buffer, draw_count_buffer)
 }
 
+/// Buffer of the draw commands of each render object, read by the compute shader.
+pub fn create_render_object_draw_commands_buffer(
+    device: &wgpu::Device,
+    max_render_objects: usize,
+) -> GpuBuffer<RenderObjectDrawCommands> {
+    device.create_buffer_t::<RenderObjectDrawCommands>(&wgpu::BufferDescriptor {
+        label: Some("render object draw commands buffer"),
+        size: (mem::size_of::<RenderObjectDrawCommands>() * max_render_objects) as _,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
 fn create_render_objects_buffer(
     device: &wgpu::Device,
     max_render_objects: usize,
@@ -394,3 +634,56 @@ fn create_compute_shader_local_data_buffers(
         compute_shader_local_data_buffer,
     )
 }
+
+#[test]
+fn test_coalesce_reuploads() {
+    let mut to_reupload: Vec<Handle<RenderObject>> =
+        [2, 0, 1, 1, 2].into_iter().map(Handle::from).collect();
+
+    assert_eq!(coalesce_reuploads(&mut to_reupload), vec![0..3]);
+    assert!(to_reupload.is_empty());
+
+    let mut to_reupload: Vec<Handle<RenderObject>> =
+        [7, 3, 4, 9].into_iter().map(Handle::from).collect();
+
+    assert_eq!(
+        coalesce_reuploads(&mut to_reupload),
+        vec![3..5, 7..8, 9..10]
+    );
+}
+
+#[test]
+fn test_select_lod() {
+    let render_object = RenderObject {
+        mesh: Handle::from(0),
+        transform: m::Mat4::IDENTITY,
+        texture_index: 0,
+        selected: 0,
+        alpha_cutoff: 0.0,
+        color: m::Vec4::ONE,
+        render_bounds: mesh::RenderBounds {
+            origin: m::Vec3::ZERO,
+            radius: 1.0,
+        },
+        lod_count: 3,
+        lod_screen_sizes: DEFAULT_LOD_SCREEN_SIZES,
+        visible: 1,
+    };
+
+    let projection = m::Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 1000.0);
+    let view_proj_at = |distance: f32| {
+        projection * m::Mat4::look_at_rh(m::vec3(0.0, 0.0, distance), m::Vec3::ZERO, m::Vec3::Y)
+    };
+
+    assert_eq!(render_object.select_lod(view_proj_at(2.0)), 0);
+    assert_eq!(render_object.select_lod(view_proj_at(5.0)), 1);
+    // never past the last LOD level
+    assert_eq!(render_object.select_lod(view_proj_at(500.0)), 2);
+}
+
+#[test]
+fn test_gpu_struct_sizes() {
+    // keep in sync with the RenderObject and RenderObjectDrawCommands structs in the shaders
+    assert_eq!(mem::size_of::<RenderObject>(), 160);
+    assert_eq!(mem::size_of::<RenderObjectDrawCommands>(), 20);
+}
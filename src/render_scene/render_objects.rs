@@ -0,0 +1,821 @@
+use super::mesh_pass::{self, IndirectBatch};
+use super::{
+    RenderLimits, RenderObject, RenderObjectDescriptor, RenderObjectDrawCommands,
+    DEFAULT_LOD_SCREEN_SIZES, MAX_LODS, REMOVED_DRAW_COMMAND_INDEX,
+};
+use crate::mesh;
+use macaw as m;
+use penguin_util::handle::{Handle, HandleMap};
+use penguin_util::raw_gpu_types::DrawIndexedIndirect;
+use penguin_util::GpuBuffer;
+use std::slice;
+
+/// CPU-side render objects and their batches in the mesh passes. Shared by the RenderScene and the
+/// base render scene layer, which own the GPU buffers the data is uploaded to.
+pub struct RenderObjects {
+    /// Data for each render object that doesn't change per mesh pass, such as the transform.
+    pub render_objects: HandleMap<RenderObject>,
+    pub should_rebuild_batches: bool,
+    /// Render objects that need to be reuploaded to the GPU.
+    pub render_objects_to_reupload: Vec<Handle<RenderObject>>,
+    /// Removed render objects, whose slots are reused by register_object.
+    free_render_objects: Vec<Handle<RenderObject>>,
+    /// The draw commands of each render object in the forward pass. Indexed by render object.
+    pub draw_commands: Vec<RenderObjectDrawCommands>,
+    /// Render objects whose draw commands changed, e.g. when the batches were rebuilt.
+    pub draw_commands_to_reupload: Vec<Handle<RenderObject>>,
+    /// The mesh of each LOD level of each render object, from most to least detailed. Indexed by
+    /// render object.
+    lod_meshes: Vec<Vec<Handle<mesh::Mesh>>>,
+    /// Mesh pass for forward rendering.
+    pub forward_pass: mesh_pass::LegacyMeshPass,
+    /// Named mesh passes added with register_mesh_pass, e.g. a shadow pass. Each is batched
+    /// independently of the forward pass.
+    pub custom_passes: Vec<CustomMeshPass>,
+    /// Sizes of the GPU buffers the render objects and draw commands are uploaded to.
+    limits: RenderLimits,
+    /// Index of the first non-indexed draw command of the forward pass. The indexed draw commands
+    /// come first, the compute shader emits the ones from here on as DrawIndirect.
+    pub first_non_indexed_draw_command: u32,
+}
+
+/// A named mesh pass with its own batches.
+pub struct CustomMeshPass {
+    pub name: &'static str,
+    pub pass: mesh_pass::LegacyMeshPass,
+}
+
+impl Default for RenderObjects {
+    fn default() -> Self {
+        Self::new(RenderLimits::default())
+    }
+}
+
+impl RenderObjects {
+    /// Render objects that fit in GPU buffers of the given limits.
+    pub fn new(limits: RenderLimits) -> Self {
+        Self {
+            render_objects: HandleMap::new(),
+            should_rebuild_batches: true,
+            render_objects_to_reupload: Vec::new(),
+            free_render_objects: Vec::new(),
+            draw_commands: Vec::new(),
+            draw_commands_to_reupload: Vec::new(),
+            lod_meshes: Vec::new(),
+            forward_pass: mesh_pass::LegacyMeshPass::new(),
+            custom_passes: Vec::new(),
+            limits,
+            first_non_indexed_draw_command: 0,
+        }
+    }
+
+    /// Adds a named mesh pass. Render objects registered afterwards are added to it if their
+    /// descriptor lists its name in custom_passes.
+    pub fn register_mesh_pass(&mut self, name: &'static str) {
+        self.register_mesh_pass_with_sort_key(name, mesh_pass::batch_sort_key);
+    }
+
+    /// Adds a named mesh pass that batches its objects by the given sort key, e.g. by mesh only
+    /// for a depth pass.
+    pub fn register_mesh_pass_with_sort_key(
+        &mut self,
+        name: &'static str,
+        batch_sort_key: mesh_pass::BatchSortKeyFn,
+    ) {
+        assert!(
+            self.mesh_pass(name).is_none(),
+            "mesh pass {:?} is already registered",
+            name
+        );
+
+        self.custom_passes.push(CustomMeshPass {
+            name,
+            pass: mesh_pass::LegacyMeshPass::with_batch_sort_key(batch_sort_key),
+        });
+    }
+
+    /// The custom mesh pass with the given name, if it's registered.
+    pub fn mesh_pass(&self, name: &str) -> Option<&mesh_pass::LegacyMeshPass> {
+        self.custom_passes
+            .iter()
+            .find(|custom_pass| custom_pass.name == name)
+            .map(|custom_pass| &custom_pass.pass)
+    }
+
+    /// The custom mesh passes the descriptor lists. Unregistered names are logged and skipped.
+    fn listed_custom_passes<'a>(
+        custom_passes: &'a mut [CustomMeshPass],
+        desc: &'a RenderObjectDescriptor,
+    ) -> impl Iterator<Item = &'a mut mesh_pass::LegacyMeshPass> + 'a {
+        for name in desc.custom_passes {
+            if !custom_passes
+                .iter()
+                .any(|custom_pass| custom_pass.name == *name)
+            {
+                log::warn!("mesh pass {:?} isn't registered", name);
+            }
+        }
+
+        custom_passes
+            .iter_mut()
+            .filter(move |custom_pass| desc.custom_passes.contains(&custom_pass.name))
+            .map(|custom_pass| &mut custom_pass.pass)
+    }
+
+    /// Adds a RenderObject and adds it to the listed mesh passes. Fails if there are already
+    /// RenderLimits::max_instances render objects.
+    pub fn register_object(
+        &mut self,
+        desc: &RenderObjectDescriptor,
+    ) -> anyhow::Result<Handle<RenderObject>> {
+        self.register_object_with_lods(desc, std::slice::from_ref(&desc.mesh_handle))
+    }
+
+    /// Adds a RenderObject with a mesh per LOD level, from most to least detailed, and adds it to
+    /// the listed mesh passes. The compute shader picks the LOD level to draw each frame, based on
+    /// the size of the object's render bounds on screen. The first mesh replaces the descriptor's
+    /// mesh handle. Fails if there are already RenderLimits::max_instances render objects.
+    pub fn register_object_with_lods(
+        &mut self,
+        desc: &RenderObjectDescriptor,
+        lod_meshes: &[Handle<mesh::Mesh>],
+    ) -> anyhow::Result<Handle<RenderObject>> {
+        if self.free_render_objects.is_empty()
+            && self.render_objects.len() >= self.limits.max_instances
+        {
+            anyhow::bail!(
+                "can't register more than {} (RenderLimits::max_instances) render objects, the \
+                 size of the render objects buffer",
+                self.limits.max_instances
+            );
+        }
+
+        assert!(
+            !lod_meshes.is_empty() && lod_meshes.len() <= MAX_LODS,
+            "a render object needs 1 to {} (MAX_LODS) LOD meshes, got {}",
+            MAX_LODS,
+            lod_meshes.len()
+        );
+
+        let render_object_data = RenderObject {
+            mesh: lod_meshes[0],
+            transform: desc.transform,
+            texture_index: desc.texture_index,
+            selected: 0,
+            alpha_cutoff: desc.alpha_cutoff,
+            color: desc.color,
+            render_bounds: desc.render_bounds,
+            lod_count: lod_meshes.len() as _,
+            lod_screen_sizes: DEFAULT_LOD_SCREEN_SIZES,
+            visible: 1,
+        };
+
+        // reuse the slot of a removed render object if there is one
+        let render_object = match self.free_render_objects.pop() {
+            Some(render_object) => {
+                self.render_objects[render_object] = render_object_data;
+                self.lod_meshes[render_object.id as usize] = lod_meshes.to_vec();
+                self.draw_commands[render_object.id as usize] = RenderObjectDrawCommands::default();
+                render_object
+            }
+            None => {
+                self.lod_meshes.push(lod_meshes.to_vec());
+                self.draw_commands.push(RenderObjectDrawCommands::default());
+                self.render_objects.push(render_object_data)
+            }
+        };
+
+        if desc.draw_forward_pass {
+            self.forward_pass.unbatched_objects.push(render_object);
+        }
+        for pass in Self::listed_custom_passes(&mut self.custom_passes, desc) {
+            pass.unbatched_objects.push(render_object);
+        }
+
+        // this render object's data will need to be updated in GPU memory.
+        self.render_objects_to_reupload.push(render_object);
+        self.draw_commands_to_reupload.push(render_object);
+
+        Ok(render_object)
+    }
+
+    /// Adds a RenderObject per transform, all with the descriptor's mesh and material, and adds
+    /// them to the listed mesh passes. Since they share a mesh and material, they're drawn with a
+    /// single instanced draw command. The descriptor's transform is ignored.
+    ///
+    /// Only as many instances as fit in RenderLimits::max_instances are registered, the rest are
+    /// logged and skipped, so fewer handles than transforms may be returned.
+    pub fn register_instances(
+        &mut self,
+        desc: &RenderObjectDescriptor,
+        transforms: &[m::Mat4],
+    ) -> Vec<Handle<RenderObject>> {
+        let max_instances = self.limits.max_instances;
+        let free_count = max_instances.saturating_sub(self.render_objects.len());
+        if transforms.len() > free_count {
+            log::error!(
+                "registering {} instances, but only {} more render objects fit in the render \
+                 objects buffer (RenderLimits::max_instances is {}), skipping the rest",
+                transforms.len(),
+                free_count,
+                max_instances
+            );
+        }
+        let transforms = &transforms[..transforms.len().min(free_count)];
+
+        self.render_objects.reserve(transforms.len());
+        self.lod_meshes.reserve(transforms.len());
+        self.draw_commands.reserve(transforms.len());
+        self.render_objects_to_reupload.reserve(transforms.len());
+        self.draw_commands_to_reupload.reserve(transforms.len());
+        if desc.draw_forward_pass {
+            self.forward_pass
+                .unbatched_objects
+                .reserve(transforms.len());
+        }
+
+        let first = self.render_objects.len();
+
+        for transform in transforms {
+            self.render_objects.push(RenderObject {
+                mesh: desc.mesh_handle,
+                transform: *transform,
+                texture_index: desc.texture_index,
+                selected: 0,
+                alpha_cutoff: desc.alpha_cutoff,
+                color: desc.color,
+                render_bounds: desc.render_bounds,
+                lod_count: 1,
+                lod_screen_sizes: DEFAULT_LOD_SCREEN_SIZES,
+                visible: 1,
+            });
+            self.lod_meshes.push(vec![desc.mesh_handle]);
+            self.draw_commands.push(RenderObjectDrawCommands::default());
+        }
+
+        let render_objects = (first..self.render_objects.len())
+            .map(Handle::from)
+            .collect::<Vec<_>>();
+
+        if desc.draw_forward_pass {
+            self.forward_pass
+                .unbatched_objects
+                .extend_from_slice(&render_objects);
+        }
+        for pass in Self::listed_custom_passes(&mut self.custom_passes, desc) {
+            pass.unbatched_objects.extend_from_slice(&render_objects);
+        }
+
+        // the handles are contiguous, so they're reuploaded with a single write
+        self.render_objects_to_reupload
+            .extend_from_slice(&render_objects);
+        self.draw_commands_to_reupload
+            .extend_from_slice(&render_objects);
+
+        render_objects
+    }
+
+    /// Removes a render object from the mesh passes, so that it's no longer drawn. Its slot is
+    /// reused by the next registered render object.
+    pub fn remove_object(&mut self, render_object: Handle<RenderObject>) {
+        self.remove_objects(slice::from_ref(&render_object));
+    }
+
+    /// Removes render objects from the mesh passes, rebuilding the passes once for all of them.
+    pub fn remove_objects(&mut self, render_objects: &[Handle<RenderObject>]) {
+        self.forward_pass.remove_objects(render_objects);
+        for custom_pass in &mut self.custom_passes {
+            custom_pass.pass.remove_objects(render_objects);
+        }
+
+        for &render_object in render_objects {
+            let already_removed = self
+                .free_render_objects
+                .iter()
+                .any(|free| free.id == render_object.id);
+
+            if !already_removed {
+                self.draw_commands[render_object.id as usize].draw_command_index =
+                    REMOVED_DRAW_COMMAND_INDEX;
+                self.draw_commands_to_reupload.push(render_object);
+                self.free_render_objects.push(render_object);
+            }
+        }
+    }
+
+    /// Changes the mesh of a render object, re-batching it in the mesh passes. Replaces all of its
+    /// LOD levels.
+    pub fn set_object_mesh(
+        &mut self,
+        render_object: Handle<RenderObject>,
+        mesh: Handle<mesh::Mesh>,
+    ) {
+        if self.render_objects[render_object].mesh.id == mesh.id
+            && self.render_objects[render_object].lod_count == 1
+        {
+            return;
+        }
+
+        self.render_objects[render_object].mesh = mesh;
+        self.render_objects[render_object].lod_count = 1;
+        self.lod_meshes[render_object.id as usize] = vec![mesh];
+
+        self.forward_pass.remove_object(render_object);
+        self.forward_pass.unbatched_objects.push(render_object);
+
+        for custom_pass in &mut self.custom_passes {
+            // only re-batch it in the passes it was drawn in
+            if custom_pass.pass.contains_object(render_object) {
+                custom_pass.pass.remove_object(render_object);
+                custom_pass.pass.unbatched_objects.push(render_object);
+            }
+        }
+
+        self.render_objects_to_reupload.push(render_object);
+    }
+
+    pub fn enqueue_model_matrix_update(
+        &mut self,
+        render_object: Handle<RenderObject>,
+        model_matrix: m::Mat4,
+    ) {
+        self.render_objects[render_object].transform = model_matrix;
+        self.render_objects_to_reupload.push(render_object);
+    }
+
+    pub fn enqueue_color_update(&mut self, render_object: Handle<RenderObject>, color: m::Vec4) {
+        self.render_objects[render_object].color = color;
+        self.render_objects_to_reupload.push(render_object);
+    }
+
+    pub fn enqueue_alpha_cutoff_update(
+        &mut self,
+        render_object: Handle<RenderObject>,
+        alpha_cutoff: f32,
+    ) {
+        self.render_objects[render_object].alpha_cutoff = alpha_cutoff;
+        self.render_objects_to_reupload.push(render_object);
+    }
+
+    /// Tints the render object in the viewport, e.g. when it's selected in the editor.
+    pub fn set_selected(&mut self, render_object: Handle<RenderObject>, selected: bool) {
+        self.render_objects[render_object].selected = selected as u32;
+        self.render_objects_to_reupload.push(render_object);
+    }
+
+    /// Hides or shows the render object. Hidden objects stay registered, the compute pass just
+    /// doesn't emit draws for them.
+    pub fn enqueue_visibility_update(
+        &mut self,
+        render_object: Handle<RenderObject>,
+        visible: bool,
+    ) {
+        self.render_objects[render_object].visible = visible as u32;
+        self.render_objects_to_reupload.push(render_object);
+    }
+
+    /// The render object's bounds in world space.
+    pub fn world_render_bounds(&self, render_object: Handle<RenderObject>) -> mesh::RenderBounds {
+        let transform = self.render_objects[render_object].transform;
+        let bounds = self.render_objects[render_object].render_bounds;
+
+        let max_scale = transform
+            .x_axis
+            .truncate()
+            .length()
+            .max(transform.y_axis.truncate().length())
+            .max(transform.z_axis.truncate().length());
+
+        mesh::RenderBounds {
+            origin: transform.transform_point3(bounds.origin),
+            radius: bounds.radius * max_scale,
+        }
+    }
+
+    /// Rebuilds the batches of the mesh passes if any objects were added or removed, assigning
+    /// each render object its draw command. Returns the batched draw commands with instance count
+    /// set to 0, to be uploaded to the draw commands buffer, or None if nothing changed. The
+    /// non-indexed draw commands are placed after the indexed ones, starting at
+    /// first_non_indexed_draw_command.
+    ///
+    /// Never returns more than RenderLimits::max_draw_commands draw commands, or draw commands
+    /// with instances past RenderLimits::max_instances. Objects that don't fit are logged and
+    /// skipped.
+    pub fn build_batches(&mut self, meshes: &[mesh::Mesh]) -> Option<Vec<DrawIndexedIndirect>> {
+        let rebuilt = self
+            .forward_pass
+            .update_batches(&self.lod_meshes, &self.limits)
+            .unwrap_or_else(|e| {
+                log::error!("{}", e);
+                true
+            });
+
+        for custom_pass in &mut self.custom_passes {
+            if let Err(e) = custom_pass
+                .pass
+                .update_batches(&self.lod_meshes, &self.limits)
+            {
+                log::error!("mesh pass {:?}: {}", custom_pass.name, e);
+            }
+        }
+
+        if !rebuilt {
+            return None;
+        }
+
+        println!("building batches..");
+
+        let pass_draw_commands = draw_commands(&self.forward_pass, meshes);
+        self.first_non_indexed_draw_command = pass_draw_commands.first_non_indexed;
+
+        // assign draw commands to render objects
+        for (pass_object, draw_command_id) in
+            pass_draw_commands.object_draw_commands(&self.forward_pass)
+        {
+            let render_object = pass_object.original_render_object;
+            let draw_commands = &mut self.draw_commands[render_object.id as usize];
+
+            draw_commands.lod_draw_command_indices[pass_object.lod as usize] = draw_command_id;
+            if pass_object.lod == 0 {
+                draw_commands.draw_command_index = draw_command_id;
+            }
+
+            self.draw_commands_to_reupload.push(render_object);
+        }
+
+        Some(pass_draw_commands.commands)
+    }
+
+    /// The draw commands of a custom mesh pass's current batches, and the draw command of each of
+    /// its objects, or None if no pass with the name is registered. Its batches are rebuilt by
+    /// build_batches.
+    pub fn custom_pass_draw_commands(
+        &self,
+        name: &str,
+        meshes: &[mesh::Mesh],
+    ) -> Option<CustomPassDrawCommands> {
+        self.mesh_pass(name).map(|pass| {
+            let pass_draw_commands = draw_commands(pass, meshes);
+
+            CustomPassDrawCommands {
+                object_draw_commands: pass_draw_commands
+                    .object_draw_commands(pass)
+                    .map(|(pass_object, draw_command_id)| (*pass_object, draw_command_id))
+                    .collect(),
+                first_non_indexed: pass_draw_commands.first_non_indexed,
+                commands: pass_draw_commands.commands,
+            }
+        })
+    }
+
+    /// Writes the render objects that changed since the last call to the render objects buffer.
+    pub fn reupload(
+        &mut self,
+        queue: &wgpu::Queue,
+        render_objects_buffer: &GpuBuffer<RenderObject>,
+        draw_commands_buffer: &GpuBuffer<RenderObjectDrawCommands>,
+    ) {
+        super::reupload_render_objects(
+            queue,
+            render_objects_buffer,
+            draw_commands_buffer,
+            &self.render_objects,
+            &self.draw_commands,
+            &mut self.render_objects_to_reupload,
+            &mut self.draw_commands_to_reupload,
+        );
+    }
+}
+
+/// The draw commands of a custom mesh pass's batches, from RenderObjects::custom_pass_draw_commands.
+pub struct CustomPassDrawCommands {
+    /// The draw commands with instance count set to 0, the indexed ones first.
+    pub commands: Vec<DrawIndexedIndirect>,
+    /// Index into commands of the first non-indexed draw command.
+    pub first_non_indexed: u32,
+    /// Each of the pass's objects, with the index into commands of the draw command it's drawn by.
+    pub object_draw_commands: Vec<(mesh_pass::PassObject, u32)>,
+}
+
+/// The draw commands of a mesh pass's batches, indexed first.
+struct PassDrawCommands {
+    commands: Vec<DrawIndexedIndirect>,
+    /// Index into commands of each batch's draw command.
+    batch_draw_commands: Vec<u32>,
+    first_non_indexed: u32,
+}
+impl PassDrawCommands {
+    /// Each object of the pass the draw commands were created from, with the index into commands
+    /// of its draw command. The pass objects' draw_command_ids index the batches, which are
+    /// reordered to put the indexed draw commands first. Objects without a draw command get
+    /// REMOVED_DRAW_COMMAND_INDEX.
+    fn object_draw_commands<'a>(
+        &'a self,
+        pass: &'a mesh_pass::LegacyMeshPass,
+    ) -> impl Iterator<Item = (&'a mesh_pass::PassObject, u32)> + 'a {
+        pass.objects.iter().map(move |pass_object| {
+            let draw_command_id = self
+                .batch_draw_commands
+                .get(pass_object.draw_command_id as usize)
+                .copied()
+                .unwrap_or(REMOVED_DRAW_COMMAND_INDEX);
+
+            (pass_object, draw_command_id)
+        })
+    }
+}
+
+/// Creates a draw command for each unique mesh + material combo in the mesh pass.
+fn draw_commands(pass: &mesh_pass::LegacyMeshPass, meshes: &[mesh::Mesh]) -> PassDrawCommands {
+    let draw_commands = pass
+        .indirect_batches
+        .iter()
+        .map(|batch: &IndirectBatch| {
+            let mesh = meshes[batch.mesh_h.id as usize];
+            println!("mesh: {:?}, max instance count: {}", mesh, batch.count);
+
+            let first_instance = batch.first as _;
+            let instance_count = 0; // set in compute shader
+            mesh.create_draw_command(first_instance, instance_count)
+        })
+        .collect::<Vec<_>>();
+
+    // stable, the batches keep their order within each kind
+    let mut order = (0..draw_commands.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&batch| matches!(draw_commands[batch], mesh::DrawCommand::NonIndexed(_)));
+
+    let mut batch_draw_commands = vec![0; draw_commands.len()];
+    for (draw_command_id, &batch) in order.iter().enumerate() {
+        batch_draw_commands[batch] = draw_command_id as u32;
+    }
+
+    let first_non_indexed = draw_commands
+        .iter()
+        .filter(|draw_command| matches!(draw_command, mesh::DrawCommand::Indexed(_)))
+        .count() as u32;
+
+    PassDrawCommands {
+        commands: order
+            .into_iter()
+            .map(|batch| draw_commands[batch].batched())
+            .collect(),
+        batch_draw_commands,
+        first_non_indexed,
+    }
+}
+
+/// Descriptor of a render object with the given mesh, drawn in the forward pass and the listed
+/// custom passes.
+#[cfg(test)]
+fn desc(mesh_id: usize, custom_passes: &'static [&'static str]) -> RenderObjectDescriptor {
+    RenderObjectDescriptor {
+        mesh_handle: Handle::from(mesh_id),
+        transform: m::Mat4::IDENTITY,
+        render_bounds: mesh::RenderBounds {
+            origin: m::Vec3::ZERO,
+            radius: 1.0,
+        },
+        color: m::Vec4::ONE,
+        texture_index: 0,
+        alpha_cutoff: 0.0,
+        draw_forward_pass: true,
+        custom_passes,
+    }
+}
+
+#[test]
+fn test_register_instances() {
+    let meshes = [mesh::Mesh {
+        first_vertex: 0,
+        vertex_count: 24,
+        first_index: 0,
+        index_count: 36,
+    }];
+
+    let transforms = (0..1000)
+        .map(|i| {
+            m::Mat4::from_translation(m::vec3(
+                (i % 10) as f32,
+                (i / 10 % 10) as f32,
+                (i / 100) as f32,
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    let mut render_objects = RenderObjects::default();
+    let handles = render_objects.register_instances(&desc(0, &[]), &transforms);
+
+    assert_eq!(handles.len(), 1000);
+    assert_eq!(
+        render_objects.render_objects[handles[999]].transform,
+        transforms[999]
+    );
+
+    // one instanced draw command for all of them
+    let draw_commands = render_objects.build_batches(&meshes).unwrap();
+    assert_eq!(draw_commands.len(), 1);
+
+    assert_eq!(
+        super::coalesce_reuploads(&mut render_objects.render_objects_to_reupload),
+        vec![0..1000]
+    );
+}
+
+#[test]
+fn test_register_instances_past_max_instances() {
+    let mut render_objects = RenderObjects::new(RenderLimits {
+        max_draw_commands: 10,
+        max_instances: 4,
+    });
+    render_objects.register_object(&desc(0, &[])).unwrap();
+
+    // the instances that don't fit are skipped
+    let handles = render_objects.register_instances(&desc(0, &[]), &[m::Mat4::IDENTITY; 4]);
+    assert_eq!(handles.len(), 3);
+    assert_eq!(render_objects.render_objects.len(), 4);
+
+    assert!(render_objects
+        .register_instances(&desc(0, &[]), &[m::Mat4::IDENTITY])
+        .is_empty());
+    assert!(render_objects.register_object(&desc(0, &[])).is_err());
+
+    // a removed render object's slot can be reused
+    render_objects.remove_object(handles[0]);
+    assert_eq!(
+        render_objects.register_object(&desc(0, &[])).unwrap().id,
+        handles[0].id
+    );
+}
+
+#[test]
+fn test_custom_mesh_pass() {
+    let meshes = [
+        mesh::Mesh {
+            first_vertex: 0,
+            vertex_count: 24,
+            first_index: 0,
+            index_count: 36,
+        },
+        mesh::Mesh {
+            first_vertex: 24,
+            vertex_count: 4,
+            first_index: 36,
+            index_count: 6,
+        },
+    ];
+
+    let mut render_objects = RenderObjects::default();
+    render_objects.register_mesh_pass("shadow");
+
+    let caster = render_objects
+        .register_object(&desc(0, &["shadow"]))
+        .unwrap();
+    render_objects.register_object(&desc(1, &[])).unwrap();
+    render_objects.register_instances(&desc(1, &["shadow"]), &[m::Mat4::IDENTITY; 3]);
+
+    // the forward pass draws both meshes, the shadow pass batches its objects on its own
+    let forward_commands = render_objects.build_batches(&meshes).unwrap();
+    assert_eq!(forward_commands.len(), 2);
+    assert_eq!(render_objects.forward_pass.objects.len(), 5);
+
+    let shadow_pass = render_objects.mesh_pass("shadow").unwrap();
+    assert_eq!(shadow_pass.objects.len(), 4);
+    assert_eq!(
+        shadow_pass
+            .indirect_batches
+            .iter()
+            .map(|batch| (batch.mesh_h.id, batch.count))
+            .collect::<Vec<_>>(),
+        vec![(0, 1), (1, 3)]
+    );
+
+    // removing the caster only rebuilds the shadow pass from the remaining objects
+    render_objects.remove_object(caster);
+    render_objects.build_batches(&meshes);
+
+    let shadow_commands = render_objects
+        .custom_pass_draw_commands("shadow", &meshes)
+        .unwrap();
+    assert_eq!(shadow_commands.commands.len(), 1);
+    assert!(render_objects
+        .custom_pass_draw_commands("reflection", &meshes)
+        .is_none());
+}
+
+#[test]
+fn test_rebuild_only_reuploads_draw_commands() {
+    let meshes = [mesh::Mesh {
+        first_vertex: 0,
+        vertex_count: 24,
+        first_index: 0,
+        index_count: 36,
+    }];
+
+    let mut render_objects = RenderObjects::default();
+    let handles = render_objects.register_instances(&desc(0, &[]), &[m::Mat4::IDENTITY; 4]);
+    render_objects.build_batches(&meshes).unwrap();
+    render_objects.render_objects_to_reupload.clear();
+    render_objects.draw_commands_to_reupload.clear();
+
+    // the render objects themselves aren't reuploaded, only their draw commands, in one run
+    render_objects.remove_object(handles[1]);
+    render_objects.build_batches(&meshes).unwrap();
+
+    assert!(render_objects.render_objects_to_reupload.is_empty());
+    assert_eq!(
+        super::coalesce_reuploads(&mut render_objects.draw_commands_to_reupload),
+        vec![0..4]
+    );
+    assert_eq!(
+        render_objects.draw_commands[handles[1].id as usize].draw_command_index,
+        REMOVED_DRAW_COMMAND_INDEX
+    );
+}
+
+#[test]
+fn test_non_indexed_draw_commands_last() {
+    let meshes = [
+        mesh::Mesh {
+            first_vertex: 0,
+            vertex_count: 3,
+            first_index: 0,
+            index_count: 0,
+        },
+        mesh::Mesh {
+            first_vertex: 3,
+            vertex_count: 24,
+            first_index: 0,
+            index_count: 36,
+        },
+    ];
+
+    let mut render_objects = RenderObjects::default();
+    let triangle = render_objects.register_object(&desc(0, &[])).unwrap();
+    let cube = render_objects.register_object(&desc(1, &[])).unwrap();
+
+    let draw_commands = render_objects.build_batches(&meshes).unwrap();
+    assert_eq!(render_objects.first_non_indexed_draw_command, 1);
+
+    // the triangle's mesh sorts first, but its draw command goes after the indexed ones
+    assert_eq!(
+        draw_commands
+            .iter()
+            .map(|draw_command| (draw_command.index_count, draw_command.first_index))
+            .collect::<Vec<_>>(),
+        vec![(36, 0), (3, 0)]
+    );
+    assert_eq!(
+        render_objects.draw_commands[cube.id as usize].draw_command_index,
+        0
+    );
+    assert_eq!(
+        render_objects.draw_commands[triangle.id as usize].draw_command_index,
+        1
+    );
+}
+
+#[test]
+fn test_custom_pass_non_indexed_draw_commands() {
+    let meshes = [
+        mesh::Mesh {
+            first_vertex: 0,
+            vertex_count: 3,
+            first_index: 0,
+            index_count: 0,
+        },
+        mesh::Mesh {
+            first_vertex: 3,
+            vertex_count: 24,
+            first_index: 0,
+            index_count: 36,
+        },
+    ];
+
+    let mut render_objects = RenderObjects::default();
+    render_objects.register_mesh_pass("shadow");
+
+    let triangle = render_objects
+        .register_object(&desc(0, &["shadow"]))
+        .unwrap();
+    let cube = render_objects
+        .register_object(&desc(1, &["shadow"]))
+        .unwrap();
+    render_objects.build_batches(&meshes);
+
+    let shadow_commands = render_objects
+        .custom_pass_draw_commands("shadow", &meshes)
+        .unwrap();
+    assert_eq!(shadow_commands.first_non_indexed, 1);
+
+    // the triangle's batch comes first, but its draw command is moved after the cube's
+    let draw_command_index = |render_object: Handle<RenderObject>| {
+        shadow_commands
+            .object_draw_commands
+            .iter()
+            .find(|(pass_object, _)| pass_object.original_render_object.id == render_object.id)
+            .map(|&(_, draw_command_index)| draw_command_index)
+    };
+    assert_eq!(draw_command_index(cube), Some(0));
+    assert_eq!(draw_command_index(triangle), Some(1));
+    assert_eq!(shadow_commands.commands[1].index_count, 3);
+}
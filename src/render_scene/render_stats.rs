@@ -0,0 +1,339 @@
+use crate::graphics_context::{GpuPass, GraphicsContext, TimestampQueries};
+use penguin_util::raw_gpu_types::{DrawIndexedIndirect, DrawIndirectCount};
+use penguin_util::GpuBuffer;
+use std::future::Future;
+use std::mem;
+
+/// Statistics about the rendered frame, for display in the editor.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RenderStats {
+    /// The number of batched draw commands.
+    pub draw_count: u32,
+    /// The number of draw commands the compute shader emitted (the previous frame).
+    pub visible_draw_count: u32,
+    /// The number of triangles drawn by the emitted draw commands (the previous frame).
+    pub triangle_count: u32,
+    /// The number of vertices processed by the emitted draw commands, one per index of each
+    /// instance (the previous frame).
+    pub vertex_count: u32,
+    /// GPU time of the compute pass in milliseconds (the previous frame). None if timestamp
+    /// queries aren't supported.
+    pub compute_time_ms: Option<f32>,
+    /// GPU time of the shadow and render passes in milliseconds (the previous frame). None if
+    /// timestamp queries aren't supported.
+    pub render_time_ms: Option<f32>,
+}
+
+type MapFuture = std::pin::Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+/// A staging buffer that GPU data is copied into and read back from, without waiting for the GPU.
+/// It's mapped once the copy into it is submitted, and read once the mapping has resolved.
+struct ReadbackBuffer {
+    buffer: wgpu::Buffer,
+    state: ReadbackState,
+}
+
+enum ReadbackState {
+    /// Free to copy into.
+    Idle,
+    /// A copy into the buffer is recorded, but maybe not submitted yet.
+    Copied,
+    /// Mapping was requested after the copy was submitted.
+    Mapping(MapFuture),
+}
+
+impl ReadbackBuffer {
+    fn new(buffer: wgpu::Buffer) -> Self {
+        Self {
+            buffer,
+            state: ReadbackState::Idle,
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        matches!(self.state, ReadbackState::Idle)
+    }
+
+    /// Requests mapping the buffer if a copy into it was recorded. Should be called after the
+    /// commands with the copy are submitted.
+    fn map_copied(&mut self) {
+        if let ReadbackState::Copied = self.state {
+            self.state = ReadbackState::Mapping(Box::pin(
+                self.buffer.slice(..).map_async(wgpu::MapMode::Read),
+            ));
+        }
+    }
+
+    /// Calls read with the buffer's contents and unmaps it, if the mapping has resolved. Doesn't
+    /// block, the device should have been polled for the mapping to make progress.
+    fn try_read(&mut self, label: &str, read: impl FnOnce(&[u8])) {
+        let map_future = match &mut self.state {
+            ReadbackState::Mapping(map_future) => map_future,
+            _ => return,
+        };
+
+        let waker = noop_waker();
+        let mut context = std::task::Context::from_waker(&waker);
+
+        match map_future.as_mut().poll(&mut context) {
+            std::task::Poll::Pending => {}
+            std::task::Poll::Ready(Err(e)) => {
+                log::error!("failed to map {}: {}", label, e);
+                self.state = ReadbackState::Idle;
+            }
+            std::task::Poll::Ready(Ok(())) => {
+                read(&self.buffer.slice(..).get_mapped_range());
+                self.buffer.unmap();
+                self.state = ReadbackState::Idle;
+            }
+        }
+    }
+}
+
+/// A waker that does nothing. The readback buffers are polled every frame rather than woken.
+fn noop_waker() -> std::task::Waker {
+    use std::task::{RawWaker, RawWakerVTable};
+
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    // the vtable functions don't use the data pointer
+    unsafe { std::task::Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+}
+
+/// Reads back the draw count and the draw commands written by the compute shader.
+///
+/// Double-buffered: the data is copied into an idle staging buffer, which is mapped after the copy
+/// is submitted and read a frame or more later, once the mapping has resolved. The CPU never waits
+/// for the GPU. If neither staging buffer is idle, the frame isn't copied.
+pub struct DrawCountReadback {
+    /// Each holds the draw count followed by max_draw_commands draw commands.
+    staging_buffers: [ReadbackBuffer; 2],
+}
+impl DrawCountReadback {
+    pub fn init(device: &wgpu::Device, max_draw_commands: usize) -> Self {
+        let create_staging_buffer = || {
+            ReadbackBuffer::new(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("draw count readback buffer"),
+                size: (mem::size_of::<DrawIndirectCount>()
+                    + mem::size_of::<DrawIndexedIndirect>() * max_draw_commands)
+                    as _,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }))
+        };
+
+        Self {
+            staging_buffers: [create_staging_buffer(), create_staging_buffer()],
+        }
+    }
+
+    /// Records a copy of the indexed draw count and the output draw commands buffer into an idle
+    /// staging buffer. Should be recorded after the compute pass, and followed by map_copied once
+    /// submitted. The output draw commands buffer should hold the max_draw_commands passed to init.
+    /// Non-indexed draws aren't counted.
+    pub fn copy(
+        &mut self,
+        cmd: &mut wgpu::CommandEncoder,
+        draw_count_buffer: &GpuBuffer<DrawIndirectCount>,
+        out_draw_commands_buffer: &GpuBuffer<DrawIndexedIndirect>,
+    ) {
+        let staging_buffer = match self.staging_buffers.iter_mut().find(|b| b.is_idle()) {
+            Some(staging_buffer) => staging_buffer,
+            None => return,
+        };
+
+        let count_size = mem::size_of::<DrawIndirectCount>() as wgpu::BufferAddress;
+
+        cmd.copy_buffer_to_buffer(draw_count_buffer, 0, &staging_buffer.buffer, 0, count_size);
+
+        cmd.copy_buffer_to_buffer(
+            out_draw_commands_buffer,
+            0,
+            &staging_buffer.buffer,
+            count_size,
+            out_draw_commands_buffer.byte_len(),
+        );
+
+        staging_buffer.state = ReadbackState::Copied;
+    }
+
+    /// Requests mapping the staging buffer copied into. Should be called after the commands
+    /// recorded with copy are submitted.
+    pub fn map_copied(&mut self) {
+        self.staging_buffers
+            .iter_mut()
+            .for_each(ReadbackBuffer::map_copied);
+    }
+
+    /// Updates the visible draw, triangle and vertex counts of the render stats with the copied
+    /// data whose mapping has resolved, if any. Polls the device without blocking.
+    pub fn read_ready(&mut self, device: &wgpu::Device, render_stats: &mut RenderStats) {
+        device.poll(wgpu::Maintain::Poll);
+
+        for staging_buffer in &mut self.staging_buffers {
+            staging_buffer.try_read("draw count readback buffer", |data| {
+                let (count_bytes, draw_commands_bytes) =
+                    data.split_at(mem::size_of::<DrawIndirectCount>());
+
+                let count = bytemuck::from_bytes::<DrawIndirectCount>(count_bytes).count;
+                let draw_commands: &[DrawIndexedIndirect] =
+                    bytemuck::cast_slice(draw_commands_bytes);
+
+                // the count can't be larger than the buffer, but don't trust the GPU with a slice
+                // index
+                let emitted_draws = &draw_commands[..(count as usize).min(draw_commands.len())];
+                let (triangle_count, vertex_count) = count_primitives(emitted_draws);
+
+                render_stats.visible_draw_count = count;
+                render_stats.triangle_count = triangle_count;
+                render_stats.vertex_count = vertex_count;
+            });
+        }
+    }
+}
+
+/// Reads back the timestamps written around the GPU passes. Double-buffered without waiting for
+/// the GPU, like DrawCountReadback. Does nothing if the graphics context doesn't support timestamp
+/// queries.
+pub struct TimestampReadback {
+    /// The query set is resolved into this buffer, then copied to a staging buffer.
+    resolve_buffer: wgpu::Buffer,
+    staging_buffers: [ReadbackBuffer; 2],
+}
+impl TimestampReadback {
+    const SIZE: wgpu::BufferAddress =
+        (TimestampQueries::COUNT as usize * mem::size_of::<u64>()) as _;
+
+    pub fn init(device: &wgpu::Device) -> Self {
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("timestamp resolve buffer"),
+            size: Self::SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let create_staging_buffer = || {
+            ReadbackBuffer::new(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("timestamp readback buffer"),
+                size: Self::SIZE,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }))
+        };
+
+        Self {
+            resolve_buffer,
+            staging_buffers: [create_staging_buffer(), create_staging_buffer()],
+        }
+    }
+
+    /// Records resolving the timestamps into an idle staging buffer. Should be recorded after the
+    /// last timed pass, and followed by map_copied once submitted.
+    pub fn copy(&mut self, cmd: &mut wgpu::CommandEncoder, context: &GraphicsContext) {
+        let queries = match &context.timestamp_queries {
+            Some(queries) => queries,
+            None => return,
+        };
+
+        let staging_buffer = match self.staging_buffers.iter_mut().find(|b| b.is_idle()) {
+            Some(staging_buffer) => staging_buffer,
+            None => return,
+        };
+
+        cmd.resolve_query_set(
+            &queries.query_set,
+            0..TimestampQueries::COUNT,
+            &self.resolve_buffer,
+            0,
+        );
+        cmd.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &staging_buffer.buffer,
+            0,
+            Self::SIZE,
+        );
+
+        staging_buffer.state = ReadbackState::Copied;
+    }
+
+    /// Requests mapping the staging buffer copied into. Should be called after the commands
+    /// recorded with copy are submitted.
+    pub fn map_copied(&mut self) {
+        self.staging_buffers
+            .iter_mut()
+            .for_each(ReadbackBuffer::map_copied);
+    }
+
+    /// Updates the pass times of the render stats with the copied timestamps whose mapping has
+    /// resolved, if any. Polls the device without blocking.
+    pub fn read_ready(&mut self, context: &GraphicsContext, render_stats: &mut RenderStats) {
+        let queries = match &context.timestamp_queries {
+            Some(queries) => queries,
+            None => return,
+        };
+
+        context.device.poll(wgpu::Maintain::Poll);
+
+        for staging_buffer in &mut self.staging_buffers {
+            staging_buffer.try_read("timestamp readback buffer", |data| {
+                let timestamps: &[u64] = bytemuck::cast_slice(data);
+
+                let pass_time_ms = |pass: GpuPass| {
+                    let begin = TimestampQueries::begin_index(pass) as usize;
+                    timestamps_to_ms(timestamps[begin], timestamps[begin + 1], queries.period)
+                };
+
+                render_stats.compute_time_ms = Some(pass_time_ms(GpuPass::Compute));
+                render_stats.render_time_ms = Some(pass_time_ms(GpuPass::Render));
+            });
+        }
+    }
+}
+
+/// Converts the time between two timestamps to milliseconds. period is nanoseconds per tick.
+fn timestamps_to_ms(begin: u64, end: u64, period: f32) -> f32 {
+    (end.wrapping_sub(begin) as f64 * period as f64 / 1e6) as f32
+}
+
+/// Sums the triangles and vertices drawn by the draw commands, over all of their instances.
+fn count_primitives(draw_commands: &[DrawIndexedIndirect]) -> (u32, u32) {
+    draw_commands
+        .iter()
+        .fold((0, 0), |(triangles, vertices), draw_command| {
+            let instance_count = draw_command.instance_count;
+
+            (
+                triangles + draw_command.index_count / 3 * instance_count,
+                vertices + draw_command.index_count * instance_count,
+            )
+        })
+}
+
+#[test]
+fn test_count_primitives() {
+    let draw_command = |index_count, instance_count| DrawIndexedIndirect {
+        index_count,
+        instance_count,
+        first_index: 0,
+        base_vertex: 0,
+        first_instance: 0,
+    };
+
+    assert_eq!(count_primitives(&[]), (0, 0));
+    assert_eq!(
+        count_primitives(&[draw_command(36, 10), draw_command(6, 1)]),
+        (122, 366)
+    );
+}
+
+#[test]
+fn test_timestamps_to_ms() {
+    assert_eq!(timestamps_to_ms(1_000, 3_001_000, 1.0), 3.0);
+    assert_eq!(timestamps_to_ms(0, 1_000_000, 0.5), 0.5);
+}
@@ -0,0 +1,377 @@
+///! Saving and loading of scenes and camera views to and from RON files.
+use crate::camera::{CameraView, MainCamera};
+use crate::components::{
+    AlphaCutoff, Color, MaterialComponent, MeshComponent, Name, PointLight, Rotation, Scale,
+    Translation, Visible,
+};
+use anyhow::*;
+use legion::systems::CommandBuffer;
+use legion::IntoQuery;
+use macaw as m;
+use serde::{Deserialize, Serialize};
+
+/// Serializable m::Vec3.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Vec3([f32; 3]);
+impl From<m::Vec3> for Vec3 {
+    fn from(v: m::Vec3) -> Self {
+        Self(v.to_array())
+    }
+}
+impl From<Vec3> for m::Vec3 {
+    fn from(v: Vec3) -> Self {
+        m::Vec3::from(v.0)
+    }
+}
+
+/// Serializable m::Quat.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Quat([f32; 4]);
+impl From<m::Quat> for Quat {
+    fn from(q: m::Quat) -> Self {
+        Self(q.to_array())
+    }
+}
+impl From<Quat> for m::Quat {
+    fn from(q: Quat) -> Self {
+        m::Quat::from_array(q.0)
+    }
+}
+
+/// Serializable m::Vec4.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Vec4([f32; 4]);
+impl From<m::Vec4> for Vec4 {
+    fn from(v: m::Vec4) -> Self {
+        Self(v.to_array())
+    }
+}
+impl From<Vec4> for m::Vec4 {
+    fn from(v: Vec4) -> Self {
+        m::Vec4::from(v.0)
+    }
+}
+
+/// An entity and its saved components.
+#[derive(Debug, Serialize, Deserialize)]
+struct SceneEntity {
+    name: String,
+    translation: Option<Vec3>,
+    rotation: Option<Quat>,
+    scale: Option<Vec3>,
+    /// Index into the scene's mesh assets.
+    mesh: Option<usize>,
+    color: Option<Vec4>,
+    material: Option<u32>,
+    alpha_cutoff: Option<f32>,
+    visible: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ScenePointLight {
+    position: Vec3,
+    color: Vec3,
+    radius: f32,
+}
+
+/// A scene description, used both for the startup scene and for scenes saved from the editor.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SceneFile {
+    /// Names of the mesh assets that the entities' mesh indices refer to.
+    #[serde(default)]
+    pub mesh_assets: Vec<String>,
+    #[serde(default)]
+    entities: Vec<SceneEntity>,
+    #[serde(default)]
+    point_lights: Vec<ScenePointLight>,
+}
+
+/// Scene file loaded on startup, relative to the build output directory.
+const STARTUP_SCENE_PATH: &str = "assets/scenes/startup.ron";
+
+impl SceneFile {
+    /// Reads the startup scene, or logs why it couldn't and returns an empty scene.
+    pub fn read_startup() -> Self {
+        let path = std::path::Path::new(env!("OUT_DIR")).join(STARTUP_SCENE_PATH);
+        Self::read(path).unwrap_or_else(|e| {
+            log::error!("failed to load the startup scene: {:?}", e);
+            Self::default()
+        })
+    }
+
+    pub fn read(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let contents = std::fs::read_to_string(path.as_ref())
+            .with_context(|| format!("failed to read scene file {:?}", path.as_ref()))?;
+
+        ron::from_str(&contents)
+            .with_context(|| format!("failed to parse scene file {:?}", path.as_ref()))
+    }
+
+    /// Pushes the scene's entities and point lights into the command buffer, and returns the
+    /// pushed entities (not including the point lights).
+    pub fn spawn(self, cmd: &mut CommandBuffer) -> Vec<legion::Entity> {
+        for light in self.point_lights {
+            cmd.push((PointLight {
+                position: light.position.into(),
+                color: light.color.into(),
+                radius: light.radius,
+            },));
+        }
+
+        self.entities
+            .into_iter()
+            .map(|scene_entity| {
+                let entity = cmd.push((Name(scene_entity.name),));
+
+                if let Some(translation) = scene_entity.translation {
+                    cmd.add_component(entity, Translation(translation.into()));
+                }
+                if let Some(rotation) = scene_entity.rotation {
+                    cmd.add_component(entity, Rotation(rotation.into()));
+                }
+                if let Some(scale) = scene_entity.scale {
+                    cmd.add_component(entity, Scale(scale.into()));
+                }
+                if let Some(mesh) = scene_entity.mesh {
+                    cmd.add_component(entity, MeshComponent(mesh));
+                }
+                if let Some(color) = scene_entity.color {
+                    cmd.add_component(entity, Color(color.into()));
+                }
+                if let Some(material) = scene_entity.material {
+                    cmd.add_component(entity, MaterialComponent(material));
+                }
+                if let Some(alpha_cutoff) = scene_entity.alpha_cutoff {
+                    cmd.add_component(entity, AlphaCutoff(alpha_cutoff));
+                }
+                if let Some(visible) = scene_entity.visible {
+                    cmd.add_component(entity, Visible(visible));
+                }
+
+                entity
+            })
+            .collect()
+    }
+}
+
+/// A saved camera view.
+#[derive(Debug, Serialize, Deserialize)]
+struct CameraFile {
+    position: Vec3,
+    /// Radians.
+    yaw: f32,
+    /// Radians.
+    pitch: f32,
+    /// Radians.
+    fov_y: f32,
+    z_near: f32,
+    z_far: f32,
+}
+impl From<CameraView> for CameraFile {
+    fn from(view: CameraView) -> Self {
+        Self {
+            position: view.position.into(),
+            yaw: view.yaw,
+            pitch: view.pitch,
+            fov_y: view.fov_y,
+            z_near: view.z_near,
+            z_far: view.z_far,
+        }
+    }
+}
+impl From<CameraFile> for CameraView {
+    fn from(file: CameraFile) -> Self {
+        Self {
+            position: file.position.into(),
+            yaw: file.yaw,
+            pitch: file.pitch,
+            fov_y: file.fov_y,
+            z_near: file.z_near,
+            z_far: file.z_far,
+        }
+    }
+}
+
+/// The main camera's view as RON, as saved by save_camera.
+pub fn camera_to_string(camera: &MainCamera) -> Result<String> {
+    let file = CameraFile::from(camera.view());
+    Ok(ron::ser::to_string_pretty(
+        &file,
+        ron::ser::PrettyConfig::default(),
+    )?)
+}
+
+/// Saves the main camera's position, orientation and projection to a RON file.
+pub fn save_camera(camera: &MainCamera, path: impl AsRef<std::path::Path>) -> Result<()> {
+    std::fs::write(path.as_ref(), camera_to_string(camera)?)
+        .with_context(|| format!("failed to write camera file {:?}", path.as_ref()))
+}
+
+/// Moves the main camera to the view saved in a RON file.
+pub fn load_camera(camera: &mut MainCamera, path: impl AsRef<std::path::Path>) -> Result<()> {
+    let contents = std::fs::read_to_string(path.as_ref())
+        .with_context(|| format!("failed to read camera file {:?}", path.as_ref()))?;
+
+    let file: CameraFile = ron::from_str(&contents)
+        .with_context(|| format!("failed to parse camera file {:?}", path.as_ref()))?;
+    camera.set_view(&file.into());
+
+    Ok(())
+}
+
+/// Saves all named entities and point lights in the world to a RON file, along with the mesh
+/// assets that the entities' mesh indices refer to.
+pub fn save_scene(
+    world: &legion::World,
+    mesh_assets: &[impl AsRef<str>],
+    path: impl AsRef<std::path::Path>,
+) -> Result<()> {
+    let mut scene = SceneFile {
+        mesh_assets: mesh_assets
+            .iter()
+            .map(|asset| asset.as_ref().to_owned())
+            .collect(),
+        ..Default::default()
+    };
+
+    let mut query = <(legion::Entity, &Name)>::query();
+
+    for (entity, name) in query.iter(world) {
+        let entry = world.entry_ref(*entity)?;
+
+        scene.entities.push(SceneEntity {
+            name: name.0.clone(),
+            translation: entry
+                .get_component::<Translation>()
+                .ok()
+                .map(|t| t.0.into()),
+            rotation: entry.get_component::<Rotation>().ok().map(|r| r.0.into()),
+            scale: entry.get_component::<Scale>().ok().map(|s| s.0.into()),
+            mesh: entry.get_component::<MeshComponent>().ok().map(|m| m.0),
+            color: entry.get_component::<Color>().ok().map(|c| c.0.into()),
+            material: entry.get_component::<MaterialComponent>().ok().map(|m| m.0),
+            alpha_cutoff: entry.get_component::<AlphaCutoff>().ok().map(|a| a.0),
+            visible: entry.get_component::<Visible>().ok().map(|v| v.0),
+        });
+    }
+
+    for light in <&PointLight>::query().iter(world) {
+        scene.point_lights.push(ScenePointLight {
+            position: light.position.into(),
+            color: light.color.into(),
+            radius: light.radius,
+        });
+    }
+
+    let contents = ron::ser::to_string_pretty(&scene, ron::ser::PrettyConfig::default())?;
+    std::fs::write(path.as_ref(), contents)
+        .with_context(|| format!("failed to write scene file {:?}", path.as_ref()))?;
+
+    Ok(())
+}
+
+/// Loads the entities and point lights of a RON scene file into the command buffer. Render
+/// objects are registered for entities with a MeshComponent once the commands are flushed.
+pub fn load_scene(
+    cmd: &mut CommandBuffer,
+    path: impl AsRef<std::path::Path>,
+) -> Result<Vec<legion::Entity>> {
+    Ok(SceneFile::read(path)?.spawn(cmd))
+}
+
+#[test]
+fn test_scene_round_trip() {
+    let path = std::env::temp_dir().join("penguin_test_scene.ron");
+
+    let mut world = legion::World::default();
+    world.push((
+        Name::from("Cube"),
+        Translation(m::vec3(1., 2., 3.)),
+        MeshComponent(0),
+    ));
+    world.push((PointLight::default(),));
+    world.push((
+        Name::from("Cone"),
+        Translation(m::vec3(-4., 0., 5.)),
+        Rotation(m::Quat::from_rotation_y(1.0)),
+        MeshComponent(1),
+    ));
+
+    save_scene(&world, &["cube.obj", "cone.obj"], &path).unwrap();
+    assert_eq!(
+        SceneFile::read(&path).unwrap().mesh_assets,
+        vec!["cube.obj", "cone.obj"]
+    );
+    world.clear();
+
+    let mut cmd = CommandBuffer::new(&world);
+    load_scene(&mut cmd, &path).unwrap();
+    cmd.flush(&mut world, &mut legion::Resources::default());
+
+    let mut loaded = <(&Name, &Translation)>::query()
+        .iter(&world)
+        .map(|(name, translation)| (name.0.clone(), translation.0))
+        .collect::<Vec<_>>();
+    loaded.sort_by(|a, b| a.0.cmp(&b.0));
+
+    assert_eq!(
+        loaded,
+        vec![
+            ("Cone".to_owned(), m::vec3(-4., 0., 5.)),
+            ("Cube".to_owned(), m::vec3(1., 2., 3.)),
+        ]
+    );
+
+    assert_eq!(<&PointLight>::query().iter(&world).count(), 1);
+
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_startup_scene() {
+    let path = std::path::Path::new(env!("OUT_DIR")).join(STARTUP_SCENE_PATH);
+    let scene = SceneFile::read(path).unwrap();
+
+    for entity in &scene.entities {
+        if let Some(mesh) = entity.mesh {
+            assert!(
+                mesh < scene.mesh_assets.len(),
+                "{} has no mesh asset",
+                entity.name
+            );
+        }
+    }
+}
+
+#[test]
+fn test_camera_round_trip() {
+    let path = std::env::temp_dir().join("penguin_test_camera.ron");
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: wgpu::TextureFormat::Bgra8UnormSrgb,
+        width: 800,
+        height: 600,
+        present_mode: wgpu::PresentMode::Fifo,
+    };
+
+    let mut camera = MainCamera::init(&config, crate::graphics_context::DepthMode::Standard);
+    camera.set_view(&CameraView {
+        position: m::vec3(1.5, -2.25, 0.1),
+        yaw: 0.3,
+        pitch: -0.7,
+        fov_y: 1.2,
+        z_near: 0.05,
+        z_far: 500.0,
+    });
+    let saved = camera.view();
+    save_camera(&camera, &path).unwrap();
+
+    camera.focus(m::vec3(10., 0., 0.));
+    camera.update(std::time::Duration::from_secs(1));
+    camera.projection.fov_y = 0.5;
+
+    load_camera(&mut camera, &path).unwrap();
+    assert_eq!(camera.view(), saved);
+
+    std::fs::remove_file(path).ok();
+}
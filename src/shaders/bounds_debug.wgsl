@@ -0,0 +1,28 @@
+// wireframe spheres at the world space bounds of the render objects, for debugging culling
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]] var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    // point on the unit sphere
+    [[location(0)]] position: vec3<f32>;
+};
+
+struct InstanceInput {
+    // xyz is the origin, w is the radius
+    [[location(1)]] bounds: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(vert: VertexInput, inst: InstanceInput) -> [[builtin(position)]] vec4<f32> {
+    let world_position = inst.bounds.xyz + vert.position * inst.bounds.w;
+    return camera.view_proj * vec4<f32>(world_position, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_main() -> [[location(0)]] vec4<f32> {
+    return vec4<f32>(0.1, 1.0, 0.3, 1.0);
+}
@@ -19,11 +19,22 @@ struct DrawCommandsStorage {
 
 // 2
 //
+struct RenderBounds {
+    origin: vec3<f32>;
+    radius: f32;
+};
+
 struct RenderObject {
     mesh_handle: u32;
     transform: mat4x4<f32>;
-    draw_command_index: u32;
-    // todo: render_bounds: RenderBounds, (for culling)
+    texture_index: u32;
+    selected: u32;
+    alpha_cutoff: f32;
+    color: vec4<f32>;
+    render_bounds: RenderBounds;
+    lod_count: u32;
+    lod_screen_sizes: array<f32, 3>;
+    visible: u32;
 };
 struct RenderObjectsStorage {
     data: array<RenderObject>;
@@ -33,8 +44,7 @@ struct RenderObjectsStorage {
 // 3
 //
 struct DrawOutputInfo {
-    has_output_slot: atomic<u32>;
-    output_slot: atomic<u32>;
+    instance_count: atomic<u32>;
 };
 struct DrawOutputInfoStorage {
     data: array<DrawOutputInfo>;
@@ -57,15 +67,46 @@ struct AtomicU32Storage {
     data: array<atomic<u32> >;
 };
 
-// unused, but plan to use for culling
+// 7
+//
+struct ComputeUniform {
+    object_count: u32;
+    first_non_indexed_draw_command: u32;
+};
+
+// 8
+//
+struct RenderObjectDrawCommands {
+    draw_command_index: u32;
+    lod_draw_command_indices: array<u32, 4>;
+};
+struct RenderObjectDrawCommandsStorage {
+    data: array<RenderObjectDrawCommands>;
+};
+
+// 9
+//
+struct DrawIndirect {
+    vertex_count: u32;
+    instance_count: atomic<u32>;
+    first_vertex: u32;
+    first_instance: u32;
+};
+struct NonIndexedDrawCommandsStorage {
+    data: array<DrawIndirect>;
+};
+
+// used for frustum culling and LOD selection
 [[group(0), binding(0)]] var<uniform> camera: CameraUniform;
 
 // IN
 //
 // prebuilt draw commands
 [[group(0), binding(1)]] var<storage, read> draw_commands: DrawCommandsStorage;
-// render objects (to get the draw call ids from)
+// render objects
 [[group(0), binding(2)]] var<storage, read> render_objects: RenderObjectsStorage;
+// the draw command ids of each render object
+[[group(0), binding(8)]] var<storage, read> render_object_draw_commands: RenderObjectDrawCommandsStorage;
 
 // LOCAL
 //
@@ -74,62 +115,167 @@ struct AtomicU32Storage {
 
 // OUT
 //
-// draw count
+// draw counts, of the indexed and the non-indexed draw commands
 [[group(0), binding(4)]] var<storage, read_write> draw_counts: DrawIndirectCountStorage;
-// output draw commands, the draw commands that will be executed
+// output draw commands, the indexed draw commands that will be executed
 [[group(0), binding(5)]] var<storage, read_write> out_draw_commands: DrawCommandsStorage;
 // instances
 [[group(0), binding(6)]] var<storage, read_write> instance_index_to_render_object_map: AtomicU32Storage;
 
+// number of render objects
+[[group(0), binding(7)]] var<uniform> compute_uniform: ComputeUniform;
+
+// OUT
+//
+// output non-indexed draw commands
+[[group(0), binding(9)]] var<storage, read_write> out_non_indexed_draw_commands: NonIndexedDrawCommandsStorage;
+
+// the render bounds in world space, xyz is the center and w the radius
+fn worldBounds(render_object: RenderObject) -> vec4<f32> {
+    let transform = render_object.transform;
+
+    let center = transform * vec4<f32>(render_object.render_bounds.origin, 1.0);
+    let max_scale = max(length(transform[0].xyz), max(length(transform[1].xyz), length(transform[2].xyz)));
+
+    return vec4<f32>(center.xyz, render_object.render_bounds.radius * max_scale);
+}
+
+// whether the sphere is entirely on the outer side of the plane. the plane isn't normalized, a
+// plane without a normal (the far plane of an infinite projection) never culls
+fn isOutsidePlane(plane: vec4<f32>, bounds: vec4<f32>) -> bool {
+    return dot(plane.xyz, bounds.xyz) + plane.w < -bounds.w * length(plane.xyz);
+}
+
+// whether the render bounds are at least partially inside the view frustum. the planes are extracted
+// from the view projection matrix (Gribb-Hartmann), keep in sync with camera::Frustum. the near and
+// far planes are the same pair for standard and reverse-Z depth
+fn isInFrustum(render_object: RenderObject) -> bool {
+    let bounds = worldBounds(render_object);
+    let m = camera.view_proj;
+
+    let row0 = vec4<f32>(m[0][0], m[1][0], m[2][0], m[3][0]);
+    let row1 = vec4<f32>(m[0][1], m[1][1], m[2][1], m[3][1]);
+    let row2 = vec4<f32>(m[0][2], m[1][2], m[2][2], m[3][2]);
+    let row3 = vec4<f32>(m[0][3], m[1][3], m[2][3], m[3][3]);
+
+    return !(isOutsidePlane(row3 + row0, bounds)
+        || isOutsidePlane(row3 - row0, bounds)
+        || isOutsidePlane(row3 + row1, bounds)
+        || isOutsidePlane(row3 - row1, bounds)
+        || isOutsidePlane(row2, bounds)
+        || isOutsidePlane(row3 - row2, bounds));
+}
+
 fn isVisible(render_object: RenderObject) -> bool {
-    // todo frustum culling
     // todo occlusion culling
-    return true;
+    return render_object.visible != 0u && isInFrustum(render_object);
 }
 
-[[stage(compute), workgroup_size(1)]]
-fn cs_main([[builtin(global_invocation_id)]] gid: vec3<u32>) {
-    let render_object_id = gid.x;
-
+// picks the LOD level to draw the render object with, from the fraction of the screen height covered
+// by its render bounds. keep in sync with RenderObject::select_lod
+fn selectLod(render_object_id: u32) -> u32 {
     let render_object = render_objects.data[render_object_id];
-    let draw_command_index = render_object.draw_command_index;
 
-    if (isVisible(render_object)) {
-        // check if this draw call is already in the output draw buffer
-        let is_draw_invoked = atomicAdd(&output_info.data[draw_command_index].has_output_slot, 1u);
+    let bounds = worldBounds(render_object);
+    let radius = bounds.w;
 
-        var output_slot: u32;
-        if (is_draw_invoked < 1u) {
-            // this is the first time this draw command is invoked, give it a new draw command slot
-            output_slot = atomicAdd(&draw_counts.data[0].count, 1u);
+    let clip_w = (camera.view_proj * vec4<f32>(bounds.xyz, 1.0)).w;
 
-            // assign draw command to output draw command slot
-            out_draw_commands.data[output_slot] = draw_commands.data[draw_command_index];
+    // the camera is inside the bounds
+    if (clip_w <= radius) {
+        return 0u;
+    }
+
+    // the y scale of the projection, the length of the view matrix's rotation row is 1
+    let view_proj = camera.view_proj;
+    let projection_scale = length(vec3<f32>(view_proj[0][1], view_proj[1][1], view_proj[2][1]));
+    let screen_size = radius * projection_scale / clip_w;
+
+    var lod = 0u;
+    loop {
+        if (lod + 1u >= render_object.lod_count) {
+            break;
+        }
+        // indexed through the storage buffer, arrays in values can only be indexed by constants
+        if (screen_size >= render_objects.data[render_object_id].lod_screen_sizes[lod]) {
+            break;
+        }
+        lod = lod + 1u;
+    }
+    return lod;
+}
 
-            // store the output slot
-            atomicStore(&output_info.data[draw_command_index].output_slot, output_slot);
+// WORKGROUP_SIZE is substituted with compute_pipeline::WORKGROUP_SIZE on shader creation
+[[stage(compute), workgroup_size(WORKGROUP_SIZE)]]
+fn cs_main([[builtin(global_invocation_id)]] gid: vec3<u32>) {
+    let render_object_id = gid.x;
 
-        } else {
-            // this is not the first time this draw command is invoked
+    // the last workgroup may have invocations past the last render object
+    if (render_object_id >= compute_uniform.object_count) {
+        return;
+    }
 
-            let value_indicating_not_set = 4294967295u; // u32::MAX
-            loop {
-                // spin until the output slot is actually set on the first "thread" this draw command was invoked in
-                output_slot = atomicLoad(&output_info.data[draw_command_index].output_slot);
+    let render_object = render_objects.data[render_object_id];
 
-                if (output_slot != value_indicating_not_set) {
-                    break;
-                }
-            }
-        }
+    // removed render object, keep in sync with render_scene::REMOVED_DRAW_COMMAND_INDEX
+    if (render_object_draw_commands.data[render_object_id].draw_command_index == 4294967295u) {
+        return;
+    }
+
+    // emit the draw command of the selected LOD level's mesh
+    let lod = selectLod(render_object_id);
+    let draw_command_index = render_object_draw_commands.data[render_object_id].lod_draw_command_indices[lod];
+
+    // LOD level that didn't fit in the draw commands buffer
+    if (draw_command_index == 4294967295u) {
+        return;
+    }
 
+    if (isVisible(render_object)) {
         // get the index of the instance within the draw command
-        let instance_slot = atomicAdd(&out_draw_commands.data[output_slot].instance_count, 1u);
+        let instance_slot = atomicAdd(&output_info.data[draw_command_index].instance_count, 1u);
 
         // get the index of the instance in the global instance array
-        let instance_index = out_draw_commands.data[output_slot].first_instance + instance_slot;
+        let instance_index = draw_commands.data[draw_command_index].first_instance + instance_slot;
 
         // map instance to be drawn to the render object (for use in the vertex shader)
         instance_index_to_render_object_map.data[instance_index] = render_object_id;
     }
 }
+
+// Runs after cs_main, one invocation per draw command. Copies each draw command with visible
+// instances into the next output draw command slot.
+[[stage(compute), workgroup_size(WORKGROUP_SIZE)]]
+fn cs_emit([[builtin(global_invocation_id)]] gid: vec3<u32>) {
+    let draw_command_index = gid.x;
+
+    // the last workgroup may have invocations past the last draw command
+    if (draw_command_index >= arrayLength(&output_info.data)) {
+        return;
+    }
+
+    let instance_count = atomicLoad(&output_info.data[draw_command_index].instance_count);
+
+    // draw commands without visible instances aren't given a slot, so they aren't counted
+    if (instance_count == 0u) {
+        return;
+    }
+
+    // non-indexed draw commands come after the indexed ones, and are emitted as DrawIndirect with
+    // the vertex range stored in place of the index range. they have their own draw count
+    let is_non_indexed = draw_command_index >= compute_uniform.first_non_indexed_draw_command;
+    let draw_count_index = select(0u, 1u, is_non_indexed);
+
+    let output_slot = atomicAdd(&draw_counts.data[draw_count_index].count, 1u);
+
+    // assign draw command to output draw command slot
+    if (is_non_indexed) {
+        out_non_indexed_draw_commands.data[output_slot].vertex_count = draw_commands.data[draw_command_index].index_count;
+        atomicStore(&out_non_indexed_draw_commands.data[output_slot].instance_count, instance_count);
+        out_non_indexed_draw_commands.data[output_slot].first_vertex = draw_commands.data[draw_command_index].first_index;
+        out_non_indexed_draw_commands.data[output_slot].first_instance = draw_commands.data[draw_command_index].first_instance;
+    } else {
+        out_draw_commands.data[output_slot] = draw_commands.data[draw_command_index];
+        atomicStore(&out_draw_commands.data[output_slot].instance_count, instance_count);
+    }
+}
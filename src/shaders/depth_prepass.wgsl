@@ -0,0 +1,90 @@
+// depth-only pass writing the depth of the closest opaque fragments before the main pass
+
+// vertex ---------------------------------------------
+// input -----------
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+};
+
+struct InstanceInput {
+    [[builtin(instance_index)]] index: u32;
+};
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>;
+};
+
+struct RenderBounds {
+    origin: vec3<f32>;
+    radius: f32;
+};
+
+struct RenderObject {
+    mesh_handle: u32;
+    transform: mat4x4<f32>;
+    texture_index: u32;
+    selected: u32;
+    alpha_cutoff: f32;
+    color: vec4<f32>;
+    render_bounds: RenderBounds;
+    lod_count: u32;
+    lod_screen_sizes: array<f32, 3>;
+    visible: u32;
+};
+
+struct RenderObjectsStorage {
+    data: array<RenderObject>;
+};
+
+struct InstanceIndexToRenderObjectMapStorage {
+    data: array<u32>;
+};
+
+[[group(0), binding(0)]] var<uniform> camera: CameraUniform;
+[[group(0), binding(1)]] var<storage, read> render_objects: RenderObjectsStorage;
+[[group(0), binding(2)]] var<storage, read> instance_index_to_render_object_id: InstanceIndexToRenderObjectMapStorage;
+
+// output ----
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+    [[location(1), interpolate(flat)]] texture_index: u32;
+    [[location(2), interpolate(flat)]] alpha_cutoff: f32;
+};
+
+// vertex main -----
+[[stage(vertex)]]
+fn vs_main(vert: VertexInput, inst: InstanceInput) -> VertexOutput {
+    let render_object_id = instance_index_to_render_object_id.data[inst.index];
+    let render_object = render_objects.data[render_object_id];
+
+    var out: VertexOutput;
+    out.uv = vert.uv;
+    out.texture_index = render_object.texture_index;
+    out.alpha_cutoff = render_object.alpha_cutoff;
+
+    // same operations in the same order as the main vertex shader, so that the depth values
+    // match as closely as possible when the main pass tests against them
+    let world_position = render_object.transform * vec4<f32>(vert.position, 1.0);
+    out.clip_position = camera.view_proj * world_position;
+
+    return out;
+}
+
+// fragment ---------------------------------------------------
+// the main pass' material textures, the rest of its fragment bind group is unused
+[[group(1), binding(0)]] var t_diffuse: texture_2d_array<f32>;
+[[group(1), binding(1)]] var s_diffuse: sampler;
+
+// discards the same alpha tested cutouts as the main pass, so that they don't write depth
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) {
+    // sampled before the discard, like in the main pass, implicit derivatives need uniform
+    // control flow
+    let alpha = textureSample(t_diffuse, s_diffuse, in.uv, i32(in.texture_index)).a;
+
+    if (alpha < in.alpha_cutoff) {
+        discard;
+    }
+}
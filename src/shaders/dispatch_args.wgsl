@@ -0,0 +1,31 @@
+// 0
+//
+struct ComputeUniform {
+    object_count: u32;
+};
+
+// 1
+//
+struct DispatchIndirect {
+    x: u32;
+    y: u32;
+    z: u32;
+};
+
+// number of render objects
+[[group(0), binding(0)]] var<uniform> compute_uniform: ComputeUniform;
+
+// OUT
+//
+// workgroup counts of the culling compute pass
+[[group(0), binding(1)]] var<storage, read_write> dispatch_args: DispatchIndirect;
+
+[[stage(compute), workgroup_size(1)]]
+fn cs_main() {
+    // substituted with compute_pipeline::WORKGROUP_SIZE on shader creation
+    let workgroup_size = u32(WORKGROUP_SIZE);
+
+    dispatch_args.x = (compute_uniform.object_count + workgroup_size - 1u) / workgroup_size;
+    dispatch_args.y = 1u;
+    dispatch_args.z = 1u;
+}
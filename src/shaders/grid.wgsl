@@ -0,0 +1,71 @@
+// infinite ground grid at y = 0, drawn as a quad that follows the camera and fades out with
+// distance
+
+struct GridUniform {
+    view_proj: mat4x4<f32>;
+    // w is the distance at which the grid has faded out
+    camera_position: vec4<f32>;
+};
+
+[[group(0), binding(0)]] var<uniform> grid: GridUniform;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] world_position: vec3<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+    // two triangles, (-1, -1), (1, -1), (1, 1), (-1, -1), (1, 1), (-1, 1)
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0)
+    );
+    let corner = corners[vertex_index];
+
+    // centered below the camera and large enough to cover the faded out distance
+    let fade_distance = grid.camera_position.w;
+    let xz = grid.camera_position.xz + corner * fade_distance;
+    let world_position = vec3<f32>(xz.x, 0.0, xz.y);
+
+    var out: VertexOutput;
+    out.clip_position = grid.view_proj * vec4<f32>(world_position, 1.0);
+    out.world_position = world_position;
+
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let coord = in.world_position.xz;
+
+    // distance to the closest line in pixels, using the screen-space derivatives so that lines
+    // are about a pixel wide at any distance
+    let derivative = fwidth(coord);
+    let to_line = abs(fract(coord - 0.5) - 0.5) / derivative;
+    let line = 1.0 - min(min(to_line.x, to_line.y), 1.0);
+
+    var color = vec3<f32>(0.5, 0.5, 0.5);
+    // x axis in red, z axis in blue
+    if (abs(coord.y) < derivative.y) {
+        color = vec3<f32>(0.9, 0.2, 0.2);
+    }
+    if (abs(coord.x) < derivative.x) {
+        color = vec3<f32>(0.2, 0.3, 0.9);
+    }
+
+    let fade_distance = grid.camera_position.w;
+    let distance = length(coord - grid.camera_position.xz);
+    let fade = 1.0 - smoothstep(0.25 * fade_distance, fade_distance, distance);
+
+    let alpha = line * fade;
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    return vec4<f32>(color, alpha);
+}
@@ -0,0 +1,54 @@
+// depth-only pass rendering the scene from the light's point of view
+
+// vertex ---------------------------------------------
+// input -----------
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+};
+
+struct InstanceInput {
+    [[builtin(instance_index)]] index: u32;
+};
+
+struct LightUniform {
+    view_proj: mat4x4<f32>;
+};
+
+struct RenderBounds {
+    origin: vec3<f32>;
+    radius: f32;
+};
+
+struct RenderObject {
+    mesh_handle: u32;
+    transform: mat4x4<f32>;
+    texture_index: u32;
+    selected: u32;
+    alpha_cutoff: f32;
+    color: vec4<f32>;
+    render_bounds: RenderBounds;
+    lod_count: u32;
+    lod_screen_sizes: array<f32, 3>;
+    visible: u32;
+};
+
+struct RenderObjectsStorage {
+    data: array<RenderObject>;
+};
+
+struct InstanceIndexToRenderObjectMapStorage {
+    data: array<u32>;
+};
+
+[[group(0), binding(0)]] var<uniform> light: LightUniform;
+[[group(0), binding(1)]] var<storage, read> render_objects: RenderObjectsStorage;
+[[group(0), binding(2)]] var<storage, read> instance_index_to_render_object_id: InstanceIndexToRenderObjectMapStorage;
+
+// vertex main -----
+[[stage(vertex)]]
+fn vs_main(vert: VertexInput, inst: InstanceInput) -> [[builtin(position)]] vec4<f32> {
+    let render_object_id = instance_index_to_render_object_id.data[inst.index];
+    let render_object = render_objects.data[render_object_id];
+
+    return light.view_proj * render_object.transform * vec4<f32>(vert.position, 1.0);
+}
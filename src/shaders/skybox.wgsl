@@ -0,0 +1,41 @@
+// cubemap background, drawn as a fullscreen triangle before the scene
+
+struct SkyboxUniform {
+    inverse_view_proj: mat4x4<f32>;
+    // swapped with reverse-Z
+    near_depth: f32;
+    far_depth: f32;
+};
+
+[[group(0), binding(0)]] var<uniform> skybox: SkyboxUniform;
+[[group(0), binding(1)]] var t_cubemap: texture_cube<f32>;
+[[group(0), binding(2)]] var s_cubemap: sampler;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] ndc: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3), covering the whole screen
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    let ndc = uv * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    // the depth doesn't matter, as the skybox neither tests nor writes depth
+    out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+    out.ndc = ndc;
+
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    // the view direction is the direction from the near plane to the far plane through the fragment
+    let near = skybox.inverse_view_proj * vec4<f32>(in.ndc, skybox.near_depth, 1.0);
+    let far = skybox.inverse_view_proj * vec4<f32>(in.ndc, skybox.far_depth, 1.0);
+    let direction = far.xyz / far.w - near.xyz / near.w;
+
+    return textureSample(t_cubemap, s_cubemap, direction);
+}
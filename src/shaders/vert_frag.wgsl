@@ -4,6 +4,7 @@ struct VertexInput {
     [[location(0)]] position: vec3<f32>;
     [[location(1)]] normal: vec3<f32>;
     [[location(2)]] uv: vec2<f32>;
+    [[location(3)]] tangent: vec4<f32>;
 };
 
 struct InstanceInput {
@@ -18,10 +19,28 @@ struct CameraUniform {
     view_proj: mat4x4<f32>;
 };
 
+struct LightUniform {
+    view_proj: mat4x4<f32>;
+    // direction the light rays travel in, w is unused
+    direction: vec4<f32>;
+};
+
+struct RenderBounds {
+    origin: vec3<f32>;
+    radius: f32;
+};
+
 struct RenderObject {
     mesh_handle: u32;
     transform: mat4x4<f32>;
-    draw_command_index: u32;
+    texture_index: u32;
+    selected: u32;
+    alpha_cutoff: f32;
+    color: vec4<f32>;
+    render_bounds: RenderBounds;
+    lod_count: u32;
+    lod_screen_sizes: array<f32, 3>;
+    visible: u32;
 };
 
 struct RenderObjectsStorage {
@@ -35,23 +54,44 @@ struct InstanceIndexToRenderObjectMapStorage {
 [[group(0), binding(0)]] var<uniform> camera: CameraUniform;
 [[group(0), binding(1)]] var<storage, read> render_objects: RenderObjectsStorage;
 [[group(0), binding(2)]] var<storage, read> instance_index_to_render_object_id: InstanceIndexToRenderObjectMapStorage;
+[[group(0), binding(3)]] var<uniform> light: LightUniform;
 
 // output ----
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     [[location(0)]] uv: vec2<f32>;
+    [[location(1)]] color: vec4<f32>;
+    [[location(2)]] light_space_position: vec4<f32>;
+    [[location(3)]] world_normal: vec3<f32>;
+    [[location(4)]] world_tangent: vec4<f32>;
+    [[location(5)]] world_position: vec3<f32>;
+    [[location(6), interpolate(flat)]] texture_index: u32;
+    [[location(7), interpolate(flat)]] selected: u32;
+    [[location(8), interpolate(flat)]] alpha_cutoff: f32;
 };
 
 // vertex main -----
 [[stage(vertex)]]
 fn vs_main(vert: VertexInput, inst: InstanceInput) -> VertexOutput {
     let render_object_id = instance_index_to_render_object_id.data[inst.index];
-    let model_matrix = render_objects.data[render_object_id].transform;
+    let render_object = render_objects.data[render_object_id];
+    let model_matrix = render_object.transform;
 
+    let world_position = model_matrix * vec4<f32>(vert.position, 1.0);
+    // assumes uniform scaling, the vectors are normalized in the fragment shader
+    let normal_matrix = mat3x3<f32>(model_matrix[0].xyz, model_matrix[1].xyz, model_matrix[2].xyz);
 
     var out: VertexOutput;
     out.uv = vert.uv;
-    out.clip_position = camera.view_proj * model_matrix * vec4<f32>(vert.position, 1.0);
+    out.color = render_object.color;
+    out.texture_index = render_object.texture_index;
+    out.selected = render_object.selected;
+    out.alpha_cutoff = render_object.alpha_cutoff;
+    out.clip_position = camera.view_proj * world_position;
+    out.light_space_position = light.view_proj * world_position;
+    out.world_normal = normal_matrix * vert.normal;
+    out.world_tangent = vec4<f32>(normal_matrix * vert.tangent.xyz, vert.tangent.w);
+    out.world_position = world_position.xyz;
 
     return out;
 }
@@ -59,10 +99,107 @@ fn vs_main(vert: VertexInput, inst: InstanceInput) -> VertexOutput {
 // fragment ---------------------------------------------------
 
 // input ---------
-[[group(1), binding(0)]] var t_diffuse: texture_2d<f32>;
+// material textures, one layer per material
+[[group(1), binding(0)]] var t_diffuse: texture_2d_array<f32>;
 [[group(1), binding(1)]] var s_diffuse: sampler;
+[[group(1), binding(2)]] var t_shadow: texture_depth_2d;
+[[group(1), binding(3)]] var s_shadow: sampler_comparison;
+// tangent-space normal map, a flat normal map is bound for meshes without one
+[[group(1), binding(4)]] var t_normal: texture_2d<f32>;
+[[group(1), binding(5)]] var s_normal: sampler;
+
+struct PointLight {
+    position: vec3<f32>;
+    radius: f32;
+    // w is unused
+    color: vec4<f32>;
+};
+
+struct PointLightsStorage {
+    data: array<PointLight>;
+};
+
+struct PointLightCountUniform {
+    count: u32;
+};
+
+[[group(1), binding(6)]] var<storage, read> point_lights: PointLightsStorage;
+[[group(1), binding(7)]] var<uniform> point_light_count: PointLightCountUniform;
+
+// 1.0 if lit, 0.0 if in shadow
+fn shadow_factor(light_space_position: vec4<f32>) -> f32 {
+    let position = light_space_position.xyz / light_space_position.w;
+    // ndc to texture coordinates, y is flipped
+    let uv = position.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5, 0.5);
+
+    let lit = textureSampleCompare(t_shadow, s_shadow, uv, position.z);
+
+    // everything outside of the shadow map is lit
+    let outside = uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0 || position.z > 1.0;
+    return select(lit, 1.0, outside);
+}
+
+// world space normal, perturbed by the normal map
+fn surface_normal(in: VertexOutput) -> vec3<f32> {
+    let normal = normalize(in.world_normal);
+    // re-orthogonalize, the interpolated tangent may no longer be orthogonal to the normal
+    let tangent = normalize(in.world_tangent.xyz - normal * dot(normal, in.world_tangent.xyz));
+    let bitangent = cross(normal, tangent) * in.world_tangent.w;
+
+    let tangent_space_normal = textureSample(t_normal, s_normal, in.uv).xyz * 2.0 - 1.0;
+
+    return normalize(mat3x3<f32>(tangent, bitangent, normal) * tangent_space_normal);
+}
+
+// sum of the diffuse light from the point lights
+fn point_lighting(world_position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    var lighting = vec3<f32>(0.0);
+
+    for (var i = 0u; i < point_light_count.count; i = i + 1u) {
+        let point_light = point_lights.data[i];
+
+        let to_light = point_light.position - world_position;
+        let distance = length(to_light);
+
+        // inverse square falloff, smoothly reaching zero at the light's radius
+        let falloff = clamp(1.0 - pow(distance / point_light.radius, 4.0), 0.0, 1.0);
+        let attenuation = falloff * falloff / (distance * distance + 1.0);
+
+        let diffuse = max(dot(normal, to_light / distance), 0.0);
+
+        lighting = lighting + point_light.color.rgb * diffuse * attenuation;
+    }
+
+    return lighting;
+}
 
 [[stage(fragment)]]
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.uv);
+    // all textures are sampled before the discard, implicit derivatives need uniform control flow
+    let albedo = textureSample(t_diffuse, s_diffuse, in.uv, i32(in.texture_index));
+    let normal = surface_normal(in);
+    let shadow = shadow_factor(in.light_space_position);
+
+    // alpha tested cutouts, keep in sync with depth_prepass.wgsl
+    if (albedo.a < in.alpha_cutoff) {
+        discard;
+    }
+
+    let diffuse = max(dot(normal, -light.direction.xyz), 0.0);
+
+    // hard shadows, shadowed fragments keep some ambient light
+    let ambient = 0.4;
+    let light_intensity = ambient + (1.0 - ambient) * diffuse * shadow;
+
+    let lighting = vec3<f32>(light_intensity) + point_lighting(in.world_position, normal);
+
+    let color = albedo * in.color * vec4<f32>(lighting, 1.0);
+
+    // tint objects selected in the editor
+    if (in.selected != 0u) {
+        let selection_tint = vec3<f32>(1.0, 0.6, 0.1);
+        return vec4<f32>(mix(color.rgb, selection_tint, 0.5), color.a);
+    }
+
+    return color;
 }
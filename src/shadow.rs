@@ -0,0 +1,215 @@
+use crate::graphics_context::DebugLabels;
+use crate::{mesh, render_scene, texture, RenderInstance, Vertex, VertexArena};
+use macaw as m;
+use penguin_util::{GpuBuffer, GpuBufferDeviceExt};
+use std::slice;
+
+/// Width and height of the shadow map.
+pub const SHADOW_MAP_SIZE: u32 = 2048;
+
+/// A light infinitely far away, casting parallel rays (e.g. the sun).
+#[derive(Debug, Copy, Clone)]
+pub struct DirectionalLight {
+    /// Direction the light rays travel in.
+    pub direction: m::Vec3,
+    /// Half the width and height of the area around the origin covered by the shadow map.
+    pub extent: f32,
+    /// Distance from the origin to the light's point of view.
+    pub distance: f32,
+}
+penguin_util::impl_default!(
+    DirectionalLight,
+    Self {
+        direction: m::vec3(-0.4, -1.0, -0.3),
+        extent: 20.0,
+        distance: 50.0,
+    }
+);
+impl DirectionalLight {
+    pub fn uniform_data(&self) -> LightUniformData {
+        LightUniformData {
+            view_proj: self.view_proj(),
+            direction: self.direction.normalize().extend(0.0),
+        }
+    }
+
+    /// Orthographic view projection matrix from the light's point of view.
+    pub fn view_proj(&self) -> m::Mat4 {
+        let direction = self.direction.normalize();
+        let eye = -direction * self.distance;
+
+        // look_at breaks down if looking along the up vector
+        let up = match direction.y.abs() > 0.99 {
+            true => m::Vec3::Z,
+            false => m::Vec3::Y,
+        };
+
+        let view = m::Mat4::look_at_rh(eye, m::Vec3::ZERO, up);
+        let proj = m::Mat4::orthographic_rh(
+            -self.extent,
+            self.extent,
+            -self.extent,
+            self.extent,
+            0.1,
+            2.0 * self.distance,
+        );
+
+        proj * view
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct LightUniformData {
+    pub view_proj: m::Mat4,
+    /// Normalized direction the light rays travel in. w is unused.
+    pub direction: m::Vec4,
+}
+unsafe impl bytemuck::Pod for LightUniformData {}
+unsafe impl bytemuck::Zeroable for LightUniformData {}
+
+/// Depth-only pass that renders the scene from the directional light's point of view into the
+/// shadow map. Reuses the indirect draw buffers of the main pass.
+pub struct ShadowPass {
+    /// Depth texture with a comparison sampler, sampled in the fragment shader.
+    pub shadow_map: texture::Texture,
+    /// Light-space view projection, also used by the main pass to sample the shadow map.
+    pub light_uniform_buffer: GpuBuffer<LightUniformData>,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+impl ShadowPass {
+    /// The vertex bind group layout is shared with the main pass, with the light uniform in place
+    /// of the camera uniform.
+    pub fn init(
+        device: &wgpu::Device,
+        light: &DirectionalLight,
+        vertex_bind_group_layout: &wgpu::BindGroupLayout,
+        render_objects_buffer: &wgpu::Buffer,
+        instance_map_buffer: &wgpu::Buffer,
+    ) -> Self {
+        let shadow_map = texture::Texture::create_shadow_map(device, SHADOW_MAP_SIZE);
+
+        let light_uniform_buffer =
+            device.create_buffer_init_t::<LightUniformData>(&wgpu::util::BufferInitDescriptor {
+                label: Some("light uniform buffer"),
+                contents: bytemuck::cast_slice(slice::from_ref(&light.uniform_data())),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let bind_group = crate::bind_groups::BindGroupBuilder::<4>::builder()
+            .buffer(0, &light_uniform_buffer) // light uniform in place of camera uniform
+            .buffer(1, render_objects_buffer)
+            .buffer(2, instance_map_buffer)
+            .buffer(3, &light_uniform_buffer)
+            .build(device, Some("shadow bind group"), vertex_bind_group_layout);
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("shadow shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shadow.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("shadow pipeline layout"),
+            bind_group_layouts: &[vertex_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("shadow pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    mesh::MeshVertex::buffer_layout(),
+                    RenderInstance::buffer_layout(),
+                ],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                // offset depth values to avoid shadow acne
+                bias: wgpu::DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            fragment: None, // depth only
+            multiview: None,
+        });
+
+        Self {
+            shadow_map,
+            light_uniform_buffer,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    /// Writes the light's view projection and direction to the light uniform buffer.
+    pub fn update_light(&self, queue: &wgpu::Queue, light: &DirectionalLight) {
+        self.light_uniform_buffer
+            .write(queue, 0, slice::from_ref(&light.uniform_data()));
+    }
+
+    /// Records the shadow pass. Should be recorded before the main render pass.
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &self,
+        cmd: &mut wgpu::CommandEncoder,
+        vertex_arena: &VertexArena,
+        instance_buffer: &wgpu::Buffer,
+        draw_commands_buffer: &wgpu::Buffer,
+        non_indexed_draw_commands_buffer: &wgpu::Buffer,
+        draw_count_buffer: &wgpu::Buffer,
+        max_draw_count: u32,
+        labels: &DebugLabels,
+    ) {
+        cmd.push_debug_group(&labels.shadow_pass);
+        {
+            let mut render_pass = cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(labels.shadow_pass.as_str()),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.shadow_map.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+
+            render_pass.set_vertex_buffer(0, vertex_arena.vertices_slice());
+            render_pass.set_index_buffer(vertex_arena.indices_slice(), wgpu::IndexFormat::Uint32);
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+            render_scene::record_emitted_draws(
+                &mut render_pass,
+                draw_commands_buffer,
+                non_indexed_draw_commands_buffer,
+                draw_count_buffer,
+                max_draw_count,
+                labels.shadow_pass_draw_marker(),
+            );
+        }
+        cmd.pop_debug_group();
+    }
+}
@@ -0,0 +1,187 @@
+use crate::graphics_context::DepthMode;
+use crate::{bind_groups, camera, texture, GraphicsContext};
+use macaw as m;
+use penguin_util::{GpuBuffer, GpuBufferDeviceExt};
+use std::slice;
+
+/// Faces of the skybox cubemap in the textures assets directory, in the order +X, -X, +Y, -Y, +Z,
+/// -Z.
+const SKYBOX_FACES: [&str; 6] = [
+    "skybox/right.jpg",
+    "skybox/left.jpg",
+    "skybox/top.jpg",
+    "skybox/bottom.jpg",
+    "skybox/front.jpg",
+    "skybox/back.jpg",
+];
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct SkyboxUniformData {
+    pub inverse_view_proj: m::Mat4,
+    /// Depth of the near and far planes, which are swapped with reverse-Z.
+    pub near_depth: f32,
+    pub far_depth: f32,
+    _padding: [f32; 2],
+}
+unsafe impl bytemuck::Pod for SkyboxUniformData {}
+unsafe impl bytemuck::Zeroable for SkyboxUniformData {}
+
+impl SkyboxUniformData {
+    fn new(camera_uniform_data: &camera::CameraUniformData, depth_mode: DepthMode) -> Self {
+        Self {
+            inverse_view_proj: camera_uniform_data.view_proj.inverse(),
+            near_depth: depth_mode.near_depth(),
+            far_depth: depth_mode.far_depth(),
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+/// Cubemap background. Drawn as a fullscreen triangle at the start of the main render pass, so
+/// that the scene is drawn on top of it.
+pub struct Skybox {
+    pub cubemap: texture::Texture,
+    uniform_buffer: GpuBuffer<SkyboxUniformData>,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    depth_mode: DepthMode,
+}
+impl Skybox {
+    /// Loads the skybox cubemap, falling back to a gradient if the cubemap assets are missing.
+    pub fn init(
+        context: &GraphicsContext,
+        camera_uniform_data: &camera::CameraUniformData,
+    ) -> Self {
+        let device = &context.device;
+
+        let cubemap = texture::Texture::cubemap_from_assets(
+            device,
+            &context.queue,
+            SKYBOX_FACES,
+            &texture::SamplerDescriptorConfig::trilinear_clamped(),
+        )
+        .unwrap_or_else(|e| {
+            log::warn!("failed to load skybox, using a gradient: {:?}", e);
+            texture::Texture::gradient_cubemap(
+                device,
+                &context.queue,
+                [170, 200, 230],
+                [40, 90, 170],
+                [60, 60, 70],
+            )
+        });
+
+        let uniform_buffer =
+            device.create_buffer_init_t::<SkyboxUniformData>(&wgpu::util::BufferInitDescriptor {
+                label: Some("skybox uniform buffer"),
+                contents: bytemuck::cast_slice(slice::from_ref(&SkyboxUniformData::new(
+                    camera_uniform_data,
+                    context.depth_mode,
+                ))),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        const FRAGMENT: wgpu::ShaderStages = wgpu::ShaderStages::FRAGMENT;
+
+        let bind_group_layout = bind_groups::BindGroupLayoutBuilder::<3>::builder()
+            .uniform_buffer(0, FRAGMENT)
+            .texture_cube(1, FRAGMENT)
+            .sampler(2, FRAGMENT)
+            .build(device, Some("skybox bind group layout"));
+
+        let bind_group = bind_groups::BindGroupBuilder::<3>::builder()
+            .buffer(0, &uniform_buffer)
+            .texture_view(1, &cubemap.view)
+            .sampler(2, &cubemap.sampler)
+            .build(device, Some("skybox bind group"), &bind_group_layout);
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("skybox shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/skybox.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("skybox pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("skybox pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            // shares the main pass' depth buffer, but neither tests nor writes depth
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                // must match the sample count of the main pass
+                count: context.sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format: context.config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            cubemap,
+            uniform_buffer,
+            pipeline,
+            bind_group,
+            depth_mode: context.depth_mode,
+        }
+    }
+
+    /// Changes the depth the skybox is drawn at, written with the next camera update.
+    pub fn set_depth_mode(&mut self, depth_mode: DepthMode) {
+        self.depth_mode = depth_mode;
+    }
+
+    /// Writes the inverse of the camera's view projection to the skybox uniform buffer.
+    pub fn update_camera(
+        &self,
+        queue: &wgpu::Queue,
+        camera_uniform_data: &camera::CameraUniformData,
+    ) {
+        self.uniform_buffer.write(
+            queue,
+            0,
+            slice::from_ref(&SkyboxUniformData::new(
+                camera_uniform_data,
+                self.depth_mode,
+            )),
+        );
+    }
+
+    /// Draws the skybox. Should be recorded before the scene in the main render pass.
+    pub fn record<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.push_debug_group("skybox");
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        render_pass.pop_debug_group();
+    }
+}
@@ -4,6 +4,68 @@ pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
+    /// Size the texture was created with. wgpu 0.12 textures don't expose their size.
+    pub size: wgpu::Extent3d,
+}
+
+/// Options for the sampler created together with a texture.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SamplerDescriptorConfig {
+    /// What to do if the sampler gets a texture coord outside of the texture (all axes).
+    pub address_mode: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    pub mipmap_filter: wgpu::FilterMode,
+    /// Max anisotropy level (1, 2, 4, 8 or 16). Requires all filters to be linear.
+    pub anisotropy_clamp: Option<std::num::NonZeroU8>,
+}
+penguin_util::impl_default!(
+    SamplerDescriptorConfig,
+    Self {
+        address_mode: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        anisotropy_clamp: None,
+    }
+);
+impl SamplerDescriptorConfig {
+    /// Clamped, trilinear filtering. Suitable for UI atlases.
+    pub fn trilinear_clamped() -> Self {
+        Self {
+            address_mode: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy_clamp: None,
+        }
+    }
+
+    /// Repeating, anisotropic filtering. Suitable for textures viewed at steep angles, such as
+    /// ground textures.
+    pub fn anisotropic(level: u8) -> Self {
+        Self {
+            address_mode: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy_clamp: std::num::NonZeroU8::new(level),
+        }
+    }
+
+    fn create_sampler(&self, device: &wgpu::Device, label: Option<&str>) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label,
+            address_mode_u: self.address_mode,
+            address_mode_v: self.address_mode,
+            address_mode_w: self.address_mode,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            anisotropy_clamp: self.anisotropy_clamp,
+            ..Default::default()
+        })
+    }
 }
 
 impl Texture {
@@ -11,10 +73,148 @@ impl Texture {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         asset_name: &str,
+        sampler_config: &SamplerDescriptorConfig,
     ) -> Result<Self> {
         let texture_assets_dir = std::path::Path::new(env!("OUT_DIR")).join("assets/textures");
         let image = image::open(texture_assets_dir.join(asset_name)).with_context(|| "texture")?;
-        Self::from_image(device, queue, &image, Some(asset_name))
+        Self::from_image(device, queue, &image, Some(asset_name), sampler_config)
+    }
+
+    /// Loads a texture asset, or returns a white texture if it can't be loaded.
+    pub fn from_asset_or_default(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        asset_name: &str,
+        sampler_config: &SamplerDescriptorConfig,
+    ) -> Self {
+        Self::from_asset(device, queue, asset_name, sampler_config).unwrap_or_else(|e| {
+            log::warn!("failed to load texture {}: {:?}", asset_name, e);
+            Self::white(device, queue)
+        })
+    }
+
+    /// 1x1 white texture, in place of textures that failed to load.
+    pub fn white(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self::from_rgba8(
+            device,
+            queue,
+            &[255; 4],
+            (1, 1),
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            Some("white"),
+            &SamplerDescriptorConfig::default(),
+        )
+    }
+}
+
+// async loading
+/// Decoded RGBA8 pixels of a texture asset, ready to be uploaded to the GPU.
+pub struct TexturePixels {
+    pub name: String,
+    pub rgba: image::RgbaImage,
+}
+impl TexturePixels {
+    /// Decodes a texture asset on the calling thread. Absolute paths, such as the textures of mesh
+    /// materials, are loaded as they are instead of from the texture assets directory.
+    pub fn decode_asset(asset_name: &str) -> Result<Self> {
+        let texture_assets_dir = std::path::Path::new(env!("OUT_DIR")).join("assets/textures");
+        let image = image::open(texture_assets_dir.join(asset_name))
+            .with_context(|| format!("texture {}", asset_name))?;
+
+        Ok(Self {
+            name: asset_name.to_owned(),
+            rgba: image.to_rgba8(),
+        })
+    }
+
+    /// 1x1 white image, shown in place of textures that are still loading or failed to load.
+    pub fn placeholder() -> Self {
+        Self {
+            name: "placeholder".to_owned(),
+            rgba: image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4])),
+        }
+    }
+}
+
+/// A texture asset being decoded on another thread. Resolves to the decoded pixels, which are
+/// uploaded with Texture::upload on the thread that owns the queue.
+pub struct TextureLoad {
+    pub asset_name: String,
+    state: std::sync::Arc<std::sync::Mutex<TextureLoadState>>,
+}
+
+#[derive(Default)]
+struct TextureLoadState {
+    result: Option<Result<TexturePixels>>,
+    waker: Option<std::task::Waker>,
+}
+
+impl TextureLoad {
+    /// Takes the decoded pixels without blocking if the load has finished. Returns None if it's
+    /// still decoding, or if the result has already been taken.
+    pub fn try_take(&mut self) -> Option<Result<TexturePixels>> {
+        self.state.lock().unwrap().result.take()
+    }
+}
+
+impl std::future::Future for TextureLoad {
+    type Output = Result<TexturePixels>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+
+        match state.result.take() {
+            Some(result) => std::task::Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                std::task::Poll::Pending
+            }
+        }
+    }
+}
+
+impl Texture {
+    /// Starts decoding a texture asset on a new thread.
+    pub fn load_async(asset_name: &str) -> TextureLoad {
+        let state = std::sync::Arc::new(std::sync::Mutex::new(TextureLoadState::default()));
+
+        let thread_state = state.clone();
+        let thread_asset_name = asset_name.to_owned();
+        std::thread::spawn(move || {
+            let result = TexturePixels::decode_asset(&thread_asset_name);
+
+            let mut state = thread_state.lock().unwrap();
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+
+        TextureLoad {
+            asset_name: asset_name.to_owned(),
+            state,
+        }
+    }
+
+    /// Uploads decoded pixels to a new texture.
+    pub fn upload(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pixels: &TexturePixels,
+        sampler_config: &SamplerDescriptorConfig,
+    ) -> Self {
+        Self::from_rgba8(
+            device,
+            queue,
+            &pixels.rgba,
+            pixels.rgba.dimensions(),
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            Some(pixels.name.as_str()),
+            sampler_config,
+        )
     }
 }
 
@@ -25,9 +225,10 @@ impl Texture {
         queue: &wgpu::Queue,
         bytes: &[u8],
         label: Option<&str>,
+        sampler_config: &SamplerDescriptorConfig,
     ) -> Result<Self> {
         let image = image::load_from_memory(bytes)?;
-        Self::from_image(device, queue, &image, label)
+        Self::from_image(device, queue, &image, label, sampler_config)
     }
 
     pub fn from_image(
@@ -35,12 +236,30 @@ impl Texture {
         queue: &wgpu::Queue,
         image: &image::DynamicImage,
         label: Option<&str>,
+        sampler_config: &SamplerDescriptorConfig,
     ) -> Result<Self> {
-        let pixel_data = image.to_rgba8();
-
         use image::GenericImageView;
-        let dimensions = image.dimensions();
 
+        Ok(Self::from_rgba8(
+            device,
+            queue,
+            &image.to_rgba8(),
+            image.dimensions(),
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            label,
+            sampler_config,
+        ))
+    }
+
+    fn from_rgba8(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pixel_data: &[u8],
+        dimensions: (u32, u32),
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+        sampler_config: &SamplerDescriptorConfig,
+    ) -> Self {
         let extent = wgpu::Extent3d {
             width: dimensions.0,
             height: dimensions.1,
@@ -52,7 +271,7 @@ impl Texture {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
         });
 
@@ -63,7 +282,7 @@ impl Texture {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            &pixel_data,
+            pixel_data,
             // layout of the texture
             wgpu::ImageDataLayout {
                 offset: 0,
@@ -74,36 +293,339 @@ impl Texture {
         );
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            // what to do if the sampler gets a texture coord outside of the texture
-            // address_mode_u: wgpu::AddressMode::ClampToEdge,
-            // address_mode_v: wgpu::AddressMode::ClampToEdge,
-            // address_mode_w: wgpu::AddressMode::ClampToEdge,
-            address_mode_u: wgpu::AddressMode::MirrorRepeat,
-            address_mode_v: wgpu::AddressMode::MirrorRepeat,
-            address_mode_w: wgpu::AddressMode::MirrorRepeat,
-            //
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
+        let sampler = sampler_config.create_sampler(device, label);
+
+        Self {
+            texture,
+            view,
+            sampler,
+            size: extent,
+        }
+    }
+}
+
+// normal maps
+impl Texture {
+    /// Loads a tangent-space normal map asset. Normal maps store vectors rather than colors, so
+    /// they're loaded in a linear format. Falls back to a flat normal map if the asset can't be
+    /// loaded.
+    pub fn normal_map_from_asset(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        asset_name: &str,
+    ) -> Self {
+        let texture_assets_dir = std::path::Path::new(env!("OUT_DIR")).join("assets/textures");
+
+        match image::open(texture_assets_dir.join(asset_name)) {
+            Ok(image) => {
+                use image::GenericImageView;
+
+                Self::from_rgba8(
+                    device,
+                    queue,
+                    &image.to_rgba8(),
+                    image.dimensions(),
+                    wgpu::TextureFormat::Rgba8Unorm,
+                    Some(asset_name),
+                    &SamplerDescriptorConfig::default(),
+                )
+            }
+            Err(e) => {
+                log::warn!("failed to load normal map {}: {:?}", asset_name, e);
+                Self::flat_normal_map(device, queue)
+            }
+        }
+    }
+
+    /// 1x1 normal map pointing straight out of the surface, for meshes without a normal map.
+    pub fn flat_normal_map(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self::from_rgba8(
+            device,
+            queue,
+            &[128, 128, 255, 255],
+            (1, 1),
+            wgpu::TextureFormat::Rgba8Unorm,
+            Some("flat normal map"),
+            &SamplerDescriptorConfig::default(),
+        )
+    }
+}
+
+// texture arrays
+impl Texture {
+    /// Loads texture assets into the layers of a single texture, in order. Assets of a different
+    /// size than the first one are resized to its size. The assets are decoded in parallel. Assets
+    /// that fail to load get a white layer.
+    pub fn array_from_assets(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        asset_names: &[&str],
+        sampler_config: &SamplerDescriptorConfig,
+    ) -> Result<Self> {
+        let loads = asset_names
+            .iter()
+            .map(|asset_name| Self::load_async(asset_name))
+            .collect::<Vec<_>>();
+
+        let layers = loads
+            .into_iter()
+            .map(|load| {
+                let asset_name = load.asset_name.clone();
+                penguin_util::pollster::block_on(load).unwrap_or_else(|e| {
+                    log::warn!("failed to load texture array layer {}: {:?}", asset_name, e);
+                    TexturePixels::placeholder()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Self::array_from_pixels(device, queue, &layers, sampler_config)
+    }
+
+    /// Uploads decoded images into the layers of a single texture, in order. Images of a different
+    /// size than the first one are resized to its size.
+    pub fn array_from_pixels(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layers: &[TexturePixels],
+        sampler_config: &SamplerDescriptorConfig,
+    ) -> Result<Self> {
+        let (width, height) = layers
+            .first()
+            .map(|layer| layer.rgba.dimensions())
+            .context("texture array without layers")?;
+
+        let mut pixel_data = Vec::new();
+
+        for layer in layers {
+            if layer.rgba.dimensions() != (width, height) {
+                let resized = image::imageops::resize(
+                    &layer.rgba,
+                    width,
+                    height,
+                    image::imageops::FilterType::Triangle,
+                );
+                pixel_data.extend_from_slice(&resized);
+            } else {
+                pixel_data.extend_from_slice(&layer.rgba);
+            }
+        }
+
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers.len() as u32,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("texture array"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &pixel_data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * width),
+                rows_per_image: std::num::NonZeroU32::new(height),
+            },
+            extent,
+        );
+
+        // the view must be an array even with a single layer, to match the shader
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("texture array"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
             ..Default::default()
         });
+        let sampler = sampler_config.create_sampler(device, Some("texture array"));
 
         Ok(Self {
             texture,
             view,
             sampler,
+            size: extent,
         })
     }
 }
 
+// cubemaps
+impl Texture {
+    /// Loads six square texture assets of the same size into a cubemap, in the order +X, -X, +Y,
+    /// -Y, +Z, -Z.
+    pub fn cubemap_from_assets(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        face_asset_names: [&str; 6],
+        sampler_config: &SamplerDescriptorConfig,
+    ) -> Result<Self> {
+        use image::GenericImageView;
+
+        let texture_assets_dir = std::path::Path::new(env!("OUT_DIR")).join("assets/textures");
+
+        let mut size = None;
+        let mut pixel_data = Vec::new();
+
+        for face_asset_name in face_asset_names {
+            let image = image::open(texture_assets_dir.join(face_asset_name))
+                .with_context(|| format!("cubemap face {}", face_asset_name))?;
+
+            let dimensions = image.dimensions();
+            if dimensions.0 != dimensions.1 || size.unwrap_or(dimensions.0) != dimensions.0 {
+                bail!(
+                    "cubemap faces must be square and of the same size, {} is {:?}",
+                    face_asset_name,
+                    dimensions
+                );
+            }
+            size = Some(dimensions.0);
+
+            pixel_data.extend_from_slice(&image.to_rgba8());
+        }
+
+        Ok(Self::cubemap_from_rgba8(
+            device,
+            queue,
+            &pixel_data,
+            size.unwrap(),
+            Some("cubemap"),
+            sampler_config,
+        ))
+    }
+
+    /// Generates a cubemap with a vertical gradient from the horizon to the zenith, for when no
+    /// cubemap assets are available.
+    pub fn gradient_cubemap(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        horizon: [u8; 3],
+        zenith: [u8; 3],
+        ground: [u8; 3],
+    ) -> Self {
+        const SIZE: u32 = 64;
+
+        let mut pixel_data = Vec::with_capacity((SIZE * SIZE * 4 * 6) as usize);
+
+        for face in 0..6 {
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    let u = (x as f32 + 0.5) / SIZE as f32 * 2.0 - 1.0;
+                    let v = (y as f32 + 0.5) / SIZE as f32 * 2.0 - 1.0;
+
+                    // direction through the texel, see the cubemap face layout in the wgpu spec
+                    let direction = match face {
+                        0 => [1.0, -v, -u],
+                        1 => [-1.0, -v, u],
+                        2 => [u, 1.0, v],
+                        3 => [u, -1.0, -v],
+                        4 => [u, -v, 1.0],
+                        _ => [-u, -v, -1.0],
+                    };
+                    let length = direction.iter().map(|c| c * c).sum::<f32>().sqrt();
+                    let height = direction[1] / length;
+
+                    let (from, to, t) = if height >= 0.0 {
+                        (horizon, zenith, height)
+                    } else {
+                        (horizon, ground, (-height * 4.0).min(1.0))
+                    };
+
+                    for channel in 0..3 {
+                        let value = from[channel] as f32 * (1.0 - t) + to[channel] as f32 * t;
+                        pixel_data.push(value as u8);
+                    }
+                    pixel_data.push(255);
+                }
+            }
+        }
+
+        Self::cubemap_from_rgba8(
+            device,
+            queue,
+            &pixel_data,
+            SIZE,
+            Some("gradient cubemap"),
+            &SamplerDescriptorConfig::trilinear_clamped(),
+        )
+    }
+
+    /// The pixel data contains the six faces after each other.
+    fn cubemap_from_rgba8(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pixel_data: &[u8],
+        size: u32,
+        label: Option<&str>,
+        sampler_config: &SamplerDescriptorConfig,
+    ) -> Self {
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            pixel_data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * size),
+                rows_per_image: std::num::NonZeroU32::new(size),
+            },
+            extent,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label,
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = sampler_config.create_sampler(device, label);
+
+        Self {
+            texture,
+            view,
+            sampler,
+            size: extent,
+        }
+    }
+}
+
 // depth
 impl Texture {
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
+    /// The sampler is a comparison sampler, to bind with the depth_texture_2d and
+    /// comparison_sampler layout entries. Only a depth texture with a sample count of 1 can be
+    /// sampled.
     pub fn create_depth_texture(
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
     ) -> Self {
         let extent = wgpu::Extent3d {
             width: config.width,
@@ -115,7 +637,7 @@ impl Texture {
             label: Some("depth texture"),
             size: extent,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: Self::DEPTH_FORMAT,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
@@ -142,10 +664,97 @@ impl Texture {
             texture,
             view,
             sampler,
+            size: extent,
         }
     }
 }
 
+// shadows
+impl Texture {
+    /// Creates a square depth texture to render shadows into, with a comparison sampler for
+    /// sampling it in shaders. Nearest filtering gives hard shadows.
+    pub fn create_shadow_map(device: &wgpu::Device, size: u32) -> Self {
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shadow map"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow map sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+            size: extent,
+        }
+    }
+}
+
+// multisampling
+impl Texture {
+    /// Creates a multisampled color texture with the surface's format and size, to be resolved
+    /// into the surface texture. Returns None if sample_count is 1.
+    pub fn create_msaa_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> Option<Self> {
+        if sample_count <= 1 {
+            return None;
+        }
+
+        let extent = wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("msaa texture"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler =
+            SamplerDescriptorConfig::default().create_sampler(device, Some("msaa sampler"));
+
+        Some(Self {
+            texture,
+            view,
+            sampler,
+            size: extent,
+        })
+    }
+}
+
 pub mod bind_group_layout_entry {
     const TEXTURE_BINDING_TYPE: wgpu::BindingType = wgpu::BindingType::Texture {
         multisampled: false,
@@ -185,3 +794,9 @@ pub mod bind_group_layout_entry {
         }
     }
 }
+
+#[test]
+fn test_load_async_missing_asset() {
+    let load = Texture::load_async("missing-texture.png");
+    assert!(penguin_util::pollster::block_on(load).is_err());
+}
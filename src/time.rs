@@ -1,7 +1,15 @@
+/// Default upper limit of the delta time, so that a long stall (a breakpoint, dragging the window)
+/// doesn't make time based animation jump.
+pub const DEFAULT_MAX_DELTA_TIME: std::time::Duration = std::time::Duration::from_millis(100);
+
 pub struct Clock {
     pub start_time: std::time::Instant,
     pub previous_tick: std::time::Instant,
+    /// Duration between the two previous ticks, clamped to max_delta_time.
     pub last_delta_time: std::time::Duration,
+    /// Duration between the two previous ticks.
+    pub unclamped_delta_time: std::time::Duration,
+    pub max_delta_time: std::time::Duration,
 }
 impl Clock {
     pub fn start() -> Self {
@@ -11,14 +19,57 @@ impl Clock {
             start_time: now,
             previous_tick: now,
             last_delta_time: std::time::Duration::from_secs(1),
+            unclamped_delta_time: std::time::Duration::from_secs(1),
+            max_delta_time: DEFAULT_MAX_DELTA_TIME,
         }
     }
 
     /// Sets previous_time to the current time and returns the duration since the previously set
-    /// previous_time.
+    /// previous_time, clamped to max_delta_time.
     pub fn tick(&mut self) -> std::time::Duration {
-        self.last_delta_time = self.previous_tick.elapsed();
+        self.unclamped_delta_time = self.previous_tick.elapsed();
+        self.last_delta_time = self.unclamped_delta_time.min(self.max_delta_time);
         self.previous_tick = std::time::Instant::now();
         self.last_delta_time
     }
 }
+
+/// Scales or pauses the passage of time, e.g. for slow motion debugging.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeScale {
+    pub time_scale: f32,
+    pub paused: bool,
+}
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self {
+            time_scale: 1.0,
+            paused: false,
+        }
+    }
+}
+impl TimeScale {
+    /// Returns the scaled duration, or zero when paused.
+    pub fn apply(&self, duration: std::time::Duration) -> std::time::Duration {
+        if self.paused {
+            std::time::Duration::ZERO
+        } else {
+            duration.mul_f32(self.time_scale.max(0.0))
+        }
+    }
+}
+
+#[test]
+fn test_clock_clamps_delta_time() {
+    let mut clock = Clock::start();
+    // as if the previous frame was stalled at a breakpoint
+    clock.previous_tick -= std::time::Duration::from_secs(5);
+
+    assert_eq!(clock.tick(), DEFAULT_MAX_DELTA_TIME);
+    assert_eq!(clock.last_delta_time, DEFAULT_MAX_DELTA_TIME);
+    assert!(clock.unclamped_delta_time >= std::time::Duration::from_secs(5));
+
+    // short frames are left as they are
+    assert!(clock.tick() < DEFAULT_MAX_DELTA_TIME);
+    assert_eq!(clock.last_delta_time, clock.unclamped_delta_time);
+}